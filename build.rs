@@ -0,0 +1,45 @@
+//! Captures build metadata shown by the `version` command. Values that can't be determined, like
+//! the commit when building outside a git checkout, are empty.
+use std::{env, path::Path, process::Command};
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let (commit, dirty) = git_state(&manifest_dir).unwrap_or_default();
+    println!("cargo:rustc-env=TT_GIT_COMMIT={commit}");
+    println!("cargo:rustc-env=TT_GIT_DIRTY={dirty}");
+    println!(
+        "cargo:rustc-env=TT_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    for path in [".git/HEAD", ".git/index", ".git/refs"] {
+        if Path::new(&manifest_dir).join(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+}
+
+/// The commit hash and whether there are uncommitted changes, `None` unless the crate is the root
+/// of a git checkout, so that a parent repository isn't reported.
+fn git_state(dir: &str) -> Option<(String, bool)> {
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+    let root = git(&["rev-parse", "--show-toplevel"])?;
+    if Path::new(&root).canonicalize().ok()? != Path::new(dir).canonicalize().ok()? {
+        return None;
+    }
+    let commit = git(&["rev-parse", "--short=12", "HEAD"])?;
+    let dirty = !git(&["status", "--porcelain", "--untracked-files=no"])?.is_empty();
+    Some((commit, dirty))
+}
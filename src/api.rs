@@ -0,0 +1,66 @@
+//! Types and functions for embedding the tracker in other programs. Nothing here prints, errors
+//! are returned instead.
+//!
+//! Sessions are read from and saved to [`Config::sessions_path`]:
+//!
+//! ```
+//! use time_tracker::api::{self, Config, DateTime, Session, Tag};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let sessions_path = std::env::temp_dir().join("time_tracker_api_doc");
+//! # std::fs::create_dir_all(&sessions_path)?;
+//! let config = Config {
+//!     sessions_path,
+//!     ..Default::default()
+//! };
+//! let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+//! session.write("Parser.")?;
+//! session.tag_session(&Tag::from_text("rust")?);
+//! session.save(&config)?;
+//!
+//! let last = api::load_last_session(&config)?.expect("session was just saved");
+//! assert_eq!(last.marks[0].contents(), "Parser.");
+//! assert!(last.duration()? >= api::Duration::from_secs(60 * 60));
+//! # std::fs::remove_dir_all(&config.sessions_path)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::config::Options;
+pub use crate::{
+    config::Config,
+    date_time::{DateRange, DateTime, Duration},
+    session::{Aggregator, Attribute, Mark, MarkSelector, Session, Tag, WriteMode, WriteTarget},
+};
+use std::error::Error;
+
+/// Reads the config file of the user, `~/.timetracker.toml`, along with a project-local one
+/// found from the working directory.
+pub fn load_config() -> Result<Config, Box<dyn Error>> {
+    Config::build(&Options::default())
+}
+
+/// Newest session of [`Config::workspace`], `None` when there are no sessions yet.
+pub fn load_last_session(config: &Config) -> Result<Option<Session>, Box<dyn Error>> {
+    Session::get_last(config)
+}
+
+/// All sessions in the sessions directory, oldest first. Files that couldn't be read are in
+/// [`Aggregator::failures`].
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let sessions_path = std::env::temp_dir().join("time_tracker_api_doc_load_all");
+/// # std::fs::create_dir_all(&sessions_path)?;
+/// # let config = time_tracker::api::Config { sessions_path, ..Default::default() };
+/// let aggregator = time_tracker::api::load_all(&config)?;
+/// for session in aggregator.sessions() {
+///     println!("{}: {}", session.path.display(), session.duration()?.as_secs());
+/// }
+/// # std::fs::remove_dir_all(&config.sessions_path)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_all(config: &Config) -> Result<Aggregator, Box<dyn Error>> {
+    Aggregator::build(config)
+}
@@ -1,282 +1,3262 @@
+#[cfg(feature = "git")]
+use crate::git;
 use crate::{
-    date_time::DateTime,
-    get_git_branch_name, resolve_path,
-    session::{Attribute, Tag},
+    crypto,
+    date_time::{
+        Clock, DateStyle, DateTime, DisplayPrefs, Duration, DurationFormat, TimestampFormat,
+    },
+    exec,
+    hook::Hooks,
+    report::{Goal, Period},
+    resolve_path,
+    session::{Attribute, MarkSelector, Session, Tag, TagNormalizer, WriteMode, WriteTarget},
+    style::ColorMode,
+    view::ViewTemplate,
+};
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
 };
-use std::{error::Error, fs, io, path::PathBuf};
 
 const CONFIG_PATH: &str = "~/.timetracker.toml";
+const CONFIG_LOCAL_FILE_NAME: &str = ".timetracker.toml";
 const CONFIG_SESSIONS_PATH: &str = "sessions_path";
+const CONFIG_DAY_STARTS_AT: &str = "day_starts_at";
+const CONFIG_DURATION_FORMAT: &str = "duration_format";
+const CONFIG_ROUNDING: &str = "rounding";
+const CONFIG_TIMESTAMP_FORMAT: &str = "timestamp_format";
+const CONFIG_CLOCK: &str = "clock";
+const CONFIG_DATE_STYLE: &str = "date_style";
+const CONFIG_BACKUPS: &str = "backups";
+const CONFIG_BACKUPS_DEFAULT: usize = 5;
+const CONFIG_WEEK_SPLIT: &str = "week_split";
+const CONFIG_UNTRACKED_TAGS: &str = "untracked_tags";
+const CONFIG_WEEKLY_GOAL: &str = "weekly_goal";
+const CONFIG_AUTO_STOP_AFTER: &str = "auto_stop_after";
+const CONFIG_PROFILE_SECTION_PREFIX: &str = "profile.";
+/// Maps aliases to canonical tags, `alias = 'tag'`. Only read at the top level of a file.
+const CONFIG_TAG_ALIASES_SECTION: &str = "tag_aliases";
+const CONFIG_TAGS_CASE_INSENSITIVE: &str = "tags_case_insensitive";
+const CONFIG_COLOR: &str = "color";
+const CONFIG_VIEW_TEMPLATE: &str = "view_template";
+const CONFIG_ON_START: &str = "on_start";
+const CONFIG_ON_MARK: &str = "on_mark";
+const CONFIG_ON_STOP: &str = "on_stop";
+const CONFIG_AUTO_BRANCH_TAG: &str = "auto_branch_tag";
+const CONFIG_EDITOR: &str = "editor";
+const CONFIG_VERBOSE_CHANGES: &str = "verbose_changes";
+const CONFIG_RECORD_HOST: &str = "record_host";
+const CONFIG_ENCRYPT_CONTENTS: &str = "encrypt_contents";
+const CONFIG_EVENT_LOG: &str = "event_log";
+const CONFIG_CREATE_MISSING_DIRS: &str = "create_missing_dirs";
+const CONFIG_MERGE_MAX_GAP: &str = "merge_max_gap";
+const CONFIG_MERGE_MAX_GAP_DEFAULT: Duration = Duration::from_secs(30 * 60);
+const CONFIG_POMODORO: &str = "pomodoro";
+const CONFIG_HOURLY_RATE: &str = "hourly_rate";
+const CONFIG_CURRENCY: &str = "currency";
+const CONFIG_GAP_THRESHOLD: &str = "gap_threshold";
+const CONFIG_GAP_THRESHOLD_DEFAULT: Duration = Duration::from_secs(15 * 60);
+/// Keys accepted at the top level of a config file and in profiles, see [`Config::set`].
+const CONFIG_KEYS: &[&str] = &[
+    CONFIG_SESSIONS_PATH,
+    CONFIG_DAY_STARTS_AT,
+    CONFIG_DURATION_FORMAT,
+    CONFIG_ROUNDING,
+    CONFIG_TIMESTAMP_FORMAT,
+    CONFIG_CLOCK,
+    CONFIG_DATE_STYLE,
+    CONFIG_BACKUPS,
+    CONFIG_WEEK_SPLIT,
+    CONFIG_UNTRACKED_TAGS,
+    CONFIG_WEEKLY_GOAL,
+    CONFIG_VIEW_TEMPLATE,
+    CONFIG_COLOR,
+    CONFIG_TAGS_CASE_INSENSITIVE,
+    CONFIG_AUTO_STOP_AFTER,
+    CONFIG_EVENT_LOG,
+    CONFIG_CREATE_MISSING_DIRS,
+    CONFIG_MERGE_MAX_GAP,
+    CONFIG_POMODORO,
+    CONFIG_HOURLY_RATE,
+    CONFIG_CURRENCY,
+    CONFIG_GAP_THRESHOLD,
+    CONFIG_AUTO_BRANCH_TAG,
+    CONFIG_ON_START,
+    CONFIG_ON_MARK,
+    CONFIG_ON_STOP,
+    CONFIG_EDITOR,
+    CONFIG_VERBOSE_CHANGES,
+    CONFIG_RECORD_HOST,
+    CONFIG_ENCRYPT_CONTENTS,
+];
+const WATCH_INTERVAL_DEFAULT: Duration = Duration::from_secs(5);
+const ENV_PROFILE: &str = "TIMETRACKER_PROFILE";
+/// Error of the options that need git in builds without the `git` feature.
+#[cfg(not(feature = "git"))]
+const GIT_NOT_COMPILED: &str = "git support not compiled in";
 
 #[derive(PartialEq, Debug)]
 pub struct Config {
     pub sessions_path: PathBuf,
+    /// Path of the config file that was used, the project-local one if it was found.
+    pub source: PathBuf,
+    /// Time at which a day starts for the purposes of daily and weekly totals.
+    pub day_starts_at: chrono::NaiveTime,
+    pub duration_format: DurationFormat,
+    /// Increment session totals are rounded to when presented, zero disables rounding.
+    pub rounding: Duration,
+    pub timestamp_format: TimestampFormat,
+    /// Clock and date style of dates shown to the user, session files aren't affected.
+    pub display: DisplayPrefs,
+    /// Number of backups kept per session file, zero disables backups.
+    pub backups: usize,
+    /// Counts each interval to the week it's in instead of the week its session started in,
+    /// intervals crossing the start of a week are split.
+    pub week_split: bool,
+    /// Intervals with any of these tags don't count towards totals, like skipped ones.
+    pub untracked_tags: Vec<Tag>,
+    /// Time to track in a week, shown with the week total when set.
+    pub weekly_goal: Option<Goal>,
+    /// Active sessions without a mark for longer than this are stale, see [`Session::is_stale`].
+    pub auto_stop_after: Option<Duration>,
+    /// Sessions of a workspace are tracked independently of the others, `None` is the default
+    /// workspace. Set from the command line, not from config files.
+    pub workspace: Option<String>,
+    /// Applied to tags given on the command line and read from session files.
+    pub tag_normalizer: TagNormalizer,
+    pub color: ColorMode,
+    pub view_template: ViewTemplate,
+    /// Tags new marks with the git branch of the working directory, `branch:<name>`.
+    pub auto_branch_tag: bool,
+    /// Logs each change to a session, see [`events::append`](crate::events::append).
+    pub event_log: bool,
+    /// `start` creates a missing sessions directory without asking.
+    pub create_missing_dirs: bool,
+    /// Longest time between sessions that `merge` combines without `--force`.
+    pub merge_max_gap: Duration,
+    /// Length of a work interval, a mark running for longer is flagged as due for a break.
+    pub pomodoro: Option<Duration>,
+    /// Amount billed for an hour of billable time, see [`Action::Billing`].
+    pub hourly_rate: Option<f64>,
+    /// Shown after billed amounts.
+    pub currency: String,
+    /// Gaps between sessions shorter than this are flagged, see [`Action::Gaps`].
+    pub gap_threshold: Duration,
+    /// Empty when hooks are disabled with `--no-hooks`.
+    pub hooks: Hooks,
+    /// Command `edit` opens files with, `$VISUAL` or `$EDITOR` when `None`. Quoting is handled
+    /// by [`exec::split`].
+    pub editor: Option<String>,
+    /// Commands list what they changed in the session file, see
+    /// [`Session::diff`](crate::session::Session::diff). Also shown in verbose output.
+    pub verbose_changes: bool,
+    /// New marks are labeled with the name of the machine they were written on, for sessions
+    /// directories synced between machines.
+    pub record_host: bool,
+    /// Mark contents are written encrypted with a passphrase, the times stay readable without it,
+    /// see [`crypto::Cipher`](crate::crypto::Cipher).
+    pub encrypt_contents: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            sessions_path: PathBuf::new(),
+            source: PathBuf::new(),
+            day_starts_at: chrono::NaiveTime::default(),
+            duration_format: DurationFormat::default(),
+            rounding: Duration::default(),
+            timestamp_format: TimestampFormat::default(),
+            display: DisplayPrefs::default(),
+            backups: CONFIG_BACKUPS_DEFAULT,
+            week_split: false,
+            untracked_tags: Vec::new(),
+            weekly_goal: None,
+            auto_stop_after: None,
+            workspace: None,
+            tag_normalizer: TagNormalizer::default(),
+            color: ColorMode::default(),
+            view_template: ViewTemplate::default(),
+            auto_branch_tag: false,
+            event_log: false,
+            create_missing_dirs: false,
+            merge_max_gap: CONFIG_MERGE_MAX_GAP_DEFAULT,
+            pomodoro: None,
+            hourly_rate: None,
+            currency: String::new(),
+            gap_threshold: CONFIG_GAP_THRESHOLD_DEFAULT,
+            hooks: Hooks::default(),
+            editor: None,
+            verbose_changes: false,
+            record_host: false,
+            encrypt_contents: false,
+        }
+    }
 }
 
 impl Config {
-    pub fn build() -> Result<Config, Box<dyn Error>> {
+    pub fn build(options: &Options) -> Result<Config, Box<dyn Error>> {
         let path = resolve_path(CONFIG_PATH)?;
+        let local_path = env::current_dir()
+            .ok()
+            .and_then(|dir| Config::find_local(&dir, &path));
         let contents = match fs::read_to_string(&path) {
-            Ok(val) => val,
+            Ok(val) => Some(val),
             Err(err) => {
-                if err.kind() == io::ErrorKind::NotFound {
-                    let contents = format!("{CONFIG_SESSIONS_PATH}=''");
-                    fs::write(&path, &contents)?;
+                if err.kind() != io::ErrorKind::NotFound {
+                    return Err(err)?;
+                }
+                if local_path.is_none() {
                     return Err(format!(
-                        "config file not found, created one at `{CONFIG_PATH}`"
+                        "config file `{CONFIG_PATH}` not found, run `time_tracker init` to create one"
                     ))?;
                 }
-                return Err(err)?;
+                None
             }
         };
-        let from_file = Config::from_file(&contents)?;
-        let config = Config {
-            sessions_path: from_file.sessions_path,
-        };
+
+        let mut files = Vec::new();
+        if let Some(contents) = contents {
+            files.push(ConfigFile::parse(&contents)?);
+        }
+        if let Some(local_path) = &local_path {
+            let contents = fs::read_to_string(local_path)?;
+            let mut file = ConfigFile::parse(&contents)
+                .map_err(|err| format!("{}: {err}", local_path.display()))?;
+            file.base_dir = local_path.parent().map(|v| v.to_path_buf());
+            files.push(file);
+        }
+        // The command line option takes precedence over the environment variable.
+        let profile = options
+            .profile
+            .clone()
+            .or_else(|| env::var(ENV_PROFILE).ok())
+            .filter(|v| !v.trim().is_empty());
+        let mut config = Config::from_files(&files, profile.as_deref())?;
+        config.source = local_path.unwrap_or(path);
+        config.workspace = options.name.clone();
+        if options.no_hooks || options.dry_run {
+            config.hooks = Hooks::default();
+        }
+        if options.dry_run {
+            config.event_log = false;
+        }
         Ok(config)
     }
 
-    fn from_file(contents: &str) -> Result<Config, String> {
-        let contents = contents.trim();
-        if !contents.starts_with(&format!("{CONFIG_SESSIONS_PATH}='")) || !contents.ends_with("'") {
-            return Err(format!(
-                "wrong config file format, please use `{CONFIG_SESSIONS_PATH}='<path>'`"
-            ))?;
+    /// Path of the global config file, which `init` writes.
+    pub fn get_global_path() -> Result<PathBuf, &'static str> {
+        resolve_path(CONFIG_PATH)
+    }
+
+    /// Contents of a new config file, the other options are listed commented out with their
+    /// defaults.
+    pub fn get_template(sessions_path: &str) -> Result<String, String> {
+        let sessions_path = Config::quote(sessions_path)?;
+        Ok(format!(
+            "\
+            # Directory the session files are kept in.\n\
+            {CONFIG_SESSIONS_PATH} = {sessions_path}\n\
+            \n\
+            # Time at which a new day starts, sessions before it count to the previous day.\n\
+            # {CONFIG_DAY_STARTS_AT} = '00:00'\n\
+            # `hms` for `1h 12m 0s` or `clock` for `1:12`.\n\
+            # {CONFIG_DURATION_FORMAT} = 'hms'\n\
+            # Time to track in a week, shown by `view`.\n\
+            # {CONFIG_WEEKLY_GOAL} = '40h'\n\
+            # Number of backups kept per session file.\n\
+            # {CONFIG_BACKUPS} = {CONFIG_BACKUPS_DEFAULT}\n\
+            "
+        ))
+    }
+
+    /// `contents` of a config file with the top-level `sessions_path` set to `sessions_path`, see
+    /// [`Config::set_value`].
+    pub fn set_sessions_path(contents: &str, sessions_path: &str) -> Result<String, String> {
+        Config::set_value(
+            contents,
+            CONFIG_SESSIONS_PATH,
+            &Config::quote(sessions_path)?,
+        )
+    }
+
+    /// Raw values of the top-level keys of `contents`, in the order they appear in the file.
+    pub fn get_values(contents: &str) -> Result<Vec<(String, String)>, String> {
+        let file = ConfigFile::parse(contents)?;
+        Ok(file.get_entries("").into_iter().cloned().collect())
+    }
+
+    /// Raw value of the top-level `key` of `contents`, `None` if the file doesn't set it. Fails
+    /// for keys the config doesn't know.
+    pub fn get_value(contents: &str, key: &str) -> Result<Option<String>, String> {
+        if !CONFIG_KEYS.contains(&key) {
+            return Err(format!("unknown config key `{key}`"));
+        }
+        let values = Config::get_values(contents)?;
+        // Later entries override the earlier ones.
+        Ok(values
+            .into_iter()
+            .rev()
+            .find(|(v, _)| v == key)
+            .map(|(_, v)| v))
+    }
+
+    /// `contents` of a config file with the top-level `key` set to `value`, which is checked the
+    /// same way as when the file is loaded. An unquoted `value` that isn't valid as it is, like a
+    /// duration, is quoted. Only the line of `key` is replaced, a missing key is added after the
+    /// last top-level line.
+    pub fn set_value(contents: &str, key: &str, value: &str) -> Result<String, String> {
+        let value = value.trim();
+        let value = match Config::default().set(key, value, None) {
+            Ok(()) => value.to_owned(),
+            Err(err) if value.starts_with(['\'', '"', '[']) => return Err(err),
+            Err(err) => {
+                let quoted = Config::quote(value).map_err(|_| err)?;
+                Config::default().set(key, &quoted, None)?;
+                quoted
+            }
+        };
+        let line = format!("{key} = {value}");
+        let mut lines: Vec<String> = contents.lines().map(|v| v.to_owned()).collect();
+        // Only keys before the first section are top-level.
+        let top_level = lines
+            .iter()
+            .position(|v| v.trim().starts_with("["))
+            .unwrap_or(lines.len());
+        let existing = lines[..top_level]
+            .iter()
+            .rposition(|v| v.split_once("=").is_some_and(|(v, _)| v.trim() == key));
+        match existing {
+            Some(i) => lines[i] = line,
+            None => {
+                let last = lines[..top_level].iter().rposition(|v| {
+                    let v = v.trim();
+                    !v.is_empty() && !v.starts_with("#")
+                });
+                lines.insert(last.map_or(0, |i| i + 1), line);
+            }
+        }
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        ConfigFile::parse(&contents)?;
+        Ok(contents)
+    }
+
+    /// Quotes `text` as a config string, which has no escapes.
+    fn quote(text: &str) -> Result<String, String> {
+        if !text.contains('\'') {
+            Ok(format!("'{text}'"))
+        } else if !text.contains('"') {
+            Ok(format!("\"{text}\""))
+        } else {
+            Err(format!("`{text}` can't contain both `'` and `\"`"))
         }
-        let sessions_path = &contents[15..contents.len() - 1];
-        if sessions_path.is_empty() {
+    }
+
+    /// Searches `dir` and all of its ancestors for a project-local config file, the global config
+    /// file is never returned.
+    fn find_local(dir: &Path, global: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|v| v.join(CONFIG_LOCAL_FILE_NAME))
+            .find(|v| v != global && v.is_file())
+    }
+
+    #[cfg(test)]
+    fn from_file(contents: &str, profile: Option<&str>) -> Result<Config, String> {
+        Config::from_files(&[ConfigFile::parse(contents)?], profile)
+    }
+
+    /// Files are applied in order, each overriding the values of the previous ones. Within a file,
+    /// top-level keys are applied first, keys of the selected profile then override them.
+    fn from_files(files: &[ConfigFile], profile: Option<&str>) -> Result<Config, String> {
+        let mut config = Config::default();
+        if let Some(profile) = profile {
+            let section = format!("{CONFIG_PROFILE_SECTION_PREFIX}{profile}");
+            if !files.iter().any(|v| v.sections.contains(&section)) {
+                let mut available = Vec::new();
+                for name in files
+                    .iter()
+                    .flat_map(|v| &v.sections)
+                    .filter_map(|v| v.strip_prefix(CONFIG_PROFILE_SECTION_PREFIX))
+                {
+                    let name = format!("`{name}`");
+                    if !available.contains(&name) {
+                        available.push(name);
+                    }
+                }
+                let available = if available.is_empty() {
+                    String::from("none")
+                } else {
+                    available.join(", ")
+                };
+                return Err(format!(
+                    "unknown profile `{profile}`, available profiles: {available}"
+                ));
+            }
+        }
+
+        for file in files {
+            let mut entries = file.get_entries("");
+            if let Some(profile) = profile {
+                entries
+                    .extend(file.get_entries(&format!("{CONFIG_PROFILE_SECTION_PREFIX}{profile}")));
+            }
+            for (key, value) in entries {
+                config.set(key, value, file.base_dir.as_deref())?;
+            }
+            for (alias, tag) in file.get_entries(CONFIG_TAG_ALIASES_SECTION) {
+                config.set_tag_alias(alias, tag)?;
+            }
+        }
+        if config.sessions_path.as_os_str().is_empty() {
             return Err("wrong config, sessions_path is empty")?;
         }
-        let sessions_path = resolve_path(&sessions_path)?;
-        let config = Config { sessions_path };
+        let normalizer = &config.tag_normalizer;
+        config.untracked_tags = config
+            .untracked_tags
+            .iter()
+            .map(|v| normalizer.apply(v))
+            .collect();
         Ok(config)
     }
+
+    /// Later files override aliases of the previous ones.
+    fn set_tag_alias(&mut self, alias: &str, tag: &str) -> Result<(), String> {
+        let alias = if alias.starts_with(['\'', '"']) {
+            ConfigFile::parse_string(alias)?
+        } else {
+            alias.to_owned()
+        };
+        let invalid = |err| format!("invalid tag alias `{alias}`: {err}");
+        let alias = Tag::from_text(&alias).map_err(invalid)?.to_string();
+        let tag = Tag::from_text(&ConfigFile::parse_string(tag)?).map_err(invalid)?;
+        let aliases = &mut self.tag_normalizer.aliases;
+        aliases.retain(|(v, _)| v != &alias);
+        aliases.push((alias, tag));
+        Ok(())
+    }
+
+    /// Relative paths are resolved against `base_dir` if given, otherwise they are kept relative
+    /// to the working directory.
+    fn set(&mut self, key: &str, value: &str, base_dir: Option<&Path>) -> Result<(), String> {
+        match key {
+            CONFIG_SESSIONS_PATH => {
+                let sessions_path = ConfigFile::parse_string(value)?;
+                // Emptiness is checked once all files are applied, another file can still set it.
+                if sessions_path.is_empty() {
+                    self.sessions_path = PathBuf::new();
+                    return Ok(());
+                }
+                let sessions_path = resolve_path(&sessions_path)?;
+                self.sessions_path = match base_dir {
+                    Some(dir) if sessions_path.is_relative() => dir.join(sessions_path),
+                    _ => sessions_path,
+                };
+            }
+            CONFIG_DAY_STARTS_AT => {
+                let time = ConfigFile::parse_string(value)?;
+                self.day_starts_at = chrono::NaiveTime::parse_from_str(&time, "%H:%M")
+                    .map_err(|_| format!("invalid {CONFIG_DAY_STARTS_AT} `{time}`, use `HH:MM`"))?;
+            }
+            CONFIG_DURATION_FORMAT => {
+                self.duration_format =
+                    DurationFormat::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_ROUNDING => {
+                self.rounding = Duration::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_TIMESTAMP_FORMAT => {
+                self.timestamp_format =
+                    TimestampFormat::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_CLOCK => {
+                self.display.clock = Clock::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_DATE_STYLE => {
+                self.display.date_style = DateStyle::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_BACKUPS => {
+                self.backups = ConfigFile::parse_integer(value)?;
+            }
+            CONFIG_WEEK_SPLIT => {
+                self.week_split = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_UNTRACKED_TAGS => {
+                self.untracked_tags = ConfigFile::parse_string_array(value)?
+                    .iter()
+                    .map(|v| Tag::from_text(v))
+                    .collect::<Result<Vec<Tag>, String>>()
+                    .map_err(|err| format!("invalid {CONFIG_UNTRACKED_TAGS}: {err}"))?;
+            }
+            CONFIG_WEEKLY_GOAL => {
+                self.weekly_goal = Some(Goal::from_text(&ConfigFile::parse_string(value)?)?);
+            }
+            CONFIG_VIEW_TEMPLATE => {
+                self.view_template = ViewTemplate::parse(&ConfigFile::parse_string(value)?)
+                    .map_err(|err| format!("invalid {CONFIG_VIEW_TEMPLATE}: {err}"))?;
+            }
+            CONFIG_COLOR => {
+                self.color = ColorMode::from_text(&ConfigFile::parse_string(value)?)?;
+            }
+            CONFIG_TAGS_CASE_INSENSITIVE => {
+                self.tag_normalizer.case_insensitive = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_AUTO_STOP_AFTER => {
+                let value = ConfigFile::parse_string(value)?;
+                let time = Duration::from_text(&value)?;
+                if value.trim().starts_with('-') || time == Duration::ZERO {
+                    return Err(format!("{CONFIG_AUTO_STOP_AFTER} must be longer than zero"));
+                }
+                self.auto_stop_after = Some(time);
+            }
+            CONFIG_EVENT_LOG => {
+                self.event_log = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_CREATE_MISSING_DIRS => {
+                self.create_missing_dirs = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_MERGE_MAX_GAP => {
+                let value = ConfigFile::parse_string(value)?;
+                if value.trim().starts_with('-') {
+                    return Err(format!("{CONFIG_MERGE_MAX_GAP} can't be negative"));
+                }
+                self.merge_max_gap = Duration::from_text(&value)?;
+            }
+            CONFIG_POMODORO => {
+                let value = ConfigFile::parse_string(value)?;
+                let time = Duration::from_text(&value)?;
+                if value.trim().starts_with('-') || time == Duration::ZERO {
+                    return Err(format!("{CONFIG_POMODORO} must be longer than zero"));
+                }
+                self.pomodoro = Some(time);
+            }
+            CONFIG_HOURLY_RATE => {
+                self.hourly_rate = Some(ConfigFile::parse_float(value)?);
+            }
+            CONFIG_CURRENCY => {
+                self.currency = ConfigFile::parse_string(value)?.trim().to_owned();
+            }
+            CONFIG_GAP_THRESHOLD => {
+                let value = ConfigFile::parse_string(value)?;
+                let time = Duration::from_text(&value)?;
+                if value.trim().starts_with('-') || time == Duration::ZERO {
+                    return Err(format!("{CONFIG_GAP_THRESHOLD} must be longer than zero"));
+                }
+                self.gap_threshold = time;
+            }
+            CONFIG_AUTO_BRANCH_TAG => {
+                self.auto_branch_tag = ConfigFile::parse_boolean(value)?;
+                #[cfg(not(feature = "git"))]
+                if self.auto_branch_tag {
+                    return Err(format!("{CONFIG_AUTO_BRANCH_TAG}: {GIT_NOT_COMPILED}"));
+                }
+            }
+            CONFIG_ON_START | CONFIG_ON_MARK | CONFIG_ON_STOP => {
+                let command = ConfigFile::parse_string(value)?;
+                exec::split(&command).map_err(|err| format!("invalid {key}: {err}"))?;
+                let command = Some(command).filter(|v| !v.trim().is_empty());
+                match key {
+                    CONFIG_ON_START => self.hooks.on_start = command,
+                    CONFIG_ON_MARK => self.hooks.on_mark = command,
+                    _ => self.hooks.on_stop = command,
+                }
+            }
+            CONFIG_VERBOSE_CHANGES => {
+                self.verbose_changes = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_RECORD_HOST => {
+                self.record_host = ConfigFile::parse_boolean(value)?;
+            }
+            CONFIG_ENCRYPT_CONTENTS => {
+                self.encrypt_contents = ConfigFile::parse_boolean(value)?;
+                if self.encrypt_contents && !crypto::is_supported() {
+                    return Err(format!(
+                        "{CONFIG_ENCRYPT_CONTENTS}: encryption support not compiled in"
+                    ));
+                }
+            }
+            CONFIG_EDITOR => {
+                let command = ConfigFile::parse_string(value)?;
+                exec::command(&command).map_err(|err| format!("invalid {key}: {err}"))?;
+                self.editor = Some(command);
+            }
+            key => return Err(format!("unknown config key `{key}`")),
+        }
+        Ok(())
+    }
+}
+
+/// Minimal subset of TOML, supports sections and `key = 'value'` pairs. Keys above the first section
+/// belong to the top-level section, which has an empty name.
+#[derive(PartialEq, Debug)]
+pub struct ConfigFile {
+    sections: Vec<String>,
+    /// Entries in the order they appear in the file, stored as `(section, (key, raw value))`.
+    entries: Vec<(String, (String, String))>,
+    /// Directory relative paths are resolved against.
+    base_dir: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    fn parse(contents: &str) -> Result<ConfigFile, String> {
+        let mut sections = vec![String::new()];
+        let mut entries = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+            if line.starts_with("[") && line.ends_with("]") {
+                let name = line[1..line.len() - 1].trim();
+                let is_known = name == CONFIG_TAG_ALIASES_SECTION
+                    || (name.starts_with(CONFIG_PROFILE_SECTION_PREFIX)
+                        && name.len() > CONFIG_PROFILE_SECTION_PREFIX.len());
+                if !is_known {
+                    return Err(format!("unknown config section `{name}` on line {}", i + 1));
+                }
+                if sections.iter().any(|v| v == name) {
+                    return Err(format!(
+                        "duplicate config section `{name}` on line {}",
+                        i + 1
+                    ));
+                }
+                sections.push(name.to_owned());
+                continue;
+            }
+            let Some((key, value)) = line.split_once("=") else {
+                return Err(format!(
+                    "wrong config file format on line {}, please use `{CONFIG_SESSIONS_PATH}='<path>'`",
+                    i + 1
+                ));
+            };
+            let section = sections
+                .last()
+                .expect("top-level section is always present");
+            entries.push((
+                section.clone(),
+                (key.trim().to_owned(), value.trim().to_owned()),
+            ));
+        }
+        Ok(ConfigFile {
+            sections,
+            entries,
+            base_dir: None,
+        })
+    }
+
+    fn get_entries(&self, section: &str) -> Vec<&(String, String)> {
+        self.entries
+            .iter()
+            .filter(|(name, _)| name == section)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Strings can be surrounded by either single or double quotes, no escaping is supported.
+    pub fn parse_string(value: &str) -> Result<String, String> {
+        let value = value.trim();
+        let is_quoted = value.len() >= 2
+            && ((value.starts_with("'") && value.ends_with("'"))
+                || (value.starts_with("\"") && value.ends_with("\"")));
+        if !is_quoted {
+            return Err(format!("expected a quoted string, found `{value}`"));
+        }
+        Ok(value[1..value.len() - 1].to_owned())
+    }
+
+    /// Arrays of strings like `['a', "b"]`, see [`ConfigFile::parse_string`].
+    fn parse_string_array(value: &str) -> Result<Vec<String>, String> {
+        let value = value.trim();
+        let err = || format!("expected an array of quoted strings, found `{value}`");
+        let mut rest = value
+            .strip_prefix("[")
+            .and_then(|v| v.strip_suffix("]"))
+            .ok_or_else(err)?
+            .trim_start();
+        let mut strings = Vec::new();
+        while let Some(quote) = rest.chars().next() {
+            if quote != '\'' && quote != '"' {
+                return Err(err());
+            }
+            let end = rest[1..].find(quote).ok_or_else(err)? + 2;
+            strings.push(ConfigFile::parse_string(&rest[..end])?);
+            rest = rest[end..].trim_start();
+            // Trailing comma is allowed.
+            match rest.strip_prefix(",") {
+                Some(val) => rest = val.trim_start(),
+                None if rest.is_empty() => break,
+                None => return Err(err()),
+            }
+        }
+        Ok(strings)
+    }
+
+    fn parse_integer(value: &str) -> Result<usize, String> {
+        let value = value.trim();
+        value
+            .parse()
+            .map_err(|_| format!("expected a non-negative integer, found `{value}`"))
+    }
+
+    /// Non-negative numbers, with or without a fractional part.
+    fn parse_float(value: &str) -> Result<f64, String> {
+        let value = value.trim();
+        value
+            .parse()
+            .ok()
+            .filter(|v: &f64| v.is_finite() && *v >= 0.0)
+            .ok_or_else(|| format!("expected a non-negative number, found `{value}`"))
+    }
+
+    fn parse_boolean(value: &str) -> Result<bool, String> {
+        match value.trim() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            value => Err(format!("expected `true` or `false`, found `{value}`")),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Default)]
+pub struct Options {
+    pub profile: Option<String>,
+    /// Workspace to run the command in, see [`Config::workspace`].
+    pub name: Option<String>,
+    /// See [`Output::quiet`](crate::output::Output::quiet).
+    pub quiet: bool,
+    /// See [`Output::verbose`](crate::output::Output::verbose).
+    pub verbose: bool,
+    /// Disables [`Config::hooks`].
+    pub no_hooks: bool,
+    /// See [`Output::yes`](crate::output::Output::yes).
+    pub yes: bool,
+    /// See [`Output::dry_run`](crate::output::Output::dry_run), also disables hooks and the event
+    /// log.
+    pub dry_run: bool,
+    /// Runs the `version` command with the remaining arguments, `-V` or `--version`.
+    pub version: bool,
+}
+
+impl Options {
+    /// Parses options given before the command name, returns them along with the remaining
+    /// arguments. `--name` is also accepted among the arguments of the command.
+    pub fn build(args: &[String]) -> Result<(Options, Vec<String>), Box<dyn Error>> {
+        let mut options = Options::default();
+        let mut args = args;
+        while let Some(arg) = args.first() {
+            if !arg.starts_with("-") {
+                break;
+            }
+            match arg.as_str() {
+                "--profile" => {
+                    let Some(name) = args.get(1) else {
+                        return Err("no profile specified")?;
+                    };
+                    options.profile = Some(name.trim().to_owned());
+                    args = &args[2..];
+                }
+                "--name" => {
+                    let Some(name) = args.get(1) else {
+                        return Err("no workspace name specified")?;
+                    };
+                    options.name = Some(Options::parse_name(name)?);
+                    args = &args[2..];
+                }
+                "--no-hooks" => {
+                    options.no_hooks = true;
+                    args = &args[1..];
+                }
+                "-q" | "--quiet" => {
+                    options.quiet = true;
+                    args = &args[1..];
+                }
+                "-v" | "--verbose" => {
+                    options.verbose = true;
+                    args = &args[1..];
+                }
+                "-y" | "--yes" => {
+                    options.yes = true;
+                    args = &args[1..];
+                }
+                "--dry-run" => {
+                    options.dry_run = true;
+                    args = &args[1..];
+                }
+                // The rest are arguments of the `version` command.
+                "-V" | "--version" => {
+                    options.version = true;
+                    args = &args[1..];
+                    break;
+                }
+                arg => return Err(format!("unrecognized option `{arg}`"))?,
+            }
+        }
+        if options.quiet && options.verbose {
+            return Err("`--quiet` can't be used with `--verbose`")?;
+        }
+        let mut args = args.to_vec();
+        if let Some(i) = args.iter().position(|v| v.trim() == "--name") {
+            let Some(name) = args.get(i + 1) else {
+                return Err("no workspace name specified")?;
+            };
+            if options.name.is_some() {
+                return Err("workspace name specified twice")?;
+            }
+            options.name = Some(Options::parse_name(name)?);
+            args.drain(i..i + 2);
+        }
+        Ok((options, args))
+    }
+
+    fn parse_name(text: &str) -> Result<String, String> {
+        let text = text.trim();
+        let is_valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if text.is_empty() || !text.chars().all(is_valid) {
+            return Err(format!(
+                "invalid workspace name `{text}`, use letters, digits, `-` and `_`"
+            ));
+        }
+        Ok(text.to_owned())
+    }
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Action {
-    Start { date: DateTime },
-    Mark { date: DateTime },
-    Remark { date: DateTime },
-    Unmark,
-    Path,
-    View,
-    Attribute { attribute: Attribute },
-    Tag { tag: Tag },
-    Untag { tag: Tag },
-    Write { text: String },
-    Version,
+    Start {
+        date: DateTime,
+    },
+    /// Starts a session with the title and tags of the previous one, see
+    /// [`Session::derive_from`].
+    Continue {
+        date: DateTime,
+    },
+    Mark {
+        date: DateTime,
+        future: bool,
+    },
+    /// Moves the last mark, the first one with `first`. `last` allows editing a session that has
+    /// already ended, the same for the other commands editing the last mark.
+    Remark {
+        date: DateTime,
+        future: bool,
+        last: bool,
+        first: bool,
+    },
+    /// Removes the selected mark, `force` allows removing the stop mark.
+    Unmark {
+        mark: MarkSelector,
+        force: bool,
+    },
+    /// Starts a break, see [`Session::pause`].
+    Pause {
+        date: DateTime,
+    },
+    Unpause {
+        date: DateTime,
+    },
+    /// Records an interruption after the fact, see [`Session::insert_idle`].
+    Idle {
+        start: DateTime,
+        end: DateTime,
+    },
+    Path {
+        target: PathTarget,
+    },
+    /// `porcelain` prints stable `key<TAB>value` lines for scripts, see
+    /// [`Status::render_porcelain`](crate::status::Status::render_porcelain).
+    View {
+        porcelain: bool,
+    },
+    /// Redraws `view` every `interval` until interrupted.
+    Watch {
+        interval: Duration,
+    },
+    /// Single line summary of the session shown by `view`, as JSON for Waybar with `waybar`.
+    Status {
+        waybar: bool,
+    },
+    /// `force` reopens an ended session without asking.
+    Attribute {
+        attribute: Attribute,
+        force: bool,
+    },
+    /// `session` applies the tag to the whole session instead of the last mark.
+    Tag {
+        tag: Tag,
+        session: bool,
+        mark: MarkSelector,
+        last: bool,
+    },
+    Untag {
+        tag: Tag,
+        session: bool,
+        mark: MarkSelector,
+        last: bool,
+    },
+    /// Without `mode`, asks before overwriting the text already there.
+    Write {
+        text: String,
+        target: WriteTarget,
+        mode: Option<WriteMode>,
+        last: bool,
+    },
+    Title {
+        title: String,
+    },
+    /// `index` selects the backup to restore, counted from the newest one starting at 1. The
+    /// backups are listed and the user is asked when it's not given.
+    Restore {
+        index: Option<usize>,
+    },
+    /// `fix` fixes the problems that can be fixed safely.
+    Validate {
+        fix: bool,
+    },
+    /// Rewrites session files in the canonical layout, only the newest one with `current`.
+    /// `check` only lists the files that would change.
+    Fmt {
+        current: bool,
+        check: bool,
+    },
+    /// Opens the last session file in the editor, the config file with `config`.
+    Edit {
+        config: bool,
+    },
+    /// Prints the value `key` has in the config file.
+    ConfigGet {
+        key: String,
+    },
+    /// Sets `key` to `value` in the config file, keeping the rest of the file.
+    ConfigSet {
+        key: String,
+        value: String,
+    },
+    /// Prints the top-level values of the config file.
+    ConfigList,
+    /// Asks for the sessions directory and writes the config file, or updates the sessions
+    /// directory of an existing one.
+    Init,
+    /// Rewrites session files of older format versions in the current one.
+    Migrate,
+    /// Prints the event log, from the start of `since` when given.
+    Events {
+        since: Option<chrono::NaiveDate>,
+    },
+    /// Imports sessions from a CSV file, see [`import::parse_csv`](crate::import::parse_csv).
+    Import {
+        path: PathBuf,
+        one_session_per_row: bool,
+        /// Imports the valid rows when some are invalid instead of importing nothing.
+        skip_invalid: bool,
+    },
+    /// Renames a tag in all sessions, see [`Session::retag`].
+    Retag {
+        from: Tag,
+        to: Tag,
+        dry_run: bool,
+    },
+    /// Combines two sessions into the earlier one, see [`Session::merge`]. The last two sessions
+    /// when `paths` aren't given.
+    Merge {
+        paths: Option<(PathBuf, PathBuf)>,
+        /// Merges sessions further apart than [`Config::merge_max_gap`].
+        force: bool,
+        /// Skips the time between the sessions instead of tracking it.
+        skip_gap: bool,
+    },
+    /// Splits the session in progress at `date` in two, see [`Session::split`].
+    Split {
+        date: DateTime,
+    },
+    /// Moves stopped sessions that ended before the day `before` to the archive, see
+    /// [`Session::archive`].
+    Archive {
+        before: chrono::NaiveDate,
+    },
+    /// Moves sessions that hold nothing to the trash, see [`Session::is_empty`].
+    Prune {
+        dry_run: bool,
+        /// Skips the confirmation.
+        yes: bool,
+    },
+    /// Time per day, or per week when `weeks` is given.
+    Graph {
+        weeks: Option<usize>,
+    },
+    /// Calendar of the month of `day`, the current month when not given.
+    Calendar {
+        day: Option<chrono::NaiveDate>,
+    },
+    /// Statistics of sessions started from the day `first` through the day `last`.
+    Stats {
+        first: Option<chrono::NaiveDate>,
+        last: Option<chrono::NaiveDate>,
+        include_archived: bool,
+        /// Adds the time of each context, see [`Mark::context`](crate::session::Mark::context).
+        by_context: bool,
+    },
+    /// Time left in the current work interval, see [`Config::pomodoro`].
+    Pomodoro,
+    /// Time since the last mark, the start and when today's share of the weekly goal is reached.
+    When,
+    /// Toggles whether the last mark is billable.
+    Billable {
+        last: bool,
+    },
+    /// Sets the context of the last mark, removes it when `None`, see [`Session::set_context`].
+    At {
+        context: Option<String>,
+        last: bool,
+    },
+    /// Billable time and amount of the month starting at `month`, the current month when `None`.
+    Billing {
+        month: Option<chrono::NaiveDate>,
+    },
+    /// Sessions of `day` with the gaps between them, today when `None`.
+    Gaps {
+        day: Option<chrono::NaiveDate>,
+    },
+    /// Total and average time of each weekday in the last `weeks` weeks, averaged over the days
+    /// with any time when `nonzero`.
+    Weekdays {
+        weeks: Option<usize>,
+        nonzero: bool,
+    },
+    /// Time tracked in each hour of the day by sessions from `first` through `last`, all of them
+    /// when `None`.
+    Hours {
+        first: Option<chrono::NaiveDate>,
+        last: Option<chrono::NaiveDate>,
+    },
+    /// Lists the goals of tags with their progress.
+    Goals,
+    /// Sets the goal of `tag` for a period, or clears all of its goals when `goal` isn't given.
+    Goal {
+        tag: Tag,
+        goal: Option<(Goal, Period)>,
+    },
+    /// Version with the commit and target it was built from, as JSON with `json`.
+    Version {
+        json: bool,
+    },
     // Set,
 }
 
+#[derive(PartialEq, Debug)]
+pub enum PathTarget {
+    Session,
+    /// Newest session that isn't active, see [`Session::get_last_stopped`].
+    LastStopped,
+    /// See [`Config::sessions_path`].
+    Dir,
+    Config,
+}
+
 impl Action {
     pub fn build(name: &str, args: &[String]) -> Result<Action, Box<dyn Error>> {
         let out = match name {
-            "start" => match args.len() {
-                0 => Action::Start {
-                    date: DateTime::now(),
-                },
-                1 => Action::Start {
-                    date: DateTime::now().modify(&args[0])?,
-                },
-                _ => return Err("too many arguments")?,
+            "start" => Action::Start {
+                date: Action::parse_date(args)?,
             },
-            "mark" => match args.len() {
-                0 => Action::Mark {
-                    date: DateTime::now(),
-                },
-                1 => Action::Mark {
-                    date: DateTime::now().modify(&args[0])?,
-                },
-                _ => return Err("too many arguments")?,
+            "continue" => Action::Continue {
+                date: Action::parse_date(args)?,
             },
-            "remark" => match args.len() {
-                0 => Action::Remark {
-                    date: DateTime::now(),
-                },
-                1 => Action::Remark {
-                    date: DateTime::now().modify(&args[0])?,
-                },
-                _ => return Err("too many arguments")?,
+            "mark" | "remark" => {
+                let (future, args) = Action::take_flag(args, "--future");
+                let (last, args) = match name {
+                    "remark" => Action::take_flag(&args, "--last"),
+                    _ => (false, args),
+                };
+                let (first, args) = match name {
+                    "remark" => Action::take_flag(&args, "--first"),
+                    _ => (false, args),
+                };
+                let (start, args) = match name {
+                    "remark" => Action::take_flag(&args, "--start"),
+                    _ => (false, args),
+                };
+                let date = Action::parse_date(&args)?;
+                match name {
+                    "mark" => Action::Mark { date, future },
+                    "remark" => Action::Remark {
+                        date,
+                        future,
+                        last,
+                        first: first || start,
+                    },
+                    x => panic!("unreachable Action::Mark pattern {x}"),
+                }
+            }
+            "pause" => Action::Pause {
+                date: Action::parse_date(args)?,
             },
+            "unpause" => Action::Unpause {
+                date: Action::parse_date(args)?,
+            },
+            "idle" => {
+                let now = DateTime::now();
+                let (start, end) = match args {
+                    [time] => {
+                        if time.trim().starts_with('-') {
+                            return Err("idle duration can't be negative")?;
+                        }
+                        let time = Duration::from_text(time)?;
+                        (now.plus_seconds(-(time.as_secs() as i64)), now)
+                    }
+                    [start, end] => {
+                        let end = Action::parse_past_time(&now, end)?;
+                        (Action::parse_past_time(&end, start)?, end)
+                    }
+                    _ => return Err("idle takes a duration or a start and an end time")?,
+                };
+                Action::Idle { start, end }
+            }
             "unmark" => {
-                if args.len() != 0 {
+                let (force, args) = Action::take_flag(args, "--force");
+                let (mark, args) = Action::take_mark_selector(&args)?;
+                if !args.is_empty() {
                     return Err("too many arguments")?;
                 }
-                Action::Unmark
+                Action::Unmark { mark, force }
             }
             "path" => {
-                if args.len() != 0 {
+                let target = match args.len() {
+                    0 => PathTarget::Session,
+                    1 => match args[0].trim() {
+                        "--config" => PathTarget::Config,
+                        "--dir" => PathTarget::Dir,
+                        "--last-stopped" => PathTarget::LastStopped,
+                        arg => return Err(format!("unrecognized option `{arg}`"))?,
+                    },
+                    _ => return Err("too many arguments")?,
+                };
+                Action::Path { target }
+            }
+            "view" => {
+                let (porcelain, args) = Action::take_flag(args, "--porcelain");
+                if !args.is_empty() {
                     return Err("too many arguments")?;
                 }
-                Action::Path
+                Action::View { porcelain }
             }
-            "view" => {
-                if args.len() != 0 {
+            "watch" => {
+                let (interval, args) = Action::take_option(args, "--interval")?;
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                let interval = match interval {
+                    Some(text) => {
+                        let interval = match text.trim().parse() {
+                            Ok(secs) => Duration::from_secs(secs),
+                            Err(_) => Duration::from_text(&text)?,
+                        };
+                        if text.trim().starts_with('-') || interval == Duration::ZERO {
+                            return Err("interval must be longer than zero")?;
+                        }
+                        interval
+                    }
+                    None => WATCH_INTERVAL_DEFAULT,
+                };
+                Action::Watch { interval }
+            }
+            "status" => {
+                let (waybar, args) = Action::take_flag(args, "--waybar");
+                if !args.is_empty() {
                     return Err("too many arguments")?;
                 }
-                Action::View
+                Action::Status { waybar }
+            }
+            "attribute" => {
+                let (force, args) = Action::take_flag(args, "--force");
+                match args.len() {
+                    0 => Err("no attribute specified")?,
+                    // Removing the attribute has to be asked for by name.
+                    1 if args[0].trim().eq_ignore_ascii_case("none") => {
+                        Err("use `clear` to remove the attribute")?
+                    }
+                    1 => Action::Attribute {
+                        attribute: Attribute::from_text(&args[0])?,
+                        force,
+                    },
+                    _ => Err("too many arguments")?,
+                }
             }
-            "attribute" => match args.len() {
-                0 => Err("no attribute specified")?,
-                1 => Action::Attribute {
-                    attribute: Attribute::from_text(&args[0])?,
-                },
-                _ => Err("too many arguments")?,
-            },
             "tag" | "untag" => {
-                if args.len() == 0 {
+                let (session, args) = Action::take_flag(args, "--session");
+                let (last, args) = Action::take_flag(&args, "--last");
+                let (mark, args) = Action::take_mark_selector(&args)?;
+                if args.is_empty() {
                     return Err("no label specified")?;
                 } else if args.len() > 1 {
                     return Err("too many arguments")?;
                 }
+                if session && mark != MarkSelector::Last {
+                    return Err("`--session` can't be used with `--mark` or `--at`")?;
+                }
                 let tag = Tag::from_text(&args[0])?;
                 match name {
-                    "tag" => Action::Tag { tag },
-                    "untag" => Action::Untag { tag },
+                    "tag" => Action::Tag {
+                        tag,
+                        session,
+                        mark,
+                        last,
+                    },
+                    "untag" => Action::Untag {
+                        tag,
+                        session,
+                        mark,
+                        last,
+                    },
                     x => panic!("unreachable Action::Label pattern {x}"),
                 }
             }
             "write" => {
-                if args.len() == 0 {
+                let (last, args) = Action::take_flag(args, "--last");
+                let (append, args) = Action::take_flag(&args, "--append");
+                let (title, args) = Action::take_flag(&args, "--title");
+                let (force, args) = Action::take_flag(&args, "--force");
+                let mode = match (append, title, force) {
+                    (true, true, _) => return Err("`--append` can't be used with `--title`")?,
+                    (_, false, true) => return Err("`--force` only applies to `--title`")?,
+                    (true, false, _) => Some(WriteMode::Append),
+                    (false, true, force) => Some(WriteMode::Title { force }),
+                    (false, false, _) => None,
+                };
+                let (session, args) = Action::take_flag(&args, "--session");
+                let (mark, args) = Action::take_mark_selector(&args)?;
+                let target = match (session, mark) {
+                    (true, MarkSelector::Last) => WriteTarget::Session,
+                    (true, _) => return Err("`--session` can't be used with `--mark` or `--at`")?,
+                    (false, mark) => WriteTarget::Mark(mark),
+                };
+                if args.is_empty() {
                     return Err("no text specified")?;
-                } else if args.len() > 1 {
-                    return Err("too many arguments")?;
                 }
                 let text = match args[0].trim() {
-                    "-b" => get_git_branch_name()?,
+                    "-c" | "--commit" => {
+                        if args.len() > 2 {
+                            return Err("too many arguments")?;
+                        }
+                        Action::get_commit_message(args.get(1).map(|v| v.trim()))?
+                    }
+                    _ if args.len() > 1 => return Err("too many arguments")?,
+                    "-b" => Action::get_branch_name()?,
                     text => text.to_owned(),
                 };
-                Action::Write { text }
+                Action::Write {
+                    text,
+                    target,
+                    mode,
+                    last,
+                }
             }
-            "version" => {
-                if args.len() != 0 {
+            "title" => {
+                if args.is_empty() {
+                    return Err("no title specified")?;
+                }
+                let title = Session::parse_title(&args.join(" "))?;
+                Action::Title { title }
+            }
+            "restore" => {
+                if args.len() > 1 {
                     return Err("too many arguments")?;
                 }
-                Action::Version
+                let index = args
+                    .first()
+                    .map(|v| v.trim())
+                    .map(|v| {
+                        v.parse()
+                            .map_err(|_| format!("invalid backup number `{v}`"))
+                    })
+                    .transpose()?;
+                Action::Restore { index }
             }
-            name => return Err(format!("unrecognized command `{name}`"))?,
-        };
-        Ok(out)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn config_from_file_works() {
-        let path = "./notes/sessions";
-        let contents = format!("{CONFIG_SESSIONS_PATH}='{path}'");
-        let config = Config {
-            sessions_path: PathBuf::from(&path),
-        };
-        assert_eq!(Config::from_file(&contents).unwrap(), config);
-    }
-
-    #[test]
-    fn config_from_file_fails_when_wrong_format() {
-        assert!(Config::from_file("sessions_path=''")
-            .unwrap_err()
-            .contains("sessions_path is empty"));
-    }
-
-    #[test]
-    fn action_build_works() -> Result<(), Box<dyn Error>> {
-        assert_eq!(
-            Action::build("start", &[])?,
-            Action::Start {
-                date: DateTime::now()
+            "validate" => {
+                let (fix, args) = Action::take_flag(args, "--fix");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Validate { fix }
             }
-        );
-        assert_eq!(
-            Action::build("start", &[String::from("0m")])?,
-            Action::Start {
-                date: DateTime::now()
+            "fmt" => {
+                let (current, args) = Action::take_flag(args, "--current");
+                let (check, args) = Action::take_flag(&args, "--check");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Fmt { current, check }
             }
-        );
-        assert!(Action::build("start", &[String::from("0m"), String::from("hello")]).is_err());
-        assert!(Action::build("start", &[String::from("hello")]).is_err());
-
-        assert_eq!(
-            Action::build("mark", &[])?,
-            Action::Mark {
-                date: DateTime::now()
+            "edit" => {
+                let (config, args) = Action::take_flag(args, "--config");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Edit { config }
             }
-        );
-        assert_eq!(
-            Action::build("mark", &[String::from("0m")])?,
-            Action::Mark {
-                date: DateTime::now()
+            "config" => match args {
+                [] => Err("no config command specified")?,
+                [command, rest @ ..] => match (command.trim(), rest) {
+                    ("get", [key]) => Action::ConfigGet {
+                        key: key.trim().to_owned(),
+                    },
+                    ("set", [key, value]) => Action::ConfigSet {
+                        key: key.trim().to_owned(),
+                        value: value.to_owned(),
+                    },
+                    ("list", []) => Action::ConfigList,
+                    ("get", []) | ("set", [] | [_]) => Err("not enough arguments")?,
+                    ("get" | "set" | "list", _) => Err("too many arguments")?,
+                    (command, _) => Err(format!(
+                        "unknown config command `{command}`, use `get`, `set` or `list`"
+                    ))?,
+                },
+            },
+            "init" => {
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Init
             }
-        );
-        assert!(Action::build("mark", &[String::from("0m"), String::from("hello")]).is_err());
-        assert!(Action::build("mark", &[String::from("hello")]).is_err());
-
-        assert_eq!(
-            Action::build("remark", &[])?,
-            Action::Remark {
-                date: DateTime::now()
+            "migrate" => {
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Migrate
             }
-        );
-        assert_eq!(
+            "events" => {
+                let (since, args) = Action::take_option(args, "--since")?;
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                let since = since.as_deref().map(Action::parse_day).transpose()?;
+                Action::Events { since }
+            }
+            "import" => {
+                let (one_session_per_row, args) = Action::take_flag(args, "--one-session-per-row");
+                let (skip_invalid, args) = Action::take_flag(&args, "--skip-invalid");
+                let [format, path] = &args[..] else {
+                    return Err("import takes the format and the file to import")?;
+                };
+                if format.trim() != "csv" {
+                    return Err(format!(
+                        "unsupported import format `{}`, use `csv`",
+                        format.trim()
+                    ))?;
+                }
+                Action::Import {
+                    path: resolve_path(path)?,
+                    one_session_per_row,
+                    skip_invalid,
+                }
+            }
+            "retag" => {
+                let (dry_run, args) = Action::take_flag(args, "--dry-run");
+                let [from, to] = &args[..] else {
+                    return Err("retag takes the tag to rename and its new name")?;
+                };
+                let from = Tag::from_text(from)?;
+                let to = Tag::from_text(to)?;
+                if from == to {
+                    return Err("tags are the same")?;
+                }
+                Action::Retag { from, to, dry_run }
+            }
+            "merge" => {
+                let (force, args) = Action::take_flag(args, "--force");
+                let (skip_gap, args) = Action::take_flag(&args, "--skip-gap");
+                let paths = match &args[..] {
+                    [] => None,
+                    [a, b] => Some((PathBuf::from(a.trim()), PathBuf::from(b.trim()))),
+                    _ => return Err("merge takes either two session files or nothing")?,
+                };
+                Action::Merge {
+                    paths,
+                    force,
+                    skip_gap,
+                }
+            }
+            "split" => {
+                // A bare time is in the past, where the session to split is.
+                let date = match args {
+                    [] => return Err("split takes the time to split the session at")?,
+                    [time] if chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M").is_ok() => {
+                        Action::parse_past_time(&DateTime::now(), time)?
+                    }
+                    _ => Action::parse_date(args)?,
+                };
+                Action::Split { date }
+            }
+            "archive" => {
+                let [before] = args else {
+                    return Err("archive takes the day to archive sessions before")?;
+                };
+                let today = DateTime::now().date.date_naive();
+                Action::Archive {
+                    before: Action::parse_cutoff(today, before)?,
+                }
+            }
+            "prune" => {
+                let (dry_run, args) = Action::take_flag(args, "--dry-run");
+                let (yes, args) = Action::take_flag(&args, "--yes");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Prune { dry_run, yes }
+            }
+            "graph" => {
+                let (weeks, args) = Action::take_option(args, "--weeks")?;
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                let weeks = weeks.as_deref().map(Action::parse_weeks).transpose()?;
+                Action::Graph { weeks }
+            }
+            "cal" => {
+                if args.len() > 1 {
+                    return Err("too many arguments")?;
+                }
+                let day = args.first().map(|v| Action::parse_month(v)).transpose()?;
+                Action::Calendar { day }
+            }
+            "stats" => {
+                let (include_archived, args) = Action::take_flag(args, "--include-archived");
+                let (by_context, args) = Action::take_flag(&args, "--by-context");
+                let (first, args) = Action::take_option(&args, "--from")?;
+                let (last, args) = Action::take_option(&args, "--to")?;
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                let first = first.as_deref().map(Action::parse_day).transpose()?;
+                let last = last.as_deref().map(Action::parse_day).transpose()?;
+                if first.zip(last).is_some_and(|(first, last)| first > last) {
+                    return Err("`--from` must not be after `--to`")?;
+                }
+                Action::Stats {
+                    first,
+                    last,
+                    include_archived,
+                    by_context,
+                }
+            }
+            "when" => {
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::When
+            }
+            "pomodoro" => match args {
+                [] => Action::Pomodoro,
+                _ => Err("too many arguments")?,
+            },
+            "billable" => {
+                let (last, args) = Action::take_flag(args, "--last");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Billable { last }
+            }
+            "at" => {
+                let (last, args) = Action::take_flag(args, "--last");
+                let (clear, args) = Action::take_flag(&args, "--clear");
+                match (clear, args.as_slice()) {
+                    (false, []) => Err("no context specified, use `--clear` to remove it")?,
+                    (false, args) => Action::At {
+                        context: Some(args.join(" ")),
+                        last,
+                    },
+                    (true, []) => Action::At {
+                        context: None,
+                        last,
+                    },
+                    (true, _) => Err("`--clear` doesn't take a context")?,
+                }
+            }
+            "report" => match args {
+                [] => Err("no report specified")?,
+                [kind, rest @ ..] if kind.trim() == "billing" => match rest {
+                    [] => Action::Billing { month: None },
+                    [month] => Action::Billing {
+                        month: Some(Action::parse_month(month)?),
+                    },
+                    _ => Err("too many arguments")?,
+                },
+                [kind, rest @ ..] if kind.trim() == "gaps" => match rest {
+                    [] => Action::Gaps { day: None },
+                    [day] => Action::Gaps {
+                        day: Some(Action::parse_day(day)?),
+                    },
+                    _ => Err("too many arguments")?,
+                },
+                [kind, rest @ ..] if kind.trim() == "weekdays" => {
+                    let (nonzero, rest) = Action::take_flag(rest, "--nonzero");
+                    let (weeks, rest) = Action::take_option(&rest, "--weeks")?;
+                    if !rest.is_empty() {
+                        return Err("too many arguments")?;
+                    }
+                    let weeks = weeks.as_deref().map(Action::parse_weeks).transpose()?;
+                    Action::Weekdays { weeks, nonzero }
+                }
+                [kind, rest @ ..] if kind.trim() == "hours" => {
+                    let (first, rest) = Action::take_option(rest, "--from")?;
+                    let (last, rest) = Action::take_option(&rest, "--to")?;
+                    if !rest.is_empty() {
+                        return Err("too many arguments")?;
+                    }
+                    let first = first.as_deref().map(Action::parse_day).transpose()?;
+                    let last = last.as_deref().map(Action::parse_day).transpose()?;
+                    if first.zip(last).is_some_and(|(first, last)| first > last) {
+                        return Err("`--from` must not be after `--to`")?;
+                    }
+                    Action::Hours { first, last }
+                }
+                [kind, ..] => Err(format!(
+                    "unknown report `{}`, use `billing`, `gaps`, `hours` or `weekdays`",
+                    kind.trim()
+                ))?,
+            },
+            "goal" => {
+                let (clear, args) = Action::take_flag(args, "--clear");
+                let (week, args) = Action::take_flag(&args, "--week");
+                let (month, args) = Action::take_flag(&args, "--month");
+                let Some(tag) = args.first() else {
+                    if clear || week || month {
+                        return Err("no tag specified")?;
+                    }
+                    return Ok(Action::Goals);
+                };
+                let tag = Tag::from_text(tag)?;
+                if week && month {
+                    return Err("`--week` and `--month` can't be used together")?;
+                }
+                let period = if month { Period::Month } else { Period::Week };
+                let goal = match (&args[1..], clear) {
+                    ([], true) if !week && !month => None,
+                    ([], true) => return Err("`--clear` removes goals of all periods")?,
+                    ([], false) => return Err("no goal specified")?,
+                    ([goal], false) => Some((Goal::from_text(goal)?, period)),
+                    ([_], true) => return Err("`--clear` takes no goal")?,
+                    _ => return Err("too many arguments")?,
+                };
+                Action::Goal { tag, goal }
+            }
+            "version" => {
+                let (json, args) = Action::take_flag(args, "--json");
+                if !args.is_empty() {
+                    return Err("too many arguments")?;
+                }
+                Action::Version { json }
+            }
+            name => return Err(format!("unrecognized command `{name}`"))?,
+        };
+        Ok(out)
+    }
+
+    /// Whether the action only writes session files through `save` and `create`, which show the
+    /// changes instead in a dry run. Actions that don't write anything are supported as well.
+    pub fn supports_dry_run(&self) -> bool {
+        !matches!(
+            self,
+            Action::Restore { .. }
+                | Action::Validate { fix: true }
+                | Action::Fmt { .. }
+                | Action::Edit { .. }
+                | Action::ConfigSet { .. }
+                | Action::Init
+                | Action::Migrate
+                | Action::Import { .. }
+                | Action::Archive { .. }
+                | Action::Prune { .. }
+                | Action::Goal { .. }
+        )
+    }
+
+    /// Tags are given before the config is read, they are normalized once it is.
+    pub fn normalize_tags(&mut self, normalizer: &TagNormalizer) {
+        match self {
+            Action::Tag { tag, .. } | Action::Untag { tag, .. } | Action::Goal { tag, .. } => {
+                *tag = normalizer.apply(tag);
+            }
+            Action::Retag { from, to, .. } => {
+                *from = normalizer.apply(from);
+                *to = normalizer.apply(to);
+            }
+            _ => (),
+        }
+    }
+
+    /// Dates can span multiple arguments (`2025-01-07 14:30`), no arguments means now.
+    fn parse_date(args: &[String]) -> Result<DateTime, String> {
+        if args.is_empty() {
+            return Ok(DateTime::now());
+        }
+        DateTime::now().parse_user_input(&args.join(" "))
+    }
+
+    /// Returns the closest `HH:MM` at or before `date`.
+    fn parse_past_time(date: &DateTime, text: &str) -> Result<DateTime, String> {
+        let text = text.trim();
+        chrono::NaiveTime::parse_from_str(text, "%H:%M")
+            .map_err(|_| format!("invalid time `{text}`, use `HH:MM`"))?;
+        date.modify(&format!("-{text}")).map_err(|e| e.to_string())
+    }
+
+    /// See [`git::get_commit_message`], run in the working directory.
+    #[cfg(feature = "git")]
+    fn get_commit_message(commit: Option<&str>) -> Result<String, Box<dyn Error>> {
+        Ok(git::get_commit_message(&env::current_dir()?, commit)?)
+    }
+
+    #[cfg(not(feature = "git"))]
+    fn get_commit_message(_commit: Option<&str>) -> Result<String, Box<dyn Error>> {
+        Err(GIT_NOT_COMPILED)?
+    }
+
+    /// See [`git::get_branch_name`], run in the working directory.
+    #[cfg(feature = "git")]
+    fn get_branch_name() -> Result<String, Box<dyn Error>> {
+        Ok(git::get_branch_name(&env::current_dir()?)?)
+    }
+
+    #[cfg(not(feature = "git"))]
+    fn get_branch_name() -> Result<String, Box<dyn Error>> {
+        Err(GIT_NOT_COMPILED)?
+    }
+
+    /// Accepts a day `YYYY-MM-DD` or an age relative to `today`, a number of days, weeks, months
+    /// or years, `30d`, `2w`, `6m` or `1y`.
+    fn parse_cutoff(today: chrono::NaiveDate, text: &str) -> Result<chrono::NaiveDate, String> {
+        let text = text.trim();
+        if let Ok(day) = Action::parse_day(text) {
+            return Ok(day);
+        }
+        let invalid = || format!("invalid cutoff `{text}`, use `YYYY-MM-DD` or an age like `6m`");
+        let (count, unit) =
+            text.split_at(text.len() - text.chars().last().map_or(0, char::len_utf8));
+        let count: u32 = count.parse().map_err(|_| invalid())?;
+        let day = match unit {
+            "d" => today.checked_sub_days(chrono::Days::new(count.into())),
+            "w" => today.checked_sub_days(chrono::Days::new(u64::from(count) * 7)),
+            "m" => today.checked_sub_months(chrono::Months::new(count)),
+            "y" => count
+                .checked_mul(12)
+                .and_then(|v| today.checked_sub_months(chrono::Months::new(v))),
+            _ => return Err(invalid()),
+        };
+        day.ok_or_else(invalid)
+    }
+
+    fn parse_day(text: &str) -> Result<chrono::NaiveDate, String> {
+        chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("invalid date `{}`, use `YYYY-MM-DD`", text.trim()))
+    }
+
+    /// Returns the value following `option` in `args`, if present, along with the rest of the
+    /// arguments.
+    fn take_option(args: &[String], option: &str) -> Result<(Option<String>, Vec<String>), String> {
+        let Some(i) = args.iter().position(|v| v.trim() == option) else {
+            return Ok((None, args.to_vec()));
+        };
+        let Some(value) = args.get(i + 1) else {
+            return Err(format!("no value specified for `{option}`"));
+        };
+        let mut rest = args.to_vec();
+        rest.drain(i..i + 2);
+        Ok((Some(value.to_owned()), rest))
+    }
+
+    /// `YYYY-MM`, returns the first day of the month.
+    fn parse_month(text: &str) -> Result<chrono::NaiveDate, String> {
+        let text = text.trim();
+        chrono::NaiveDate::parse_from_str(&format!("{text}-01"), "%F")
+            .map_err(|_| format!("invalid month `{text}`, use `YYYY-MM`"))
+    }
+
+    /// Mark selected with `--mark <index>` or `--at <HH:MM>`, the last mark without them, along
+    /// with the rest of the arguments.
+    fn take_mark_selector(args: &[String]) -> Result<(MarkSelector, Vec<String>), String> {
+        let (index, args) = Action::take_option(args, "--mark")?;
+        let (at, args) = Action::take_option(&args, "--at")?;
+        let mark = match (index, at) {
+            (Some(_), Some(_)) => return Err(String::from("`--mark` can't be used with `--at`")),
+            (Some(index), None) => {
+                let index = index.trim();
+                MarkSelector::Index(
+                    index
+                        .parse()
+                        .map_err(|_| format!("invalid mark `{index}`, use a number like `-2`"))?,
+                )
+            }
+            (None, Some(at)) => MarkSelector::At(Action::parse_past_time(&DateTime::now(), &at)?),
+            (None, None) => MarkSelector::Last,
+        };
+        Ok((mark, args))
+    }
+
+    /// Positive number of weeks.
+    fn parse_weeks(text: &str) -> Result<usize, String> {
+        text.trim()
+            .parse()
+            .ok()
+            .filter(|v| *v > 0)
+            .ok_or_else(|| format!("invalid number of weeks `{}`", text.trim()))
+    }
+
+    /// Returns whether `flag` is present in `args` along with the rest of the arguments.
+    fn take_flag(args: &[String], flag: &str) -> (bool, Vec<String>) {
+        let rest: Vec<String> = args.iter().filter(|v| v.trim() != flag).cloned().collect();
+        (rest.len() != args.len(), rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn config_from_file_works() {
+        let path = "./notes/sessions";
+        let contents = format!("{CONFIG_SESSIONS_PATH}='{path}'");
+        let config = Config {
+            sessions_path: PathBuf::from(&path),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_file(&contents, None).unwrap(), config);
+        let contents = format!("# Comment\n\n{CONFIG_SESSIONS_PATH} = \"{path}\"\n");
+        assert_eq!(Config::from_file(&contents, None).unwrap(), config);
+    }
+
+    #[test]
+    fn config_from_file_fails_when_wrong_format() {
+        assert!(Config::from_file("sessions_path=''", None)
+            .unwrap_err()
+            .contains("sessions_path is empty"));
+        assert!(Config::from_file("", None)
+            .unwrap_err()
+            .contains("sessions_path is empty"));
+        assert!(Config::from_file("sessions_path", None).is_err());
+        assert!(Config::from_file("sessions_path=./notes", None).is_err());
+        assert!(Config::from_file("sessions_path='./notes\"", None).is_err());
+        assert!(
+            Config::from_file("sessions_path='./notes'\nhello='world'", None)
+                .unwrap_err()
+                .contains("unknown config key `hello`")
+        );
+        assert!(Config::from_file("sessions_path='./notes'\n[hello]", None)
+            .unwrap_err()
+            .contains("unknown config section `hello`"));
+    }
+
+    #[test]
+    fn config_from_file_parses_day_starts_at() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.day_starts_at, chrono::NaiveTime::default());
+        let config =
+            Config::from_file("sessions_path='./notes'\nday_starts_at='04:30'", None).unwrap();
+        assert_eq!(
+            config.day_starts_at,
+            chrono::NaiveTime::from_hms_opt(4, 30, 0).unwrap()
+        );
+        assert!(Config::from_file("sessions_path='./notes'\nday_starts_at='25:00'", None).is_err());
+        assert!(Config::from_file("sessions_path='./notes'\nday_starts_at='4'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_duration_format() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.duration_format, DurationFormat::Hms);
+        let config =
+            Config::from_file("sessions_path='./notes'\nduration_format='decimal'", None).unwrap();
+        assert_eq!(config.duration_format, DurationFormat::Decimal);
+        assert!(
+            Config::from_file("sessions_path='./notes'\nduration_format='hours'", None).is_err()
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_timestamp_format() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.timestamp_format, TimestampFormat::Relative);
+        let config =
+            Config::from_file("sessions_path='./notes'\ntimestamp_format='absolute'", None)
+                .unwrap();
+        assert_eq!(config.timestamp_format, TimestampFormat::Absolute);
+        assert!(
+            Config::from_file("sessions_path='./notes'\ntimestamp_format='iso'", None).is_err()
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_display_prefs() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.display, DisplayPrefs::default());
+        let config = Config::from_file(
+            "sessions_path='./notes'\nclock='12'\ndate_style='long'",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.display,
+            DisplayPrefs {
+                clock: Clock::H12,
+                date_style: DateStyle::Long
+            }
+        );
+        assert!(Config::from_file("sessions_path='./notes'\nclock='13'", None).is_err());
+        assert!(Config::from_file("sessions_path='./notes'\ndate_style='us'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_backups() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.backups, CONFIG_BACKUPS_DEFAULT);
+        let config = Config::from_file("sessions_path='./notes'\nbackups = 0", None).unwrap();
+        assert_eq!(config.backups, 0);
+        assert!(Config::from_file("sessions_path='./notes'\nbackups='3'", None).is_err());
+        assert!(Config::from_file("sessions_path='./notes'\nbackups=-1", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_week_split() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert!(!config.week_split);
+        let config = Config::from_file("sessions_path='./notes'\nweek_split = true", None).unwrap();
+        assert!(config.week_split);
+        assert!(Config::from_file("sessions_path='./notes'\nweek_split='true'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_view_template() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.view_template, ViewTemplate::default());
+        let config = Config::from_file(
+            "sessions_path='./notes'\nview_template = \"{start}\\n{week}\"",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.view_template,
+            ViewTemplate::parse("{start}\n{week}").unwrap()
+        );
+        assert_eq!(
+            Config::from_file(
+                "sessions_path='./notes'\nview_template = '{start} {branch}'",
+                None
+            ),
+            Err(String::from(
+                "invalid view_template: unknown placeholder `{branch}`"
+            ))
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_color() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.color, ColorMode::Auto);
+        let config = Config::from_file("sessions_path='./notes'\ncolor = 'never'", None).unwrap();
+        assert_eq!(config.color, ColorMode::Never);
+        assert!(Config::from_file("sessions_path='./notes'\ncolor = 'blue'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_tag_aliases() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.tag_normalizer, TagNormalizer::default());
+        let config = Config::from_file(
+            "\
+            sessions_path='./notes'\n\
+            tags_case_insensitive = true\n\
+            untracked_tags = ['Break']\n\
+            [tag_aliases]\n\
+            ta = \"time tracker\"\n\
+            'c a' = 'client-alpha'\n\
+            ta = 'Time Tracker'\n\
+            ",
+            None,
+        )
+        .unwrap();
+        let tag = |v| Tag::from_text(v).unwrap();
+        assert_eq!(
+            config.tag_normalizer,
+            TagNormalizer {
+                aliases: vec![
+                    (String::from("c a"), tag("client-alpha")),
+                    (String::from("ta"), tag("Time Tracker")),
+                ],
+                case_insensitive: true,
+            }
+        );
+        assert_eq!(config.untracked_tags, vec![tag("break")]);
+        assert_eq!(config.tag_normalizer.apply(&tag("TA")), tag("time tracker"));
+        assert!(
+            Config::from_file("sessions_path='./notes'\n[tag_aliases]\nta = time", None).is_err()
+        );
+        assert!(
+            Config::from_file("sessions_path='./notes'\n[tag_aliases]\nta = '`'", None).is_err()
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_untracked_tags() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert!(config.untracked_tags.is_empty());
+        let config = Config::from_file(
+            "sessions_path='./notes'\nuntracked_tags = [\"break\", 'lunch, late' ,]",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.untracked_tags,
+            vec![
+                Tag::from_text("break").unwrap(),
+                Tag::from_text("lunch, late").unwrap()
+            ]
+        );
+        let config =
+            Config::from_file("sessions_path='./notes'\nuntracked_tags = []", None).unwrap();
+        assert!(config.untracked_tags.is_empty());
+        for value in ["'break'", "[break]", "['break' 'lunch']", "['break", "['']"] {
+            let contents = format!("sessions_path='./notes'\nuntracked_tags = {value}");
+            assert!(Config::from_file(&contents, None).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn config_from_file_parses_event_log() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert!(!config.event_log);
+        let config = Config::from_file("sessions_path='./notes'\nevent_log = true", None).unwrap();
+        assert!(config.event_log);
+    }
+
+    #[test]
+    fn config_template_works() {
+        let contents = Config::get_template("~/notes/it's").unwrap();
+        let config = Config::from_file(&contents, None).unwrap();
+        assert_eq!(config.sessions_path, resolve_path("~/notes/it's").unwrap());
+        assert_eq!(config.backups, CONFIG_BACKUPS_DEFAULT);
+        assert!(Config::get_template("'\"").is_err());
+    }
+
+    #[test]
+    fn config_set_sessions_path_works() {
+        let contents = "# Notes.\nsessions_path = './old'\nbackups = 2\n\n[profile.work]\nsessions_path = './work'";
+        assert_eq!(
+            Config::set_sessions_path(contents, "./new").unwrap(),
+            "# Notes.\nsessions_path = './new'\nbackups = 2\n\n[profile.work]\nsessions_path = './work'\n"
+        );
+        assert_eq!(
+            Config::set_sessions_path("[profile.work]\nsessions_path = './work'\n", "./new")
+                .unwrap(),
+            "sessions_path = './new'\n[profile.work]\nsessions_path = './work'\n"
+        );
+        assert_eq!(
+            Config::set_sessions_path("", "./new").unwrap(),
+            "sessions_path = './new'\n"
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_verbose_changes() {
+        let config = Config::from_file("sessions_path = './s'", None).unwrap();
+        assert!(!config.verbose_changes);
+        let config =
+            Config::from_file("sessions_path = './s'\nverbose_changes = true", None).unwrap();
+        assert!(config.verbose_changes);
+        assert!(Config::from_file("sessions_path = './s'\nverbose_changes = 'yes'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_record_host() {
+        let config = Config::from_file("sessions_path = './s'", None).unwrap();
+        assert!(!config.record_host);
+        let config = Config::from_file("sessions_path = './s'\nrecord_host = true", None).unwrap();
+        assert!(config.record_host);
+        assert!(Config::from_file("sessions_path = './s'\nrecord_host = 1", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_encrypt_contents() {
+        let config = Config::from_file("sessions_path = './s'", None).unwrap();
+        assert!(!config.encrypt_contents);
+        let config = Config::from_file("sessions_path = './s'\nencrypt_contents = false", None);
+        assert!(!config.unwrap().encrypt_contents);
+        let config = Config::from_file("sessions_path = './s'\nencrypt_contents = true", None);
+        assert_eq!(config.is_ok(), crypto::is_supported());
+    }
+
+    #[test]
+    fn config_from_file_parses_commands() {
+        let config = Config::from_file(
+            "sessions_path = './s'\neditor = \"code --wait\"\non_stop = 'notify-send \"Stopped\"'",
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.editor, Some(String::from("code --wait")));
+        assert_eq!(
+            config.hooks.on_stop,
+            Some(String::from("notify-send \"Stopped\""))
+        );
+        assert!(Config::from_file("sessions_path = './s'\neditor = ''", None).is_err());
+        assert!(Config::from_file("sessions_path = './s'\non_stop = \"echo 'hi\"", None).is_err());
+    }
+
+    #[test]
+    fn config_keys_are_known() {
+        for key in CONFIG_KEYS {
+            let err = Config::default().set(key, "", None).err();
+            assert_ne!(err, Some(format!("unknown config key `{key}`")));
+        }
+    }
+
+    #[test]
+    fn config_get_value_works() {
+        let contents = "backups = 2\nbackups = 3\n[profile.work]\nweekly_goal = '10h'";
+        assert_eq!(
+            Config::get_value(contents, "backups"),
+            Ok(Some(String::from("3")))
+        );
+        assert_eq!(Config::get_value(contents, "weekly_goal"), Ok(None));
+        assert!(Config::get_value(contents, "backup").is_err());
+        assert_eq!(
+            Config::get_values(contents),
+            Ok(vec![
+                (String::from("backups"), String::from("2")),
+                (String::from("backups"), String::from("3"))
+            ])
+        );
+    }
+
+    #[test]
+    fn config_set_value_works() {
+        let contents =
+            "# Notes.\nsessions_path = './s'\n# backups = 5\n\n[profile.work]\nbackups = 1";
+        assert_eq!(
+            Config::set_value(contents, "backups", "2").unwrap(),
+            "# Notes.\nsessions_path = './s'\nbackups = 2\n# backups = 5\n\n[profile.work]\nbackups = 1\n"
+        );
+        assert_eq!(
+            Config::set_value(contents, "weekly_goal", "40h").unwrap(),
+            "# Notes.\nsessions_path = './s'\nweekly_goal = '40h'\n# backups = 5\n\n[profile.work]\nbackups = 1\n"
+        );
+        assert_eq!(
+            Config::set_value(contents, "currency", "100").unwrap(),
+            "# Notes.\nsessions_path = './s'\ncurrency = '100'\n# backups = 5\n\n[profile.work]\nbackups = 1\n"
+        );
+        assert_eq!(
+            Config::set_value("backups = 1\nbackups = 2\n", "backups", "3").unwrap(),
+            "backups = 1\nbackups = 3\n"
+        );
+        assert_eq!(
+            Config::set_value("", "untracked_tags", "['break']").unwrap(),
+            "untracked_tags = ['break']\n"
+        );
+        assert!(Config::set_value(contents, "backup", "2").is_err());
+        assert!(Config::set_value(contents, "backups", "-1").is_err());
+        assert!(Config::set_value(contents, "weekly_goal", "40 hours").is_err());
+        assert!(Config::set_value(contents, "day_starts_at", "'25:00'").is_err());
+        assert!(Config::set_value(contents, "week_split", "yes").is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_create_missing_dirs() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert!(!config.create_missing_dirs);
+        let config =
+            Config::from_file("sessions_path='./notes'\ncreate_missing_dirs = true", None).unwrap();
+        assert!(config.create_missing_dirs);
+    }
+
+    #[cfg(feature = "git")]
+    #[test]
+    fn config_from_file_parses_auto_branch_tag() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert!(!config.auto_branch_tag);
+        let config =
+            Config::from_file("sessions_path='./notes'\nauto_branch_tag = true", None).unwrap();
+        assert!(config.auto_branch_tag);
+        assert!(
+            Config::from_file("sessions_path='./notes'\nauto_branch_tag = 'yes'", None).is_err()
+        );
+    }
+
+    #[cfg(not(feature = "git"))]
+    #[test]
+    fn config_from_file_rejects_auto_branch_tag_without_git() {
+        let config =
+            Config::from_file("sessions_path='./notes'\nauto_branch_tag = false", None).unwrap();
+        assert!(!config.auto_branch_tag);
+        let err =
+            Config::from_file("sessions_path='./notes'\nauto_branch_tag = true", None).unwrap_err();
+        assert!(err.to_string().contains(GIT_NOT_COMPILED));
+    }
+
+    #[test]
+    fn config_from_file_parses_hooks() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.hooks, Hooks::default());
+        let contents = "sessions_path='./notes'\non_stop = 'notify-send \"Stopped\"'\non_mark = ''";
+        let config = Config::from_file(contents, None).unwrap();
+        assert_eq!(
+            config.hooks,
+            Hooks {
+                on_stop: Some(String::from("notify-send \"Stopped\"")),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn config_from_file_parses_auto_stop_after() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.auto_stop_after, None);
+        let config =
+            Config::from_file("sessions_path='./notes'\nauto_stop_after = '10h'", None).unwrap();
+        assert_eq!(
+            config.auto_stop_after,
+            Some(Duration::from_secs(10 * 60 * 60))
+        );
+        assert!(
+            Config::from_file("sessions_path='./notes'\nauto_stop_after = '0h'", None).is_err()
+        );
+        assert!(
+            Config::from_file("sessions_path='./notes'\nauto_stop_after = '-1h'", None).is_err()
+        );
+        assert!(Config::from_file("sessions_path='./notes'\nauto_stop_after = 10", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_merge_max_gap() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.merge_max_gap, CONFIG_MERGE_MAX_GAP_DEFAULT);
+        let config =
+            Config::from_file("sessions_path='./notes'\nmerge_max_gap = '2h'", None).unwrap();
+        assert_eq!(config.merge_max_gap, Duration::from_secs(2 * 60 * 60));
+        let config =
+            Config::from_file("sessions_path='./notes'\nmerge_max_gap = '0m'", None).unwrap();
+        assert_eq!(config.merge_max_gap, Duration::ZERO);
+        assert!(Config::from_file("sessions_path='./notes'\nmerge_max_gap = '-1h'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_gap_threshold() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.gap_threshold, CONFIG_GAP_THRESHOLD_DEFAULT);
+        let config =
+            Config::from_file("sessions_path='./notes'\ngap_threshold = '5m'", None).unwrap();
+        assert_eq!(config.gap_threshold, Duration::from_secs(5 * 60));
+        assert!(Config::from_file("sessions_path='./notes'\ngap_threshold = '0m'", None).is_err());
+        assert!(Config::from_file("sessions_path='./notes'\ngap_threshold = '-5m'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_pomodoro() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.pomodoro, None);
+        let config =
+            Config::from_file("sessions_path='./notes'\npomodoro = \"50m\"", None).unwrap();
+        assert_eq!(config.pomodoro, Some(Duration::from_secs(50 * 60)));
+        for value in ["'0m'", "'-50m'", "50"] {
+            let contents = format!("sessions_path='./notes'\npomodoro = {value}");
+            assert!(Config::from_file(&contents, None).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn config_from_file_parses_billing() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.hourly_rate, None);
+        assert_eq!(config.currency, "");
+        let config = Config::from_file(
+            "sessions_path='./notes'\nhourly_rate = 85.5\ncurrency = \"EUR\"",
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.hourly_rate, Some(85.5));
+        assert_eq!(config.currency, "EUR");
+        let config = Config::from_file("sessions_path='./notes'\nhourly_rate = 85", None).unwrap();
+        assert_eq!(config.hourly_rate, Some(85.0));
+        for rate in ["-1", "'85'", "NaN", "inf", ""] {
+            let contents = format!("sessions_path='./notes'\nhourly_rate = {rate}");
+            assert!(Config::from_file(&contents, None).is_err(), "{rate}");
+        }
+        assert!(Config::from_file("sessions_path='./notes'\ncurrency = EUR", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_weekly_goal() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.weekly_goal, None);
+        let config =
+            Config::from_file("sessions_path='./notes'\nweekly_goal = '30h'", None).unwrap();
+        assert_eq!(config.weekly_goal, Some(Goal::from_text("30h").unwrap()));
+        assert!(Config::from_file("sessions_path='./notes'\nweekly_goal = '0h'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_parses_rounding() {
+        let config = Config::from_file("sessions_path='./notes'", None).unwrap();
+        assert_eq!(config.rounding, Duration::ZERO);
+        let config =
+            Config::from_file("sessions_path='./notes'\nrounding = \"15m\"", None).unwrap();
+        assert_eq!(config.rounding, Duration::from_secs(15 * 60));
+        assert!(Config::from_file("sessions_path='./notes'\nrounding='15'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_file_selects_profile() {
+        let contents = "\
+            sessions_path='./notes/sessions'\n\
+            \n\
+            [profile.work]\n\
+            sessions_path='./work/sessions'\n\
+            \n\
+            [profile.personal]\n\
+            ";
+        assert_eq!(
+            Config::from_file(contents, None).unwrap().sessions_path,
+            PathBuf::from("./notes/sessions")
+        );
+        assert_eq!(
+            Config::from_file(contents, Some("work"))
+                .unwrap()
+                .sessions_path,
+            PathBuf::from("./work/sessions")
+        );
+        // Falls back to the top-level value when the profile doesn't override it.
+        assert_eq!(
+            Config::from_file(contents, Some("personal"))
+                .unwrap()
+                .sessions_path,
+            PathBuf::from("./notes/sessions")
+        );
+        assert_eq!(
+            Config::from_file(contents, Some("hobby")).unwrap_err(),
+            "unknown profile `hobby`, available profiles: `work`, `personal`"
+        );
+        assert!(Config::from_file("[profile.work]\nsessions_path='./work'", Some("work")).is_ok());
+        assert!(Config::from_file("[profile.work]\nsessions_path='./work'", None).is_err());
+    }
+
+    #[test]
+    fn config_from_files_overrides_with_local_file() {
+        let global = ConfigFile::parse(
+            "sessions_path='./notes/sessions'\n[profile.work]\nsessions_path='./work/sessions'",
+        )
+        .unwrap();
+        let mut local = ConfigFile::parse("sessions_path='notes'").unwrap();
+        local.base_dir = Some(PathBuf::from("/projects/tracker"));
+        let config = Config::from_files(&[global, local], None).unwrap();
+        assert_eq!(
+            config.sessions_path,
+            PathBuf::from("/projects/tracker/notes")
+        );
+
+        // Empty global value is fine as long as the local file sets it.
+        let global = ConfigFile::parse("sessions_path=''").unwrap();
+        let mut local = ConfigFile::parse("sessions_path='/notes'").unwrap();
+        local.base_dir = Some(PathBuf::from("/projects/tracker"));
+        let config = Config::from_files(&[global, local], None).unwrap();
+        assert_eq!(config.sessions_path, PathBuf::from("/notes"));
+
+        let global = ConfigFile::parse("sessions_path='./notes/sessions'").unwrap();
+        let mut local = ConfigFile::parse("[profile.work]\nsessions_path='work'").unwrap();
+        local.base_dir = Some(PathBuf::from("/projects/tracker"));
+        let files = [global, local];
+        assert_eq!(
+            Config::from_files(&files, Some("work"))
+                .unwrap()
+                .sessions_path,
+            PathBuf::from("/projects/tracker/work")
+        );
+        assert_eq!(
+            Config::from_files(&files, None).unwrap().sessions_path,
+            PathBuf::from("./notes/sessions")
+        );
+    }
+
+    #[test]
+    fn config_find_local_works() {
+        let dir = testing::temp_dir("config_find_local_works");
+        let nested = dir.join("project").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        let global = dir.join(CONFIG_LOCAL_FILE_NAME);
+        fs::write(&global, "").unwrap();
+        assert_eq!(Config::find_local(&nested, &global), None);
+
+        let local = dir.join("project").join(CONFIG_LOCAL_FILE_NAME);
+        fs::write(&local, "").unwrap();
+        assert_eq!(Config::find_local(&nested, &global), Some(local.clone()));
+        assert_eq!(
+            Config::find_local(&dir.join("project"), &global),
+            Some(local)
+        );
+        assert_eq!(Config::find_local(&dir, &global), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn options_build_works() -> Result<(), Box<dyn Error>> {
+        let args = [String::from("view")];
+        assert_eq!(Options::build(&args)?, (Options::default(), args.to_vec()));
+
+        let args = [
+            String::from("--profile"),
+            String::from("work"),
+            String::from("mark"),
+            String::from("-5m"),
+        ];
+        let options = Options {
+            profile: Some(String::from("work")),
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[2..].to_vec()));
+
+        let args = [
+            String::from("--name"),
+            String::from("internal"),
+            String::from("view"),
+        ];
+        let options = Options {
+            name: Some(String::from("internal")),
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[2..].to_vec()));
+        let args = [
+            String::from("mark"),
+            String::from("--name"),
+            String::from("internal"),
+            String::from("-5m"),
+        ];
+        let options = Options {
+            name: Some(String::from("internal")),
+            ..Default::default()
+        };
+        assert_eq!(
+            Options::build(&args)?,
+            (options, vec![String::from("mark"), String::from("-5m")])
+        );
+
+        let args = [
+            String::from("-q"),
+            String::from("--profile"),
+            String::from("work"),
+            String::from("mark"),
+        ];
+        let options = Options {
+            profile: Some(String::from("work")),
+            quiet: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[3..].to_vec()));
+        let args = [String::from("--verbose"), String::from("view")];
+        let options = Options {
+            verbose: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[1..].to_vec()));
+        let args = [String::from("-y"), String::from("prune")];
+        let options = Options {
+            yes: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[1..].to_vec()));
+        let args = [String::from("--dry-run"), String::from("mark")];
+        let options = Options {
+            dry_run: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[1..].to_vec()));
+        let args = [String::from("-V"), String::from("--json")];
+        let options = Options {
+            version: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[1..].to_vec()));
+        let args = [String::from("--no-hooks"), String::from("mark")];
+        let options = Options {
+            no_hooks: true,
+            ..Default::default()
+        };
+        assert_eq!(Options::build(&args)?, (options, args[1..].to_vec()));
+        assert!(
+            Options::build(&[String::from("-q"), String::from("-v"), String::from("view")])
+                .is_err()
+        );
+
+        assert!(Options::build(&[String::from("--profile")]).is_err());
+        assert!(Options::build(&[String::from("view"), String::from("--name")]).is_err());
+        assert!(Options::build(&[String::from("--name"), String::from("a b")]).is_err());
+        assert!(Options::build(&[String::from("--name"), String::from("a/b")]).is_err());
+        assert!(Options::build(&[
+            String::from("--name"),
+            String::from("a"),
+            String::from("view"),
+            String::from("--name"),
+            String::from("b"),
+        ])
+        .is_err());
+        assert!(Options::build(&[String::from("--hello"), String::from("view")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn action_build_works() -> Result<(), Box<dyn Error>> {
+        let args = |v: &[&str]| v.iter().map(|v| v.to_string()).collect::<Vec<String>>();
+        assert_eq!(
+            Action::build("start", &[])?,
+            Action::Start {
+                date: DateTime::now()
+            }
+        );
+        assert_eq!(
+            Action::build("start", &[String::from("0m")])?,
+            Action::Start {
+                date: DateTime::now()
+            }
+        );
+        assert!(Action::build("start", &[String::from("0m"), String::from("hello")]).is_err());
+        assert_eq!(
+            Action::build("continue", &[])?,
+            Action::Continue {
+                date: DateTime::now()
+            }
+        );
+        assert!(Action::build("continue", &[String::from("hello")]).is_err());
+        assert_eq!(
+            Action::build("start", &[String::from("today"), String::from("0:00")])?,
+            Action::Start {
+                date: DateTime::now().parse_user_input("today 0:00")?
+            }
+        );
+        assert!(Action::build("start", &[String::from("2025-02-30 10:00")]).is_err());
+        assert!(Action::build("start", &[String::from("hello")]).is_err());
+
+        assert_eq!(
+            Action::build("mark", &[])?,
+            Action::Mark {
+                date: DateTime::now(),
+                future: false
+            }
+        );
+        assert_eq!(
+            Action::build("mark", &[String::from("0m")])?,
+            Action::Mark {
+                date: DateTime::now(),
+                future: false
+            }
+        );
+        assert_eq!(
+            Action::build("mark", &[String::from("--future"), String::from("0m")])?,
+            Action::Mark {
+                date: DateTime::now(),
+                future: true
+            }
+        );
+        assert!(Action::build(
+            "mark",
+            &[
+                String::from("0m"),
+                String::from("--future"),
+                String::from("hello")
+            ]
+        )
+        .is_err());
+        assert!(Action::build("mark", &[String::from("0m"), String::from("hello")]).is_err());
+        assert!(Action::build("mark", &[String::from("hello")]).is_err());
+
+        assert_eq!(
+            Action::build("remark", &[])?,
+            Action::Remark {
+                date: DateTime::now(),
+                future: false,
+                last: false,
+                first: false
+            }
+        );
+        assert_eq!(
             Action::build("remark", &[String::from("0m")])?,
             Action::Remark {
-                date: DateTime::now()
+                date: DateTime::now(),
+                future: false,
+                last: false,
+                first: false
+            }
+        );
+        assert_eq!(
+            Action::build("remark", &[String::from("--future"), String::from("0m")])?,
+            Action::Remark {
+                date: DateTime::now(),
+                future: true,
+                last: false,
+                first: false
+            }
+        );
+        assert_eq!(
+            Action::build("remark", &args(&["--last", "0m"]))?,
+            Action::Remark {
+                date: DateTime::now(),
+                future: false,
+                last: true,
+                first: false
+            }
+        );
+        for flag in ["--first", "--start"] {
+            assert_eq!(
+                Action::build("remark", &args(&[flag, "-25m"]))?,
+                Action::Remark {
+                    date: DateTime::now().plus_minutes(-25),
+                    future: false,
+                    last: false,
+                    first: true
+                }
+            );
+        }
+        assert_eq!(
+            Action::build("unmark", &[])?,
+            Action::Unmark {
+                mark: MarkSelector::Last,
+                force: false
+            }
+        );
+        assert_eq!(
+            Action::build("unmark", &args(&["--mark", "-2", "--force"]))?,
+            Action::Unmark {
+                mark: MarkSelector::Index(-2),
+                force: true
             }
         );
+        assert!(matches!(
+            Action::build("unmark", &args(&["--at", "11:20"]))?,
+            Action::Unmark {
+                mark: MarkSelector::At(_),
+                force: false
+            }
+        ));
+        assert!(Action::build("unmark", &args(&["-2"])).is_err());
+        assert!(Action::build("mark", &args(&["--first"])).is_err());
+        assert!(Action::build("mark", &args(&["--last"])).is_err());
+        assert!(Action::build(
+            "remark",
+            &[
+                String::from("0m"),
+                String::from("--future"),
+                String::from("hello")
+            ]
+        )
+        .is_err());
         assert!(Action::build("remark", &[String::from("0m"), String::from("hello")]).is_err());
         assert!(Action::build("remark", &[String::from("hello")]).is_err());
 
-        assert_eq!(Action::build("path", &[])?, Action::Path);
+        assert_eq!(
+            Action::build("path", &[])?,
+            Action::Path {
+                target: PathTarget::Session
+            }
+        );
+        assert_eq!(
+            Action::build("path", &[String::from("--config")])?,
+            Action::Path {
+                target: PathTarget::Config
+            }
+        );
+        assert_eq!(
+            Action::build("path", &[String::from("--dir")])?,
+            Action::Path {
+                target: PathTarget::Dir
+            }
+        );
+        assert_eq!(
+            Action::build("path", &[String::from("--last-stopped")])?,
+            Action::Path {
+                target: PathTarget::LastStopped
+            }
+        );
         assert!(Action::build("path", &[String::from("hello")]).is_err());
+        assert!(Action::build("path", &[String::from("--config"), String::from("hello")]).is_err());
 
-        assert_eq!(Action::build("view", &[])?, Action::View);
+        assert_eq!(
+            Action::build("view", &[])?,
+            Action::View { porcelain: false }
+        );
+        assert_eq!(
+            Action::build("view", &args(&["--porcelain"]))?,
+            Action::View { porcelain: true }
+        );
         assert!(Action::build("view", &[String::from("hello")]).is_err());
 
+        let Action::Split { date } = Action::build("split", &args(&["13:00"]))? else {
+            panic!("expected Action::Split");
+        };
+        assert!(date <= DateTime::now());
+        assert_eq!(date.date.format("%R").to_string(), "13:00");
+        assert_eq!(
+            Action::build("split", &args(&["2025-01-07", "13:00"]))?,
+            Action::Split {
+                date: DateTime::now().parse_user_input("2025-01-07 13:00")?
+            }
+        );
+        assert!(Action::build("split", &[]).is_err());
+
+        assert_eq!(
+            Action::build("archive", &args(&["2024-01-01"]))?,
+            Action::Archive {
+                before: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+            }
+        );
+        assert!(Action::build("archive", &[]).is_err());
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 8, 31).unwrap();
+        for (text, expected) in [
+            ("30d", chrono::NaiveDate::from_ymd_opt(2025, 8, 1)),
+            ("2w", chrono::NaiveDate::from_ymd_opt(2025, 8, 17)),
+            ("6m", chrono::NaiveDate::from_ymd_opt(2025, 2, 28)),
+            ("1y", chrono::NaiveDate::from_ymd_opt(2024, 8, 31)),
+        ] {
+            assert_eq!(Action::parse_cutoff(today, text), Ok(expected.unwrap()));
+        }
+        for text in ["", "m", "6", "-6m", "6h", "6é", "2024-13-01"] {
+            assert!(Action::parse_cutoff(today, text).is_err(), "{text}");
+        }
+
+        assert_eq!(
+            Action::build("merge", &[])?,
+            Action::Merge {
+                paths: None,
+                force: false,
+                skip_gap: false
+            }
+        );
+        assert_eq!(
+            Action::build("merge", &args(&["a.md", "--force", "b.md", "--skip-gap"]))?,
+            Action::Merge {
+                paths: Some((PathBuf::from("a.md"), PathBuf::from("b.md"))),
+                force: true,
+                skip_gap: true
+            }
+        );
+        assert!(Action::build("merge", &args(&["a.md"])).is_err());
+
+        assert_eq!(
+            Action::build("prune", &args(&["--yes", "--dry-run"]))?,
+            Action::Prune {
+                dry_run: true,
+                yes: true
+            }
+        );
+        assert!(Action::build("prune", &args(&["all"])).is_err());
+
+        assert_eq!(
+            Action::build("watch", &[])?,
+            Action::Watch {
+                interval: WATCH_INTERVAL_DEFAULT
+            }
+        );
+        assert_eq!(
+            Action::build("watch", &args(&["--interval", "2"]))?,
+            Action::Watch {
+                interval: Duration::from_secs(2)
+            }
+        );
+        assert_eq!(
+            Action::build("watch", &args(&["--interval", "1m"]))?,
+            Action::Watch {
+                interval: Duration::from_secs(60)
+            }
+        );
+        assert!(Action::build("watch", &args(&["--interval", "0"])).is_err());
+        assert!(Action::build("watch", &args(&["--interval", "-5s"])).is_err());
+        assert!(Action::build("watch", &args(&["--interval", "soon"])).is_err());
+
+        assert_eq!(
+            Action::build("status", &[])?,
+            Action::Status { waybar: false }
+        );
+        assert_eq!(
+            Action::build("status", &[String::from("--waybar")])?,
+            Action::Status { waybar: true }
+        );
+        assert!(Action::build("status", &[String::from("--json")]).is_err());
+
         assert!(Action::build("attribute", &[]).is_err());
         assert!(Action::build("attribute", &[String::from("hello")]).is_err());
         assert_eq!(
             Action::build("attribute", &[String::from("skip")])?,
             Action::Attribute {
-                attribute: Attribute::Skip
+                attribute: Attribute::Skip,
+                force: false
             }
         );
         assert!(Action::build("attribute", &[String::from("skip"), String::from("skip")]).is_err());
+        assert!(Action::build("attribute", &[String::from("none")]).is_err());
+        assert_eq!(
+            Action::build("attribute", &[String::from("clear")])?,
+            Action::Attribute {
+                attribute: Attribute::None,
+                force: false
+            }
+        );
+        assert_eq!(
+            Action::build("attribute", &[String::from("end")])?,
+            Action::Attribute {
+                attribute: Attribute::Stop,
+                force: false
+            }
+        );
+        assert_eq!(
+            Action::build(
+                "attribute",
+                &[String::from("--force"), String::from("clear")]
+            )?,
+            Action::Attribute {
+                attribute: Attribute::None,
+                force: true
+            }
+        );
 
         assert!(Action::build("tag", &[]).is_err());
         assert_eq!(
             Action::build("tag", &[String::from("hello")])?,
             Action::Tag {
-                tag: Tag::from_text("hello")?
+                tag: Tag::from_text("hello")?,
+                session: false,
+                mark: MarkSelector::Last,
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build(
+                "tag",
+                &[String::from("--session"), String::from("client-a")]
+            )?,
+            Action::Tag {
+                tag: Tag::from_text("client-a")?,
+                session: true,
+                mark: MarkSelector::Last,
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build("tag", &args(&["--last", "hello"]))?,
+            Action::Tag {
+                tag: Tag::from_text("hello")?,
+                session: false,
+                mark: MarkSelector::Last,
+                last: true
             }
         );
+        assert!(Action::build("tag", &[String::from("--session")]).is_err());
         assert!(Action::build("tag", &[String::from("skip"), String::from("hello")]).is_err());
 
         assert!(Action::build("untag", &[]).is_err());
         assert_eq!(
             Action::build("untag", &[String::from("hello")])?,
             Action::Untag {
-                tag: Tag::from_text("hello")?
+                tag: Tag::from_text("hello")?,
+                session: false,
+                mark: MarkSelector::Last,
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build(
+                "untag",
+                &[String::from("client-a"), String::from("--session")]
+            )?,
+            Action::Untag {
+                tag: Tag::from_text("client-a")?,
+                session: true,
+                mark: MarkSelector::Last,
+                last: false
             }
         );
         assert!(Action::build("untag", &[String::from("skip"), String::from("hello")]).is_err());
+        assert_eq!(
+            Action::build("tag", &args(&["rust", "--mark", "-2"]))?,
+            Action::Tag {
+                tag: Tag::from_text("rust")?,
+                session: false,
+                mark: MarkSelector::Index(-2),
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build("untag", &args(&["--at", "14:30", "rust"]))?,
+            Action::Untag {
+                tag: Tag::from_text("rust")?,
+                session: false,
+                mark: MarkSelector::At(DateTime::now().modify("-14:30").unwrap()),
+                last: false
+            }
+        );
+        assert!(Action::build("tag", &args(&["rust", "--mark"])).is_err());
+        assert!(Action::build("tag", &args(&["rust", "--mark", "last"])).is_err());
+        assert!(Action::build("tag", &args(&["rust", "--at", "2pm"])).is_err());
+        assert!(Action::build("tag", &args(&["rust", "--mark", "1", "--at", "14:30"])).is_err());
+        assert!(Action::build("tag", &args(&["rust", "--mark", "1", "--session"])).is_err());
 
         assert!(Action::build("write", &[]).is_err());
         assert!(Action::build("write", &[String::from("hello"), String::from("bye")]).is_err());
+        assert!(Action::build("write", &args(&["--commit", "HEAD", "HEAD~1"])).is_err());
+        #[cfg(not(feature = "git"))]
+        for flag in ["-b", "--commit"] {
+            let err = Action::build("write", &args(&[flag])).unwrap_err();
+            assert_eq!(err.to_string(), GIT_NOT_COMPILED);
+        }
         assert_eq!(
             Action::build("write", &[String::from("this is content")]).unwrap(),
             Action::Write {
-                text: String::from("this is content")
+                text: String::from("this is content"),
+                target: WriteTarget::Mark(MarkSelector::Last),
+                mode: None,
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build("write", &args(&["--last", "late note"])).unwrap(),
+            Action::Write {
+                text: String::from("late note"),
+                target: WriteTarget::Mark(MarkSelector::Last),
+                mode: None,
+                last: true
+            }
+        );
+        assert!(Action::build("write", &args(&["--last"])).is_err());
+        assert_eq!(
+            Action::build("write", &args(&["--session", "--append", "Agenda."])).unwrap(),
+            Action::Write {
+                text: String::from("Agenda."),
+                target: WriteTarget::Session,
+                mode: Some(WriteMode::Append),
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build("write", &args(&["--mark", "-2", "Earlier."])).unwrap(),
+            Action::Write {
+                text: String::from("Earlier."),
+                target: WriteTarget::Mark(MarkSelector::Index(-2)),
+                mode: None,
+                last: false
+            }
+        );
+        assert!(Action::build("write", &args(&["--session", "--mark", "1", "x"])).is_err());
+        assert_eq!(
+            Action::build("write", &args(&["--title", "--force", "feat/parser"])).unwrap(),
+            Action::Write {
+                text: String::from("feat/parser"),
+                target: WriteTarget::Mark(MarkSelector::Last),
+                mode: Some(WriteMode::Title { force: true }),
+                last: false
+            }
+        );
+        assert!(Action::build("write", &args(&["--force", "text"])).is_err());
+        assert!(Action::build("write", &args(&["--title", "--append", "text"])).is_err());
+        assert!(Action::build("write", &args(&["--mark", "x", "text"])).is_err());
+
+        assert!(Action::build("title", &[]).is_err());
+        assert_eq!(
+            Action::build(
+                "title",
+                &[String::from("Client X"), String::from("onboarding")]
+            )?,
+            Action::Title {
+                title: String::from("Client X onboarding")
+            }
+        );
+        assert!(Action::build("title", &[String::from("#1")]).is_err());
+
+        assert_eq!(
+            Action::build("restore", &[])?,
+            Action::Restore { index: None }
+        );
+        assert_eq!(
+            Action::build("restore", &[String::from("2")])?,
+            Action::Restore { index: Some(2) }
+        );
+        assert!(Action::build("restore", &[String::from("last")]).is_err());
+        assert!(Action::build("restore", &[String::from("1"), String::from("2")]).is_err());
+
+        assert_eq!(
+            Action::build("validate", &[])?,
+            Action::Validate { fix: false }
+        );
+        assert_eq!(
+            Action::build("validate", &[String::from("--fix")])?,
+            Action::Validate { fix: true }
+        );
+        assert!(Action::build("validate", &[String::from("--all")]).is_err());
+        assert_eq!(
+            Action::build("fmt", &[])?,
+            Action::Fmt {
+                current: false,
+                check: false
+            }
+        );
+        assert_eq!(
+            Action::build("fmt", &args(&["--check", "--current"]))?,
+            Action::Fmt {
+                current: true,
+                check: true
+            }
+        );
+        assert!(Action::build("fmt", &args(&["--all"])).is_err());
+        assert_eq!(Action::build("edit", &[])?, Action::Edit { config: false });
+        assert_eq!(
+            Action::build("edit", &args(&["--config"]))?,
+            Action::Edit { config: true }
+        );
+        assert!(Action::build("edit", &args(&["notes.md"])).is_err());
+        assert_eq!(
+            Action::build("config", &args(&["get", "backups"]))?,
+            Action::ConfigGet {
+                key: String::from("backups")
+            }
+        );
+        assert_eq!(
+            Action::build("config", &args(&["set", "weekly_goal", "40h"]))?,
+            Action::ConfigSet {
+                key: String::from("weekly_goal"),
+                value: String::from("40h")
+            }
+        );
+        assert_eq!(
+            Action::build("config", &args(&["list"]))?,
+            Action::ConfigList
+        );
+        assert!(Action::build("config", &[]).is_err());
+        assert!(Action::build("config", &args(&["get"])).is_err());
+        assert!(Action::build("config", &args(&["set", "backups"])).is_err());
+        assert!(Action::build("config", &args(&["list", "backups"])).is_err());
+        assert!(Action::build("config", &args(&["unset", "backups"])).is_err());
+        assert_eq!(Action::build("init", &[])?, Action::Init);
+        assert!(Action::build("init", &args(&["~/notes"])).is_err());
+        assert_eq!(Action::build("migrate", &[])?, Action::Migrate);
+        assert!(Action::build("migrate", &args(&["--all"])).is_err());
+
+        assert_eq!(Action::build("graph", &[])?, Action::Graph { weeks: None });
+        assert_eq!(
+            Action::build("graph", &[String::from("--weeks"), String::from("8")])?,
+            Action::Graph { weeks: Some(8) }
+        );
+        assert!(Action::build("graph", &[String::from("--weeks"), String::from("0")]).is_err());
+        assert!(Action::build("graph", &[String::from("8")]).is_err());
+
+        assert_eq!(Action::build("cal", &[])?, Action::Calendar { day: None });
+        assert_eq!(
+            Action::build("cal", &[String::from("2025-03")])?,
+            Action::Calendar {
+                day: chrono::NaiveDate::from_ymd_opt(2025, 3, 1)
+            }
+        );
+        assert!(Action::build("cal", &[String::from("2025-13")]).is_err());
+        assert!(Action::build("cal", &[String::from("2025-03-01")]).is_err());
+
+        assert_eq!(
+            Action::build("stats", &[])?,
+            Action::Stats {
+                first: None,
+                last: None,
+                include_archived: false,
+                by_context: false
+            }
+        );
+        assert_eq!(
+            Action::build("stats", &[String::from("--include-archived")])?,
+            Action::Stats {
+                first: None,
+                last: None,
+                include_archived: true,
+                by_context: false
+            }
+        );
+        assert_eq!(
+            Action::build(
+                "stats",
+                &[
+                    String::from("--to"),
+                    String::from("2025-01-31"),
+                    String::from("--from"),
+                    String::from("2025-01-01")
+                ]
+            )?,
+            Action::Stats {
+                first: chrono::NaiveDate::from_ymd_opt(2025, 1, 1),
+                last: chrono::NaiveDate::from_ymd_opt(2025, 1, 31),
+                include_archived: false,
+                by_context: false
+            }
+        );
+        assert_eq!(
+            Action::build("stats", &args(&["--by-context"]))?,
+            Action::Stats {
+                first: None,
+                last: None,
+                include_archived: false,
+                by_context: true
+            }
+        );
+        assert!(Action::build("stats", &[String::from("--from")]).is_err());
+        assert!(Action::build("stats", &[String::from("--from"), String::from("01-01")]).is_err());
+        assert!(Action::build(
+            "stats",
+            &[
+                String::from("--from"),
+                String::from("2025-02-01"),
+                String::from("--to"),
+                String::from("2025-01-01")
+            ]
+        )
+        .is_err());
+
+        assert_eq!(Action::build("pomodoro", &[])?, Action::Pomodoro);
+        assert!(Action::build("pomodoro", &args(&["25m"])).is_err());
+        assert_eq!(
+            Action::build("billable", &[])?,
+            Action::Billable { last: false }
+        );
+        assert_eq!(
+            Action::build("billable", &args(&["--last"]))?,
+            Action::Billable { last: true }
+        );
+        assert!(Action::build("billable", &args(&["yes"])).is_err());
+        assert_eq!(
+            Action::build("at", &args(&["home", "office"]))?,
+            Action::At {
+                context: Some(String::from("home office")),
+                last: false
+            }
+        );
+        assert_eq!(
+            Action::build("at", &args(&["--clear", "--last"]))?,
+            Action::At {
+                context: None,
+                last: true
+            }
+        );
+        assert!(Action::build("at", &[]).is_err());
+        assert!(Action::build("at", &args(&["--clear", "home"])).is_err());
+        assert_eq!(
+            Action::build("report", &args(&["billing"]))?,
+            Action::Billing { month: None }
+        );
+        assert_eq!(
+            Action::build("report", &args(&["billing", "2025-02"]))?,
+            Action::Billing {
+                month: chrono::NaiveDate::from_ymd_opt(2025, 2, 1)
+            }
+        );
+        assert_eq!(Action::build("when", &[])?, Action::When);
+        assert!(Action::build("when", &args(&["now"])).is_err());
+        assert!(Action::build("report", &[]).is_err());
+        assert!(Action::build("report", &args(&["invoice"])).is_err());
+        assert!(Action::build("report", &args(&["billing", "2025-13"])).is_err());
+        assert!(Action::build("report", &args(&["billing", "2025-02-03"])).is_err());
+        assert!(Action::build("report", &args(&["billing", "2025-02", "x"])).is_err());
+        assert_eq!(
+            Action::build("report", &args(&["gaps"]))?,
+            Action::Gaps { day: None }
+        );
+        assert_eq!(
+            Action::build("report", &args(&["gaps", "2025-02-03"]))?,
+            Action::Gaps {
+                day: chrono::NaiveDate::from_ymd_opt(2025, 2, 3)
+            }
+        );
+        assert!(Action::build("report", &args(&["gaps", "2025-02"])).is_err());
+        assert!(Action::build("report", &args(&["gaps", "2025-02-03", "x"])).is_err());
+        assert_eq!(
+            Action::build("report", &args(&["hours"]))?,
+            Action::Hours {
+                first: None,
+                last: None
+            }
+        );
+        assert_eq!(
+            Action::build("report", &args(&["hours", "--from", "2025-02-03"]))?,
+            Action::Hours {
+                first: chrono::NaiveDate::from_ymd_opt(2025, 2, 3),
+                last: None
             }
         );
+        assert!(Action::build(
+            "report",
+            &args(&["hours", "--from", "2025-02-03", "--to", "2025-02-01"])
+        )
+        .is_err());
+        assert!(Action::build("report", &args(&["hours", "x"])).is_err());
+        assert_eq!(
+            Action::build("report", &args(&["weekdays"]))?,
+            Action::Weekdays {
+                weeks: None,
+                nonzero: false
+            }
+        );
+        assert_eq!(
+            Action::build("report", &args(&["weekdays", "--nonzero", "--weeks", "4"]))?,
+            Action::Weekdays {
+                weeks: Some(4),
+                nonzero: true
+            }
+        );
+        assert!(Action::build("report", &args(&["weekdays", "--weeks", "0"])).is_err());
+        assert!(Action::build("report", &args(&["weekdays", "4"])).is_err());
+
+        let rust = Tag::from_text("rust")?;
+        assert_eq!(Action::build("goal", &[])?, Action::Goals);
+        assert_eq!(
+            Action::build("goal", &args(&["rust", "10h"]))?,
+            Action::Goal {
+                tag: rust.clone(),
+                goal: Some((Goal::from_text("10h")?, Period::Week))
+            }
+        );
+        assert_eq!(
+            Action::build("goal", &args(&["rust", "40h", "--month"]))?,
+            Action::Goal {
+                tag: rust.clone(),
+                goal: Some((Goal::from_text("40h")?, Period::Month))
+            }
+        );
+        assert_eq!(
+            Action::build("goal", &args(&["--clear", "rust"]))?,
+            Action::Goal {
+                tag: rust,
+                goal: None
+            }
+        );
+        assert!(Action::build("goal", &args(&["rust"])).is_err());
+        assert!(Action::build("goal", &args(&["--week"])).is_err());
+        assert!(Action::build("goal", &args(&["rust", "10h", "--week", "--month"])).is_err());
+        assert!(Action::build("goal", &args(&["rust", "10h", "--clear"])).is_err());
+        assert!(Action::build("goal", &args(&["rust", "10"])).is_err());
+
+        assert_eq!(
+            Action::build("pause", &[])?,
+            Action::Pause {
+                date: DateTime::now()
+            }
+        );
+        assert_eq!(
+            Action::build("unpause", &[String::from("0m")])?,
+            Action::Unpause {
+                date: DateTime::now()
+            }
+        );
+        assert!(Action::build("unpause", &[String::from("hello")]).is_err());
+
+        let now = DateTime::now();
+        assert_eq!(
+            Action::build("idle", &args(&["40m"]))?,
+            Action::Idle {
+                start: now.plus_minutes(-40),
+                end: now.clone(),
+            }
+        );
+        let end = now.modify("-12:50").unwrap();
+        assert_eq!(
+            Action::build("idle", &args(&["12:10", "12:50"]))?,
+            Action::Idle {
+                start: end.modify("-12:10").unwrap(),
+                end,
+            }
+        );
+        assert!(Action::build("idle", &[]).is_err());
+        assert!(Action::build("idle", &args(&["-40m"])).is_err());
+        assert!(Action::build("idle", &args(&["12:10", "1h"])).is_err());
+        assert!(Action::build("idle", &args(&["12:10", "12:50", "13:00"])).is_err());
 
-        assert_eq!(Action::build("version", &[])?, Action::Version);
+        assert_eq!(
+            Action::build("events", &[])?,
+            Action::Events { since: None }
+        );
+        assert_eq!(
+            Action::build("events", &args(&["--since", "2025-01-07"]))?,
+            Action::Events {
+                since: chrono::NaiveDate::from_ymd_opt(2025, 1, 7)
+            }
+        );
+        assert!(Action::build("events", &args(&["--since", "today"])).is_err());
+        assert!(Action::build("events", &args(&["2025-01-07"])).is_err());
+
+        assert_eq!(
+            Action::build("import", &args(&["csv", "--skip-invalid", "hours.csv"]))?,
+            Action::Import {
+                path: PathBuf::from("hours.csv"),
+                one_session_per_row: false,
+                skip_invalid: true,
+            }
+        );
+        assert_eq!(
+            Action::build(
+                "import",
+                &args(&["--one-session-per-row", "csv", "hours.csv"])
+            )?,
+            Action::Import {
+                path: PathBuf::from("hours.csv"),
+                one_session_per_row: true,
+                skip_invalid: false,
+            }
+        );
+        assert!(Action::build("import", &args(&["csv"])).is_err());
+        assert!(Action::build("import", &args(&["json", "hours.json"])).is_err());
+
+        assert_eq!(
+            Action::build("retag", &args(&["clienta", "client-alpha", "--dry-run"]))?,
+            Action::Retag {
+                from: Tag::from_text("clienta")?,
+                to: Tag::from_text("client-alpha")?,
+                dry_run: true
+            }
+        );
+        assert!(Action::build("retag", &args(&["clienta"])).is_err());
+        assert!(Action::build("retag", &args(&["clienta", "client`alpha"])).is_err());
+        assert!(Action::build("retag", &args(&["clienta", "clienta"])).is_err());
+
+        assert_eq!(
+            Action::build("version", &[])?,
+            Action::Version { json: false }
+        );
+        assert_eq!(
+            Action::build("version", &args(&["--json"]))?,
+            Action::Version { json: true }
+        );
         assert!(Action::build("version", &[String::from("hello")]).is_err());
 
         assert!(Action::build("hello", &[]).is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn action_normalize_tags_works() {
+        let normalizer = TagNormalizer {
+            aliases: vec![(String::from("ta"), Tag::from_text("time tracker").unwrap())],
+            case_insensitive: true,
+        };
+        let mut action = Action::build("tag", &[String::from("TA")]).unwrap();
+        action.normalize_tags(&normalizer);
+        assert_eq!(
+            action,
+            Action::Tag {
+                tag: Tag::from_text("time tracker").unwrap(),
+                session: false,
+                mark: MarkSelector::Last,
+                last: false
+            }
+        );
+        let mut action =
+            Action::build("retag", &[String::from("Rust"), String::from("ta")]).unwrap();
+        action.normalize_tags(&normalizer);
+        assert_eq!(
+            action,
+            Action::Retag {
+                from: Tag::from_text("rust").unwrap(),
+                to: Tag::from_text("time tracker").unwrap(),
+                dry_run: false
+            }
+        );
+    }
 }
@@ -0,0 +1,149 @@
+//! Encryption of mark contents with a passphrase, see
+//! [`Config::encrypt_contents`](crate::Config::encrypt_contents). A payload is the salt, the nonce
+//! and the sealed text in base64. The key is derived from the passphrase and the salt with
+//! Argon2id, the text is sealed with XChaCha20-Poly1305.
+#[cfg(feature = "encryption")]
+use argon2::Argon2;
+#[cfg(feature = "encryption")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+#[cfg(feature = "encryption")]
+use std::{cell::RefCell, collections::HashMap};
+
+#[cfg(feature = "encryption")]
+const SALT_LENGTH: usize = 16;
+#[cfg(feature = "encryption")]
+const NONCE_LENGTH: usize = 24;
+#[cfg(feature = "encryption")]
+const KEY_LENGTH: usize = 32;
+/// Error of reading or writing encrypted contents in builds without the `encryption` feature.
+#[cfg(not(feature = "encryption"))]
+const ENCRYPTION_NOT_COMPILED: &str = "encryption support not compiled in";
+
+/// Whether this build can read and write encrypted contents.
+pub fn is_supported() -> bool {
+    cfg!(feature = "encryption")
+}
+
+/// Encrypts and decrypts with one passphrase. Keys are derived once for each salt and kept for
+/// the invocation, as the derivation is slow on purpose.
+#[cfg(feature = "encryption")]
+pub struct Cipher {
+    passphrase: String,
+    /// Salt of the payloads this cipher writes, random for each cipher.
+    salt: [u8; SALT_LENGTH],
+    keys: RefCell<HashMap<[u8; SALT_LENGTH], [u8; KEY_LENGTH]>>,
+}
+
+#[cfg(not(feature = "encryption"))]
+pub struct Cipher;
+
+#[cfg(feature = "encryption")]
+impl Cipher {
+    pub fn new(passphrase: &str) -> Result<Cipher, String> {
+        if passphrase.is_empty() {
+            Err("passphrase cannot be empty")?
+        }
+        let mut salt = [0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        Ok(Cipher {
+            passphrase: passphrase.to_owned(),
+            salt,
+            keys: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn encrypt(&self, text: &str) -> Result<String, String> {
+        let key = self.get_key(&self.salt)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = XChaCha20Poly1305::new(&key.into())
+            .encrypt(&nonce, text.as_bytes())
+            .map_err(|_| "couldn't encrypt contents")?;
+        let mut payload = self.salt.to_vec();
+        payload.extend_from_slice(&nonce);
+        payload.extend(sealed);
+        Ok(STANDARD.encode(payload))
+    }
+
+    pub fn decrypt(&self, payload: &str) -> Result<String, String> {
+        let invalid = "encrypted contents are damaged";
+        let bytes = STANDARD.decode(payload.trim()).map_err(|_| invalid)?;
+        if bytes.len() < SALT_LENGTH + NONCE_LENGTH {
+            Err(invalid)?
+        }
+        let (salt, rest) = bytes.split_at(SALT_LENGTH);
+        let (nonce, sealed) = rest.split_at(NONCE_LENGTH);
+        let key = self.get_key(salt.try_into().expect("salt has the right length"))?;
+        let text = XChaCha20Poly1305::new(&key.into())
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .map_err(|_| "wrong passphrase or damaged encrypted contents")?;
+        String::from_utf8(text).map_err(|_| invalid.to_owned())
+    }
+
+    fn get_key(&self, salt: &[u8; SALT_LENGTH]) -> Result<[u8; KEY_LENGTH], String> {
+        if let Some(key) = self.keys.borrow().get(salt) {
+            return Ok(*key);
+        }
+        let mut key = [0; KEY_LENGTH];
+        Argon2::default()
+            .hash_password_into(self.passphrase.as_bytes(), salt, &mut key)
+            .map_err(|err| format!("couldn't derive the key: {err}"))?;
+        self.keys.borrow_mut().insert(*salt, key);
+        Ok(key)
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+impl Cipher {
+    pub fn new(_passphrase: &str) -> Result<Cipher, String> {
+        Err(ENCRYPTION_NOT_COMPILED)?
+    }
+
+    pub fn encrypt(&self, _text: &str) -> Result<String, String> {
+        Err(ENCRYPTION_NOT_COMPILED)?
+    }
+
+    pub fn decrypt(&self, _payload: &str) -> Result<String, String> {
+        Err(ENCRYPTION_NOT_COMPILED)?
+    }
+}
+
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cipher_round_trip_works() {
+        let cipher = Cipher::new("correct horse").unwrap();
+        let payload = cipher.encrypt("Call with Client A.").unwrap();
+        assert!(!payload.contains("Client"));
+        assert_ne!(cipher.encrypt("Call with Client A.").unwrap(), payload);
+        assert_eq!(cipher.decrypt(&payload).unwrap(), "Call with Client A.");
+
+        // Payloads of another cipher with the same passphrase use another salt.
+        let other = Cipher::new("correct horse").unwrap();
+        assert_eq!(other.decrypt(&payload).unwrap(), "Call with Client A.");
+    }
+
+    #[test]
+    fn cipher_decrypt_fails_with_wrong_passphrase() {
+        let payload = Cipher::new("correct horse")
+            .unwrap()
+            .encrypt("Notes.")
+            .unwrap();
+        let wrong = Cipher::new("battery staple").unwrap();
+        assert_eq!(
+            wrong.decrypt(&payload),
+            Err(String::from(
+                "wrong passphrase or damaged encrypted contents"
+            ))
+        );
+        assert!(wrong.decrypt("not base64!").is_err());
+        assert!(wrong.decrypt("AAAA").is_err());
+        assert!(Cipher::new("").is_err());
+    }
+}
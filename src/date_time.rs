@@ -1,14 +1,19 @@
 use chrono::{Datelike, Timelike};
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign},
+    str::FromStr,
+};
 
 // TODO: Use this in place of almost all chrono::DateTime.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
 pub struct DateTime {
     pub date: chrono::DateTime<chrono::Local>,
 }
 
-// TODO: Implement From<chrono::DateTime> trait.
-// TODO: Implement trait for comparison between DateTime and chrono::DateTime?
-// TODO: Go through and make all functions that should be methods methods.
 impl DateTime {
     pub fn now() -> DateTime {
         let now = chrono::Local::now();
@@ -20,7 +25,7 @@ impl DateTime {
     }
 
     pub fn new(date: &chrono::DateTime<chrono::Local>) -> DateTime {
-        DateTime { date: date.clone() }
+        DateTime { date: *date }
     }
 
     fn plus_milli(&self, milli: i64) -> DateTime {
@@ -30,48 +35,71 @@ impl DateTime {
         DateTime { date }
     }
 
-    #[allow(dead_code)]
     pub fn plus_seconds(&self, seconds: i64) -> DateTime {
-        self.plus_milli(seconds * 1000)
+        self.plus_milli(seconds * Duration::SECOND.as_millis() as i64)
     }
 
-    #[allow(dead_code)]
     pub fn plus_minutes(&self, minutes: i64) -> DateTime {
-        self.plus_milli(minutes * 60 * 1000)
+        self.plus_milli(minutes * Duration::MINUTE.as_millis() as i64)
     }
 
-    #[allow(dead_code)]
     pub fn plus_hours(&self, hours: i64) -> DateTime {
-        self.plus_milli(hours * 60 * 60 * 1000)
+        self.plus_milli(hours * Duration::HOUR.as_millis() as i64)
     }
 
-    #[allow(dead_code)]
+    /// Moves the date by calendar days, keeping the local time even when a DST transition happens
+    /// in between.
     pub fn plus_days(&self, days: i64) -> DateTime {
-        self.plus_milli(days * 24 * 60 * 60 * 1000)
-    }
-
-    pub fn format(date: &chrono::DateTime<chrono::Local>) -> String {
-        date.format("%FT%T%:z").to_string()
+        DateTime {
+            date: add_days(&self.date, days),
+        }
     }
 
     pub fn to_formatted(&self) -> String {
-        DateTime::format(&self.date)
+        self.date.format("%FT%T%:z").to_string()
     }
 
     pub fn to_formatted_pretty(&self) -> String {
         self.date.format("%F %T %:z").to_string()
     }
 
-    /// If the date is today, prints just the time, otherwise prints the whole date.
+    /// Date and time without seconds and offset, `2025-01-07 14:32`.
     pub fn to_formatted_pretty_short(&self) -> String {
-        let now = DateTime::now().date;
-        let difference = now.timestamp_millis() - self.date.timestamp_millis();
-        let difference = difference.abs();
-        let is_same_day = difference < 24 * 60 * 60 * 1000 && now.day() == self.date.day();
-        if is_same_day {
-            self.to_formatted_time()
-        } else {
-            self.to_formatted_pretty()
+        self.date.format("%F %H:%M").to_string()
+    }
+
+    /// Date and time without seconds as the user prefers it, `2025-01-07 14:32` by default.
+    pub fn to_formatted_display(&self, prefs: &DisplayPrefs) -> String {
+        format!(
+            "{} {}",
+            prefs.format_date(self.date.date_naive()),
+            prefs.format_time(&self.date, false)
+        )
+    }
+
+    /// Time with seconds as the user prefers it, `14:32:05` or `2:32:05 PM`.
+    pub fn to_formatted_display_time(&self, prefs: &DisplayPrefs) -> String {
+        prefs.format_time(&self.date, true)
+    }
+
+    /// Dates within the past week relative to `now`, `today 14:32`, `yesterday 09:10` or
+    /// `Mon 08:55`, falls back to [`DateTime::to_formatted_display`] otherwise.
+    pub fn to_formatted_relative(&self, now: &DateTime, prefs: &DisplayPrefs) -> String {
+        let days = (now.date.date_naive() - self.date.date_naive()).num_days();
+        let time = prefs.format_time(&self.date, false);
+        match days {
+            0 => format!("today {time}"),
+            1 => format!("yesterday {time}"),
+            2..=6 => format!("{} {time}", self.date.format("%a")),
+            _ => self.to_formatted_display(prefs),
+        }
+    }
+
+    /// Formats the date according to `format`, relative dates are relative to now.
+    pub fn to_formatted_with(&self, format: &TimestampFormat, prefs: &DisplayPrefs) -> String {
+        match format {
+            TimestampFormat::Relative => self.to_formatted_relative(&DateTime::now(), prefs),
+            TimestampFormat::Absolute => self.to_formatted_display(prefs),
         }
     }
 
@@ -79,132 +107,614 @@ impl DateTime {
         self.date.format("%T").to_string()
     }
 
-    // TEST: that it works when the months change in the middle of the week.
-    #[allow(dead_code)]
-    pub fn get_start_of_week(
-        date: &chrono::DateTime<chrono::Local>,
-    ) -> chrono::DateTime<chrono::Local> {
-        let days_since_monday: i64 = date.weekday().num_days_from_monday().into();
-        let date: chrono::DateTime<chrono::Local> = chrono::DateTime::from_timestamp_millis(
-            date.timestamp_millis() - days_since_monday * 24 * 60 * 60 * 1000,
-        )
-        .unwrap()
-        .into();
-        let date = date
-            .with_time(chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-            .unwrap();
-        date
+    /// Returns the day this date belongs to, see [`DateTime::start_of_day`].
+    pub fn day(&self, boundary: chrono::NaiveTime) -> chrono::NaiveDate {
+        get_day(&self.date, boundary)
     }
 
-    // TODO: move to it's own struct or combine with std::time::Duration?
-    pub fn get_time(
-        start: &chrono::DateTime<chrono::Local>,
-        end: &chrono::DateTime<chrono::Local>,
-    ) -> u64 {
-        let time = end.timestamp_millis() - start.timestamp_millis();
-        time.try_into()
-            .expect("start date must always be smaller than end date")
-    }
-
-    pub fn get_time_hr_from_milli(milli: u64) -> String {
-        let mut timestamp = milli / 1000;
-        const UNIT: u64 = 60;
-        let seconds = timestamp % UNIT;
-        timestamp /= UNIT;
-        let minutes = timestamp % UNIT;
-        timestamp /= UNIT;
-        let hours = timestamp;
-        format!("{hours}h {minutes}m {seconds}s")
-    }
-
-    // TODO: Refactor.
-    pub fn modify(&self, text: &str) -> Result<Self, &'static str> {
-        let mut text = text.trim();
-        let mut sign: i64 = 1;
-        if text.starts_with("--") {
-            return Err("time cannot start with `--`");
+    /// Returns the start of the day this date belongs to. Days start at `boundary` instead of
+    /// midnight, so with a boundary of `04:00` the date `03:59` still belongs to the previous day.
+    pub fn start_of_day(&self, boundary: chrono::NaiveTime) -> DateTime {
+        DateTime {
+            date: get_start_of_day(&self.date, boundary),
         }
-        if text.starts_with("-") {
-            text = &text[1..];
-            sign = -1;
+    }
+
+    /// Weeks start on Monday at `boundary`, see [`DateTime::start_of_day`].
+    pub fn start_of_week(&self, boundary: chrono::NaiveTime) -> DateTime {
+        DateTime {
+            date: get_start_of_week(&self.date, boundary),
         }
-        let text = text;
-        let sign = sign;
-
-        if text.ends_with("h") || text.ends_with("m") || text.ends_with("s") {
-            let time: i64 = text[0..text.len() - 1]
-                .parse::<u32>()
-                .map_err(|_e| "invalid time")?
-                .into();
-            let out = match text.chars().last().unwrap() {
-                's' => self.plus_seconds(sign * time),
-                'm' => self.plus_minutes(sign * time),
-                'h' => self.plus_hours(sign * time),
-                _ => panic!("no other option possible in this conditional"),
-            };
-            return Ok(out);
+    }
+
+    /// Parses a date given by the user. Accepts everything [`DateTime::modify`] does, relative to
+    /// `self`, and absolute dates `YYYY-MM-DD HH:MM`, `MM-DD HH:MM` (year of `self`),
+    /// `today HH:MM` and `yesterday HH:MM`.
+    pub fn parse_user_input(&self, text: &str) -> Result<DateTime, String> {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        let (day, time) = match parts[..] {
+            [day, time] => (day, time),
+            _ => return self.modify(text).map_err(|e| e.to_string()),
+        };
+        let time = chrono::NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("invalid time `{time}`, use `HH:MM`"))?;
+        let today = self.date.date_naive();
+        let day = match day {
+            "today" => today,
+            "yesterday" => today
+                .pred_opt()
+                .ok_or("date is out of the supported range")?,
+            day => {
+                let day = match day.matches("-").count() {
+                    1 => format!("{}-{day}", today.year()),
+                    _ => day.to_owned(),
+                };
+                chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                    .map_err(|_| format!("invalid date `{day}`, use `YYYY-MM-DD` or `MM-DD`"))?
+            }
+        };
+        let date = day
+            .and_time(time)
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .ok_or_else(|| format!("`{day} {time}` doesn't exist in local time"))?;
+        Ok(DateTime { date })
+    }
+
+    /// See [`RelativeTime`] for the accepted formats.
+    pub fn modify(&self, text: &str) -> Result<Self, RelativeTimeError> {
+        let time: RelativeTime = text.parse()?;
+        Ok(time.apply(self))
+    }
+}
+
+impl From<chrono::DateTime<chrono::Local>> for DateTime {
+    fn from(date: chrono::DateTime<chrono::Local>) -> DateTime {
+        DateTime { date }
+    }
+}
+
+impl PartialEq<chrono::DateTime<chrono::Local>> for DateTime {
+    fn eq(&self, other: &chrono::DateTime<chrono::Local>) -> bool {
+        self.date == *other
+    }
+}
+
+impl PartialOrd<chrono::DateTime<chrono::Local>> for DateTime {
+    fn partial_cmp(&self, other: &chrono::DateTime<chrono::Local>) -> Option<Ordering> {
+        Some(self.date.cmp(other))
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_formatted_pretty())
+    }
+}
+
+/// Calendar day the date belongs to when days start at `boundary`.
+fn get_day<Tz: chrono::TimeZone>(
+    date: &chrono::DateTime<Tz>,
+    boundary: chrono::NaiveTime,
+) -> chrono::NaiveDate {
+    let local = date.naive_local();
+    if local.time() < boundary {
+        local
+            .date()
+            .pred_opt()
+            .expect("date must be within the supported range")
+    } else {
+        local.date()
+    }
+}
+
+// Generic over the time zone so that DST transitions can be tested independently of the time zone
+// of the machine.
+fn get_start_of_day<Tz: chrono::TimeZone>(
+    date: &chrono::DateTime<Tz>,
+    boundary: chrono::NaiveTime,
+) -> chrono::DateTime<Tz> {
+    let day = get_day(date, boundary);
+    from_local(&date.timezone(), day.and_time(boundary))
+}
+
+fn get_start_of_week<Tz: chrono::TimeZone>(
+    date: &chrono::DateTime<Tz>,
+    boundary: chrono::NaiveTime,
+) -> chrono::DateTime<Tz> {
+    let day = get_day(date, boundary);
+    let monday = day - chrono::Days::new(day.weekday().num_days_from_monday().into());
+    from_local(&date.timezone(), monday.and_time(boundary))
+}
+
+fn add_days<Tz: chrono::TimeZone>(date: &chrono::DateTime<Tz>, days: i64) -> chrono::DateTime<Tz> {
+    let local = date.naive_local();
+    let delta = chrono::Days::new(days.unsigned_abs());
+    let local = if days < 0 {
+        local.checked_sub_days(delta)
+    } else {
+        local.checked_add_days(delta)
+    }
+    .expect("date must be within the supported range");
+    from_local(&date.timezone(), local)
+}
+
+/// Converts local date and time to a date. Ambiguous times resolve to the earlier instant,
+/// nonexistent ones, skipped by a DST transition, to the first minute after the gap.
+fn from_local<Tz: chrono::TimeZone>(tz: &Tz, local: chrono::NaiveDateTime) -> chrono::DateTime<Tz> {
+    let mut local = local;
+    loop {
+        match tz.from_local_datetime(&local) {
+            chrono::LocalResult::Single(date) | chrono::LocalResult::Ambiguous(date, _) => {
+                return date
+            }
+            chrono::LocalResult::None => local += chrono::TimeDelta::minutes(1),
         }
+    }
+}
 
-        const SEPARATOR: &str = ":";
-        let separator_count = text.matches(SEPARATOR).collect::<Vec<&str>>().len();
-        if text.len() <= "23:59".len()
-            && separator_count == 1
-            && !text.starts_with(SEPARATOR)
-            && !text.ends_with(SEPARATOR)
-        {
-            let colon_index = text
-                .find(SEPARATOR)
-                .expect("should contain exactly one colon");
-            let hour = text[0..colon_index]
-                .parse::<u32>()
-                .map_err(|_e| "invalid hour")
-                .and_then(|v| if v < 24 { Ok(v) } else { Err("") })
-                .map_err(|_e| "hour must be less than 24")?;
-            let minute = text[colon_index + 1..text.len()]
-                .parse::<u32>()
-                .map_err(|_e| "invalid minute")
-                .and_then(|v| if v < 60 { Ok(v) } else { Err("") })
-                .map_err(|_e| "minute must be less than 60")?;
-
-            let date_parsed = self
-                .date
-                .with_hour(hour)
-                .unwrap()
-                .with_minute(minute)
-                .unwrap();
-            let difference = date_parsed.timestamp_millis() - self.date.timestamp_millis();
-            let is_same_day = if difference == 0 {
-                sign > 0
-            } else {
-                sign * difference > 0
+/// Dates from `start`, inclusive, up to `end`, exclusive. A missing end leaves that side open.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct DateRange {
+    pub start: Option<DateTime>,
+    pub end: Option<DateTime>,
+}
+
+impl DateRange {
+    /// Whole days from `first` through `last`, each starting at `boundary`.
+    pub fn from_days(
+        first: Option<chrono::NaiveDate>,
+        last: Option<chrono::NaiveDate>,
+        boundary: chrono::NaiveTime,
+    ) -> DateRange {
+        let get_start = |day: chrono::NaiveDate| DateTime {
+            date: from_local(&chrono::Local, day.and_time(boundary)),
+        };
+        DateRange {
+            start: first.map(get_start),
+            end: last.map(|v| get_start(v).plus_days(1)),
+        }
+    }
+
+    pub fn contains(&self, date: &chrono::DateTime<chrono::Local>) -> bool {
+        self.start.as_ref().is_none_or(|v| v <= date) && self.end.as_ref().is_none_or(|v| v > date)
+    }
+}
+
+/// Time relative to some date, parsed from user input.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum RelativeTime {
+    /// `1h30m`, `-15s`, offset in milliseconds.
+    Offset(i64),
+    /// `13:15`, `-9:02`, the closest such time at or after the date, or before it when
+    /// `backwards`.
+    ClockTime {
+        hour: u32,
+        minute: u32,
+        backwards: bool,
+    },
+    /// `10`, `-10`, the closest such minute at or after the date, or before it when `backwards`.
+    MinuteOfHour { minute: u32, backwards: bool },
+}
+
+impl RelativeTime {
+    pub fn apply(&self, dt: &DateTime) -> DateTime {
+        match *self {
+            RelativeTime::Offset(offset) => dt.plus_milli(offset),
+            RelativeTime::ClockTime {
+                hour,
+                minute,
+                backwards,
+            } => {
+                let time = chrono::NaiveTime::from_hms_opt(hour, minute, 0)
+                    .expect("clock time must be valid");
+                let date = DateTime {
+                    date: from_local(&dt.date.timezone(), dt.date.date_naive().and_time(time)),
+                };
+                match (backwards, date.date.cmp(&dt.date)) {
+                    (false, Ordering::Less) => date.plus_days(1),
+                    (true, Ordering::Equal | Ordering::Greater) => date.plus_days(-1),
+                    _ => date,
+                }
+            }
+            RelativeTime::MinuteOfHour { minute, backwards } => {
+                let date = DateTime {
+                    date: dt.date.with_minute(minute).unwrap(),
+                };
+                match (backwards, date.date.cmp(&dt.date)) {
+                    (false, Ordering::Less) => date.plus_hours(1),
+                    (true, Ordering::Equal | Ordering::Greater) => date.plus_hours(-1),
+                    _ => date,
+                }
+            }
+        }
+    }
+
+    /// Parses unsigned offsets like `1h30m` or `45s` into milliseconds. Units must be in
+    /// descending order and can't repeat.
+    fn parse_offset(text: &str) -> Result<i64, &'static str> {
+        let mut offset: i64 = 0;
+        let mut number = String::new();
+        let mut unit_last: Option<Duration> = None;
+        for char in text.chars() {
+            if char.is_ascii_digit() {
+                number.push(char);
+                continue;
+            }
+            let unit = match char {
+                'h' => Duration::HOUR,
+                'm' => Duration::MINUTE,
+                's' => Duration::SECOND,
+                _ => return Err("invalid time"),
             };
-            if is_same_day {
-                return Ok(DateTime { date: date_parsed });
-            } else {
-                let date = DateTime::new(&date_parsed).plus_days(sign);
-                return Ok(date);
+            if number.is_empty() {
+                return Err("time unit must be preceded by a number");
+            }
+            if unit_last.is_some_and(|v| unit >= v) {
+                return Err("time units must be in descending order and can't repeat");
             }
+            let value: i64 = number.parse::<u32>().map_err(|_e| "invalid time")?.into();
+            offset += value * unit.as_millis() as i64;
+            unit_last = Some(unit);
+            number.clear();
         }
+        if !number.is_empty() {
+            return Err("number must be followed by a time unit");
+        }
+        Ok(offset)
+    }
 
+    fn parse_number(text: &str, limit: u32, reason: &'static str) -> Result<u32, &'static str> {
         text.parse::<u32>()
-            .map_err(|_e| "invalid minute")
-            .and_then(|v| if v < 60 { Ok(v) } else { Err("") })
-            .map_err(|_e| "minute must be less than 60")
-            .map(|v| {
-                let date_parsed = self.date.with_minute(v).unwrap();
-                let difference = date_parsed.timestamp_millis() - self.date.timestamp_millis();
-                let is_same_hour = if difference == 0 {
-                    sign > 0
-                } else {
-                    sign * difference > 0
-                };
-                if is_same_hour {
-                    return DateTime { date: date_parsed };
-                } else {
-                    return DateTime::new(&date_parsed).plus_hours(sign);
+            .ok()
+            .filter(|v| *v < limit)
+            .ok_or(reason)
+    }
+}
+
+impl FromStr for RelativeTime {
+    type Err = RelativeTimeError;
+
+    fn from_str(text: &str) -> Result<RelativeTime, RelativeTimeError> {
+        let token = text.trim();
+        let error = |reason| RelativeTimeError {
+            token: token.to_owned(),
+            reason,
+        };
+        if token.starts_with("--") {
+            return Err(error("time cannot start with `--`"));
+        }
+        let (backwards, text) = match token.strip_prefix("-") {
+            Some(text) => (true, text),
+            None => (false, token.strip_prefix("+").unwrap_or(token)),
+        };
+        if text.starts_with(['-', '+']) {
+            return Err(error("time can have only one sign"));
+        }
+
+        if text.ends_with(['h', 'm', 's']) {
+            let offset = RelativeTime::parse_offset(text).map_err(error)?;
+            let offset = if backwards { -offset } else { offset };
+            return Ok(RelativeTime::Offset(offset));
+        }
+
+        if let Some((hour, minute)) = text.split_once(":") {
+            if text.len() > "23:59".len() || hour.is_empty() || minute.is_empty() {
+                return Err(error("failed to parse provided time"));
+            }
+            let hour =
+                RelativeTime::parse_number(hour, 24, "hour must be less than 24").map_err(error)?;
+            let minute = RelativeTime::parse_number(minute, 60, "minute must be less than 60")
+                .map_err(error)?;
+            return Ok(RelativeTime::ClockTime {
+                hour,
+                minute,
+                backwards,
+            });
+        }
+
+        let minute =
+            RelativeTime::parse_number(text, 60, "failed to parse provided time").map_err(error)?;
+        Ok(RelativeTime::MinuteOfHour { minute, backwards })
+    }
+}
+
+/// Canonical form, parsing it again results in the same value.
+impl fmt::Display for RelativeTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = |backwards| if backwards { "-" } else { "" };
+        match *self {
+            RelativeTime::Offset(offset) => {
+                let millis = offset.unsigned_abs();
+                let hours = millis / Duration::HOUR.as_millis();
+                let minutes = millis % Duration::HOUR.as_millis() / Duration::MINUTE.as_millis();
+                let seconds = millis % Duration::MINUTE.as_millis() / Duration::SECOND.as_millis();
+                let mut text = String::new();
+                for (value, unit) in [(hours, 'h'), (minutes, 'm'), (seconds, 's')] {
+                    if value > 0 {
+                        text += &format!("{value}{unit}");
+                    }
                 }
-            })
-            .map_err(|_e| "failed to parse provided time")
+                if text.is_empty() {
+                    text = String::from("0s");
+                }
+                write!(f, "{}{text}", sign(offset < 0))
+            }
+            RelativeTime::ClockTime {
+                hour,
+                minute,
+                backwards,
+            } => write!(f, "{}{hour}:{minute:02}", sign(backwards)),
+            RelativeTime::MinuteOfHour { minute, backwards } => {
+                write!(f, "{}{minute}", sign(backwards))
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RelativeTimeError {
+    /// The user input that failed to parse.
+    pub token: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RelativeTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} `{}`", self.reason, self.token)
+    }
+}
+
+impl Error for RelativeTimeError {}
+
+/// Length of time with millisecond precision.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default, Clone, Copy)]
+pub struct Duration(u64);
+
+impl Duration {
+    pub const ZERO: Duration = Duration(0);
+    pub const SECOND: Duration = Duration(1000);
+    pub const MINUTE: Duration = Duration(60 * Duration::SECOND.0);
+    pub const HOUR: Duration = Duration(60 * Duration::MINUTE.0);
+
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration(millis)
+    }
+
+    pub const fn from_secs(secs: u64) -> Duration {
+        Duration(secs * Duration::SECOND.0)
+    }
+
+    /// Parses durations like `15m` or `1h30m`, see [`RelativeTime`] offsets.
+    pub fn from_text(text: &str) -> Result<Duration, String> {
+        let text = text.trim();
+        let millis = RelativeTime::parse_offset(text)
+            .map_err(|reason| format!("invalid duration `{text}`: {reason}"))?;
+        Ok(Duration(millis as u64))
+    }
+
+    /// Rounds to the nearest multiple of `increment`, ties round up. Zero `increment` leaves the
+    /// duration as is.
+    pub fn round(self, increment: Duration) -> Duration {
+        if increment == Duration::ZERO {
+            return self;
+        }
+        Duration((self.0 + increment.0 / 2) / increment.0 * increment.0)
+    }
+
+    /// Time elapsed from `start` to `end`, returns error if `end` precedes `start`.
+    pub fn between(
+        start: &chrono::DateTime<chrono::Local>,
+        end: &chrono::DateTime<chrono::Local>,
+    ) -> Result<Duration, String> {
+        let time = end.timestamp_millis() - start.timestamp_millis();
+        let time = time.try_into().map_err(|_| {
+            format!(
+                "`{}` precedes `{}`",
+                DateTime::new(end).to_formatted_pretty(),
+                DateTime::new(start).to_formatted_pretty()
+            )
+        })?;
+        Ok(Duration(time))
+    }
+
+    pub fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    pub fn as_secs(self) -> u64 {
+        self.0 / Duration::SECOND.0
+    }
+
+    /// Inverse of [`Duration::from_text`] like `1h30m`, zero units and milliseconds are left out.
+    pub fn to_text(self) -> String {
+        let hours = self.0 / Duration::HOUR.0;
+        let minutes = self.0 % Duration::HOUR.0 / Duration::MINUTE.0;
+        let seconds = self.0 % Duration::MINUTE.0 / Duration::SECOND.0;
+        let text: String = [(hours, 'h'), (minutes, 'm'), (seconds, 's')]
+            .iter()
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, unit)| format!("{value}{unit}"))
+            .collect();
+        if text.is_empty() {
+            return String::from("0s");
+        }
+        text
+    }
+
+    pub fn format(self, format: &DurationFormat) -> String {
+        let hours = self.0 / Duration::HOUR.0;
+        let minutes = self.0 % Duration::HOUR.0 / Duration::MINUTE.0;
+        match format {
+            DurationFormat::Hms => {
+                let seconds = self.0 % Duration::MINUTE.0 / Duration::SECOND.0;
+                format!("{hours}h {minutes}m {seconds}s")
+            }
+            DurationFormat::Clock => format!("{hours}:{minutes:02}"),
+            DurationFormat::Decimal => {
+                // Rounds half up to hundredths of an hour.
+                let hundredths = (self.0 * 100 + Duration::HOUR.0 / 2) / Duration::HOUR.0;
+                format!("{}.{:02}h", hundredths / 100, hundredths % 100)
+            }
+        }
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        Duration(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sum for Duration {
+    fn sum<I: Iterator<Item = Duration>>(iter: I) -> Duration {
+        iter.fold(Duration::ZERO, |acc, val| acc + val)
+    }
+}
+
+/// How dates are presented to the user.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub enum TimestampFormat {
+    /// `today 14:32`
+    #[default]
+    Relative,
+    /// `2025-01-07 14:32`
+    Absolute,
+}
+
+impl TimestampFormat {
+    pub fn from_text(text: &str) -> Result<TimestampFormat, String> {
+        match text.trim() {
+            "relative" => Ok(TimestampFormat::Relative),
+            "absolute" => Ok(TimestampFormat::Absolute),
+            text => Err(format!(
+                "invalid timestamp format `{text}`, use `relative` or `absolute`"
+            )),
+        }
+    }
+}
+
+/// Clock of [`DisplayPrefs`].
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub enum Clock {
+    /// `2:32 PM`
+    H12,
+    /// `14:32`
+    #[default]
+    H24,
+}
+
+impl Clock {
+    pub fn from_text(text: &str) -> Result<Clock, String> {
+        match text.trim() {
+            "12" => Ok(Clock::H12),
+            "24" => Ok(Clock::H24),
+            text => Err(format!("invalid clock `{text}`, use `12` or `24`")),
+        }
+    }
+}
+
+/// How [`DisplayPrefs`] show the date without the time.
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub enum DateStyle {
+    /// `2025-01-07`
+    #[default]
+    Iso,
+    /// `Jan 7, 2025`
+    Short,
+    /// `Tuesday, January 7, 2025`
+    Long,
+}
+
+impl DateStyle {
+    pub fn from_text(text: &str) -> Result<DateStyle, String> {
+        match text.trim() {
+            "iso" => Ok(DateStyle::Iso),
+            "short" => Ok(DateStyle::Short),
+            "long" => Ok(DateStyle::Long),
+            text => Err(format!(
+                "invalid date style `{text}`, use `iso`, `short` or `long`"
+            )),
+        }
+    }
+}
+
+/// How dates are shown to the user. Session files always use the ISO format of
+/// [`DateTime::to_formatted_pretty`], so that they stay portable.
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct DisplayPrefs {
+    pub clock: Clock,
+    pub date_style: DateStyle,
+}
+
+impl DisplayPrefs {
+    pub fn format_date(&self, date: chrono::NaiveDate) -> String {
+        let format = match self.date_style {
+            DateStyle::Iso => "%F",
+            DateStyle::Short => "%b %-d, %Y",
+            DateStyle::Long => "%A, %B %-d, %Y",
+        };
+        date.format(format).to_string()
+    }
+
+    /// Date with its weekday, which [`DateStyle::Long`] already includes, `Tue 2025-01-07`.
+    pub fn format_day(&self, date: chrono::NaiveDate) -> String {
+        match self.date_style {
+            DateStyle::Long => self.format_date(date),
+            _ => format!("{} {}", date.format("%a"), self.format_date(date)),
+        }
+    }
+
+    /// Start of `hour` of the day, `09:00` or `9 AM`.
+    pub fn format_hour(&self, hour: u32) -> String {
+        let time = chrono::NaiveTime::from_hms_opt(hour % 24, 0, 0).expect("hour must be valid");
+        match self.clock {
+            Clock::H24 => time.format("%H:%M").to_string(),
+            Clock::H12 => time.format("%-I %p").to_string(),
+        }
+    }
+
+    /// Hours and minutes of `date`, with seconds when `seconds` is set.
+    pub fn format_time(&self, date: &chrono::DateTime<chrono::Local>, seconds: bool) -> String {
+        let format = match (self.clock, seconds) {
+            (Clock::H24, false) => "%H:%M",
+            (Clock::H24, true) => "%T",
+            (Clock::H12, false) => "%-I:%M %p",
+            (Clock::H12, true) => "%-I:%M:%S %p",
+        };
+        date.format(format).to_string()
+    }
+}
+
+#[derive(PartialEq, Debug, Default, Clone)]
+pub enum DurationFormat {
+    /// `5h 15m 0s`
+    #[default]
+    Hms,
+    /// `5:15`
+    Clock,
+    /// `5.25h`
+    Decimal,
+}
+
+impl DurationFormat {
+    pub fn from_text(text: &str) -> Result<DurationFormat, String> {
+        match text.trim() {
+            "hms" => Ok(DurationFormat::Hms),
+            "clock" => Ok(DurationFormat::Clock),
+            "decimal" => Ok(DurationFormat::Decimal),
+            text => Err(format!(
+                "invalid duration format `{text}`, use `hms`, `clock` or `decimal`"
+            )),
+        }
     }
 }
 
@@ -229,7 +739,7 @@ mod tests {
         assert_eq!(date.nanosecond(), 0);
         assert_eq!(date.offset(), now.offset());
 
-        assert_eq!(formatted, DateTime::format(&date));
+        assert_eq!(formatted, DateTime::new(&date).to_formatted());
     }
 
     #[test]
@@ -240,6 +750,38 @@ mod tests {
         assert_eq!(DateTime::new(&testing::date_default()), dt);
     }
 
+    #[test]
+    fn date_time_from_works() {
+        let date = testing::date_default();
+        assert_eq!(DateTime::from(date), DateTime::new(&date));
+        let dt: DateTime = date.into();
+        assert_eq!(dt.date, date);
+    }
+
+    #[test]
+    fn date_time_compares() {
+        let date = testing::date_default();
+        let dt = DateTime::new(&date);
+        let later = dt.plus_seconds(1);
+        assert!(dt < later);
+        assert!(later > dt);
+        assert_eq!(dt.cmp(&dt), Ordering::Equal);
+        assert_eq!(dt.clone().max(later.clone()), later);
+
+        assert_eq!(dt, date);
+        assert_ne!(later, date);
+        assert!(later > date);
+        assert!(dt >= date);
+        assert!(dt < later.date);
+    }
+
+    #[test]
+    fn date_time_display_works() {
+        let dt = DateTime::new(&testing::date_default());
+        assert_eq!(dt.to_string(), dt.to_formatted_pretty());
+        assert_eq!(format!("{dt}"), dt.to_formatted_pretty());
+    }
+
     #[test]
     fn date_time_plus_milli_works() {
         fn date_default_plus_milli(milli: i64) -> chrono::DateTime<chrono::Local> {
@@ -361,26 +903,194 @@ mod tests {
 
     #[test]
     fn date_time_to_formatted_pretty_short_works() {
-        let date = &testing::date_default();
+        let dt = DateTime::new(&testing::date_default().with_minute(5).unwrap());
+        assert_eq!(dt.to_formatted_pretty_short(), "2002-05-08 12:05");
+        let dt = DateTime::new(&testing::date_default().with_second(59).unwrap());
+        assert_eq!(dt.to_formatted_pretty_short(), "2002-05-08 12:00");
+    }
 
-        let dt = DateTime::new(&date);
-        assert_eq!(dt.to_formatted_pretty_short(), dt.to_formatted_pretty());
+    #[test]
+    fn date_time_to_formatted_relative_works() {
+        // Wednesday.
+        let now = DateTime::new(&testing::date_default());
+        let relative = |dt: DateTime| dt.to_formatted_relative(&now, &DisplayPrefs::default());
+        assert_eq!(relative(now.clone()), "today 12:00");
+        assert_eq!(relative(now.plus_hours(-12)), "today 00:00");
+        assert_eq!(relative(now.plus_minutes(-12 * 60 - 1)), "yesterday 23:59");
+        assert_eq!(
+            relative(now.plus_days(-1).plus_hours(-2)),
+            "yesterday 10:00"
+        );
+        assert_eq!(relative(now.plus_days(-2)), "Mon 12:00");
+        assert_eq!(relative(now.plus_days(-6)), "Thu 12:00");
+        assert_eq!(relative(now.plus_days(-7)), "2002-05-01 12:00");
+        assert_eq!(relative(now.plus_days(1)), "2002-05-09 12:00");
+        assert_eq!(relative(now.plus_days(-365)), "2001-05-08 12:00");
+    }
 
-        let date = date.with_day(DateTime::now().date.day()).unwrap();
+    #[test]
+    fn date_time_to_formatted_relative_uses_prefs() {
+        let now = DateTime::new(&testing::date_default().with_hour(14).unwrap());
+        let prefs = DisplayPrefs {
+            clock: Clock::H12,
+            date_style: DateStyle::Short,
+        };
+        let relative = |dt: DateTime| dt.to_formatted_relative(&now, &prefs);
+        assert_eq!(relative(now.clone()), "today 2:00 PM");
+        assert_eq!(relative(now.plus_days(-1)), "yesterday 2:00 PM");
+        assert_eq!(relative(now.plus_days(-2)), "Mon 2:00 PM");
+        assert_eq!(relative(now.plus_days(-7)), "May 1, 2002 2:00 PM");
+    }
 
-        let dt = DateTime::new(&date.with_month(8).unwrap());
-        assert_eq!(dt.to_formatted_pretty_short(), dt.to_formatted_pretty());
+    #[test]
+    fn date_time_to_formatted_with_works() {
+        let dt = DateTime::now();
+        let prefs = DisplayPrefs::default();
+        assert!(dt
+            .to_formatted_with(&TimestampFormat::Relative, &prefs)
+            .starts_with("today "));
+        assert_eq!(
+            dt.to_formatted_with(&TimestampFormat::Absolute, &prefs),
+            dt.to_formatted_pretty_short()
+        );
+    }
 
-        let dt = DateTime::new(&date.with_year(2005).unwrap());
-        assert_eq!(dt.to_formatted_pretty_short(), dt.to_formatted_pretty());
+    #[test]
+    fn display_prefs_work() {
+        // Wednesday afternoon.
+        let dt = DateTime::new(
+            &testing::date_default()
+                .with_hour(14)
+                .and_then(|v| v.with_minute(5))
+                .and_then(|v| v.with_second(9))
+                .unwrap(),
+        );
+        let cases = [
+            (Clock::H24, DateStyle::Iso, "2002-05-08 14:05", "14:05:09"),
+            (
+                Clock::H24,
+                DateStyle::Short,
+                "May 8, 2002 14:05",
+                "14:05:09",
+            ),
+            (
+                Clock::H24,
+                DateStyle::Long,
+                "Wednesday, May 8, 2002 14:05",
+                "14:05:09",
+            ),
+            (
+                Clock::H12,
+                DateStyle::Iso,
+                "2002-05-08 2:05 PM",
+                "2:05:09 PM",
+            ),
+            (
+                Clock::H12,
+                DateStyle::Short,
+                "May 8, 2002 2:05 PM",
+                "2:05:09 PM",
+            ),
+            (
+                Clock::H12,
+                DateStyle::Long,
+                "Wednesday, May 8, 2002 2:05 PM",
+                "2:05:09 PM",
+            ),
+        ];
+        for (clock, date_style, display, time) in cases {
+            let prefs = DisplayPrefs { clock, date_style };
+            assert_eq!(dt.to_formatted_display(&prefs), display);
+            assert_eq!(dt.to_formatted_display_time(&prefs), time);
+        }
+        // Serialization doesn't depend on the preferences.
+        assert_eq!(
+            dt.to_formatted_pretty(),
+            dt.date.format("%F %T %:z").to_string()
+        );
 
-        let dt = DateTime::now();
-        assert_eq!(dt.to_formatted_pretty_short(), dt.to_formatted_time());
+        let day = dt.date.date_naive();
+        let prefs = |date_style| DisplayPrefs {
+            date_style,
+            ..Default::default()
+        };
+        assert_eq!(prefs(DateStyle::Iso).format_day(day), "Wed 2002-05-08");
+        assert_eq!(prefs(DateStyle::Short).format_day(day), "Wed May 8, 2002");
+        assert_eq!(
+            prefs(DateStyle::Long).format_day(day),
+            "Wednesday, May 8, 2002"
+        );
+        let midnight = DateTime::new(&testing::date_default().with_hour(0).unwrap());
+        let h12 = DisplayPrefs {
+            clock: Clock::H12,
+            ..Default::default()
+        };
+        assert_eq!(h12.format_time(&midnight.date, false), "12:00 AM");
+    }
+
+    #[test]
+    fn clock_and_date_style_from_text_work() {
+        assert_eq!(Clock::from_text("12"), Ok(Clock::H12));
+        assert_eq!(Clock::from_text(" 24 "), Ok(Clock::H24));
+        assert!(Clock::from_text("12h").is_err());
+        assert_eq!(DateStyle::from_text("iso"), Ok(DateStyle::Iso));
+        assert_eq!(DateStyle::from_text("short"), Ok(DateStyle::Short));
+        assert_eq!(DateStyle::from_text("long"), Ok(DateStyle::Long));
+        assert!(DateStyle::from_text("Long").is_err());
+    }
+
+    #[test]
+    fn timestamp_format_from_text_works() {
+        assert_eq!(
+            TimestampFormat::from_text("relative"),
+            Ok(TimestampFormat::Relative)
+        );
+        assert_eq!(
+            TimestampFormat::from_text(" absolute "),
+            Ok(TimestampFormat::Absolute)
+        );
+        assert!(TimestampFormat::from_text("iso").is_err());
+    }
+
+    #[test]
+    fn date_time_start_of_day_works() {
+        let boundary = chrono::NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        let date_default = testing::date_default();
+        let day_start = date_default.with_hour(4).unwrap();
+
+        // Normal daytime mark.
+        let dt = DateTime::new(&date_default);
+        assert_eq!(dt.start_of_day(boundary).date, day_start);
+        // Exactly at the boundary.
+        let dt = DateTime::new(&day_start);
+        assert_eq!(dt.start_of_day(boundary).date, day_start);
+        // Between midnight and the boundary belongs to the previous day.
+        let dt = DateTime::new(&date_default.with_hour(3).unwrap().with_minute(59).unwrap());
+        assert_eq!(
+            dt.start_of_day(boundary),
+            DateTime::new(&day_start).plus_days(-1)
+        );
+        let dt = DateTime::new(&date_default.with_hour(0).unwrap());
+        assert_eq!(
+            dt.start_of_day(boundary),
+            DateTime::new(&day_start).plus_days(-1)
+        );
+
+        let midnight = chrono::NaiveTime::default();
+        let dt = DateTime::new(&date_default.with_hour(0).unwrap());
+        assert_eq!(dt.start_of_day(midnight), dt);
+        let dt = DateTime::new(&date_default.with_hour(3).unwrap());
+        assert_eq!(
+            dt.start_of_day(midnight).date,
+            date_default.with_hour(0).unwrap()
+        );
     }
 
     #[test]
     fn date_time_get_start_of_week_works() {
-        let date = DateTime::get_start_of_week(&testing::date_default());
+        let date = DateTime::new(&testing::date_default())
+            .start_of_week(chrono::NaiveTime::default())
+            .date;
         assert_eq!(date.weekday(), chrono::Weekday::Mon);
         assert_eq!(
             date,
@@ -398,15 +1108,172 @@ mod tests {
     }
 
     #[test]
-    fn date_time_get_time_works() {
+    fn get_start_of_week_works_across_month_boundary() {
+        let tz = testing::DstZone;
+        let midnight = chrono::NaiveTime::default();
+        // Wednesday.
+        let date = testing::dst_zone_date(2025, 10, 1, 15, 0);
+        assert_eq!(
+            get_start_of_week(&date, midnight),
+            testing::dst_zone_date(2025, 9, 29, 0, 0)
+        );
+        // Sunday.
+        let date = testing::dst_zone_date(2025, 3, 2, 23, 59);
+        assert_eq!(
+            get_start_of_week(&date, midnight),
+            testing::dst_zone_date(2025, 2, 24, 0, 0)
+        );
+        assert_eq!(get_start_of_week(&date, midnight).timezone(), tz);
+    }
+
+    #[test]
+    fn get_start_of_week_works_around_dst_transitions() {
+        let midnight = chrono::NaiveTime::default();
+        // Spring forward happens on Sunday 2025-03-30.
+        let monday = testing::dst_zone_date(2025, 3, 24, 0, 0);
+        for (day, hour) in [(24, 0), (29, 23), (30, 1), (30, 3), (30, 23)] {
+            let date = testing::dst_zone_date(2025, 3, day, hour, 30);
+            assert_eq!(get_start_of_week(&date, midnight), monday, "{date}");
+        }
+        assert_eq!(
+            get_start_of_week(&testing::dst_zone_date(2025, 3, 31, 0, 30), midnight),
+            testing::dst_zone_date(2025, 3, 31, 0, 0)
+        );
+        // Fall back happens on Sunday 2025-10-26, the late evening used to end up on Tuesday.
+        let monday = testing::dst_zone_date(2025, 10, 20, 0, 0);
+        for (day, hour) in [(20, 0), (25, 23), (26, 2), (26, 3), (26, 23)] {
+            let date = testing::dst_zone_date(2025, 10, day, hour, 30);
+            assert_eq!(get_start_of_week(&date, midnight), monday, "{date}");
+        }
+        assert_eq!(
+            get_start_of_week(&testing::dst_zone_date(2025, 10, 27, 0, 30), midnight),
+            testing::dst_zone_date(2025, 10, 27, 0, 0)
+        );
+    }
+
+    #[test]
+    fn add_days_keeps_local_time_across_dst_transitions() {
+        let date = testing::dst_zone_date(2025, 3, 29, 12, 0);
+        assert_eq!(
+            add_days(&date, 1),
+            testing::dst_zone_date(2025, 3, 30, 12, 0)
+        );
+        assert_eq!(add_days(&date, 1) - date, chrono::TimeDelta::hours(23));
+        let date = testing::dst_zone_date(2025, 10, 27, 1, 0);
+        assert_eq!(
+            add_days(&date, -1),
+            testing::dst_zone_date(2025, 10, 26, 1, 0)
+        );
+        assert_eq!(date - add_days(&date, -1), chrono::TimeDelta::hours(25));
+        assert_eq!(
+            add_days(&date, -7),
+            testing::dst_zone_date(2025, 10, 20, 1, 0)
+        );
+        // 02:30 doesn't exist on 2025-03-30.
+        let date = testing::dst_zone_date(2025, 3, 29, 2, 30);
+        assert_eq!(
+            add_days(&date, 1),
+            testing::dst_zone_date(2025, 3, 30, 3, 0)
+        );
+    }
+
+    #[test]
+    fn week_buckets_sessions_correctly_after_fall_back() {
+        let midnight = chrono::NaiveTime::default();
+        // Sunday evening after the clocks went back still belongs to the week of the transition,
+        // the following Monday starts a new one.
+        let sunday = testing::dst_zone_date(2025, 10, 26, 22, 0);
+        let monday = add_days(&sunday, 1);
+        let start_of_week = get_start_of_week(&monday, midnight);
+        assert!(sunday < start_of_week);
+        assert_eq!(start_of_week, testing::dst_zone_date(2025, 10, 27, 0, 0));
+        assert_eq!(
+            get_start_of_week(&sunday, midnight),
+            add_days(&start_of_week, -7)
+        );
+    }
+
+    #[test]
+    fn get_start_of_day_handles_nonexistent_and_ambiguous_boundary() {
+        let boundary = chrono::NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        // 02:30 is skipped on 2025-03-30, the day starts right after the gap instead.
+        let date = testing::dst_zone_date(2025, 3, 30, 12, 0);
+        assert_eq!(
+            get_start_of_day(&date, boundary),
+            testing::dst_zone_date(2025, 3, 30, 3, 0)
+        );
+        // 02:30 happens twice on 2025-10-26, the day starts at the earlier one.
+        let date = testing::dst_zone_date(2025, 10, 26, 12, 0);
+        let start = get_start_of_day(&date, boundary);
+        assert_eq!(start.naive_local().time(), boundary);
+        assert_eq!(start.offset().local_minus_utc(), 2 * 60 * 60);
+        assert_eq!(
+            get_start_of_week(&date, boundary),
+            testing::dst_zone_date(2025, 10, 20, 2, 30)
+        );
+    }
+
+    #[test]
+    fn date_time_get_start_of_week_respects_day_boundary() {
+        let boundary = chrono::NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        let monday = testing::date_default()
+            .with_day(testing::date_default().day() - 2)
+            .unwrap()
+            .with_hour(4)
+            .unwrap();
+        assert_eq!(
+            DateTime::from(testing::date_default()).start_of_week(boundary),
+            monday
+        );
+        assert_eq!(DateTime::from(monday).start_of_week(boundary), monday);
+        // Monday night before the boundary still belongs to the previous week.
+        let date = monday.with_hour(3).unwrap();
+        assert_eq!(
+            DateTime::from(date).start_of_week(boundary),
+            DateTime::new(&monday).plus_days(-7)
+        );
+    }
+
+    #[test]
+    fn duration_between_works() {
         let start = DateTime::now().date.with_minute(2).unwrap();
         let end = start.with_minute(5).unwrap();
-        let time = DateTime::get_time(&start, &end);
-        assert_eq!(time, 180_000);
+        let time = Duration::between(&start, &end).unwrap();
+        assert_eq!(time.as_millis(), 180_000);
+        assert_eq!(time.as_secs(), 180);
+        assert_eq!(Duration::between(&start, &start), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn duration_between_fails_when_end_precedes_start() {
+        let start = testing::date_default();
+        let end = start.with_minute(0).unwrap().with_hour(11).unwrap();
+        assert_eq!(
+            Duration::between(&start, &end).unwrap_err(),
+            format!(
+                "`{}` precedes `{}`",
+                DateTime::new(&end).to_formatted_pretty(),
+                DateTime::new(&start).to_formatted_pretty()
+            )
+        );
     }
 
     #[test]
-    fn date_time_get_time_hr_from_milli_works() {
+    fn duration_add_and_sum_works() {
+        let mut time = Duration::from_secs(30) + Duration::from_millis(500);
+        assert_eq!(time, Duration::from_millis(30_500));
+        time += Duration::MINUTE;
+        assert_eq!(time, Duration::from_millis(90_500));
+        let total: Duration = [Duration::HOUR, Duration::MINUTE, Duration::SECOND]
+            .into_iter()
+            .sum();
+        assert_eq!(total, Duration::from_secs(60 * 60 + 60 + 1));
+        let empty: Duration = std::iter::empty().sum();
+        assert_eq!(empty, Duration::ZERO);
+    }
+
+    #[test]
+    fn duration_to_formatted_works() {
         let start = DateTime::now()
             .date
             .with_year(2000)
@@ -441,12 +1308,289 @@ mod tests {
             .with_second(2)
             .unwrap();
         let text = "9554h 2m 2s";
-        let time = DateTime::get_time(&start, &end);
-        assert_eq!(DateTime::get_time_hr_from_milli(time), text);
+        let time = Duration::between(&start, &end).unwrap();
+        assert_eq!(time.format(&DurationFormat::Hms), text);
     }
 
     #[test]
-    fn date_time_modify_works() -> Result<(), &'static str> {
+    fn duration_format_works() {
+        let zero = Duration::ZERO;
+        let sub_minute = Duration::from_secs(59);
+        let quarter = Duration::from_secs(5 * 60 * 60 + 15 * 60);
+        let over_day = Duration::from_secs(27 * 60 * 60 + 4 * 60 + 30);
+
+        let format = DurationFormat::Hms;
+        assert_eq!(zero.format(&format), "0h 0m 0s");
+        assert_eq!(sub_minute.format(&format), "0h 0m 59s");
+        assert_eq!(over_day.format(&format), "27h 4m 30s");
+
+        let format = DurationFormat::Clock;
+        assert_eq!(zero.format(&format), "0:00");
+        assert_eq!(sub_minute.format(&format), "0:00");
+        assert_eq!(quarter.format(&format), "5:15");
+        assert_eq!(over_day.format(&format), "27:04");
+
+        let format = DurationFormat::Decimal;
+        assert_eq!(zero.format(&format), "0.00h");
+        assert_eq!(sub_minute.format(&format), "0.02h");
+        assert_eq!(quarter.format(&format), "5.25h");
+        assert_eq!(over_day.format(&format), "27.08h");
+        // 18 seconds is exactly half of a hundredth of an hour and rounds up.
+        assert_eq!(Duration::from_secs(18).format(&format), "0.01h");
+        assert_eq!(Duration::from_secs(17).format(&format), "0.00h");
+    }
+
+    #[test]
+    fn duration_to_text_works() {
+        assert_eq!(Duration::from_secs(90 * 60).to_text(), "1h30m");
+        assert_eq!(Duration::from_secs(30 * 60 * 60 + 5).to_text(), "30h5s");
+        assert_eq!(Duration::ZERO.to_text(), "0s");
+        let duration = Duration::from_secs(3 * 60 * 60 + 2 * 60 + 1);
+        assert_eq!(Duration::from_text(&duration.to_text()), Ok(duration));
+    }
+
+    #[test]
+    fn duration_from_text_works() {
+        assert_eq!(Duration::from_text("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(
+            Duration::from_text(" 1h30m "),
+            Ok(Duration::from_secs(90 * 60))
+        );
+        assert_eq!(Duration::from_text("0m"), Ok(Duration::ZERO));
+        assert!(Duration::from_text("").is_ok_and(|v| v == Duration::ZERO));
+        assert!(Duration::from_text("15").is_err());
+        assert!(Duration::from_text("-15m").is_err());
+        assert!(Duration::from_text("15x").is_err());
+    }
+
+    #[test]
+    fn duration_round_works() {
+        let minute = Duration::MINUTE;
+        let time = Duration::from_secs(60 * 60 + 7 * 60 + 29);
+        assert_eq!(time.round(minute), Duration::from_secs(67 * 60));
+        assert_eq!(
+            Duration::from_secs(67 * 60 + 30).round(minute),
+            Duration::from_secs(68 * 60)
+        );
+
+        let increment = Duration::from_secs(6 * 60);
+        assert_eq!(time.round(increment), Duration::from_secs(66 * 60));
+        assert_eq!(
+            Duration::from_secs(69 * 60).round(increment),
+            Duration::from_secs(72 * 60)
+        );
+
+        let increment = Duration::from_secs(15 * 60);
+        assert_eq!(time.round(increment), Duration::from_secs(60 * 60));
+        assert_eq!(
+            Duration::from_secs(67 * 60 + 30).round(increment),
+            Duration::from_secs(75 * 60)
+        );
+        assert_eq!(
+            Duration::from_secs(8 * 60).round(increment),
+            Duration::from_secs(15 * 60)
+        );
+
+        // Just below half an increment.
+        assert_eq!(
+            Duration::from_millis(7 * 60 * 1000 + 29_999).round(increment),
+            Duration::ZERO
+        );
+        assert_eq!(Duration::from_millis(29_999).round(minute), Duration::ZERO);
+        assert_eq!(
+            Duration::from_millis(2 * 60 * 1000 + 59_999).round(Duration::from_secs(6 * 60)),
+            Duration::ZERO
+        );
+
+        assert_eq!(time.round(Duration::ZERO), time);
+        assert_eq!(Duration::ZERO.round(increment), Duration::ZERO);
+    }
+
+    #[test]
+    fn duration_format_from_text_works() {
+        assert_eq!(DurationFormat::from_text("hms"), Ok(DurationFormat::Hms));
+        assert_eq!(
+            DurationFormat::from_text("clock"),
+            Ok(DurationFormat::Clock)
+        );
+        assert_eq!(
+            DurationFormat::from_text("decimal"),
+            Ok(DurationFormat::Decimal)
+        );
+        assert!(DurationFormat::from_text("hours").is_err());
+    }
+
+    #[test]
+    fn relative_time_parse_offset_works() {
+        let hour = Duration::HOUR.as_millis() as i64;
+        let minute = Duration::MINUTE.as_millis() as i64;
+        let second = Duration::SECOND.as_millis() as i64;
+        assert_eq!(RelativeTime::parse_offset("0s"), Ok(0));
+        assert_eq!(RelativeTime::parse_offset("45s"), Ok(45 * second));
+        assert_eq!(RelativeTime::parse_offset("90m"), Ok(90 * minute));
+        assert_eq!(RelativeTime::parse_offset("1h"), Ok(hour));
+        assert_eq!(RelativeTime::parse_offset("1h30m"), Ok(hour + 30 * minute));
+        assert_eq!(RelativeTime::parse_offset("1h5s"), Ok(hour + 5 * second));
+        assert_eq!(
+            RelativeTime::parse_offset("2h3m4s"),
+            Ok(2 * hour + 3 * minute + 4 * second)
+        );
+        assert_eq!(RelativeTime::parse_offset("01h05m"), Ok(hour + 5 * minute));
+
+        assert!(RelativeTime::parse_offset("h").is_err());
+        assert!(RelativeTime::parse_offset("1").is_err());
+        assert!(RelativeTime::parse_offset("1h30").is_err());
+        assert!(RelativeTime::parse_offset("1h1h").is_err());
+        assert!(RelativeTime::parse_offset("1m1h").is_err());
+        assert!(RelativeTime::parse_offset("1s1s").is_err());
+        assert!(RelativeTime::parse_offset("1h-1m").is_err());
+        assert!(RelativeTime::parse_offset("1d").is_err());
+        assert!(RelativeTime::parse_offset("99999999999h").is_err());
+    }
+
+    #[test]
+    fn relative_time_from_str_works() {
+        assert_eq!("1h30m".parse(), Ok(RelativeTime::Offset(90 * 60 * 1000)));
+        assert_eq!("-15s".parse(), Ok(RelativeTime::Offset(-15 * 1000)));
+        assert_eq!("+15s".parse(), Ok(RelativeTime::Offset(15 * 1000)));
+        assert_eq!(
+            "-9:02".parse(),
+            Ok(RelativeTime::ClockTime {
+                hour: 9,
+                minute: 2,
+                backwards: true
+            })
+        );
+        assert_eq!(
+            " 13:15 ".parse(),
+            Ok(RelativeTime::ClockTime {
+                hour: 13,
+                minute: 15,
+                backwards: false
+            })
+        );
+        assert_eq!(
+            "10".parse(),
+            Ok(RelativeTime::MinuteOfHour {
+                minute: 10,
+                backwards: false
+            })
+        );
+        assert_eq!(
+            "-0".parse(),
+            Ok(RelativeTime::MinuteOfHour {
+                minute: 0,
+                backwards: true
+            })
+        );
+
+        assert_eq!(
+            "1h2h".parse::<RelativeTime>(),
+            Err(RelativeTimeError {
+                token: String::from("1h2h"),
+                reason: "time units must be in descending order and can't repeat"
+            })
+        );
+        assert_eq!(
+            " 24:05".parse::<RelativeTime>(),
+            Err(RelativeTimeError {
+                token: String::from("24:05"),
+                reason: "hour must be less than 24"
+            })
+        );
+        assert_eq!(
+            "23:60".parse::<RelativeTime>().unwrap_err().to_string(),
+            "minute must be less than 60 `23:60`"
+        );
+        assert!("".parse::<RelativeTime>().is_err());
+        assert!("1:2:3".parse::<RelativeTime>().is_err());
+        assert!("123:4".parse::<RelativeTime>().is_err());
+    }
+
+    #[test]
+    fn relative_time_parse_apply_format_is_stable() {
+        let dt = DateTime::new(&testing::date_default().with_minute(30).unwrap());
+        let inputs = [
+            "0s", "-0s", "45s", "-45s", "90m", "1h30m", "-1h30m", "+2h", "25h1s", "0h5m", "12:00",
+            "-12:00", "12:30", "-12:30", "9:02", "-09:2", "23:59", "0:00", "-0:00", "10", "-10",
+            "30", "-30", "0", "-0", "+59",
+        ];
+        for input in inputs {
+            let time: RelativeTime = input.parse().unwrap();
+            let formatted = time.to_string();
+            let reparsed: RelativeTime = formatted.parse().unwrap();
+            assert_eq!(reparsed, time, "{input} -> {formatted}");
+            assert_eq!(reparsed.to_string(), formatted, "{input}");
+            assert_eq!(reparsed.apply(&dt), time.apply(&dt), "{input}");
+            assert_eq!(dt.modify(&formatted).unwrap(), dt.modify(input).unwrap());
+        }
+        assert_eq!(RelativeTime::Offset(-90 * 60 * 1000).to_string(), "-1h30m");
+        assert_eq!(RelativeTime::Offset(0).to_string(), "0s");
+        assert_eq!(
+            RelativeTime::Offset((25 * 60 * 60 + 1) * 1000).to_string(),
+            "25h1s"
+        );
+    }
+
+    #[test]
+    fn date_time_parse_user_input_works() -> Result<(), String> {
+        let dt = DateTime::new(&testing::date_default());
+        assert_eq!(dt.parse_user_input("-15m")?, dt.plus_minutes(-15));
+        assert_eq!(dt.parse_user_input("13:15")?, dt.modify("13:15").unwrap());
+        assert_eq!(
+            dt.parse_user_input("2001-03-04 09:30")?.date,
+            testing::date_default()
+                .with_year(2001)
+                .unwrap()
+                .with_month(3)
+                .unwrap()
+                .with_day(4)
+                .unwrap()
+                .with_hour(9)
+                .unwrap()
+                .with_minute(30)
+                .unwrap()
+        );
+        assert_eq!(
+            dt.parse_user_input("03-04 9:30")?.date,
+            testing::date_default()
+                .with_month(3)
+                .unwrap()
+                .with_day(4)
+                .unwrap()
+                .with_hour(9)
+                .unwrap()
+                .with_minute(30)
+                .unwrap()
+        );
+        assert_eq!(
+            dt.parse_user_input("today 14:00")?.date,
+            testing::date_default().with_hour(14).unwrap()
+        );
+        assert_eq!(
+            dt.parse_user_input("  yesterday   08:05 ")?.date,
+            testing::date_default()
+                .with_day(testing::date_default().day() - 1)
+                .unwrap()
+                .with_hour(8)
+                .unwrap()
+                .with_minute(5)
+                .unwrap()
+        );
+
+        assert!(dt.parse_user_input("2025-02-30 10:00").is_err());
+        assert!(dt.parse_user_input("02-30 10:00").is_err());
+        assert!(dt.parse_user_input("2025-02-10 24:00").is_err());
+        assert!(dt.parse_user_input("2025-02-10").is_err());
+        assert!(dt.parse_user_input("tomorrow 10:00").is_err());
+        assert!(dt.parse_user_input("today 10:00 hello").is_err());
+        assert!(dt.parse_user_input("today").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn date_time_modify_works() -> Result<(), RelativeTimeError> {
         let date = testing::date_default();
         let dt = DateTime::new(&date);
 
@@ -465,6 +1609,13 @@ mod tests {
         assert_eq!(dt.modify("60h")?, dt.plus_hours(60));
         assert_eq!(dt.modify("-60h")?, dt.plus_hours(-60));
         assert_eq!(dt.modify("+15h")?, dt.plus_hours(15));
+        assert_eq!(dt.modify("1h30m")?, dt.plus_minutes(90));
+        assert_eq!(dt.modify("-1h30m")?, dt.plus_minutes(-90));
+        assert_eq!(dt.modify("+1h30m")?, dt.plus_minutes(90));
+        assert_eq!(dt.modify("1h30m15s")?, dt.plus_seconds(90 * 60 + 15));
+        assert_eq!(dt.modify("-1h15s")?, dt.plus_seconds(-(60 * 60 + 15)));
+        assert_eq!(dt.modify("2m30s")?, dt.plus_seconds(150));
+        assert_eq!(dt.modify("0h90m")?, dt.plus_minutes(90));
 
         assert_eq!(
             DateTime::new(&testing::date_default(),).modify("10")?.date,
@@ -595,7 +1746,45 @@ mod tests {
         assert!(dt.modify("24:05").is_err());
         assert!(dt.modify("23:60").is_err());
         assert!(dt.modify("--23:40").is_err());
+        assert!(dt.modify("+-5m").is_err());
+        assert!(dt.modify("-+5m").is_err());
+        assert!(dt.modify("1h2h").is_err());
+        assert!(dt.modify("30m1h").is_err());
+        assert!(dt.modify("1s1m").is_err());
+        assert!(dt.modify("1hm").is_err());
+        assert!(dt.modify("h").is_err());
+        assert!(dt.modify("-m").is_err());
+        assert!(dt.modify("1h-30m").is_err());
+        assert!(dt.modify("1h 30m").is_err());
+        assert!(dt.modify("1x30m").is_err());
 
         Ok(())
     }
+
+    #[test]
+    fn date_range_from_days_works() {
+        let day = |d| chrono::NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        let at = |d, h| {
+            day(d)
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+                .and_local_timezone(chrono::Local)
+                .earliest()
+                .unwrap()
+        };
+        let boundary = chrono::NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        let range = DateRange::from_days(Some(day(3)), Some(day(4)), boundary);
+        assert!(!range.contains(&at(3, 3)));
+        assert!(range.contains(&at(3, 4)));
+        assert!(range.contains(&at(5, 3)));
+        assert!(!range.contains(&at(5, 4)));
+
+        let range = DateRange::from_days(None, Some(day(4)), boundary);
+        assert!(range.contains(&at(1, 0)));
+        assert!(!range.contains(&at(5, 4)));
+        let range = DateRange::from_days(Some(day(3)), None, boundary);
+        assert!(!range.contains(&at(3, 3)));
+        assert!(range.contains(&at(30, 0)));
+        assert!(DateRange::default().contains(&at(1, 0)));
+    }
 }
@@ -0,0 +1,139 @@
+/// Unchanged lines shown around each change.
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line-based diff of `before` and `after` from their longest common subsequence, removed lines
+/// come before added ones.
+fn diff<'a>(before: &'a str, after: &'a str) -> Vec<Line<'a>> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+    // `common[i][j]` is the length of the longest common subsequence of `before[i..]` and
+    // `after[j..]`.
+    let mut common = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            lines.push(Line::Same(before[i]));
+            i += 1;
+            j += 1;
+        } else if j == after.len() || (i < before.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Removed(before[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(after[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Diff of `before` and `after` in the unified format, empty when they have the same lines.
+/// `path_before` is `/dev/null` for a new file.
+pub fn unified(path_before: &str, path_after: &str, before: &str, after: &str) -> String {
+    let lines = diff(before, after);
+    let changes: Vec<usize> = (0..lines.len())
+        .filter(|&i| !matches!(lines[i], Line::Same(_)))
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes closer than twice the context share a hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(DIFF_CONTEXT);
+        let end = (i + DIFF_CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut text = format!("--- {path_before}\n+++ {path_after}\n");
+    for (start, end) in hunks {
+        let count = |range: &[Line], removed: bool| {
+            range
+                .iter()
+                .filter(|v| match v {
+                    Line::Same(_) => true,
+                    Line::Removed(_) => removed,
+                    Line::Added(_) => !removed,
+                })
+                .count()
+        };
+        let before_start = count(&lines[..start], true);
+        let after_start = count(&lines[..start], false);
+        let before_count = count(&lines[start..end], true);
+        let after_count = count(&lines[start..end], false);
+        // An empty range starts at the line before it.
+        let first = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        text.push_str(&format!(
+            "@@ -{},{before_count} +{},{after_count} @@\n",
+            first(before_start, before_count),
+            first(after_start, after_count),
+        ));
+        for line in &lines[start..end] {
+            let (prefix, line) = match line {
+                Line::Same(v) => (' ', v),
+                Line::Removed(v) => ('-', v),
+                Line::Added(v) => ('+', v),
+            };
+            text.push_str(&format!("{prefix}{line}\n"));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_works() {
+        assert_eq!(
+            diff("a\nb\nc", "a\nx\nc\nd"),
+            vec![
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Added("x"),
+                Line::Same("c"),
+                Line::Added("d"),
+            ]
+        );
+        assert_eq!(diff("", "a"), vec![Line::Added("a")]);
+        assert_eq!(diff("a\n", "a"), vec![Line::Same("a")]);
+    }
+
+    #[test]
+    fn unified_works() {
+        assert_eq!(unified("a", "b", "x\ny", "x\ny\n"), "");
+        assert_eq!(
+            unified("/dev/null", "s.md", "", "# Title\n\nnotes"),
+            "--- /dev/null\n+++ s.md\n@@ -0,0 +1,3 @@\n+# Title\n+\n+notes\n"
+        );
+        let before = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14";
+        let after = "1\n2\n3\n4\n5\nfive\n6\n7\n8\n9\n10\n11\n12\n13";
+        assert_eq!(
+            unified("s.md", "s.md", before, after),
+            "--- s.md\n+++ s.md\n\
+             @@ -3,6 +3,7 @@\n 3\n 4\n 5\n+five\n 6\n 7\n 8\n\
+             @@ -11,4 +12,3 @@\n 11\n 12\n 13\n-14\n"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    date_time::{DateRange, DateTime},
+    json::{self, Json},
+    Config,
+};
+use std::{
+    error::Error,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::PathBuf,
+};
+
+const EVENTS_FILE_NAME: &str = "events.jsonl";
+
+/// One line of the event log, written after an action changes a session.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Entry {
+    /// Name of the command, `stop` for a session stopped for being stale.
+    pub action: String,
+    pub timestamp: DateTime,
+    /// File name of the session that was changed.
+    pub session: String,
+    /// Arguments of the action, in the order they were given.
+    pub payload: Vec<(String, String)>,
+}
+
+impl Entry {
+    pub fn to_json(&self) -> String {
+        let mut str = format!(
+            "{{\"action\":{},\"timestamp\":{},\"session\":{},\"payload\":{{",
+            json::to_string(&self.action),
+            json::to_string(&self.timestamp.to_formatted()),
+            json::to_string(&self.session)
+        );
+        for (i, (key, value)) in self.payload.iter().enumerate() {
+            if i > 0 {
+                str.push(',');
+            }
+            let _ = write!(str, "{}:{}", json::to_string(key), json::to_string(value));
+        }
+        str += "}}";
+        str
+    }
+
+    pub fn from_json(text: &str) -> Result<Entry, String> {
+        let Json::Object(fields) = Json::parse(text)? else {
+            return Err(String::from("entry must be an object"));
+        };
+        let get_string = |name: &str| match fields.iter().find(|(k, _)| k == name) {
+            Some((_, Json::String(v))) => Ok(v.to_owned()),
+            _ => Err(format!("`{name}` must be a string")),
+        };
+        let timestamp = get_string("timestamp")?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp)
+            .map_err(|_| format!("invalid timestamp `{timestamp}`"))?;
+        let payload = match fields.iter().find(|(k, _)| k == "payload") {
+            None => Vec::new(),
+            Some((_, Json::Object(payload))) => payload
+                .iter()
+                .map(|(k, v)| match v {
+                    Json::String(v) => Ok((k.to_owned(), v.to_owned())),
+                    Json::Object(_) => Err(format!("payload `{k}` must be a string")),
+                })
+                .collect::<Result<_, String>>()?,
+            Some(_) => return Err(String::from("`payload` must be an object")),
+        };
+        Ok(Entry {
+            action: get_string("action")?,
+            timestamp: DateTime::new(&timestamp.with_timezone(&chrono::Local)),
+            session: get_string("session")?,
+            payload,
+        })
+    }
+
+    /// Single line with the local time, `2025-01-07 14:32:05 mark <session> date=14:30`.
+    pub fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} {:<8} {}",
+            self.timestamp.date.format("%F %T"),
+            self.action,
+            self.session
+        );
+        for (key, value) in &self.payload {
+            let _ = write!(line, " {key}={}", value.replace('\n', "\\n"));
+        }
+        line
+    }
+}
+
+fn get_path(config: &Config) -> PathBuf {
+    config.sessions_path.join(EVENTS_FILE_NAME)
+}
+
+/// Appends `entry` to the log when [`Config::event_log`] is set, the file is created if needed.
+pub fn append(config: &Config, entry: &Entry) -> io::Result<()> {
+    if !config.event_log {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_path(config))?;
+    // Written at once so that a line is never split between concurrent writers.
+    file.write_all(format!("{}\n", entry.to_json()).as_bytes())
+}
+
+/// Entries within `range` in the order they were logged, along with the lines that couldn't be
+/// parsed. A missing log has no entries.
+pub fn read(
+    config: &Config,
+    range: &DateRange,
+) -> Result<(Vec<Entry>, Vec<String>), Box<dyn Error>> {
+    let contents = match fs::read_to_string(get_path(config)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(format!("couldn't read the event log: {err}"))?,
+    };
+    let mut entries = Vec::new();
+    let mut failures = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Entry::from_json(line) {
+            Ok(entry) if range.contains(&entry.timestamp.date) => entries.push(entry),
+            Ok(_) => (),
+            Err(err) => failures.push(format!("line {} of {EVENTS_FILE_NAME}: {err}", i + 1)),
+        }
+    }
+    Ok((entries, failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use chrono::Timelike;
+
+    fn entry() -> Entry {
+        Entry {
+            action: String::from("write"),
+            // The log keeps whole seconds.
+            timestamp: DateTime::new(&testing::date_default().with_nanosecond(0).unwrap()),
+            session: String::from("2002-05-08T12-00-00+0200.md"),
+            payload: vec![(
+                String::from("text"),
+                String::from("Said \"hi\"\n\tback\\slash \u{1}"),
+            )],
+        }
+    }
+
+    #[test]
+    fn entry_json_round_trip() {
+        let entry = entry();
+        let json = entry.to_json();
+        assert!(!json.contains('\n'));
+        assert!(json.starts_with("{\"action\":\"write\",\"timestamp\":\"2002-05-08T12:00:00"));
+        assert!(json
+            .ends_with("\"payload\":{\"text\":\"Said \\\"hi\\\"\\n\\tback\\\\slash \\u0001\"}}"));
+        assert_eq!(Entry::from_json(&json), Ok(entry));
+
+        let json = " { \"session\" : \"a.md\", \"action\":\"start\",\"timestamp\":\
+            \"2002-05-08T10:00:00+00:00\", \"payload\": {\"x\": \"\\u00e9\"} } ";
+        let entry = Entry::from_json(json).unwrap();
+        assert_eq!(entry.action, "start");
+        assert_eq!(entry.payload, vec![(String::from("x"), String::from("é"))]);
+
+        assert!(Entry::from_json("").is_err());
+        assert!(Entry::from_json("{\"action\":\"start\"}").is_err());
+        assert!(Entry::from_json("{\"action\":1}").is_err());
+        assert!(Entry::from_json(&format!("{} x", entry.to_json())).is_err());
+    }
+
+    #[test]
+    fn append_and_read_work() {
+        let dir = testing::temp_dir("events_append_and_read_work");
+        let mut config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let all = DateRange::from_days(None, None, config.day_starts_at);
+        append(&config, &entry()).unwrap();
+        assert!(!dir.join(EVENTS_FILE_NAME).exists());
+        assert_eq!(read(&config, &all).unwrap(), (Vec::new(), Vec::new()));
+
+        config.event_log = true;
+        let mut later = entry();
+        later.action = String::from("mark");
+        later.timestamp = later.timestamp.plus_days(2);
+        append(&config, &entry()).unwrap();
+        append(&config, &later).unwrap();
+        fs::write(
+            dir.join(EVENTS_FILE_NAME),
+            fs::read_to_string(dir.join(EVENTS_FILE_NAME)).unwrap() + "broken\n",
+        )
+        .unwrap();
+        let (entries, failures) = read(&config, &all).unwrap();
+        assert_eq!(entries, vec![entry(), later.clone()]);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("line 3 of events.jsonl"));
+        let since = DateRange::from_days(
+            Some(later.timestamp.day(config.day_starts_at)),
+            None,
+            config.day_starts_at,
+        );
+        assert_eq!(read(&config, &since).unwrap().0, vec![later]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
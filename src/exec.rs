@@ -0,0 +1,144 @@
+use std::process::Command;
+
+/// Splits `text` into words like a POSIX shell without expansions. Single quotes keep everything
+/// as it is, in double quotes a backslash only escapes `"`, `\`, `$` and `` ` ``, outside of quotes
+/// it escapes any character.
+pub fn split(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // `None` between words, quotes can start an empty word.
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unbalanced `'` in command `{text}`")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(format!("unbalanced `\"` in command `{text}`")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unbalanced `\"` in command `{text}`")),
+                    }
+                }
+            }
+            '\\' => {
+                let Some(c) = chars.next() else {
+                    return Err(format!("trailing `\\` in command `{text}`"));
+                };
+                word.get_or_insert_with(String::new).push(c);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Command of the program and arguments in `text`, see [`split`]. Arguments added to it come
+/// after the ones in `text`, like the path given to an editor.
+pub fn command(text: &str) -> Result<Command, String> {
+    let words = split(text)?;
+    let Some((program, args)) = words.split_first() else {
+        return Err(String::from("command is empty"));
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
+
+/// Command running `text` with `sh -c`, for commands that use shell features like variables and
+/// redirection. `text` is still checked with [`split`], so that unbalanced quotes are reported
+/// clearly instead of by the shell.
+pub fn shell(text: &str) -> Result<Command, String> {
+    split(text)?;
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(text);
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_works() {
+        assert_eq!(
+            split("code --wait"),
+            Ok(vec!["code".into(), "--wait".into()])
+        );
+        assert_eq!(split("  vim   -p \t"), Ok(vec!["vim".into(), "-p".into()]));
+        assert_eq!(split(""), Ok(vec![]));
+        assert_eq!(
+            split("'/opt/My Editor/bin/edit' --title \"Time tracker\""),
+            Ok(vec![
+                "/opt/My Editor/bin/edit".into(),
+                "--title".into(),
+                "Time tracker".into()
+            ])
+        );
+        assert_eq!(split("a'b c'\"d\"e"), Ok(vec!["ab cde".into()]));
+        assert_eq!(split("'' \"\""), Ok(vec!["".into(), "".into()]));
+    }
+
+    #[test]
+    fn split_handles_escapes() {
+        assert_eq!(
+            split(r"My\ Editor a\\b \'"),
+            Ok(vec!["My Editor".into(), r"a\b".into(), "'".into()])
+        );
+        assert_eq!(
+            split(r#""say \"hi\" \$HOME \n""#),
+            Ok(vec![r#"say "hi" $HOME \n"#.into()])
+        );
+        assert_eq!(split(r"'no \escape'"), Ok(vec![r"no \escape".into()]));
+        assert_eq!(split("\"it's\""), Ok(vec!["it's".into()]));
+    }
+
+    #[test]
+    fn split_fails_on_unbalanced_quotes() {
+        assert_eq!(
+            split("code 'My File"),
+            Err(String::from("unbalanced `'` in command `code 'My File`"))
+        );
+        assert!(split("code \"My File").is_err());
+        assert!(split(r#"code "My File\""#).is_err());
+        assert!(split("code \\").is_err());
+    }
+
+    #[test]
+    fn command_works() {
+        let editor = command("code --wait").unwrap();
+        assert_eq!(editor.get_program(), "code");
+        assert_eq!(editor.get_args().collect::<Vec<_>>(), ["--wait"]);
+        assert_eq!(command("  ").unwrap_err(), String::from("command is empty"));
+        let hook = shell("echo \"$TT_EVENT\" > out").unwrap();
+        assert_eq!(hook.get_program(), "sh");
+        assert_eq!(
+            hook.get_args().collect::<Vec<_>>(),
+            ["-c", "echo \"$TT_EVENT\" > out"]
+        );
+        assert!(shell("echo 'hi").is_err());
+    }
+}
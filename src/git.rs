@@ -0,0 +1,139 @@
+use std::{error::Error, fmt, io, path::Path, process::Command};
+
+#[derive(PartialEq, Debug)]
+pub enum GitError {
+    /// `git` couldn't be run, usually because it isn't in `PATH`.
+    Missing,
+    NotRepository,
+    NoCommits,
+    /// Any other failure, with the message git printed.
+    Failed(String),
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::Missing => write!(f, "git isn't installed or isn't in PATH"),
+            GitError::NotRepository => write!(f, "not in a git repository"),
+            GitError::NoCommits => write!(f, "the git repository has no commits yet"),
+            GitError::Failed(message) => write!(f, "git failed: {message}"),
+        }
+    }
+}
+
+impl Error for GitError {}
+
+/// Current branch of the repository `dir` is in, the short hash of the commit in detached HEAD.
+pub fn get_branch_name(dir: &Path) -> Result<String, GitError> {
+    let name = run(dir, &["branch", "--show-current"])?;
+    if !name.is_empty() {
+        return Ok(name);
+    }
+    run(dir, &["rev-parse", "--short", "HEAD"])
+}
+
+/// Subject and body of `commit`, the latest commit when `None`. The subject is separated from the
+/// body by an empty line, the body is left out when empty.
+pub fn get_commit_message(dir: &Path, commit: Option<&str>) -> Result<String, GitError> {
+    let mut args = vec!["log", "-1", "--format=%s%n%n%b"];
+    if let Some(commit) = commit {
+        args.extend(["--end-of-options", commit]);
+    }
+    run(dir, &args)
+}
+
+/// Runs git in `dir` and returns its trimmed stdout.
+fn run(dir: &Path, args: &[&str]) -> Result<String, GitError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => GitError::Missing,
+            _ => GitError::Failed(err.to_string()),
+        })?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned());
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+    let err = if message.contains("not a git repository") {
+        GitError::NotRepository
+    } else if message.contains("does not have any commits yet")
+        || message.contains("Needed a single revision")
+    {
+        GitError::NoCommits
+    } else {
+        GitError::Failed(message)
+    };
+    Err(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        date_time::DateTime,
+        session::{Attribute, Session},
+        testing,
+    };
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn get_branch_name_works() {
+        let dir = testing::temp_dir("git_get_branch_name_works");
+        assert_eq!(get_branch_name(&dir), Err(GitError::NotRepository));
+        git(&dir, &["init", "-q", "-b", "feat/foo"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "first"]);
+        assert_eq!(get_branch_name(&dir), Ok(String::from("feat/foo")));
+        git(&dir, &["checkout", "-q", "--detach"]);
+        let hash = run(&dir, &["rev-parse", "--short", "HEAD"]).unwrap();
+        assert!(!hash.is_empty());
+        assert_eq!(get_branch_name(&dir), Ok(hash));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn get_commit_message_works() {
+        let dir = testing::temp_dir("git_get_commit_message_works");
+        assert_eq!(get_commit_message(&dir, None), Err(GitError::NotRepository));
+        git(&dir, &["init", "-q"]);
+        assert_eq!(get_commit_message(&dir, None), Err(GitError::NoCommits));
+        let message = "Add parser\n\nHandles nested lists.\n- stop\n# Not a heading";
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", message]);
+        let first = run(&dir, &["rev-parse", "HEAD"]).unwrap();
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "Fix typo"]);
+        assert_eq!(get_commit_message(&dir, None), Ok(String::from("Fix typo")));
+        assert_eq!(
+            get_commit_message(&dir, Some(&first[..7])),
+            Ok(message.to_owned())
+        );
+        assert!(matches!(
+            get_commit_message(&dir, Some("nosuch")),
+            Err(GitError::Failed(_))
+        ));
+
+        // The body survives saving the session.
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.write(message).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.save(&config).unwrap();
+        assert_eq!(Session::from_path(&session.path).unwrap(), session);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1539 @@
+use crate::{
+    date_time::{DateRange, DateTime, Duration, DurationFormat},
+    session::{Aggregator, Session, Tag},
+    Config,
+};
+use chrono::{Datelike, Timelike};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+const INDEX_FILE_NAME: &str = ".index.tsv";
+/// First line of the index, followed by the untracked tags the summaries were made with. An
+/// index starting with anything else is rebuilt.
+const INDEX_HEADER: &str = "# time_tracker index v1";
+const INDEX_SEPARATOR: &str = "\t";
+
+const GRAPH_BAR: &str = "█";
+const GRAPH_DAYS: usize = 14;
+/// From no time to the most, each shade covers [`CALENDAR_SHADE_STEP`] hours.
+const CALENDAR_SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+const CALENDAR_SHADE_STEP: Duration = Duration::from_secs(2 * 60 * 60);
+/// How many of the most used tags the stats show.
+const STATS_TAGS_COUNT: usize = 5;
+/// Days up to today the stats count tracked days in.
+const STATS_RECENT_DAYS: u64 = 30;
+/// Weeks up to today the weekday report covers when not given.
+const WEEKDAYS_WEEKS_DEFAULT: usize = 8;
+
+/// Totals of a session, enough to answer queries over the whole history without parsing the
+/// session file, see [`Session::summarize`].
+#[derive(PartialEq, Debug, Clone)]
+pub struct Summary {
+    pub path: PathBuf,
+    pub start: DateTime,
+    pub end: DateTime,
+    /// Skipped intervals are excluded.
+    pub time: Duration,
+    pub break_time: Duration,
+    pub marks: usize,
+    /// Intervals that count towards `time`, each with the date of the mark that starts it.
+    pub intervals: Vec<(DateTime, Duration)>,
+    /// How many marks each tag is on, tags of the session count once. Sorted alphabetically.
+    pub tags: Vec<(Tag, usize)>,
+}
+
+impl Summary {
+    /// Columns are the file name, `modified`, start, end, time, break time, number of marks,
+    /// intervals as comma separated `start+time` pairs and a column for each tag as `count tag`.
+    /// Dates and durations are in milliseconds. `None` if the text can't be stored in a column.
+    fn to_line(&self, name: &str, modified: u64) -> Option<String> {
+        let tags: Vec<String> = self
+            .tags
+            .iter()
+            .map(|(tag, count)| format!("{count} {tag}"))
+            .collect();
+        if tags
+            .iter()
+            .chain([&name.to_owned()])
+            .any(|v| v.contains(INDEX_SEPARATOR) || v.contains('\n'))
+        {
+            return None;
+        }
+        let intervals: Vec<String> = self
+            .intervals
+            .iter()
+            .map(|(start, time)| format!("{}+{}", start.date.timestamp_millis(), time.as_millis()))
+            .collect();
+        let mut columns = vec![
+            name.to_owned(),
+            modified.to_string(),
+            self.start.date.timestamp_millis().to_string(),
+            self.end.date.timestamp_millis().to_string(),
+            self.time.as_millis().to_string(),
+            self.break_time.as_millis().to_string(),
+            self.marks.to_string(),
+            intervals.join(","),
+        ];
+        columns.extend(tags);
+        Some(columns.join(INDEX_SEPARATOR))
+    }
+
+    /// Returns the file name and modification time along with the summary, see
+    /// [`Summary::to_line`].
+    fn from_line(line: &str, dir: &Path) -> Option<(String, u64, Summary)> {
+        let date = |v: &str| {
+            let date = chrono::DateTime::from_timestamp_millis(v.parse().ok()?)?;
+            Some(DateTime::from(date.with_timezone(&chrono::Local)))
+        };
+        let duration = |v: &str| Some(Duration::from_millis(v.parse().ok()?));
+        let columns: Vec<&str> = line.split(INDEX_SEPARATOR).collect();
+        let [name, modified, start, end, time, break_time, marks, intervals, tags @ ..] =
+            &columns[..]
+        else {
+            return None;
+        };
+        let intervals = intervals
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                let (start, time) = v.split_once('+')?;
+                Some((date(start)?, duration(time)?))
+            })
+            .collect::<Option<Vec<(DateTime, Duration)>>>()?;
+        let tags = tags
+            .iter()
+            .map(|v| {
+                let (count, tag) = v.split_once(' ')?;
+                Some((Tag::from_text(tag).ok()?, count.parse().ok()?))
+            })
+            .collect::<Option<Vec<(Tag, usize)>>>()?;
+        let summary = Summary {
+            path: dir.join(name),
+            start: date(start)?,
+            end: date(end)?,
+            time: duration(time)?,
+            break_time: duration(break_time)?,
+            marks: marks.parse().ok()?,
+            intervals,
+            tags,
+        };
+        Some((name.to_string(), modified.parse().ok()?, summary))
+    }
+}
+
+/// Cache of the summaries of session files, kept in the sessions directory. An entry is valid
+/// while its file has the modification time it was summarized at.
+#[derive(PartialEq, Debug, Default)]
+pub struct Index {
+    /// By file name, each with the modification time of the file in milliseconds.
+    entries: BTreeMap<String, (u64, Summary)>,
+}
+
+impl Index {
+    fn get_path(config: &Config) -> PathBuf {
+        config.sessions_path.join(INDEX_FILE_NAME)
+    }
+
+    /// Summaries depend on [`Config::untracked_tags`] and [`Config::tag_normalizer`], changing
+    /// them invalidates the index.
+    fn get_header(config: &Config) -> String {
+        let mut header = String::from(INDEX_HEADER);
+        if !config.untracked_tags.is_empty() {
+            let tags: Vec<String> = config
+                .untracked_tags
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            header += &format!(" untracked: {}", tags.join(", "));
+        }
+        if !config.tag_normalizer.is_identity() {
+            header += &format!(" tags: {}", config.tag_normalizer.describe());
+        }
+        header
+    }
+
+    /// Missing or malformed index is empty, it gets rebuilt from the session files.
+    fn read(config: &Config) -> Index {
+        fs::read_to_string(Index::get_path(config))
+            .ok()
+            .and_then(|v| Index::parse(&v, &Index::get_header(config), &config.sessions_path))
+            .unwrap_or_default()
+    }
+
+    fn parse(contents: &str, header: &str, dir: &Path) -> Option<Index> {
+        let mut lines = contents.lines();
+        if lines.next()? != header {
+            return None;
+        }
+        let mut entries = BTreeMap::new();
+        for line in lines {
+            let (name, modified, summary) = Summary::from_line(line, dir)?;
+            entries.insert(name, (modified, summary));
+        }
+        Some(Index { entries })
+    }
+
+    fn write(&self, config: &Config) -> io::Result<()> {
+        let mut contents = Index::get_header(config);
+        for (name, (modified, summary)) in &self.entries {
+            if let Some(line) = summary.to_line(name, *modified) {
+                contents += "\n";
+                contents += &line;
+            }
+        }
+        contents += "\n";
+        fs::write(Index::get_path(config), contents)
+    }
+
+    fn get_modified(path: &Path) -> io::Result<u64> {
+        let modified = fs::metadata(path)?.modified()?;
+        let millis = modified
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?
+            .as_millis();
+        Ok(millis as u64)
+    }
+
+    /// Stores the summary of `session` right after it was saved. Active sessions are removed
+    /// instead, their time keeps changing.
+    pub fn update(config: &Config, session: &Session) -> Result<(), Box<dyn Error>> {
+        let name = session
+            .path
+            .file_name()
+            .ok_or("session path has no file name")?
+            .to_string_lossy()
+            .into_owned();
+        let mut index = Index::read(config);
+        let mut session = session.clone();
+        session.normalize_tags(&config.tag_normalizer);
+        match session.summarize(&config.untracked_tags) {
+            Ok(summary) if !session.is_active() => {
+                let modified = Index::get_modified(&session.path)?;
+                index.entries.insert(name, (modified, summary));
+            }
+            _ => {
+                if index.entries.remove(&name).is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        index.write(config)?;
+        Ok(())
+    }
+}
+
+/// Runs of consecutive days with tracked time.
+#[derive(PartialEq, Debug)]
+struct Streaks {
+    /// Consecutive tracked days ending today, 0 when today has no time yet.
+    current: usize,
+    longest: usize,
+    /// Tracked days among the last [`STATS_RECENT_DAYS`] days, today included.
+    recent: usize,
+}
+
+impl Streaks {
+    fn new(days: &BTreeSet<chrono::NaiveDate>, today: chrono::NaiveDate) -> Streaks {
+        let mut current = 0;
+        let mut day = Some(today);
+        while let Some(v) = day.filter(|v| days.contains(v)) {
+            current += 1;
+            day = v.pred_opt();
+        }
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<chrono::NaiveDate> = None;
+        for day in days {
+            let is_next = previous.and_then(|v| v.succ_opt()) == Some(*day);
+            run = if is_next { run + 1 } else { 1 };
+            longest = longest.max(run);
+            previous = Some(*day);
+        }
+        let recent = match today.checked_sub_days(chrono::Days::new(STATS_RECENT_DAYS - 1)) {
+            Some(first) => days.range(first..=today).count(),
+            None => days.range(..=today).count(),
+        };
+        Streaks {
+            current,
+            longest,
+            recent,
+        }
+    }
+}
+
+/// Summaries of all sessions, read through the [`Index`].
+pub struct History {
+    summaries: Vec<Summary>,
+    /// Files in the sessions directory that couldn't be read as sessions.
+    pub failures: Vec<(PathBuf, Box<dyn Error>)>,
+}
+
+impl History {
+    /// Only files that changed since they were indexed are parsed, see [`Session::from_paths`],
+    /// the index is updated with them. Failures are collected the same way as in
+    /// [`Aggregator::build`]. Archived sessions aren't included.
+    pub fn load(config: &Config) -> Result<History, Box<dyn Error>> {
+        History::load_from(config, false)
+    }
+
+    /// Like [`History::load`], but with the sessions in the archive as well, see
+    /// [`Aggregator::read_archive_dir`].
+    pub fn load_with_archive(config: &Config) -> Result<History, Box<dyn Error>> {
+        History::load_from(config, true)
+    }
+
+    fn load_from(config: &Config, include_archived: bool) -> Result<History, Box<dyn Error>> {
+        let archived = Aggregator::read_archive_dir(config)?;
+        let file_name = |path: &Path| {
+            path.file_name()
+                .expect("session files must have a name")
+                .to_string_lossy()
+                .into_owned()
+        };
+        let mut paths = Aggregator::read_sessions_dir(config)?;
+        if include_archived {
+            // Sessions are archived only once they ended, before all the others started.
+            paths.splice(0..0, archived.iter().cloned());
+        }
+        let mut index = Index::read(config);
+        let mut is_changed = false;
+        let mut summaries = Vec::new();
+        let mut failures = Vec::new();
+        let mut files = Vec::new();
+        let mut stale = Vec::new();
+        for path in paths {
+            let name = file_name(&path);
+            let modified = Index::get_modified(&path).ok();
+            let summary = match index.entries.get(&name) {
+                Some((val, summary)) if Some(*val) == modified => Some(Summary {
+                    // Moving a file to the archive keeps its entry.
+                    path: path.clone(),
+                    ..summary.clone()
+                }),
+                _ => {
+                    stale.push(path.clone());
+                    None
+                }
+            };
+            files.push((path, name, modified, summary));
+        }
+
+        let mut parsed = Session::from_paths(&stale).into_iter();
+        for (path, name, modified, summary) in &files {
+            if let Some(summary) = summary {
+                summaries.push(summary.clone());
+                continue;
+            }
+            let summary = parsed
+                .next()
+                .expect("every stale file must be parsed")
+                .and_then(|mut session| {
+                    session.normalize_tags(&config.tag_normalizer);
+                    let summary = session.summarize(&config.untracked_tags)?;
+                    Ok((session.is_active(), summary))
+                });
+            match (summary, modified) {
+                (Ok((false, summary)), Some(modified)) => {
+                    index
+                        .entries
+                        .insert(name.clone(), (*modified, summary.clone()));
+                    is_changed = true;
+                    summaries.push(summary);
+                }
+                (Ok((_, summary)), _) => summaries.push(summary),
+                (Err(err), _) => {
+                    is_changed |= index.entries.remove(name).is_some();
+                    failures.push((path.clone(), err));
+                }
+            }
+        }
+        let len = index.entries.len();
+        // Entries of archived sessions are kept for when they are included.
+        index.entries.retain(|name, _| {
+            files.iter().any(|(_, v, _, _)| v == name)
+                || archived.iter().any(|v| &file_name(v) == name)
+        });
+        is_changed |= index.entries.len() != len;
+        if is_changed {
+            if let Err(err) = index.write(config) {
+                eprintln!("Warning: couldn't update the index: {err}");
+            }
+        }
+
+        // An empty directory isn't an error, there are just no sessions yet.
+        if summaries.is_empty() {
+            if let Some((_, err)) = failures.first() {
+                return Err(format!(
+                    "no valid session found, {} files couldn't be read, first error: {err}",
+                    failures.len()
+                ))?;
+            }
+        }
+        Ok(History {
+            summaries,
+            failures,
+        })
+    }
+
+    /// Time tracked on each day, intervals count to the day of the mark that starts them. Days
+    /// without any time are missing.
+    fn daily_times(&self, boundary: chrono::NaiveTime) -> BTreeMap<chrono::NaiveDate, Duration> {
+        let mut times = BTreeMap::new();
+        for (start, time) in self.summaries.iter().flat_map(|v| &v.intervals) {
+            *times.entry(start.day(boundary)).or_insert(Duration::ZERO) += *time;
+        }
+        times
+    }
+
+    /// Break time of each day, a session's breaks count to the day it starts. Days without any
+    /// breaks are missing.
+    fn daily_break_times(
+        &self,
+        boundary: chrono::NaiveTime,
+    ) -> BTreeMap<chrono::NaiveDate, Duration> {
+        let mut times = BTreeMap::new();
+        for summary in self
+            .summaries
+            .iter()
+            .filter(|v| v.break_time != Duration::ZERO)
+        {
+            *times
+                .entry(summary.start.day(boundary))
+                .or_insert(Duration::ZERO) += summary.break_time;
+        }
+        times
+    }
+
+    /// Time tracked in each hour of the day by sessions that start within `range`, see
+    /// [`hourly_times`].
+    fn hourly_times_in(&self, range: &DateRange) -> [Duration; 24] {
+        let intervals: Vec<(chrono::DateTime<chrono::Local>, Duration)> = self
+            .get_summaries_in(range)
+            .into_iter()
+            .flat_map(|v| &v.intervals)
+            .map(|(start, time)| (start.date, *time))
+            .collect();
+        hourly_times(&intervals)
+    }
+
+    /// Days with any tracked time, skipped intervals don't count.
+    fn tracked_days(&self, boundary: chrono::NaiveTime) -> BTreeSet<chrono::NaiveDate> {
+        self.daily_times(boundary)
+            .into_iter()
+            .filter(|(_, time)| *time != Duration::ZERO)
+            .map(|(day, _)| day)
+            .collect()
+    }
+
+    /// Bar chart of the last [`GRAPH_DAYS`] days up to `today`, or of the last `weeks` weeks,
+    /// fitted to `width` columns.
+    pub fn graph(
+        &self,
+        config: &Config,
+        today: chrono::NaiveDate,
+        weeks: Option<usize>,
+        width: usize,
+    ) -> String {
+        let daily_times = self.daily_times(config.day_starts_at);
+        let daily_break_times = self.daily_break_times(config.day_starts_at);
+        let rows: Vec<(String, Duration, Duration)> = match weeks {
+            None => (0..GRAPH_DAYS as u64)
+                .rev()
+                .filter_map(|i| today.checked_sub_days(chrono::Days::new(i)))
+                .map(|day| {
+                    let time = daily_times.get(&day).copied().unwrap_or_default();
+                    let breaks = daily_break_times.get(&day).copied().unwrap_or_default();
+                    (config.display.format_day(day), time, breaks)
+                })
+                .collect(),
+            Some(weeks) => {
+                let monday = today.week(chrono::Weekday::Mon).first_day();
+                let week_sum = |times: &BTreeMap<chrono::NaiveDate, Duration>, monday| {
+                    times
+                        .range(monday..monday + chrono::Days::new(7))
+                        .map(|(_, time)| *time)
+                        .sum()
+                };
+                (0..weeks as u64)
+                    .rev()
+                    .filter_map(|i| monday.checked_sub_days(chrono::Days::new(i * 7)))
+                    .map(|monday| {
+                        (
+                            monday.format("%G-W%V").to_string(),
+                            week_sum(&daily_times, monday),
+                            week_sum(&daily_break_times, monday),
+                        )
+                    })
+                    .collect()
+            }
+        };
+        History::render_graph(&rows, width, &config.duration_format)
+    }
+
+    /// Total and average time of each weekday in the last `weeks` weeks up to `today`, see
+    /// [`weekday_times`].
+    pub fn weekdays(
+        &self,
+        config: &Config,
+        today: chrono::NaiveDate,
+        weeks: Option<usize>,
+        nonzero: bool,
+    ) -> String {
+        let weeks = weeks.unwrap_or(WEEKDAYS_WEEKS_DEFAULT);
+        let first = today - chrono::Days::new(weeks as u64 * 7 - 1);
+        let daily_times = self.daily_times(config.day_starts_at);
+        let format = &config.duration_format;
+        let range = format!(
+            "{} to {}",
+            config.display.format_date(first),
+            config.display.format_date(today)
+        );
+        let mut rows = vec![("Range", range)];
+        let names = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        for (name, (total, average)) in
+            names
+                .into_iter()
+                .zip(weekday_times(&daily_times, first, today, nonzero))
+        {
+            let times = format!(
+                "{} average, {} total",
+                average.format(format),
+                total.format(format)
+            );
+            rows.push((name, times));
+        }
+        History::render_table(&rows)
+    }
+
+    /// Bar chart of the time tracked in each hour of the day by sessions that start within `range`,
+    /// fitted to `width` columns.
+    pub fn hours(&self, config: &Config, range: &DateRange, width: usize) -> String {
+        let rows: Vec<(String, Duration, Duration)> = self
+            .hourly_times_in(range)
+            .iter()
+            .enumerate()
+            .map(|(hour, time)| {
+                (
+                    config.display.format_hour(hour as u32),
+                    *time,
+                    Duration::ZERO,
+                )
+            })
+            .collect();
+        History::render_graph(&rows, width, &config.duration_format)
+    }
+
+    /// Each row is a label, a bar, the time and the break time, the longest bar fills the space left
+    /// in `width`. Break time is only shown when there is some.
+    fn render_graph(
+        rows: &[(String, Duration, Duration)],
+        width: usize,
+        format: &DurationFormat,
+    ) -> String {
+        // Empty days have no time either so that they stand out.
+        let times: Vec<String> = rows
+            .iter()
+            .map(|(_, time, _)| match *time {
+                Duration::ZERO => String::new(),
+                time => time.format(format),
+            })
+            .collect();
+        let label_width = rows
+            .iter()
+            .map(|(label, _, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let time_width = times.iter().map(|v| v.chars().count()).max().unwrap_or(0);
+        let bar_width = width.saturating_sub(label_width + time_width + 2).max(1) as u64;
+        let time_max = rows
+            .iter()
+            .map(|(_, time, _)| time.as_millis())
+            .max()
+            .unwrap_or(0);
+        rows.iter()
+            .zip(&times)
+            .map(|((label, time, breaks), time_formatted)| {
+                let bar_len = match time_max {
+                    0 => 0,
+                    max => (time.as_millis() * bar_width + max / 2) / max,
+                };
+                let bar = GRAPH_BAR.repeat(bar_len as usize);
+                let breaks = match *breaks {
+                    Duration::ZERO => String::new(),
+                    breaks => format!("  breaks {}", breaks.format(format)),
+                };
+                format!(
+                    "{label:<label_width$} {bar:<bar_width$} {time_formatted:<time_width$}{breaks}",
+                    bar_width = bar_width as usize
+                )
+                .trim_end()
+                .to_owned()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Calendar of the month of `day`, each day shaded by the time tracked on it. Weeks start on
+    /// Monday.
+    pub fn calendar(&self, config: &Config, day: chrono::NaiveDate) -> String {
+        let daily_times = self.daily_times(config.day_starts_at);
+        let first = day.with_day(1).expect("first day of month must exist");
+        let mut cells = vec![String::from("   "); first.weekday().num_days_from_monday() as usize];
+        for day in first.iter_days().take_while(|v| v.month() == first.month()) {
+            let time = daily_times.get(&day).copied().unwrap_or_default();
+            cells.push(format!("{:>2}{}", day.day(), History::get_shade(time)));
+        }
+
+        let mut lines = vec![first.format("%B %Y").to_string()];
+        lines.push(String::from("Mo  Tu  We  Th  Fr  Sa  Su"));
+        for week in cells.chunks(7) {
+            lines.push(week.join(" ").trim_end().to_owned());
+        }
+        let step = CALENDAR_SHADE_STEP.as_secs() / 60 / 60;
+        let legend = (1..CALENDAR_SHADES.len())
+            .map(|i| {
+                let shade = CALENDAR_SHADES[i];
+                if i + 1 < CALENDAR_SHADES.len() {
+                    format!("{shade} <{}h", i as u64 * step)
+                } else {
+                    format!("{shade} {}h+", (i as u64 - 1) * step)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("  ");
+        lines.push(legend);
+        lines.join("\n")
+    }
+
+    fn get_shade(time: Duration) -> char {
+        if time == Duration::ZERO {
+            return CALENDAR_SHADES[0];
+        }
+        let i = (time.as_millis() / CALENDAR_SHADE_STEP.as_millis()) as usize + 1;
+        CALENDAR_SHADES[i.min(CALENDAR_SHADES.len() - 1)]
+    }
+
+    /// Statistics of the sessions that start within `range`, streaks are of the whole history up
+    /// to `today`.
+    pub fn stats(&self, config: &Config, range: &DateRange, today: chrono::NaiveDate) -> String {
+        let format = &config.duration_format;
+        let mut rows = Vec::new();
+        if range.start.is_some() || range.end.is_some() {
+            let start = range
+                .start
+                .as_ref()
+                .map_or(String::from("first session"), |v| {
+                    v.to_formatted_display(&config.display)
+                });
+            let end = range.end.as_ref().map_or(String::from("now"), |v| {
+                v.to_formatted_display(&config.display)
+            });
+            rows.push(("Range", format!("{start} to {end}")));
+        }
+        rows.push(("Sessions", self.get_summaries_in(range).len().to_string()));
+        let (Some(average), Some(median), Some(marks), Some(longest)) = (
+            self.average_session_time(range),
+            self.median_session_time(range),
+            self.average_marks(range),
+            self.longest_session(range),
+        ) else {
+            return History::render_table(&rows);
+        };
+        rows.push(("Total", self.total_time(range).format(format)));
+        rows.push(("Breaks", self.break_time(range).format(format)));
+        rows.push(("Average session", average.format(format)));
+        rows.push(("Median session", median.format(format)));
+        rows.push(("Marks per session", format!("{marks:.1}")));
+        rows.push((
+            "Longest session",
+            format!(
+                "{}, started {}",
+                longest.time.format(format),
+                longest
+                    .start
+                    .to_formatted_with(&config.timestamp_format, &config.display)
+            ),
+        ));
+        let tags = self.most_used_tags(range);
+        if !tags.is_empty() {
+            let tags = tags
+                .iter()
+                .take(STATS_TAGS_COUNT)
+                .map(|(tag, count)| format!("{tag} ({count})"))
+                .collect::<Vec<String>>()
+                .join(", ");
+            rows.push(("Top tags", tags));
+        }
+        let streaks = Streaks::new(&self.tracked_days(config.day_starts_at), today);
+        let days = |count: usize| match count {
+            1 => String::from("1 day"),
+            count => format!("{count} days"),
+        };
+        rows.push(("Current streak", days(streaks.current)));
+        rows.push(("Longest streak", days(streaks.longest)));
+        rows.push(("Last 30 days", format!("{} tracked", days(streaks.recent))));
+        History::render_table(&rows)
+    }
+
+    fn render_table(rows: &[(&str, String)]) -> String {
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0) + 1;
+        rows.iter()
+            .map(|(name, value)| format!("{:<width$} {value}", format!("{name}:")))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn get_summaries_in(&self, range: &DateRange) -> Vec<&Summary> {
+        self.summaries
+            .iter()
+            .filter(|v| range.contains(&v.start.date))
+            .collect()
+    }
+
+    /// Work time of the sessions, skipped intervals excluded.
+    fn total_time(&self, range: &DateRange) -> Duration {
+        self.get_summaries_in(range)
+            .into_iter()
+            .map(|v| v.time)
+            .sum()
+    }
+
+    fn break_time(&self, range: &DateRange) -> Duration {
+        self.get_summaries_in(range)
+            .into_iter()
+            .map(|v| v.break_time)
+            .sum()
+    }
+
+    /// `None` if there is no session in `range`.
+    fn average_session_time(&self, range: &DateRange) -> Option<Duration> {
+        let count = self.get_summaries_in(range).len() as u64;
+        if count == 0 {
+            return None;
+        }
+        let total = self.total_time(range);
+        Some(Duration::from_millis(total.as_millis() / count))
+    }
+
+    /// Mean of the two middle sessions for an even count, `None` if there is no session in
+    /// `range`.
+    fn median_session_time(&self, range: &DateRange) -> Option<Duration> {
+        let mut times: Vec<Duration> = self
+            .get_summaries_in(range)
+            .into_iter()
+            .map(|v| v.time)
+            .collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort();
+        let middle = times.len() / 2;
+        if times.len() % 2 == 1 {
+            return Some(times[middle]);
+        }
+        let sum = times[middle - 1].as_millis() + times[middle].as_millis();
+        Some(Duration::from_millis(sum / 2))
+    }
+
+    fn average_marks(&self, range: &DateRange) -> Option<f64> {
+        let count = self.get_summaries_in(range).len();
+        if count == 0 {
+            return None;
+        }
+        let marks: usize = self
+            .get_summaries_in(range)
+            .into_iter()
+            .map(|v| v.marks)
+            .sum();
+        Some(marks as f64 / count as f64)
+    }
+
+    /// The earliest one of equally long sessions.
+    fn longest_session(&self, range: &DateRange) -> Option<&Summary> {
+        let mut longest: Option<&Summary> = None;
+        for summary in self.get_summaries_in(range) {
+            if longest.is_none_or(|v| summary.time > v.time) {
+                longest = Some(summary);
+            }
+        }
+        longest
+    }
+
+    /// Number of marks and sessions each tag is on, most used first, equally used tags are
+    /// sorted alphabetically.
+    fn most_used_tags(&self, range: &DateRange) -> Vec<(&Tag, usize)> {
+        let mut counts: Vec<(&Tag, usize)> = Vec::new();
+        for (tag, count) in self
+            .get_summaries_in(range)
+            .into_iter()
+            .flat_map(|v| &v.tags)
+        {
+            match counts.iter_mut().find(|(v, _)| *v == tag) {
+                Some((_, total)) => *total += count,
+                None => counts.push((tag, *count)),
+            }
+        }
+        counts.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+        });
+        counts
+    }
+}
+
+/// Total and average time of each weekday from Monday on the days from `first` through `last`, in
+/// `daily_times`. The average is over every such weekday in the range, or only over the ones with
+/// any time when `nonzero`.
+fn weekday_times(
+    daily_times: &BTreeMap<chrono::NaiveDate, Duration>,
+    first: chrono::NaiveDate,
+    last: chrono::NaiveDate,
+    nonzero: bool,
+) -> [(Duration, Duration); 7] {
+    let mut totals = [Duration::ZERO; 7];
+    let mut counts = [0; 7];
+    for day in first.iter_days().take_while(|v| *v <= last) {
+        let i = day.weekday().num_days_from_monday() as usize;
+        let time = daily_times.get(&day).copied().unwrap_or_default();
+        totals[i] += time;
+        if !nonzero || time != Duration::ZERO {
+            counts[i] += 1;
+        }
+    }
+    let mut times = [(Duration::ZERO, Duration::ZERO); 7];
+    for (i, (total, count)) in totals.into_iter().zip(counts).enumerate() {
+        let average = match count {
+            0 => Duration::ZERO,
+            count => Duration::from_millis(total.as_millis() / count),
+        };
+        times[i] = (total, average);
+    }
+    times
+}
+
+/// Time of `intervals` in each hour of the day, an interval is split between the hours it spans.
+/// Hours are of the wall clock in the time zone of the dates, so an hour skipped by a daylight
+/// saving change gets no time and a repeated one gets the time of both.
+fn hourly_times<Tz: chrono::TimeZone>(
+    intervals: &[(chrono::DateTime<Tz>, Duration)],
+) -> [Duration; 24] {
+    const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+    let mut times = [Duration::ZERO; 24];
+    for (start, time) in intervals {
+        let mut date = start.clone();
+        let mut left = time.as_millis();
+        while left > 0 {
+            let into_hour = u64::from(date.minute()) * 60 * 1000
+                + u64::from(date.second()) * 1000
+                + u64::from(date.nanosecond() / 1_000_000);
+            // A leap second can go past the end of the hour, the step is never empty.
+            let step = (HOUR_MILLIS - into_hour.min(HOUR_MILLIS - 1)).min(left);
+            times[date.hour() as usize] += Duration::from_millis(step);
+            left -= step;
+            date += chrono::TimeDelta::milliseconds(step as i64);
+        }
+    }
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        session::{Attribute, MarkSelector},
+        testing,
+    };
+    use std::fs::File;
+
+    fn date(day: u32, hour: u32, minute: u32) -> DateTime {
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .unwrap();
+        DateTime::new(&date)
+    }
+
+    /// Marks as minutes from `start`, the last one stops the session.
+    fn summarize(start: &DateTime, marks: &[(i64, Attribute)], tag: &str) -> Summary {
+        let mut session = Session::new(&Config::default(), start);
+        for (i, (minutes, attribute)) in marks.iter().enumerate() {
+            if i > 0 {
+                session.mark(&start.plus_minutes(*minutes), true).unwrap();
+            }
+            session.set_attribute(attribute.clone()).unwrap();
+        }
+        session
+            .tag(&Tag::from_text(tag).unwrap(), &MarkSelector::Last)
+            .unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.summarize(&[]).unwrap()
+    }
+
+    fn history(summaries: Vec<Summary>) -> History {
+        History {
+            summaries,
+            failures: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn summary_line_round_trip_works() {
+        let mut summary = summarize(
+            &date(10, 9, 0),
+            &[
+                (0, Attribute::None),
+                (60, Attribute::Skip),
+                (90, Attribute::None),
+                (180, Attribute::None),
+            ],
+            "work",
+        );
+        summary.tags.push((Tag::from_text("client").unwrap(), 1));
+        let dir = PathBuf::from("sessions");
+        let name = summary
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let line = summary.to_line(&name, 42).unwrap();
+        summary.path = dir.join(&name);
+        assert_eq!(Summary::from_line(&line, &dir), Some((name, 42, summary)));
+        assert_eq!(Summary::from_line("name\t42", &dir), None);
+    }
+
+    #[test]
+    fn history_load_uses_index() {
+        let dir = testing::temp_dir("history_load_uses_index");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 0,
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &date(10, 9, 0));
+        session.mark(&date(10, 11, 0), true).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.save(&config).unwrap();
+        let index = fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap();
+        assert!(index.starts_with(INDEX_HEADER));
+        assert_eq!(index.lines().count(), 2);
+
+        // Unchanged files aren't parsed again.
+        let modified = fs::metadata(&session.path).unwrap().modified().unwrap();
+        fs::write(&session.path, "Not a session.").unwrap();
+        File::options()
+            .write(true)
+            .open(&session.path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let history = History::load(&config).unwrap();
+        assert!(history.failures.is_empty());
+        assert_eq!(history.summaries, vec![session.summarize(&[]).unwrap()]);
+
+        // Changed files are, and their stale entries are dropped.
+        File::options()
+            .write(true)
+            .open(&session.path)
+            .unwrap()
+            .set_modified(modified + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert!(History::load(&config).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap(),
+            format!("{INDEX_HEADER}\n")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_load_rebuilds_corrupt_index() {
+        let dir = testing::temp_dir("history_load_rebuilds_corrupt_index");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 0,
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &date(10, 9, 0));
+        session.mark(&date(10, 11, 0), true).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.save(&config).unwrap();
+        // Active sessions aren't indexed.
+        let active = Session::new(&config, &DateTime::now().plus_hours(-1));
+        active.save(&config).unwrap();
+        let index = fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap();
+
+        fs::write(dir.join(INDEX_FILE_NAME), "garbage").unwrap();
+        let history = History::load(&config).unwrap();
+        assert_eq!(history.summaries.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap(),
+            index
+        );
+
+        fs::remove_file(dir.join(INDEX_FILE_NAME)).unwrap();
+        History::load(&config).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap(),
+            index
+        );
+
+        // Summaries made with other untracked tags are stale.
+        let config = Config {
+            untracked_tags: vec![Tag::from_text("break").unwrap()],
+            ..config
+        };
+        History::load(&config).unwrap();
+        let index_untracked = fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap();
+        assert_eq!(
+            index_untracked.lines().next(),
+            Some(format!("{INDEX_HEADER} untracked: break").as_str())
+        );
+        assert_eq!(index_untracked.lines().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_load_with_archive_works() {
+        let dir = testing::temp_dir("history_load_with_archive_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 0,
+            ..Default::default()
+        };
+        let mut archived = Session::new(&config, &date(6, 9, 0));
+        archived.mark(&date(6, 10, 0), true).unwrap();
+        archived.set_attribute(Attribute::Stop).unwrap();
+        archived.save(&config).unwrap();
+        let mut session = Session::new(&config, &date(10, 9, 0));
+        session.mark(&date(10, 11, 0), true).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.save(&config).unwrap();
+        let index = fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap();
+        let path = archived.archive().unwrap();
+
+        let history = History::load(&config).unwrap();
+        assert_eq!(history.summaries, vec![session.summarize(&[]).unwrap()]);
+        // The entry of the archived session is kept.
+        assert_eq!(
+            fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap(),
+            index
+        );
+        let history = History::load_with_archive(&config).unwrap();
+        archived.path = path;
+        assert_eq!(
+            history.summaries,
+            vec![
+                archived.summarize(&[]).unwrap(),
+                session.summarize(&[]).unwrap()
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_load_works_in_empty_dir() {
+        let dir = testing::temp_dir("history_load_works_in_empty_dir");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let history = History::load(&config).unwrap();
+        assert!(history.summaries.is_empty());
+        let today = testing::date_default().date_naive();
+        assert_eq!(
+            history.stats(&config, &DateRange::default(), today),
+            "Sessions: 0"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_render_graph_works() {
+        let rows = [
+            (
+                String::from("Mon"),
+                Duration::HOUR + Duration::HOUR,
+                Duration::ZERO,
+            ),
+            (String::from("Tue"), Duration::ZERO, Duration::ZERO),
+            (String::from("Wed"), Duration::HOUR, Duration::ZERO),
+        ];
+        assert_eq!(
+            History::render_graph(&rows, 20, &DurationFormat::Clock),
+            "Mon ███████████ 2:00\nTue\nWed ██████      1:00"
+        );
+        // Bars don't disappear in narrow terminals.
+        assert_eq!(
+            History::render_graph(&rows[..1], 5, &DurationFormat::Clock),
+            "Mon █ 2:00"
+        );
+        assert_eq!(
+            History::render_graph(&rows[1..2], 20, &DurationFormat::Clock),
+            "Tue"
+        );
+        let rows = [
+            (
+                String::from("Mon"),
+                Duration::HOUR,
+                Duration::from_secs(30 * 60),
+            ),
+            (
+                String::from("Tue"),
+                Duration::HOUR + Duration::HOUR,
+                Duration::ZERO,
+            ),
+        ];
+        assert_eq!(
+            History::render_graph(&rows, 20, &DurationFormat::Clock),
+            "Mon ██████      1:00  breaks 0:30\nTue ███████████ 2:00"
+        );
+    }
+
+    #[test]
+    fn history_graph_works() {
+        let session = |start: DateTime, minutes| {
+            summarize(
+                &start,
+                &[(0, Attribute::None), (minutes, Attribute::None)],
+                "work",
+            )
+        };
+        let history = history(vec![
+            session(date(10, 9, 0), 120),
+            // Counts to the day it started.
+            session(date(12, 23, 0), 120),
+            summarize(
+                &date(11, 12, 0),
+                &[(0, Attribute::Skip), (30, Attribute::None)],
+                "lunch",
+            ),
+        ]);
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            ..Default::default()
+        };
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 13).unwrap();
+
+        let output = history.graph(&config, today, None, 30);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), GRAPH_DAYS);
+        assert_eq!(lines[0], "Fri 2025-02-28");
+        assert_eq!(lines[10], "Mon 2025-03-10 ██████████ 2:00");
+        assert!(lines[11].starts_with("Tue 2025-03-11 "));
+        assert!(lines[11].ends_with("     breaks 0:30"));
+        assert_eq!(lines[12], "Wed 2025-03-12 ██████████ 2:00");
+        assert_eq!(lines[13], "Thu 2025-03-13");
+
+        let output = history.graph(&config, today, Some(2), 30);
+        assert_eq!(
+            output,
+            "2025-W10\n2025-W11 ████████████████ 4:00  breaks 0:30"
+        );
+    }
+
+    /// `+01:00`, `+02:00` from 2025-03-30 01:00 UTC until 2025-10-26 01:00 UTC, like Central
+    /// European Time.
+    #[derive(Clone, Copy, Debug)]
+    struct Cet;
+
+    impl chrono::TimeZone for Cet {
+        type Offset = chrono::FixedOffset;
+
+        fn from_offset(_: &chrono::FixedOffset) -> Cet {
+            Cet
+        }
+
+        fn offset_from_local_date(
+            &self,
+            local: &chrono::NaiveDate,
+        ) -> chrono::MappedLocalTime<chrono::FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(chrono::NaiveTime::MIN))
+        }
+
+        /// Only needed for dates away from the changes in the tests.
+        fn offset_from_local_datetime(
+            &self,
+            local: &chrono::NaiveDateTime,
+        ) -> chrono::MappedLocalTime<chrono::FixedOffset> {
+            let utc = *local - chrono::TimeDelta::hours(1);
+            chrono::MappedLocalTime::Single(self.offset_from_utc_datetime(&utc))
+        }
+
+        fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> chrono::FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(chrono::NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> chrono::FixedOffset {
+            let change = |month, day| {
+                chrono::NaiveDate::from_ymd_opt(2025, month, day)
+                    .and_then(|v| v.and_hms_opt(1, 0, 0))
+                    .unwrap()
+            };
+            let summer = change(3, 30) <= *utc && *utc < change(10, 26);
+            chrono::FixedOffset::east_opt(if summer { 2 * 60 * 60 } else { 60 * 60 }).unwrap()
+        }
+    }
+
+    fn minutes(v: u64) -> Duration {
+        Duration::from_secs(v * 60)
+    }
+
+    #[test]
+    fn hourly_times_splits_intervals() {
+        let offset = chrono::FixedOffset::east_opt(60 * 60).unwrap();
+        let date = |day, hour, minute| {
+            chrono::NaiveDate::from_ymd_opt(2025, 3, day)
+                .and_then(|v| v.and_hms_opt(hour, minute, 0))
+                .and_then(|v| v.and_local_timezone(offset).single())
+                .unwrap()
+        };
+        let times = hourly_times(&[(date(10, 9, 40), minutes(90))]);
+        assert_eq!(times[9], minutes(20));
+        assert_eq!(times[10], minutes(60));
+        assert_eq!(times[11], minutes(10));
+        assert_eq!(times.iter().copied().sum::<Duration>(), minutes(90));
+
+        // Crossing midnight, intervals of the same hour add up.
+        let times = hourly_times(&[
+            (date(10, 23, 30), minutes(90)),
+            (date(11, 0, 50), minutes(5)),
+            (date(11, 7, 0), Duration::ZERO),
+        ]);
+        assert_eq!(times[23], minutes(30));
+        assert_eq!(times[0], minutes(65));
+        assert_eq!(times[7], Duration::ZERO);
+        assert_eq!(times.iter().copied().sum::<Duration>(), minutes(95));
+
+        let times = hourly_times(&[(date(10, 6, 0), minutes(24 * 60 + 60))]);
+        assert_eq!(times[6], minutes(120));
+        assert!(times
+            .iter()
+            .enumerate()
+            .all(|(i, time)| i == 6 || *time == minutes(60)));
+    }
+
+    #[test]
+    fn hourly_times_follows_daylight_saving_changes() {
+        let utc = |month, day, hour, minute| {
+            chrono::NaiveDate::from_ymd_opt(2025, month, day)
+                .and_then(|v| v.and_hms_opt(hour, minute, 0))
+                .unwrap()
+                .and_utc()
+                .with_timezone(&Cet)
+        };
+        // 01:40 to 03:40, the clock skips from 02:00 to 03:00.
+        let spring = utc(3, 30, 0, 40);
+        assert_eq!(spring.hour(), 1);
+        let times = hourly_times(&[(spring, minutes(60))]);
+        assert_eq!(times[1], minutes(20));
+        assert_eq!(times[2], Duration::ZERO);
+        assert_eq!(times[3], minutes(40));
+
+        // 02:40 to 02:40, the clock goes back from 03:00 to 02:00.
+        let autumn = utc(10, 26, 0, 40);
+        assert_eq!(autumn.hour(), 2);
+        let times = hourly_times(&[(autumn, minutes(60))]);
+        assert_eq!(times[2], minutes(60));
+        assert_eq!(times.iter().copied().sum::<Duration>(), minutes(60));
+    }
+
+    #[test]
+    fn history_hours_works() {
+        let history = history(vec![
+            summarize(
+                &date(10, 9, 40),
+                &[(0, Attribute::None), (90, Attribute::None)],
+                "work",
+            ),
+            // Skipped time doesn't count.
+            summarize(
+                &date(11, 12, 0),
+                &[
+                    (0, Attribute::Skip),
+                    (30, Attribute::None),
+                    (60, Attribute::None),
+                ],
+                "lunch",
+            ),
+        ]);
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            ..Default::default()
+        };
+
+        let output = history.hours(&config, &DateRange::default(), 30);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 24);
+        assert_eq!(lines[0], "00:00");
+        let bar = |len| format!("{:<19}", GRAPH_BAR.repeat(len));
+        assert_eq!(lines[9], format!("09:00 {} 0:20", bar(6)));
+        assert_eq!(lines[10], format!("10:00 {} 1:00", bar(19)));
+        assert_eq!(lines[11], format!("11:00 {} 0:10", bar(3)));
+        assert_eq!(lines[12], format!("12:00 {} 0:30", bar(10)));
+
+        let range = DateRange::from_days(
+            chrono::NaiveDate::from_ymd_opt(2025, 3, 11),
+            None,
+            chrono::NaiveTime::MIN,
+        );
+        let config = Config {
+            display: crate::date_time::DisplayPrefs {
+                clock: crate::date_time::Clock::H12,
+                ..Default::default()
+            },
+            ..config
+        };
+        let output = history.hours(&config, &range, 30);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "12 AM");
+        assert_eq!(lines[10], "10 AM");
+        assert!(lines[12].starts_with("12 PM "));
+        assert!(lines[12].ends_with(" 0:30"));
+    }
+
+    #[test]
+    fn weekday_times_counts_partial_weeks() {
+        let day = |day| chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        // Two Wednesdays, one of them empty, and a Friday.
+        let daily_times = BTreeMap::from([
+            (day(5), minutes(60)),
+            (day(7), minutes(30)),
+            (day(14), minutes(40)),
+        ]);
+
+        // Starts on a Wednesday and ends on a Friday, so there are two of each but one Tuesday.
+        let times = weekday_times(&daily_times, day(5), day(14), false);
+        assert_eq!(times[1], (Duration::ZERO, Duration::ZERO));
+        assert_eq!(times[2], (minutes(60), minutes(30)));
+        assert_eq!(times[4], (minutes(70), minutes(35)));
+
+        let times = weekday_times(&daily_times, day(5), day(14), true);
+        assert_eq!(times[1], (Duration::ZERO, Duration::ZERO));
+        assert_eq!(times[2], (minutes(60), minutes(60)));
+        assert_eq!(times[4], (minutes(70), minutes(35)));
+
+        // Ends on a Thursday, the second Friday is outside.
+        let times = weekday_times(&daily_times, day(5), day(13), false);
+        assert_eq!(times[4], (minutes(30), minutes(30)));
+        assert_eq!(times[3], (Duration::ZERO, Duration::ZERO));
+    }
+
+    #[test]
+    fn history_weekdays_works() {
+        let session = |start: DateTime, minutes| {
+            summarize(
+                &start,
+                &[(0, Attribute::None), (minutes, Attribute::None)],
+                "work",
+            )
+        };
+        let history = history(vec![
+            session(date(3, 9, 0), 120),
+            session(date(10, 9, 0), 60),
+            session(date(14, 9, 0), 45),
+        ]);
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            ..Default::default()
+        };
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 16).unwrap();
+
+        let output = history.weekdays(&config, today, Some(2), false);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "Range: 2025-03-03 to 2025-03-16");
+        assert_eq!(lines[1], "Mon:   1:30 average, 3:00 total");
+        assert_eq!(lines[5], "Fri:   0:22 average, 0:45 total");
+        assert_eq!(lines[7], "Sun:   0:00 average, 0:00 total");
+
+        // The first Monday falls out of the range.
+        let output = history.weekdays(&config, today, Some(1), true);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "Mon:   1:00 average, 1:00 total");
+        assert_eq!(lines[5], "Fri:   0:45 average, 0:45 total");
+    }
+
+    #[test]
+    fn history_calendar_works() {
+        let session = |start: DateTime, minutes| {
+            summarize(
+                &start,
+                &[(0, Attribute::None), (minutes, Attribute::None)],
+                "work",
+            )
+        };
+        let history = history(vec![
+            session(date(1, 9, 0), 30),
+            session(date(10, 9, 0), 120),
+            session(date(20, 9, 0), 299),
+            session(date(31, 9, 0), 420),
+        ]);
+        // Starts on Saturday.
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+        assert_eq!(
+            history.calendar(&Config::default(), day),
+            "\
+March 2025
+Mo  Tu  We  Th  Fr  Sa  Su
+                     1░  2
+ 3   4   5   6   7   8   9
+10▒ 11  12  13  14  15  16
+17  18  19  20▓ 21  22  23
+24  25  26  27  28  29  30
+31█
+░ <2h  ▒ <4h  ▓ <6h  █ 6h+"
+        );
+    }
+
+    #[test]
+    fn history_stats_works() {
+        let date_default = DateTime::new(&testing::date_default());
+        let mut history = history(vec![
+            summarize(
+                &date_default.plus_hours(-10),
+                &[
+                    (0, Attribute::None),
+                    (60, Attribute::Skip),
+                    (90, Attribute::None),
+                    (180, Attribute::None),
+                ],
+                "work",
+            ),
+            summarize(
+                &date_default.plus_hours(-5),
+                &[(0, Attribute::None), (60, Attribute::None)],
+                "work",
+            ),
+            summarize(
+                &date_default.plus_hours(-3),
+                &[(0, Attribute::None), (30, Attribute::None)],
+                "review",
+            ),
+        ]);
+        history.summaries[0]
+            .tags
+            .insert(0, (Tag::from_text("client").unwrap(), 1));
+        let minutes = |v: u64| Duration::from_secs(v * 60);
+        let today = date_default.day(chrono::NaiveTime::MIN);
+
+        let range = DateRange::default();
+        assert_eq!(history.total_time(&range), minutes(240));
+        assert_eq!(history.break_time(&range), minutes(30));
+        assert_eq!(history.average_session_time(&range), Some(minutes(80)));
+        assert_eq!(history.median_session_time(&range), Some(minutes(60)));
+        assert_eq!(history.average_marks(&range), Some(8.0 / 3.0));
+        let longest = history.longest_session(&range).unwrap();
+        assert_eq!(longest, &history.summaries[0]);
+        assert_eq!(longest.time, minutes(150));
+        let tags: Vec<(String, usize)> = history
+            .most_used_tags(&range)
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                (String::from("work"), 2),
+                (String::from("client"), 1),
+                (String::from("review"), 1),
+            ]
+        );
+        let output = history.stats(&Config::default(), &range, today);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "Sessions:          3");
+        assert_eq!(lines[1], "Total:             4h 0m 0s");
+        assert_eq!(lines[2], "Breaks:            0h 30m 0s");
+        assert_eq!(lines[5], "Marks per session: 2.7");
+        assert_eq!(
+            lines[7],
+            "Top tags:          work (2), client (1), review (1)"
+        );
+        assert_eq!(lines[8], "Current streak:    1 day");
+        assert_eq!(lines[9], "Longest streak:    1 day");
+        assert_eq!(lines[10], "Last 30 days:      1 day tracked");
+
+        let range = DateRange {
+            start: Some(date_default.plus_hours(-5)),
+            end: None,
+        };
+        assert_eq!(history.total_time(&range), minutes(90));
+        assert_eq!(history.break_time(&range), Duration::ZERO);
+        assert_eq!(history.median_session_time(&range), Some(minutes(45)));
+
+        let range = DateRange {
+            start: None,
+            end: Some(date_default.plus_hours(-10)),
+        };
+        assert_eq!(history.average_session_time(&range), None);
+        assert_eq!(history.longest_session(&range), None);
+        let output = history.stats(&Config::default(), &range, today);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("Range:    first session to "));
+        assert_eq!(lines[1], "Sessions: 0");
+    }
+
+    #[test]
+    fn streaks_works() {
+        let day = |day: u32| chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        let days = BTreeSet::from([day(1), day(2), day(3), day(4), day(10), day(11), day(13)]);
+        assert_eq!(
+            Streaks::new(&days, day(13)),
+            Streaks {
+                current: 1,
+                longest: 4,
+                recent: 7,
+            }
+        );
+        assert_eq!(Streaks::new(&days, day(12)).current, 0);
+        assert_eq!(Streaks::new(&days, day(11)).current, 2);
+        // Only the last 30 days, today included.
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 4, 2).unwrap();
+        assert_eq!(Streaks::new(&days, today).recent, 4);
+        assert_eq!(
+            Streaks::new(&BTreeSet::new(), day(1)),
+            Streaks {
+                current: 0,
+                longest: 0,
+                recent: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn history_tracked_days_ignores_skipped_time() {
+        let history = history(vec![
+            summarize(
+                &date(10, 9, 0),
+                &[(0, Attribute::None), (60, Attribute::None)],
+                "work",
+            ),
+            summarize(
+                &date(11, 12, 0),
+                &[(0, Attribute::Skip), (30, Attribute::None)],
+                "lunch",
+            ),
+            // Counts to the day before with the day starting at 04:00.
+            summarize(
+                &date(13, 2, 0),
+                &[(0, Attribute::None), (30, Attribute::None)],
+                "work",
+            ),
+        ]);
+        let boundary = chrono::NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+        let day = |day: u32| chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        assert_eq!(
+            history.tracked_days(boundary),
+            BTreeSet::from([day(10), day(12)])
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    date_time::DateTime,
+    exec,
+    session::{Session, Tag},
+};
+
+const ENV_SESSION_PATH: &str = "TT_SESSION_PATH";
+const ENV_EVENT: &str = "TT_EVENT";
+/// Tracked time of the session, see [`Summary::time`](crate::history::Summary::time).
+const ENV_DURATION_MS: &str = "TT_DURATION_MS";
+/// Date of the last mark of the session.
+const ENV_MARK_TIME: &str = "TT_MARK_TIME";
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Event {
+    /// A session was started or continued.
+    Start,
+    Mark,
+    /// A session was stopped, also when stopped for being stale.
+    Stop,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Start => "start",
+            Event::Mark => "mark",
+            Event::Stop => "stop",
+        }
+    }
+}
+
+/// Shell commands run after the action of their [`Event`] succeeds.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Hooks {
+    pub on_start: Option<String>,
+    pub on_mark: Option<String>,
+    pub on_stop: Option<String>,
+}
+
+impl Hooks {
+    pub fn get(&self, event: Event) -> Option<&str> {
+        match event {
+            Event::Start => self.on_start.as_deref(),
+            Event::Mark => self.on_mark.as_deref(),
+            Event::Stop => self.on_stop.as_deref(),
+        }
+    }
+
+    /// Runs the command of `event` with `sh -c` and waits for it. The action already succeeded,
+    /// so failures are only reported as warnings.
+    pub fn run(&self, event: Event, session: &Session, untracked: &[Tag]) {
+        let Some(command) = self.get(event) else {
+            return;
+        };
+        let time = match session.summarize(untracked) {
+            Ok(summary) => summary.time,
+            Err(err) => {
+                eprintln!("Warning: skipping {} hook: {err}", event.name());
+                return;
+            }
+        };
+        let mut command = match exec::shell(command) {
+            Ok(command) => command,
+            Err(err) => {
+                eprintln!("Warning: skipping {} hook: {err}", event.name());
+                return;
+            }
+        };
+        let status = command
+            .env(ENV_SESSION_PATH, &session.path)
+            .env(ENV_EVENT, event.name())
+            .env(ENV_DURATION_MS, time.as_millis().to_string())
+            .env(ENV_MARK_TIME, DateTime::new(&session.end()).to_formatted())
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("Warning: {} hook failed, {status}", event.name()),
+            Err(err) => eprintln!("Warning: couldn't run {} hook: {err}", event.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, session::Attribute, testing};
+    use std::fs;
+
+    #[test]
+    fn hooks_run_works() {
+        let dir = testing::temp_dir("hooks_run_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::new(&testing::date_default()));
+        session
+            .mark(
+                &DateTime::new(&testing::date_default()).plus_minutes(90),
+                false,
+            )
+            .unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        let output = dir.join("hook.txt");
+        let hooks = Hooks {
+            on_stop: Some(format!(
+                "printf '%s %s %s %s' \"$TT_EVENT\" \"$TT_DURATION_MS\" \"$TT_MARK_TIME\" \
+                 \"$TT_SESSION_PATH\" > '{}'",
+                output.display()
+            )),
+            ..Default::default()
+        };
+        hooks.run(Event::Start, &session, &[]);
+        assert!(!output.exists());
+        hooks.run(Event::Stop, &session, &[]);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!(
+                "stop 5400000 {} {}",
+                DateTime::new(&testing::date_default())
+                    .plus_minutes(90)
+                    .to_formatted(),
+                session.path.display()
+            )
+        );
+
+        // Failures are only reported.
+        let hooks = Hooks {
+            on_mark: Some(String::from("exit 3")),
+            ..Default::default()
+        };
+        hooks.run(Event::Mark, &session, &[]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
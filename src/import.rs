@@ -0,0 +1,309 @@
+use crate::{
+    date_time::DateTime,
+    session::{Attribute, MarkSelector, Session, Tag},
+    Config,
+};
+use std::fmt;
+
+const CSV_HEADER: [&str; 4] = ["start", "end", "tags", "notes"];
+/// Separates tags within the tags column.
+const CSV_TAG_SEPARATOR: char = ';';
+
+/// Interval read from a row, tags and notes go to the mark that starts it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Row {
+    /// Line the row starts on, counting from 1.
+    pub line: usize,
+    start: DateTime,
+    end: DateTime,
+    tags: Vec<Tag>,
+    notes: String,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct InvalidRow {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Parses CSV with the columns of [`CSV_HEADER`], the header is required. Fails only when the file
+/// as a whole can't be read, problems of individual rows are returned alongside the valid ones.
+pub fn parse_csv(contents: &str) -> Result<(Vec<Row>, Vec<InvalidRow>), String> {
+    let records = split_records(contents)?;
+    let Some((_, header)) = records.first() else {
+        return Err(String::from("file is empty"));
+    };
+    let header: Vec<String> = header.iter().map(|v| v.trim().to_lowercase()).collect();
+    if header != CSV_HEADER {
+        return Err(format!(
+            "invalid header `{}`, expected `{}`",
+            header.join(","),
+            CSV_HEADER.join(",")
+        ));
+    }
+    let mut rows = Vec::new();
+    let mut invalid = Vec::new();
+    for (line, fields) in records.into_iter().skip(1) {
+        match parse_row(line, &fields) {
+            Ok(row) => rows.push(row),
+            Err(reason) => invalid.push(InvalidRow { line, reason }),
+        }
+    }
+    Ok((rows, invalid))
+}
+
+/// Splits `contents` into records of fields, each with the line it starts on. Fields can be
+/// quoted with `"`, quoted fields can contain separators, line breaks and `""` for a quote.
+/// Empty lines are skipped.
+fn split_records(contents: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut line = 1;
+    let mut line_start = 1;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match c {
+            '"' if is_quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if is_quoted => is_quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                is_quoted = true;
+            }
+            c if is_quoted => field.push(c),
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|v| !v.trim().is_empty()) {
+                    records.push((line_start, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line_start = line;
+            }
+            c => field.push(c),
+        }
+    }
+    if is_quoted {
+        return Err(format!("quoted field on line {line_start} isn't closed"));
+    }
+    fields.push(field);
+    if fields.iter().any(|v| !v.trim().is_empty()) {
+        records.push((line_start, fields));
+    }
+    Ok(records)
+}
+
+fn parse_row(line: usize, fields: &[String]) -> Result<Row, String> {
+    let [start, end, tags, notes] = fields else {
+        return Err(format!(
+            "expected {} columns, found {}",
+            CSV_HEADER.len(),
+            fields.len()
+        ));
+    };
+    let start = parse_timestamp(start)?;
+    let end = parse_timestamp(end)?;
+    if end.date <= start.date {
+        return Err(String::from("end must be after start"));
+    }
+    let tags = tags
+        .split(CSV_TAG_SEPARATOR)
+        .filter(|v| !v.trim().is_empty())
+        .map(Tag::from_text)
+        .collect::<Result<Vec<Tag>, String>>()?;
+    Ok(Row {
+        line,
+        start,
+        end,
+        tags,
+        notes: notes.trim().to_owned(),
+    })
+}
+
+/// Accepts RFC 3339 and ISO 8601 dates without an offset, which are in local time, with `T` or a
+/// space between the date and the time and with optional seconds.
+fn parse_timestamp(text: &str) -> Result<DateTime, String> {
+    let text = text.trim();
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Ok(DateTime::new(&date.with_timezone(&chrono::Local)));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(date) = chrono::NaiveDateTime::parse_from_str(text, format) {
+            let date = date
+                .and_local_timezone(chrono::Local)
+                .earliest()
+                .ok_or_else(|| format!("`{text}` doesn't exist in local time"))?;
+            return Ok(DateTime::new(&date));
+        }
+    }
+    Err(format!(
+        "invalid timestamp `{text}`, use ISO 8601 or `YYYY-MM-DD HH:MM`"
+    ))
+}
+
+/// Turns `rows` into stopped sessions, each with the line of its first row. A row starting exactly
+/// where the previous one ended continues its session unless `one_per_row` is set.
+pub fn build_sessions(
+    config: &Config,
+    rows: &[Row],
+    one_per_row: bool,
+) -> (Vec<(usize, Session)>, Vec<InvalidRow>) {
+    let mut sessions: Vec<(usize, Session)> = Vec::new();
+    let mut invalid = Vec::new();
+    for row in rows {
+        let previous = sessions
+            .last_mut()
+            .filter(|(_, v)| !one_per_row && v.end() == row.start.date)
+            .map(|(_, v)| v);
+        let result = match previous {
+            Some(session) => {
+                // Applied to a copy so that a failure leaves the session as it was.
+                let mut appended = session.clone();
+                appended.overwrite_attribute(Attribute::None);
+                append(config, &mut appended, row).map(|_| *session = appended)
+            }
+            None => {
+                let mut session = Session::new(config, &row.start);
+                append(config, &mut session, row).map(|_| sessions.push((row.line, session)))
+            }
+        };
+        if let Err(reason) = result {
+            invalid.push(InvalidRow {
+                line: row.line,
+                reason,
+            });
+        }
+    }
+    (sessions, invalid)
+}
+
+/// Fills in the last mark of `session`, which has to be at the start of `row`, and stops the
+/// session at its end.
+fn append(config: &Config, session: &mut Session, row: &Row) -> Result<(), String> {
+    if !row.notes.is_empty() {
+        session.write(&row.notes)?;
+    }
+    for tag in &row.tags {
+        session.tag(&config.tag_normalizer.apply(tag), &MarkSelector::Last)?;
+    }
+    session.mark(&row.end, false)?;
+    session.set_attribute(Attribute::Stop)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date_time::Duration;
+
+    #[test]
+    fn split_records_works() {
+        let contents = "a,b\r\n\n\"x, \"\"y\"\"\",\"two\nlines\"\nlast,";
+        assert_eq!(
+            split_records(contents),
+            Ok(vec![
+                (1, vec![String::from("a"), String::from("b")]),
+                (
+                    3,
+                    vec![String::from("x, \"y\""), String::from("two\nlines")]
+                ),
+                (5, vec![String::from("last"), String::new()]),
+            ])
+        );
+        assert!(split_records("a,\"b\n").is_err());
+    }
+
+    #[test]
+    fn parse_csv_works() {
+        let contents = "\
+start,end,tags,notes
+2002-05-08 09:00,2002-05-08T10:30:00,rust;cli,\"Parser,
+second line\"
+2002-05-08 11:00,2002-05-08 10:00,,
+2002-05-08T11:00:00+00:00,2002-05-08 25:00,,
+2002-05-08 12:00,2002-05-08 13:00,,
+2002-05-08 12:00,2002-05-08 13:00
+";
+        let (rows, invalid) = parse_csv(contents).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(
+            rows[0].tags,
+            vec![
+                Tag::from_text("rust").unwrap(),
+                Tag::from_text("cli").unwrap()
+            ]
+        );
+        assert_eq!(rows[0].notes, "Parser,\nsecond line");
+        assert_eq!(
+            Duration::between(&rows[0].start.date, &rows[0].end.date),
+            Ok(Duration::from_secs(90 * 60))
+        );
+        assert_eq!(rows[1].line, 6);
+        let lines: Vec<usize> = invalid.iter().map(|v| v.line).collect();
+        assert_eq!(lines, vec![4, 5, 7]);
+        assert_eq!(invalid[0].to_string(), "line 4: end must be after start");
+
+        assert!(parse_csv("").is_err());
+        assert!(parse_csv("start,end,notes\n").is_err());
+        assert_eq!(
+            parse_csv("Start, End,tags,notes\n"),
+            Ok((Vec::new(), Vec::new()))
+        );
+    }
+
+    #[test]
+    fn build_sessions_works() {
+        let config = Config::default();
+        let contents = "\
+start,end,tags,notes
+2002-05-08 09:00,2002-05-08 10:00,rust,First
+2002-05-08 10:00,2002-05-08 11:00,,Second
+2002-05-08 12:00,2002-05-08 13:00,,
+";
+        let (rows, _) = parse_csv(contents).unwrap();
+        let (sessions, invalid) = build_sessions(&config, &rows, false);
+        assert_eq!(invalid, Vec::new());
+        assert_eq!(sessions.len(), 2);
+        let (line, session) = &sessions[0];
+        assert_eq!(*line, 2);
+        assert_eq!(session.marks.len(), 3);
+        assert!(!session.is_active());
+        assert_eq!(session.end(), rows[1].end.date);
+        let summary = session.summarize(&[]).unwrap();
+        assert_eq!(summary.time, Duration::from_secs(2 * 60 * 60));
+        assert_eq!(summary.tags, vec![(Tag::from_text("rust").unwrap(), 1)]);
+        assert_eq!(sessions[1].0, 4);
+
+        let (sessions, _) = build_sessions(&config, &rows, true);
+        assert_eq!(sessions.len(), 3);
+        assert!(sessions.iter().all(|(_, v)| v.marks.len() == 2));
+
+        // Rows ending in the future can't be imported.
+        let future = DateTime::now().plus_hours(2).date.format("%F %T");
+        let contents = format!("start,end,tags,notes\n2002-05-08 09:00,{future},,\n");
+        let (rows, _) = parse_csv(&contents).unwrap();
+        let (sessions, invalid) = build_sessions(&config, &rows, false);
+        assert!(sessions.is_empty());
+        assert_eq!(invalid.len(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+use std::{fmt::Write as _, iter::Peekable, str::Chars};
+
+/// `text` as a quoted JSON string.
+pub fn to_string(text: &str) -> String {
+    let mut str = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => str += "\\\"",
+            '\\' => str += "\\\\",
+            '\n' => str += "\\n",
+            '\r' => str += "\\r",
+            '\t' => str += "\\t",
+            c if c.is_control() => {
+                let _ = write!(str, "\\u{:04x}", c as u32);
+            }
+            c => str.push(c),
+        }
+    }
+    str.push('"');
+    str
+}
+
+/// The subset of JSON this crate writes, objects and strings.
+#[derive(PartialEq, Debug)]
+pub enum Json {
+    String(String),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Fails on anything but a single value, surrounding whitespace is allowed.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = Json::parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next().is_some() {
+            return Err(String::from("unexpected text after the value"));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+        skip_whitespace(chars);
+        match chars.peek() {
+            Some('"') => Ok(Json::String(Json::parse_string(chars)?)),
+            Some('{') => Json::parse_object(chars),
+            Some(c) => Err(format!(
+                "unexpected `{c}`, only objects and strings are read"
+            )),
+            None => Err(String::from("unexpected end of line")),
+        }
+    }
+
+    fn parse_object(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+        chars.next();
+        let mut fields = Vec::new();
+        skip_whitespace(chars);
+        if chars.peek() == Some(&'}') {
+            chars.next();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            skip_whitespace(chars);
+            let key = Json::parse_string(chars)?;
+            skip_whitespace(chars);
+            if chars.next() != Some(':') {
+                return Err(format!("expected `:` after `{key}`"));
+            }
+            fields.push((key, Json::parse_value(chars)?));
+            skip_whitespace(chars);
+            match chars.next() {
+                Some(',') => (),
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err(String::from("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+        if chars.next() != Some('"') {
+            return Err(String::from("expected a string"));
+        }
+        let mut str = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(str),
+                Some('\\') => {
+                    let c = match chars.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let code: String = chars.by_ref().take(4).collect();
+                            u32::from_str_radix(&code, 16)
+                                .ok()
+                                .filter(|_| code.len() == 4)
+                                .ok_or_else(|| format!("invalid escape `\\u{code}`"))
+                                .map(|v| char::from_u32(v).unwrap_or(char::REPLACEMENT_CHARACTER))?
+                        }
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        _ => return Err(String::from("invalid escape")),
+                    };
+                    str.push(c);
+                }
+                Some(c) => str.push(c),
+                None => return Err(String::from("string isn't closed")),
+            }
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|v| v.is_whitespace()).is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string_works() {
+        assert_eq!(to_string("plain"), "\"plain\"");
+        assert_eq!(
+            to_string("Said \"hi\"\n\tback\\slash \u{1}"),
+            "\"Said \\\"hi\\\"\\n\\tback\\\\slash \\u0001\""
+        );
+    }
+
+    #[test]
+    fn json_parse_works() {
+        let text = " { \"a\" : \"\\u00e9\\n\", \"b\": {}} ";
+        assert_eq!(
+            Json::parse(text),
+            Ok(Json::Object(vec![
+                (String::from("a"), Json::String(String::from("é\n"))),
+                (String::from("b"), Json::Object(Vec::new())),
+            ]))
+        );
+        let text = "Said \"hi\"\n\tback\\slash \u{1}";
+        assert_eq!(
+            Json::parse(&to_string(text)),
+            Ok(Json::String(text.to_owned()))
+        );
+        assert!(Json::parse("").is_err());
+        assert!(Json::parse("1").is_err());
+        assert!(Json::parse("{\"a\":\"b\"} x").is_err());
+        assert!(Json::parse("{\"a\" \"b\"}").is_err());
+        assert!(Json::parse("\"\\x\"").is_err());
+        assert!(Json::parse("\"open").is_err());
+    }
+}
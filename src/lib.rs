@@ -1,187 +1,1723 @@
-use config::{Action, Config};
-use date_time::DateTime;
-use session::{Aggregator, Attribute, Session, SessionFile, Tag};
-use std::{env, error::Error, fs, io, path::PathBuf, process::Command};
+use config::{Action, Config, ConfigFile, Options, PathTarget};
+use crypto::Cipher;
+use date_time::{DateRange, DateTime, Duration};
+use history::History;
+use hook::Event;
+use output::Output;
+use report::{Billing, Contexts, Gaps, Goals, Period, TagGoal};
+use session::{
+    Aggregator, Attribute, Backup, MarkSelector, Session, SessionFile, Tag, WriteMode, WriteTarget,
+};
+use status::{Pomodoro, Status, STATUS_EMPTY};
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread, time,
+};
 
+/// Exit code when the thing a command looks for doesn't exist, see [`NotFound`].
+pub const EXIT_NOT_FOUND: u8 = 3;
+const VIEW_EMPTY: &str = "No sessions yet — run `time_tracker start` to begin.";
+/// Prefix of tags added by [`Config::auto_branch_tag`], followed by the branch name.
+#[cfg(feature = "git")]
+const BRANCH_TAG_PREFIX: &str = "branch:";
+/// Clears the terminal and moves the cursor to its top left corner.
+const ANSI_CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+/// Used when the width of the terminal isn't known from `COLUMNS`.
+const GRAPH_WIDTH_DEFAULT: usize = 80;
+/// Passphrase of encrypted mark contents, asked for when not set, see [`get_cipher`].
+const ENV_PASSPHRASE: &str = "TIMETRACKER_PASSPHRASE";
+
+pub mod api;
 mod config;
+mod crypto;
 mod date_time;
+mod diff;
+mod events;
+mod exec;
+#[cfg(feature = "git")]
+mod git;
+mod history;
+mod hook;
+mod import;
+mod json;
+mod output;
+mod report;
 mod session;
+mod status;
+mod style;
 #[cfg(test)]
 mod testing;
+mod ui;
+mod view;
 
-pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
-    let (action, config) =
-        setup(&args).map_err(|err| format!("Problem parsing arguments: {err}"))?;
+/// Error of a command that looks for something that doesn't exist, exits with
+/// [`EXIT_NOT_FOUND`] so that scripts can tell it apart from other errors.
+pub struct NotFound(String);
 
-    let out = match action {
-        Action::Start { date } => start(&config, &date),
-        Action::Mark { date } => mark(&config, &date),
-        Action::Remark { date } => remark(&config, &date),
-        Action::Unmark => unmark(&config),
-        Action::Path => path(&config),
-        Action::View => view(&config),
-        Action::Attribute { attribute: attr } => attribute(&config, attr),
-        Action::Tag { tag: tag_ } => tag(&config, &tag_),
-        Action::Untag { tag } => untag(&config, &tag),
-        Action::Write { text } => write(&config, &text),
-        Action::Version => Ok(version()),
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
-    .map_err(|err| format!("Application error: {err}"))?;
-    Ok(out)
 }
 
-fn setup(args: &[String]) -> Result<(Action, Config), Box<dyn Error>> {
-    if args.len() < 2 {
-        return Err("not enough arguments")?;
+// Printed by `main` like other errors, as a quoted string.
+impl fmt::Debug for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
     }
+}
+
+impl Error for NotFound {}
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (action, config, out) =
+        setup(args).map_err(|err| format!("Problem parsing arguments: {err}"))?;
+
+    match action {
+        Action::Start { date } => start(&config, &out, &date),
+        Action::Continue { date } => continue_(&config, &out, &date),
+        Action::Mark { date, future } => mark(&config, &out, &date, future),
+        Action::Remark {
+            date,
+            future,
+            last,
+            first,
+        } => remark(&config, &out, &date, future, last, first),
+        Action::Unmark { mark, force } => unmark(&config, &out, &mark, force),
+        Action::Pause { date } => pause(&config, &out, &date),
+        Action::Unpause { date } => unpause(&config, &out, &date),
+        Action::Idle { start, end } => idle(&config, &out, &start, &end),
+        Action::Path { target } => path(&config, &out, &target),
+        Action::View { porcelain } => view(&config, &out, porcelain),
+        Action::Watch { interval } => watch(&config, &out, interval),
+        Action::Status { waybar } => status(&config, &out, waybar),
+        Action::Attribute {
+            attribute: attr,
+            force,
+        } => attribute(&config, &out, attr, force),
+        Action::Tag {
+            tag: tag_,
+            session,
+            mark,
+            last,
+        } => tag(&config, &out, &tag_, session, &mark, last),
+        Action::Untag {
+            tag,
+            session,
+            mark,
+            last,
+        } => untag(&config, &out, &tag, session, &mark, last),
+        Action::Write {
+            text,
+            target,
+            mode,
+            last,
+        } => write(&config, &out, &text, &target, mode, last),
+        Action::Title { title: title_ } => title(&config, &out, &title_),
+        Action::Restore { index } => restore(&config, &out, index),
+        Action::Validate { fix } => validate(&config, &out, fix),
+        Action::Fmt { current, check } => fmt(&config, &out, current, check),
+        Action::Migrate => migrate(&config, &out),
+        Action::Init => init(&out),
+        Action::Edit { config: config_ } => edit(&config, &out, config_),
+        Action::ConfigGet { key } => config_get(&config, &key),
+        Action::ConfigSet { key, value } => config_set(&config, &out, &key, &value),
+        Action::ConfigList => config_list(&config),
+        Action::Events { since } => events(&config, since),
+        Action::Import {
+            path,
+            one_session_per_row,
+            skip_invalid,
+        } => import(&config, &out, &path, one_session_per_row, skip_invalid),
+        Action::Retag { from, to, dry_run } => retag(&config, &out, &from, &to, dry_run),
+        Action::Merge {
+            paths,
+            force,
+            skip_gap,
+        } => merge(&config, &out, paths, force, skip_gap),
+        Action::Split { date } => split(&config, &out, &date),
+        Action::Archive { before } => archive(&config, &out, before),
+        Action::Prune { dry_run, yes } => prune(&config, &out, dry_run, yes),
+        Action::Graph { weeks } => graph(&config, &out, weeks),
+        Action::Calendar { day } => calendar(&config, &out, day),
+        Action::Stats {
+            first,
+            last,
+            include_archived,
+            by_context,
+        } => stats(&config, &out, first, last, include_archived, by_context),
+        Action::Pomodoro => pomodoro(&config, &out),
+        Action::When => when(&config, &out),
+        Action::Billable { last } => billable(&config, &out, last),
+        Action::At { context, last } => at(&config, &out, context.as_deref(), last),
+        Action::Billing { month } => billing(&config, &out, month),
+        Action::Gaps { day } => gaps(&config, &out, day),
+        Action::Hours { first, last } => hours(&config, &out, first, last),
+        Action::Weekdays { weeks, nonzero } => weekdays(&config, &out, weeks, nonzero),
+        Action::Goals => goals(&config, &out),
+        Action::Goal { tag, goal: goal_ } => goal(&config, &out, &tag, goal_),
+        Action::Version { json } => {
+            println!("{}", version(json));
+            Ok(())
+        }
+    }
+    .map_err(|err| -> Box<dyn Error> {
+        match err.downcast::<NotFound>() {
+            Ok(err) => Box::new(NotFound(format!("Application error: {err}"))),
+            Err(err) => format!("Application error: {err}").into(),
+        }
+    })?;
+    if out.dry_run {
+        out.success("Dry run, no files were changed");
+    }
+    Ok(())
+}
 
+fn setup(args: &[String]) -> Result<(Action, Config, Output), Box<dyn Error>> {
     // First arg (args[0]) is the name of the program.
-    let action = Action::build(&args[1], &args[2..])?;
-    let config = Config::build()?;
-    Ok((action, config))
+    let (options, mut args) = Options::build(args.get(1..).unwrap_or_default())?;
+    if options.version {
+        args.insert(0, String::from("version"));
+    }
+    if args.is_empty() {
+        Err("not enough arguments")?;
+    }
+
+    let mut action = Action::build(&args[0], &args[1..])?;
+    if options.dry_run && !action.supports_dry_run() {
+        Err(format!("`{}` doesn't support `--dry-run`", args[0].trim()))?;
+    }
+    // `init` creates the config file, so there's none to load yet, `version` doesn't need one.
+    let config = match action {
+        Action::Init | Action::Version { .. } => Config::default(),
+        _ => Config::build(&options)?,
+    };
+    action.normalize_tags(&config.tag_normalizer);
+    let out = Output {
+        quiet: options.quiet,
+        verbose: options.verbose,
+        yes: options.yes,
+        dry_run: options.dry_run,
+    };
+    out.detail(&format!("Loaded config: {}", config.source.display()));
+    Ok((action, config, out))
+}
+
+/// See [`Session::get_last`], the file and the time it took to find it are in verbose output.
+fn get_last(config: &Config, out: &Output) -> Result<Option<Session>, Box<dyn Error>> {
+    let session = out.time("Scanned sessions directory", || {
+        api::load_last_session(config)
+    })?;
+    if let Some(session) = &session {
+        out.detail(&format!("Read session file: {}", session.path.display()));
+    }
+    Ok(session)
+}
+
+/// Runs `edit` on `session`. An ended session is refused unless `last` is set, in which case its
+/// stop mark can be edited as well.
+fn edit_last<T>(
+    session: &mut Session,
+    last: bool,
+    edit: impl FnOnce(&mut Session) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    if session.is_active() {
+        return edit(session);
+    }
+    if !last {
+        Err(format!(
+            "last session ended at {}; start a new session or pass --last to edit it",
+            DateTime::new(&session.end()).to_formatted_pretty_short()
+        ))?;
+    }
+    session.overwrite_attribute(Attribute::None);
+    let result = edit(session);
+    session.overwrite_attribute(Attribute::Stop);
+    result
+}
+
+fn save(session: &Session, config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    if config.encrypt_contents && session.has_plain_contents() {
+        let mut session = session.clone();
+        session.encrypt_contents(&get_cipher(out)?)?;
+        return save(&session, config, out);
+    }
+    if config.verbose_changes || out.verbose {
+        show_changes(session, config, out);
+    }
+    if out.dry_run {
+        print!("{}", get_changes(&session.to_file()?)?);
+        return Ok(());
+    }
+    out.detail(&format!("Writing session file: {}", session.path.display()));
+    session.save(config)
+}
+
+/// Cipher of encrypted mark contents with the passphrase from [`ENV_PASSPHRASE`], or asked for
+/// when it isn't set.
+fn get_cipher(out: &Output) -> Result<Cipher, Box<dyn Error>> {
+    if !crypto::is_supported() {
+        Err("mark contents are encrypted: encryption support not compiled in")?;
+    }
+    let passphrase = match env::var(ENV_PASSPHRASE) {
+        Ok(val) if !val.is_empty() => val,
+        _ => ui::ask_secret("Passphrase:")
+            .map_err(|err| format!("couldn't get the passphrase: {err}, set {ENV_PASSPHRASE}"))?,
+    };
+    out.detail("Got the passphrase of encrypted contents");
+    Ok(Cipher::new(&passphrase)?)
+}
+
+/// See [`Hooks::run`](hook::Hooks::run).
+fn run_hook(config: &Config, out: &Output, event: Event, session: &Session) {
+    if config.hooks.get(event).is_some() {
+        out.detail(&format!("Running {} hook", event.name()));
+        config.hooks.run(event, session, &config.untracked_tags);
+    }
+}
+
+/// Records `action` on `session` in the event log, see [`Config::event_log`]. The action already
+/// succeeded, so failures are only reported as warnings.
+fn log_event(config: &Config, action: &str, session: &Session, payload: &[(&str, String)]) {
+    let entry = events::Entry {
+        action: action.to_owned(),
+        timestamp: DateTime::now(),
+        session: session
+            .path
+            .file_name()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        payload: payload
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_owned()))
+            .collect(),
+    };
+    if let Err(err) = events::append(config, &entry) {
+        eprintln!("Warning: couldn't write to the event log: {err}");
+    }
+}
+
+fn start(config: &Config, out: &Output, date: &DateTime) -> Result<(), Box<dyn Error>> {
+    if !fs::exists(&config.sessions_path)? && !out.dry_run {
+        let question = format!(
+            "Session directory `{}` doesn't exist, do you want to create it?",
+            Aggregator::get_absolute_dir(config).display()
+        );
+        if !config.create_missing_dirs && !out.confirm(&question, true)? {
+            out.success("Action cancelled");
+            return Ok(());
+        }
+        fs::create_dir_all(&config.sessions_path)
+            .map_err(|err| format!("couldn't create session directory: {err}"))?;
+        out.detail(&format!(
+            "Created session directory: {}",
+            config.sessions_path.display()
+        ));
+    }
+    if let Some(mut session) = get_last(config, out)? {
+        if !stop_stale(config, out, &mut session)? && session.is_active() {
+            Err("another session is already active")?;
+        }
+    }
+
+    let mut session = Session::new(config, date);
+    tag_branch(config, &mut session);
+    create(&session, config, out)?;
+    log_event(config, "start", &session, &[("date", date.to_formatted())]);
+    out.success(&format!(
+        "Started: {}",
+        &date.to_formatted_display_time(&config.display)
+    ));
+    run_hook(config, out, Event::Start, &session);
+    Ok(())
+}
+
+fn continue_(config: &Config, out: &Output, date: &DateTime) -> Result<(), Box<dyn Error>> {
+    let Some(mut previous) = get_last(config, out)? else {
+        return Err("no session to continue")?;
+    };
+    if !stop_stale(config, out, &mut previous)? && previous.is_active() {
+        Err("previous session is still active, use `time_tracker mark` instead")?;
+    }
+
+    let mut session = Session::derive_from(&previous, date);
+    tag_branch(config, &mut session);
+    create(&session, config, out)?;
+    log_event(
+        config,
+        "continue",
+        &session,
+        &[("date", date.to_formatted())],
+    );
+    out.success(&format!(
+        "Continued: {}",
+        &date.to_formatted_display_time(&config.display)
+    ));
+    if let Some(title) = session.get_title() {
+        out.success(&format!("Title: {title}"));
+    }
+    let tags: Vec<String> = session
+        .get_session_tags()
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    if !tags.is_empty() {
+        out.success(&format!("Tags: {}", tags.join(", ")));
+    }
+    run_hook(config, out, Event::Start, &session);
+    Ok(())
+}
+
+/// Stops `session` at its last mark when it's stale, see [`Config::auto_stop_after`]. Returns
+/// whether it was stopped.
+fn stop_stale(
+    config: &Config,
+    out: &Output,
+    session: &mut Session,
+) -> Result<bool, Box<dyn Error>> {
+    if !config.auto_stop_after.is_some_and(|v| session.is_stale(v)) {
+        return Ok(false);
+    }
+    session.set_attribute(Attribute::Stop)?;
+    save(session, config, out)?;
+    log_event(
+        config,
+        "stop",
+        session,
+        &[("reason", String::from("stale"))],
+    );
+    out.success(&format!(
+        "Stopped stale session at its last mark: {}",
+        DateTime::new(&session.end()).to_formatted_display_time(&config.display)
+    ));
+    run_hook(config, out, Event::Stop, session);
+    Ok(true)
 }
 
-fn start(config: &Config, date: &DateTime) -> Result<(), Box<dyn Error>> {
-    if let Some(session) = Session::get_last(&config)? {
-        if session.is_active() {
-            return Err("another session is already active")?;
+/// Lists what `session` changes compared to its file, see [`Session::diff`]. Only a summary, so
+/// a file that can't be read is skipped.
+fn show_changes(session: &Session, config: &Config, out: &Output) {
+    let Ok(before) = Session::from_path(&session.path) else {
+        return;
+    };
+    for change in session.diff(&before) {
+        if config.verbose_changes {
+            out.success(&change.to_string());
+        } else {
+            out.detail(&format!("Change: {change}"));
         }
     }
+}
+
+/// Diff of what writing `file` would change, shown instead of writing it in a dry run.
+fn get_changes(file: &SessionFile) -> Result<String, Box<dyn Error>> {
+    let path = file.path.display().to_string();
+    if !fs::exists(&file.path)? {
+        return Ok(diff::unified("/dev/null", &path, "", &file.contents));
+    }
+    let before = fs::read_to_string(&file.path)
+        .map_err(|err| format!("couldn't read session file: {err}"))?;
+    Ok(diff::unified(&path, &path, &before, &file.contents))
+}
 
-    let session = Session::new(&config, &date);
-    let SessionFile { path, contents } = session.to_file()?;
-    if fs::exists(&path)? {
-        return Err("this session file is already created")?;
+/// Writes the file of a new session, fails if it already exists.
+fn create(session: &Session, config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    let file = session.to_file()?;
+    out.detail(&format!("Creating session file: {}", file.path.display()));
+    if fs::exists(&file.path)? {
+        Err("this session file is already created")?;
     };
-    fs::write(&path, &contents).map_err(|_| "session directory doesn't exist")?;
-    println!("Started: {}", &date.to_formatted_time());
+    if out.dry_run {
+        print!("{}", get_changes(&file)?);
+        return Ok(());
+    }
+    file.write().map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => Aggregator::missing_dir_error(config),
+        _ => format!("couldn't save session: {err}"),
+    })?;
     Ok(())
 }
 
-fn mark(config: &Config, date: &DateTime) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn mark(
+    config: &Config,
+    out: &Output,
+    date: &DateTime,
+    future: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    session.mark(&date)?;
-    session.save()?;
-    println!("Marked: {}", &date.to_formatted_time());
+    session.mark(date, future)?;
+    tag_branch(config, &mut session);
+    save(&session, config, out)?;
+    log_event(config, "mark", &session, &[("date", date.to_formatted())]);
+    out.success(&format!(
+        "Marked: {}",
+        &date.to_formatted_display_time(&config.display)
+    ));
+    run_hook(config, out, Event::Mark, &session);
     Ok(())
 }
 
-fn remark(config: &Config, date: &DateTime) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn remark(
+    config: &Config,
+    out: &Output,
+    date: &DateTime,
+    future: bool,
+    last: bool,
+    first: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    session.remark(&date);
-    session.save()?;
-    println!("Remarked to: {}", &date.to_formatted_time());
+    if first {
+        // Looked up before the start moves, a session starting in between overlaps as well.
+        let preceding = session.get_preceding(config)?;
+        edit_last(&mut session, last, |session| {
+            Ok(session.remark_first(date, future)?)
+        })?;
+        if let Some(preceding) = preceding.filter(|v| v.end() > session.start()) {
+            Err(format!(
+                "session would overlap the previous session, which ended at {}",
+                DateTime::new(&preceding.end()).to_formatted_pretty()
+            ))?;
+        }
+    } else {
+        edit_last(&mut session, last, |session| {
+            Ok(session.remark(date, future)?)
+        })?;
+    }
+    // Only the first mark determines the file name, it's renamed before saving so that a
+    // collision leaves the original file untouched.
+    // The file keeps its name in a dry run, only the changed contents are shown.
+    let was_renamed = if out.dry_run {
+        false
+    } else {
+        session.rename_to_start()?
+    };
+    save(&session, config, out)?;
+    log_event(config, "remark", &session, &[("date", date.to_formatted())]);
+    if first {
+        out.success(&format!(
+            "Moved start to: {}",
+            &date.to_formatted_display_time(&config.display)
+        ));
+    } else {
+        out.success(&format!(
+            "Remarked to: {}",
+            &date.to_formatted_display_time(&config.display)
+        ));
+    }
+    if was_renamed {
+        out.success(&format!(
+            "Renamed session file to: {}",
+            session.path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn unmark(
+    config: &Config,
+    out: &Output,
+    selector: &MarkSelector,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    let mark = session.unmark(selector, force)?;
+    save(&session, config, out)?;
+    log_event(
+        config,
+        "unmark",
+        &session,
+        &[("date", mark.date().to_formatted())],
+    );
+    out.success(&format!("Removed mark: {}", mark.to_compact_line()));
+    Ok(())
+}
+
+fn pause(config: &Config, out: &Output, date: &DateTime) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    session.pause(date)?;
+    save(&session, config, out)?;
+    log_event(config, "pause", &session, &[("date", date.to_formatted())]);
+    out.success(&format!(
+        "Paused: {}",
+        &date.to_formatted_display_time(&config.display)
+    ));
+    Ok(())
+}
+
+fn unpause(config: &Config, out: &Output, date: &DateTime) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    let time = session.unpause(date)?;
+    save(&session, config, out)?;
+    log_event(
+        config,
+        "unpause",
+        &session,
+        &[("date", date.to_formatted())],
+    );
+    out.success(&format!(
+        "Unpaused: {}, break took {}",
+        &date.to_formatted_display_time(&config.display),
+        time.format(&config.duration_format)
+    ));
+    Ok(())
+}
+
+fn idle(
+    config: &Config,
+    out: &Output,
+    start: &DateTime,
+    end: &DateTime,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    session.insert_idle(start, end)?;
+    save(&session, config, out)?;
+    let payload = [("start", start.to_formatted()), ("end", end.to_formatted())];
+    log_event(config, "idle", &session, &payload);
+    out.success(&format!(
+        "Idle: {} - {}, {} not tracked",
+        &start.to_formatted_display_time(&config.display),
+        &end.to_formatted_display_time(&config.display),
+        Duration::between(&start.date, &end.date)?.format(&config.duration_format)
+    ));
+    Ok(())
+}
+
+fn path(config: &Config, out: &Output, target: &PathTarget) -> Result<(), Box<dyn Error>> {
+    let path = match target {
+        PathTarget::Session => {
+            let Some(session) = get_last(config, out)? else {
+                return Err(NotFound(String::from("no active session found")))?;
+            };
+            session.path
+        }
+        PathTarget::LastStopped => {
+            let session = out.time("Scanned sessions directory", || {
+                Session::get_last_stopped(config)
+            })?;
+            let Some(session) = session else {
+                return Err(NotFound(String::from("no stopped session found")))?;
+            };
+            session.path
+        }
+        PathTarget::Dir => config.sessions_path.clone(),
+        PathTarget::Config => config.source.clone(),
+    };
+    if !path.exists() {
+        Err(NotFound(format!("`{}` doesn't exist", path.display())))?;
+    }
+    print!("{}", path.to_str().ok_or("failed to convert path")?);
+    Ok(())
+}
+
+fn view(config: &Config, out: &Output, porcelain: bool) -> Result<(), Box<dyn Error>> {
+    let mut aggregator = load_shown_week(config, out)?;
+    if porcelain {
+        let status = if aggregator.is_empty() {
+            None
+        } else {
+            aggregator.status(config)?
+        };
+        let lines = status.map_or_else(Status::render_porcelain_empty, |v| v.render_porcelain());
+        println!("{lines}");
+        return Ok(());
+    }
+    if aggregator.is_empty() {
+        println!("{VIEW_EMPTY}");
+        return Ok(());
+    }
+    if aggregator.is_shown_encrypted(config) {
+        aggregator.decrypt_shown(config, &get_cipher(out)?)?;
+    }
+    println!("{}", aggregator.view(config)?);
+    Ok(())
+}
+
+/// Redraws `view` until interrupted, sessions are only read again when they change. Times are
+/// recomputed on every redraw.
+fn watch(config: &Config, out: &Output, interval: Duration) -> Result<(), Box<dyn Error>> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|v| v.modified()).ok();
+    let mut aggregator = load_shown_week(config, out)?;
+    let mut newest = Aggregator::read_sessions_dir(config)?.pop();
+    let mut shown_modified = aggregator.get_shown_path(config).and_then(modified);
+    loop {
+        let screen = if aggregator.is_empty() {
+            Ok(String::from(VIEW_EMPTY))
+        } else {
+            aggregator.view(config)
+        };
+        let screen = screen.unwrap_or_else(|err| format!("Error: {err}"));
+        println!("{ANSI_CLEAR_SCREEN}{screen}");
+        io::stdout().flush()?;
+        thread::sleep(time::Duration::from_millis(interval.as_millis()));
+
+        // A new or renamed file means another session is shown, everything is read again.
+        let dir_newest = Aggregator::read_sessions_dir(config)?.pop();
+        if dir_newest != newest {
+            aggregator = load_shown_week(config, out)?;
+            newest = dir_newest;
+            shown_modified = aggregator.get_shown_path(config).and_then(modified);
+            continue;
+        }
+        let Some(path) = aggregator.get_shown_path(config).map(Path::to_path_buf) else {
+            continue;
+        };
+        let path_modified = modified(&path);
+        if path_modified != shown_modified {
+            out.detail(&format!("Read session file: {}", path.display()));
+            if let Err(err) = aggregator.reload(config, &path) {
+                eprintln!("Warning: couldn't read the session again: {err}");
+            }
+            shown_modified = path_modified;
+        }
+    }
+}
+
+fn status(config: &Config, out: &Output, waybar: bool) -> Result<(), Box<dyn Error>> {
+    let aggregator = load_shown_week(config, out)?;
+    let status = if aggregator.is_empty() {
+        None
+    } else {
+        aggregator.status(config)?
+    };
+    let line = match (status, waybar) {
+        (Some(status), true) => status.render_waybar(&DateTime::now(), &config.display),
+        (Some(status), false) => status.render(&config.duration_format),
+        (None, true) => Status::render_waybar_empty(),
+        (None, false) => String::from(STATUS_EMPTY),
+    };
+    println!("{line}");
+    Ok(())
+}
+
+/// Sessions of the week shown by `view`, the current one or the week of the last session if
+/// there is no session this week.
+fn load_shown_week(config: &Config, out: &Output) -> Result<Aggregator, Box<dyn Error>> {
+    let cutoff = DateTime::now().start_of_week(config.day_starts_at);
+    // With split weeks, a session of the previous week can reach into the shown one.
+    let since = |week_start: &DateTime| {
+        if config.week_split {
+            week_start.plus_days(-7)
+        } else {
+            week_start.clone()
+        }
+    };
+    let mut aggregator = out.time("Read sessions directory", || {
+        Aggregator::build_since(config, &since(&cutoff))
+    })?;
+    if aggregator.is_empty() {
+        return Ok(aggregator);
+    }
+    // Without a session this week, the week of the last session is shown instead.
+    let week_start = aggregator.get_last_week_start(config.day_starts_at);
+    if week_start < cutoff {
+        aggregator = out.time("Read sessions directory", || {
+            Aggregator::build_since(config, &since(&week_start))
+        })?;
+    }
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    Ok(aggregator)
+}
+
+fn events(config: &Config, since: Option<chrono::NaiveDate>) -> Result<(), Box<dyn Error>> {
+    let range = DateRange::from_days(since, None, config.day_starts_at);
+    let (entries, failures) = events::read(config, &range)?;
+    for err in &failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    if entries.is_empty() && !config.event_log {
+        eprintln!("Warning: the event log is disabled, enable it with `event_log = true`");
+    }
+    for entry in &entries {
+        println!("{}", entry.to_line());
+    }
+    Ok(())
+}
+
+/// Nothing is written when any row is invalid, unless `skip_invalid` is set. Existing session files
+/// are never overwritten, a session that would replace one is an invalid row.
+fn import(
+    config: &Config,
+    out: &Output,
+    path: &Path,
+    one_session_per_row: bool,
+    skip_invalid: bool,
+) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("couldn't read `{}`: {err}", path.display()))?;
+    let (rows, mut invalid) = import::parse_csv(&contents)?;
+    let (sessions, invalid_sessions) = import::build_sessions(config, &rows, one_session_per_row);
+    invalid.extend(invalid_sessions);
+    let mut new = Vec::new();
+    for (line, session) in sessions {
+        if session.path.exists() {
+            invalid.push(import::InvalidRow {
+                line,
+                reason: format!("session file `{}` already exists", session.path.display()),
+            });
+        } else {
+            new.push(session);
+        }
+    }
+    invalid.sort_by_key(|v| v.line);
+    if !invalid.is_empty() && !skip_invalid {
+        for row in &invalid {
+            eprintln!("{row}");
+        }
+        Err(format!(
+            "{} invalid rows, nothing was imported, use `--skip-invalid` to import the rest",
+            invalid.len()
+        ))?;
+    }
+    for row in &invalid {
+        eprintln!("Warning: skipping {row}");
+    }
+    for session in &new {
+        save(session, config, out)?;
+        log_event(
+            config,
+            "import",
+            session,
+            &[("file", path.display().to_string())],
+        );
+    }
+    out.success(&format!("Imported {} sessions", new.len()));
+    Ok(())
+}
+
+fn retag(
+    config: &Config,
+    out: &Output,
+    from: &Tag,
+    to: &Tag,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut aggregator = out.time("Read sessions directory", || Aggregator::build(config))?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let changed = aggregator.retag(from, to);
+    let count: usize = changed.iter().map(|(_, count)| count).sum();
+    for (session, count) in &changed {
+        // Each file is replaced as a whole, a failure leaves it as it was.
+        if !dry_run {
+            save(session, config, out)?;
+            let payload = [
+                ("from", from.to_string()),
+                ("to", to.to_string()),
+                ("count", count.to_string()),
+            ];
+            log_event(config, "retag", session, &payload);
+        }
+        let line = format!("{}: {count} tags", session.path.display());
+        if dry_run {
+            println!("{line}");
+        } else {
+            out.success(&line);
+        }
+    }
+    let verb = if dry_run { "Would update" } else { "Updated" };
+    out.success(&format!("{verb} {count} tags in {} files", changed.len()));
+    Ok(())
+}
+
+fn merge(
+    config: &Config,
+    out: &Output,
+    paths: Option<(PathBuf, PathBuf)>,
+    force: bool,
+    skip_gap: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (a, b) = match paths {
+        // Names of files in the sessions directory work as well as paths.
+        Some(paths) => {
+            let resolve = |path: PathBuf| {
+                if path.exists() {
+                    path
+                } else {
+                    config.sessions_path.join(path)
+                }
+            };
+            (resolve(paths.0), resolve(paths.1))
+        }
+        None => {
+            let name = config.workspace.as_deref();
+            let mut dir = Aggregator::read_sessions_dir(config)?;
+            dir.retain(|v| Session::name_from_path(v) == name);
+            let [.., a, b] = &dir[..] else {
+                return Err("there are fewer than two sessions to merge")?;
+            };
+            (a.clone(), b.clone())
+        }
+    };
+    if a == b {
+        Err("can't merge a session with itself")?;
+    }
+    let mut earlier = Session::from_path(&a)?;
+    let mut later = Session::from_path(&b)?;
+    if later.start() < earlier.start() {
+        (earlier, later) = (later, earlier);
+    }
+    let gap = Duration::between(&earlier.end(), &later.start()).unwrap_or_default();
+    if !force && gap > config.merge_max_gap {
+        Err(format!(
+            "sessions are {} apart, more than {}, use --force to merge them anyway",
+            gap.format(&config.duration_format),
+            config.merge_max_gap.format(&config.duration_format)
+        ))?;
+    }
+    let attribute = if skip_gap {
+        Attribute::Skip
+    } else {
+        Attribute::None
+    };
+    earlier.merge(&later, attribute)?;
+    save(&earlier, config, out)?;
+    if out.dry_run {
+        println!("Would move to trash: {}", later.path.display());
+    } else {
+        let trashed = later
+            .trash()
+            .map_err(|err| format!("couldn't move {}: {err}", later.path.display()))?;
+        out.detail(&format!("Moved to: {}", trashed.display()));
+    }
+    let file_name = |session: &Session| {
+        session
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    log_event(config, "merge", &earlier, &[("merged", file_name(&later))]);
+    out.success(&format!(
+        "Merged {} into {}",
+        file_name(&later),
+        file_name(&earlier)
+    ));
+    Ok(())
+}
+
+fn split(config: &Config, out: &Output, date: &DateTime) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = out.time("Read sessions directory", || {
+        Session::get_containing(config, date)
+    })?
+    else {
+        return Err(format!(
+            "no session in progress at {}",
+            date.to_formatted_with(&config.timestamp_format, &config.display)
+        ))?;
+    };
+
+    let second = session.split(date)?;
+    // Created first so that nothing changes when the file name is taken.
+    create(&second, config, out)?;
+    save(&session, config, out)?;
+    log_event(config, "split", &session, &[("date", date.to_formatted())]);
+    for session in [&session, &second] {
+        let time = session.summarize(&config.untracked_tags)?.time;
+        out.success(&format!(
+            "{}: {}",
+            session.path.display(),
+            time.format(&config.duration_format)
+        ));
+    }
+    Ok(())
+}
+
+fn archive(config: &Config, out: &Output, before: chrono::NaiveDate) -> Result<(), Box<dyn Error>> {
+    let aggregator = out.time("Read sessions directory", || Aggregator::build(config))?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let sessions = aggregator.get_ended_before(before, config.day_starts_at);
+    // The index stays valid, its entries are by file name and moving keeps the modification time.
+    for session in &sessions {
+        let path = session
+            .archive()
+            .map_err(|err| format!("couldn't archive {}: {err}", session.path.display()))?;
+        out.detail(&format!("Moved to: {}", path.display()));
+        log_event(config, "archive", session, &[]);
+    }
+    out.success(&format!("Archived {} sessions", sessions.len()));
+    Ok(())
+}
+
+fn prune(config: &Config, out: &Output, dry_run: bool, yes: bool) -> Result<(), Box<dyn Error>> {
+    let aggregator = out.time("Read sessions directory", || Aggregator::build(config))?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let empty = aggregator.get_empty();
+    if empty.is_empty() {
+        out.success("No empty sessions found");
+        return Ok(());
+    }
+    for session in &empty {
+        println!("{}", session.path.display());
+    }
+    if dry_run {
+        out.success(&format!("Would move {} sessions to the trash", empty.len()));
+        return Ok(());
+    }
+    let question = format!("Move {} sessions to the trash?", empty.len());
+    if !yes && !out.confirm(&question, false)? {
+        out.success("Action cancelled");
+        return Ok(());
+    }
+    for session in &empty {
+        let path = session
+            .trash()
+            .map_err(|err| format!("couldn't move {}: {err}", session.path.display()))?;
+        out.detail(&format!("Moved to: {}", path.display()));
+        log_event(config, "prune", session, &[]);
+    }
+    out.success(&format!("Moved {} sessions to the trash", empty.len()));
+    Ok(())
+}
+
+fn graph(config: &Config, out: &Output, weeks: Option<usize>) -> Result<(), Box<dyn Error>> {
+    let history = out.time("Read sessions directory", || History::load(config))?;
+    for (_, err) in &history.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let today = DateTime::now().day(config.day_starts_at);
+    println!("{}", history.graph(config, today, weeks, get_width()));
+    Ok(())
+}
+
+/// Width of the terminal from `COLUMNS`, [`GRAPH_WIDTH_DEFAULT`] when it isn't known.
+fn get_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(GRAPH_WIDTH_DEFAULT)
+}
+
+fn calendar(
+    config: &Config,
+    out: &Output,
+    day: Option<chrono::NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let history = out.time("Read sessions directory", || History::load(config))?;
+    for (_, err) in &history.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let day = day.unwrap_or_else(|| DateTime::now().day(config.day_starts_at));
+    println!("{}", history.calendar(config, day));
+    Ok(())
+}
+
+fn stats(
+    config: &Config,
+    out: &Output,
+    first: Option<chrono::NaiveDate>,
+    last: Option<chrono::NaiveDate>,
+    include_archived: bool,
+    by_context: bool,
+) -> Result<(), Box<dyn Error>> {
+    let history = out.time("Read sessions directory", || {
+        if include_archived {
+            History::load_with_archive(config)
+        } else {
+            History::load(config)
+        }
+    })?;
+    for (_, err) in &history.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let range = DateRange::from_days(first, last, config.day_starts_at);
+    let today = DateTime::now().day(config.day_starts_at);
+    println!("{}", history.stats(config, &range, today));
+    if by_context {
+        // Contexts are only in the session files, the history index doesn't keep them.
+        let aggregator = out.time("Read session files", || match &range.start {
+            _ if include_archived => Aggregator::build_with_archive(config),
+            Some(since) => Aggregator::build_since(config, since),
+            None => Aggregator::build(config),
+        })?;
+        // Files that failed for the history as well were already reported.
+        for (path, err) in &aggregator.failures {
+            if !history.failures.iter().any(|(v, _)| v == path) {
+                eprintln!("Warning: skipping {err}");
+            }
+        }
+        let contexts = Contexts {
+            totals: aggregator.context_totals(&range, &config.untracked_tags)?,
+        };
+        println!("\n{}", contexts.render(config));
+    }
+    Ok(())
+}
+
+fn when(config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    let aggregator = load_shown_week(config, out)?;
+    let when = if aggregator.is_empty() {
+        None
+    } else {
+        aggregator.when(config)?
+    };
+    match when {
+        Some(when) => println!(
+            "{}",
+            when.render(&DateTime::now(), &config.timestamp_format, &config.display)
+        ),
+        None => println!("{VIEW_EMPTY}"),
+    }
+    Ok(())
+}
+
+fn pomodoro(config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    let Some(length) = config.pomodoro else {
+        return Err("`pomodoro` isn't set in the config")?;
+    };
+    let session = get_last(config, out)?;
+    // A stale session is treated as stopped at its last mark, like in `view`.
+    let pomodoro = match session {
+        Some(session) if !config.auto_stop_after.is_some_and(|v| session.is_stale(v)) => {
+            session.get_pomodoro(&DateTime::now(), length)
+        }
+        _ => Pomodoro::Inactive,
+    };
+    println!("{}", pomodoro.render());
     Ok(())
 }
 
-fn unmark(config: &Config) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn billable(config: &Config, out: &Output, last: bool) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    if let Some(mark) = session.unmark() {
-        session.save()?;
-        println!("Removed last mark:\n{}", mark.to_line());
+    let is_billable = edit_last(&mut session, last, |session| Ok(session.toggle_billable()?))?;
+    save(&session, config, out)?;
+    log_event(
+        config,
+        "billable",
+        &session,
+        &[("billable", is_billable.to_string())],
+    );
+    if is_billable {
+        out.success("Mark is billable");
     } else {
-        println!("Cannot remove the first mark");
+        out.success("Mark is no longer billable");
     }
     Ok(())
 }
 
-fn path(config: &Config) -> Result<(), Box<dyn Error>> {
-    let Some(session) = Session::get_last(&config)? else {
+fn at(
+    config: &Config,
+    out: &Output,
+    context: Option<&str>,
+    last: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
-    print!("{}", session.path.to_str().ok_or("failed to convert path")?);
+
+    edit_last(&mut session, last, |session| {
+        Ok(session.set_context(context)?)
+    })?;
+    save(&session, config, out)?;
+    log_event(
+        config,
+        "at",
+        &session,
+        &[("context", context.unwrap_or_default().to_owned())],
+    );
+    match context {
+        Some(context) => out.success(&format!("Context: {}", context.trim())),
+        None => out.success("Context cleared"),
+    }
+    Ok(())
+}
+
+fn billing(
+    config: &Config,
+    out: &Output,
+    month: Option<chrono::NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let month = month.unwrap_or_else(|| DateTime::now().day(config.day_starts_at));
+    let (first, last) = Period::Month.get_days(month);
+    let range = DateRange::from_days(Some(first), Some(last), config.day_starts_at);
+    let since = range.start.clone().expect("month has a start");
+    let aggregator = out.time("Read sessions directory", || {
+        Aggregator::build_since(config, &since)
+    })?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let (total, tags) = aggregator.billable_totals(&range, &config.untracked_tags)?;
+    let billing = Billing {
+        month: first,
+        total,
+        tags,
+    };
+    println!("{}", billing.render(config));
+    Ok(())
+}
+
+fn gaps(
+    config: &Config,
+    out: &Output,
+    day: Option<chrono::NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let day = day.unwrap_or_else(|| DateTime::now().day(config.day_starts_at));
+    let range = DateRange::from_days(Some(day), Some(day), config.day_starts_at);
+    let since = range.start.clone().expect("day has a start");
+    let aggregator = out.time("Read sessions directory", || {
+        Aggregator::build_since(config, &since)
+    })?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let gaps = Gaps {
+        day,
+        gaps: aggregator.gaps(&range),
+    };
+    println!("{}", gaps.render(config));
+    Ok(())
+}
+
+fn hours(
+    config: &Config,
+    out: &Output,
+    first: Option<chrono::NaiveDate>,
+    last: Option<chrono::NaiveDate>,
+) -> Result<(), Box<dyn Error>> {
+    let history = out.time("Read sessions directory", || History::load(config))?;
+    for (_, err) in &history.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let range = DateRange::from_days(first, last, config.day_starts_at);
+    println!("{}", history.hours(config, &range, get_width()));
+    Ok(())
+}
+
+fn weekdays(
+    config: &Config,
+    out: &Output,
+    weeks: Option<usize>,
+    nonzero: bool,
+) -> Result<(), Box<dyn Error>> {
+    let history = out.time("Read sessions directory", || History::load(config))?;
+    for (_, err) in &history.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    let today = DateTime::now().day(config.day_starts_at);
+    println!("{}", history.weekdays(config, today, weeks, nonzero));
+    Ok(())
+}
+
+fn goals(config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    let goals = Goals::read(config)?;
+    let now = DateTime::now();
+    // Sessions are read from the earliest period on, the week can start in the previous month.
+    let since = [Period::Week, Period::Month]
+        .iter()
+        .filter_map(|v| v.get_range(&now, config.day_starts_at).start)
+        .min()
+        .expect("periods always start");
+    let aggregator = out.time("Read sessions directory", || {
+        Aggregator::build_since(config, &since)
+    })?;
+    for (_, err) in &aggregator.failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    println!(
+        "{}",
+        goals.render(config, &now, |range| aggregator.tag_totals(range))?
+    );
+    Ok(())
+}
+
+fn goal(
+    config: &Config,
+    out: &Output,
+    tag: &Tag,
+    goal: Option<(report::Goal, Period)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut goals = Goals::read(config)?;
+    let message = match goal {
+        Some((goal, period)) => {
+            goals.set(&TagGoal {
+                tag: tag.clone(),
+                period,
+                goal,
+            })?;
+            format!(
+                "Set goal of `{tag}`: {} a {}",
+                goal.target.format(&config.duration_format),
+                period.to_text()
+            )
+        }
+        None => {
+            if !goals.clear(tag)? {
+                Err(format!("tag `{tag}` has no goal"))?;
+            }
+            format!("Cleared goals of `{tag}`")
+        }
+    };
+    out.detail(&format!("Writing goals file: {}", goals.path().display()));
+    goals
+        .write()
+        .map_err(|e| format!("couldn't save goals: {e}"))?;
+    out.success(&message);
     Ok(())
 }
 
-fn view(config: &Config) -> Result<(), Box<dyn Error>> {
-    let aggregator = Aggregator::build(&config)?;
-    println!("{}", aggregator.view());
+fn validate(config: &Config, out: &Output, fix: bool) -> Result<(), Box<dyn Error>> {
+    let mut findings = Aggregator::validate(config)?;
+    if fix {
+        let count = Aggregator::fix(config, &findings)?;
+        if count > 0 {
+            out.success(&format!("Fixed {count} problems"));
+            findings = Aggregator::validate(config)?;
+        }
+    }
+    if findings.is_empty() {
+        out.success("No problems found");
+        return Ok(());
+    }
+    for finding in &findings {
+        println!("{finding}");
+    }
+    Err(format!("found {} problems", findings.len()))?
+}
+
+fn fmt(config: &Config, out: &Output, current: bool, check: bool) -> Result<(), Box<dyn Error>> {
+    let mut paths = Aggregator::read_sessions_dir(config)?;
+    if current {
+        let name = config.workspace.as_deref();
+        paths.retain(|v| Session::name_from_path(v) == name);
+        let Some(path) = paths.pop() else {
+            return Err("no session to format")?;
+        };
+        paths = vec![path];
+    }
+    let (changed, failures) = Aggregator::format(config, &paths, check)?;
+    for path in &changed {
+        if check {
+            println!("Would format {}", path.display());
+        } else {
+            println!("Formatted {}", path.display());
+        }
+    }
+    for (_, err) in &failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    if check && !changed.is_empty() {
+        Err(format!("{} files would be formatted", changed.len()))?;
+    }
+    if !failures.is_empty() {
+        Err(format!("couldn't format {} files", failures.len()))?;
+    }
+    if changed.is_empty() {
+        out.success("All files are formatted");
+    }
+    Ok(())
+}
+
+fn migrate(config: &Config, out: &Output) -> Result<(), Box<dyn Error>> {
+    let paths = Aggregator::read_sessions_dir(config)?;
+    let (migrated, failures) = Aggregator::migrate(config, &paths)?;
+    for path in &migrated {
+        println!("Migrated {}", path.display());
+    }
+    for (_, err) in &failures {
+        eprintln!("Warning: skipping {err}");
+    }
+    if !failures.is_empty() {
+        Err(format!("couldn't migrate {} files", failures.len()))?;
+    }
+    if migrated.is_empty() {
+        out.success("All files are up to date");
+    }
+    Ok(())
+}
+
+fn edit(config: &Config, out: &Output, config_file: bool) -> Result<(), Box<dyn Error>> {
+    let path = if config_file {
+        config.source.clone()
+    } else {
+        let Some(session) = get_last(config, out)? else {
+            return Err(NotFound(String::from("no session found")))?;
+        };
+        session.path
+    };
+    let editor = config
+        .editor
+        .clone()
+        .or_else(|| env::var("VISUAL").ok())
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|v| !v.trim().is_empty())
+        .ok_or("no editor set, set `editor` in the config or the EDITOR environment variable")?;
+    out.detail(&format!("Running editor: {editor}"));
+    let status = exec::command(&editor)?
+        .arg(&path)
+        .status()
+        .map_err(|err| format!("couldn't run editor `{editor}`: {err}"))?;
+    if !status.success() {
+        Err(format!("editor `{editor}` failed, {status}"))?;
+    }
     Ok(())
 }
 
-fn attribute(config: &Config, attribute: Attribute) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn config_get(config: &Config, key: &str) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(&config.source)?;
+    let Some(value) = Config::get_value(&contents, key)? else {
+        return Err(NotFound(format!(
+            "`{key}` isn't set in {}",
+            config.source.display()
+        )))?;
+    };
+    // Strings are printed without quotes, so that scripts can use them as they are.
+    let value = ConfigFile::parse_string(&value).unwrap_or(value);
+    println!("{value}");
+    Ok(())
+}
+
+fn config_set(config: &Config, out: &Output, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(&config.source)?;
+    let contents = Config::set_value(&contents, key, value)?;
+    fs::write(&config.source, contents)
+        .map_err(|err| format!("couldn't write config file: {err}"))?;
+    out.success(&format!("Set {key} in {}", config.source.display()));
+    Ok(())
+}
+
+fn config_list(config: &Config) -> Result<(), Box<dyn Error>> {
+    let contents = fs::read_to_string(&config.source)?;
+    for (key, value) in Config::get_values(&contents)? {
+        println!("{key} = {value}");
+    }
+    Ok(())
+}
+
+fn init(out: &Output) -> Result<(), Box<dyn Error>> {
+    let config_path = Config::get_global_path()?;
+    let contents = if fs::exists(&config_path)? {
+        let contents = fs::read_to_string(&config_path)?;
+        println!("Config file {}:\n{contents}", config_path.display());
+        if !out.confirm("Do you want to change the sessions directory?", false)? {
+            return Ok(());
+        }
+        let sessions_path = ask_sessions_path(out)?;
+        Config::set_sessions_path(&contents, &sessions_path)?
+    } else {
+        let sessions_path = ask_sessions_path(out)?;
+        Config::get_template(&sessions_path)?
+    };
+    fs::write(&config_path, contents)
+        .map_err(|err| format!("couldn't write config file: {err}"))?;
+    out.success(&format!("Wrote config file: {}", config_path.display()));
+    println!(
+        "\nQuick start:\n  \
+        time_tracker start  starts a session\n  \
+        time_tracker mark   marks a break or its end\n  \
+        time_tracker view   shows the current session"
+    );
+    Ok(())
+}
+
+/// Asks for the sessions directory and creates it, returns the path as given so that `~` stays
+/// in the config file.
+fn ask_sessions_path(out: &Output) -> Result<String, Box<dyn Error>> {
+    let default = match env::var("XDG_DATA_HOME") {
+        Ok(data) if !data.is_empty() => format!("{data}/time_tracker/sessions"),
+        _ => String::from("~/notes/sessions"),
+    };
+    let sessions_path = out.ask("Where do you want to keep your sessions?", &default)?;
+    let dir = resolve_path(&sessions_path)?;
+    if dir.exists() && !dir.is_dir() {
+        Err(format!("`{}` is not a directory", dir.display()))?;
+    }
+    fs::create_dir_all(&dir).map_err(|err| format!("couldn't create session directory: {err}"))?;
+    out.detail(&format!("Created session directory: {}", dir.display()));
+    Ok(sessions_path)
+}
+
+fn attribute(
+    config: &Config,
+    out: &Output,
+    attribute: Attribute,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    session.set_attribute(attribute.to_owned());
-    session.save()?;
-    println!("Set attribute: {attribute:?}");
+    if session.set_attribute(attribute.to_owned()).is_err() {
+        let question = "Session has already ended, do you want to reopen it?";
+        if !force && !out.confirm(question, false)? {
+            out.success("Action cancelled");
+            return Ok(());
+        }
+        session.overwrite_attribute(attribute.to_owned());
+    }
+    save(&session, config, out)?;
+    match attribute {
+        Attribute::Stop => log_event(config, "stop", &session, &[]),
+        Attribute::Skip => log_event(config, "skip", &session, &[]),
+        Attribute::None => log_event(config, "attribute", &session, &[]),
+    }
+    if attribute == Attribute::None {
+        out.success("Cleared attribute");
+    } else {
+        out.success(&format!("Set attribute: {}", attribute.name()));
+    }
+    if attribute == Attribute::Stop {
+        run_hook(config, out, Event::Stop, &session);
+    }
     Ok(())
 }
 
-fn tag(config: &Config, tag: &Tag) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn tag(
+    config: &Config,
+    out: &Output,
+    tag: &Tag,
+    on_session: bool,
+    mark: &MarkSelector,
+    last: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         // TODO: message should be more like: "no session found", change all other occurrences
         return Err("no active session found")?;
     };
 
-    let was_added = session.tag(&tag);
-    session.save()?;
+    let was_added = edit_last(&mut session, last, |session| {
+        if on_session {
+            return Ok(session.tag_session(tag));
+        }
+        Ok(session.tag(tag, mark)?)
+    })?;
+    save(&session, config, out)?;
+    let payload = [
+        ("tag", tag.to_string()),
+        ("session", on_session.to_string()),
+    ];
+    log_event(config, "tag", &session, &payload);
     // TODO: Improve output.
     if was_added {
-        println!("Added tag: {tag:?}");
+        out.success(&format!("Added tag: {tag:?}"));
     } else {
-        println!("Tag `{tag:?}` already present");
+        out.success(&format!("Tag `{tag:?}` already present"));
     }
     Ok(())
 }
 
-fn untag(config: &Config, tag: &Tag) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn title(config: &Config, out: &Output, title: &str) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    let was_removed = session.untag(&tag);
-    session.save()?;
+    session.set_title(title)?;
+    save(&session, config, out)?;
+    log_event(config, "title", &session, &[("title", title.to_owned())]);
+    out.success(&format!("Title: {title}"));
+    Ok(())
+}
+
+fn restore(config: &Config, out: &Output, index: Option<usize>) -> Result<(), Box<dyn Error>> {
+    let Some(session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    let backups = Backup::list(&session.path)?;
+    if backups.is_empty() {
+        Err("no backups found for the current session")?;
+    }
+    let index = match index {
+        Some(val) => val,
+        None => {
+            for (i, backup) in backups.iter().enumerate() {
+                let date = backup
+                    .date
+                    .to_formatted_with(&config.timestamp_format, &config.display);
+                println!("{}: {date}", i + 1);
+            }
+            // Backups are listed newest first, the default restores the newest one.
+            let answer = out.ask("Which backup do you want to restore? (number)", "1")?;
+            answer
+                .parse()
+                .map_err(|_| format!("invalid backup number `{answer}`"))?
+        }
+    };
+    let Some(backup) = index.checked_sub(1).and_then(|i| backups.get(i)) else {
+        return Err(format!("backup {index} doesn't exist"))?;
+    };
+    session.restore(backup, config)?;
+    log_event(
+        config,
+        "restore",
+        &session,
+        &[("backup", backup.date.to_formatted())],
+    );
+    out.success(&format!(
+        "Restored backup from: {}",
+        backup
+            .date
+            .to_formatted_with(&config.timestamp_format, &config.display)
+    ));
+    Ok(())
+}
+
+fn untag(
+    config: &Config,
+    out: &Output,
+    tag: &Tag,
+    on_session: bool,
+    mark: &MarkSelector,
+    last: bool,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
+        return Err("no active session found")?;
+    };
+
+    let was_removed = edit_last(&mut session, last, |session| {
+        if on_session {
+            return Ok(session.untag_session(tag));
+        }
+        Ok(session.untag(tag, mark)?)
+    })?;
+    save(&session, config, out)?;
+    let payload = [
+        ("tag", tag.to_string()),
+        ("session", on_session.to_string()),
+    ];
+    log_event(config, "untag", &session, &payload);
     // TODO: Improve output.
     if was_removed {
-        println!("Removed tag: {tag:?}");
+        out.success(&format!("Removed tag: {tag:?}"));
     } else {
-        println!("Tag `{tag:?}` not present")
+        out.success(&format!("Tag `{tag:?}` not present"))
     }
     Ok(())
 }
 
-fn write(config: &Config, text: &str) -> Result<(), Box<dyn Error>> {
-    let Some(mut session) = Session::get_last(&config)? else {
+fn write(
+    config: &Config,
+    out: &Output,
+    text: &str,
+    target: &WriteTarget,
+    mode: Option<WriteMode>,
+    last: bool,
+) -> Result<(), Box<dyn Error>> {
+    write_with(config, out, text, target, mode, last, None)
+}
+
+/// Like [`write`], with `cipher` used for encrypted contents instead of asking for the passphrase.
+fn write_with(
+    config: &Config,
+    out: &Output,
+    text: &str,
+    target: &WriteTarget,
+    mode: Option<WriteMode>,
+    last: bool,
+    cipher: Option<&Cipher>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(mut session) = get_last(config, out)? else {
         return Err("no active session found")?;
     };
 
-    let has_failed = session.write(&text).is_err();
-    if has_failed {
-        println!("Current mark already contains some text, do you want to overwrite it? (y/n)");
-        let mut buf = String::new();
-        io::stdin().read_line(&mut buf)?;
-        if buf == "\n" || buf == "y\n" {
-            session
-                .marks
-                .last_mut()
-                .expect("session must always have at least one mark")
-                .erase();
-            session.write(&text).expect("content is erased");
+    // The cipher that opens the mark seals it again, so that the passphrase is asked once.
+    let was_encrypted = session.is_encrypted_at(target);
+    let asked;
+    let cipher = match cipher {
+        _ if !was_encrypted && !config.encrypt_contents => None,
+        Some(cipher) => Some(cipher),
+        None => {
+            asked = get_cipher(out)?;
+            Some(&asked)
+        }
+    };
+    let was_written = edit_last(&mut session, last, |session| {
+        if let Some(cipher) = cipher.as_ref().filter(|_| was_encrypted) {
+            session.decrypt_at(target, cipher)?;
+        }
+        let mode = if let Some(mode) = mode {
+            mode
+        } else if session.text_at(target)?.is_empty() {
+            WriteMode::New
         } else {
-            println!("Action cancelled");
-            return Ok(());
+            let question = match target {
+                WriteTarget::Session => "Session notes already contain some text",
+                WriteTarget::Mark(_) => "Mark already contains some text",
+            };
+            if !out.confirm(&format!("{question}, do you want to overwrite it?"), true)? {
+                return Ok(false);
+            }
+            WriteMode::Overwrite
+        };
+        session.write_at(target, text, mode)?;
+        match cipher {
+            Some(cipher) if config.encrypt_contents => session.encrypt_contents(cipher)?,
+            Some(cipher) => session.encrypt_at(target, cipher)?,
+            None => (),
         }
+        Ok(true)
+    })?;
+    if !was_written {
+        out.success("Action cancelled");
+        return Ok(());
     }
-    session.save()?;
-    println!("Wrote:\n{text}");
+    save(&session, config, out)?;
+    // Encrypted contents don't leak into the event log, only their length does.
+    let payload = if cipher.is_some() {
+        ("length", text.chars().count().to_string())
+    } else {
+        ("text", text.to_owned())
+    };
+    log_event(config, "write", &session, &[payload]);
+    out.success(&format!("Wrote:\n{text}"));
     Ok(())
 }
 
-fn version() {
+/// The version followed by the commit and target captured by the build script, as a JSON object
+/// with `json`. The commit is missing when the crate wasn't built from a git checkout.
+fn version(json: bool) -> String {
     let version = env!("CARGO_PKG_VERSION");
-    println!("v{version}");
+    let commit = Some(env!("TT_GIT_COMMIT")).filter(|v| !v.is_empty());
+    let dirty = env!("TT_GIT_DIRTY") == "true";
+    let target = env!("TT_TARGET");
+    if json {
+        let (commit, dirty) = match commit {
+            Some(commit) => (json::to_string(commit), dirty.to_string()),
+            None => (String::from("null"), String::from("null")),
+        };
+        return format!(
+            "{{\"version\":{},\"commit\":{commit},\"dirty\":{dirty},\"target\":{}}}",
+            json::to_string(version),
+            json::to_string(target)
+        );
+    }
+    match commit {
+        Some(commit) if dirty => format!("v{version} ({commit}, dirty, {target})"),
+        Some(commit) => format!("v{version} ({commit}, {target})"),
+        None => format!("v{version} ({target})"),
+    }
 }
 
 fn resolve_path(path: &str) -> Result<PathBuf, &'static str> {
@@ -190,8 +1726,9 @@ fn resolve_path(path: &str) -> Result<PathBuf, &'static str> {
         if !path.starts_with("~/") {
             return Err("invalid path, `~` is not followed by `/`");
         }
-        let Ok(home) = env::var("HOME") else {
-            return Err("failed to interpret environment variable HOME");
+        // Windows doesn't set HOME by default.
+        let Ok(home) = env::var("HOME").or_else(|_| env::var("USERPROFILE")) else {
+            return Err("failed to interpret environment variables HOME and USERPROFILE");
         };
         let path = PathBuf::from(home).join(&path[2..]);
         return Ok(path);
@@ -199,28 +1736,253 @@ fn resolve_path(path: &str) -> Result<PathBuf, &'static str> {
     Ok(PathBuf::from(path))
 }
 
-// TODO: move to Aggregator
-fn read_sessions_dir(config: &Config) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    let mut dir = fs::read_dir(&config.sessions_path)
-        .map_err(|_err| "session directory doesn't exist")?
-        .map(|res| res.map(|v| v.path()))
-        .collect::<Result<Vec<_>, io::Error>>()?;
-    dir.sort();
-    Ok(dir)
+/// Tags the last mark of `session` with the current branch, see [`Config::auto_branch_tag`]. Left
+/// untagged outside of a repository.
+#[cfg(feature = "git")]
+fn tag_branch(config: &Config, session: &mut Session) {
+    if !config.auto_branch_tag {
+        return;
+    }
+    let Ok(dir) = env::current_dir() else {
+        return;
+    };
+    let Ok(name) = git::get_branch_name(&dir) else {
+        return;
+    };
+    let Ok(tag) = Tag::from_text(&format!("{BRANCH_TAG_PREFIX}{name}")) else {
+        return;
+    };
+    let tag = config.tag_normalizer.apply(&tag);
+    // The last mark always exists.
+    let _ = session.tag(&tag, &MarkSelector::Last);
 }
 
-fn get_git_branch_name() -> Result<String, Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["branch", "--show-current"])
-        .output()?;
-    if output.status.success() {
-        let name = String::from_utf8(output.stdout)?.trim().to_owned();
-        if name.is_empty() {
-            return Err("branch name is empty")?;
+/// [`Config::auto_branch_tag`] can't be set without git support.
+#[cfg(not(feature = "git"))]
+fn tag_branch(_config: &Config, _session: &mut Session) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editing_ended_session_requires_last() {
+        let dir = testing::temp_dir("lib_editing_ended_session_requires_last");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let out = Output {
+            quiet: true,
+            ..Default::default()
+        };
+        let start = DateTime::now().plus_hours(-2);
+        let mut session = Session::new(&config, &start);
+        session.mark(&start.plus_hours(1), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.save(&config).unwrap();
+        let rust = Tag::from_text("rust").unwrap();
+
+        let err = tag(&config, &out, &rust, false, &MarkSelector::Last, false).unwrap_err();
+        assert!(err.to_string().starts_with("last session ended at"));
+        assert!(err.to_string().ends_with("pass --last to edit it"));
+        let mark = WriteTarget::Mark(MarkSelector::Last);
+        assert!(write(&config, &out, "Late note.", &mark, None, false).is_err());
+        assert!(remark(&config, &out, &start.plus_minutes(90), false, false, false).is_err());
+        assert_eq!(Session::from_path(&session.path).unwrap(), session);
+
+        tag(&config, &out, &rust, false, &MarkSelector::Last, true).unwrap();
+        write(&config, &out, "Late note.", &mark, None, true).unwrap();
+        remark(&config, &out, &start.plus_minutes(90), false, true, false).unwrap();
+        let edited = Session::from_path(&session.path).unwrap();
+        assert!(!edited.is_active());
+        let last = edited.marks.last().unwrap();
+        assert_eq!(last.contents(), "Late note.");
+        assert_eq!(last.tags(), vec![&rust]);
+        assert_eq!(last.date(), start.plus_minutes(90));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn write_keeps_encrypted_contents_out_of_event_log() {
+        let dir = testing::temp_dir("lib_write_keeps_encrypted_contents_out_of_event_log");
+        let mut config = Config {
+            sessions_path: dir.clone(),
+            encrypt_contents: true,
+            event_log: true,
+            ..Default::default()
+        };
+        let out = Output {
+            quiet: true,
+            ..Default::default()
+        };
+        let cipher = Cipher::new("correct horse").unwrap();
+        let session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        let mark = WriteTarget::Mark(MarkSelector::Last);
+        let text = "Call with Client A.";
+        write_with(&config, &out, text, &mark, None, false, Some(&cipher)).unwrap();
+        // Marks that are already encrypted stay so without `encrypt_contents`.
+        config.encrypt_contents = false;
+        let mode = Some(WriteMode::Overwrite);
+        write_with(&config, &out, text, &mark, mode, false, Some(&cipher)).unwrap();
+
+        let (entries, failures) = events::read(&config, &DateRange::default()).unwrap();
+        assert!(failures.is_empty());
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            assert_eq!(
+                entry.payload,
+                vec![(String::from("length"), String::from("19"))]
+            );
         }
-        Ok(name)
-    } else {
-        let error_message = String::from_utf8(output.stderr)?;
-        Err(format!("Failed to get git branch name: {error_message}"))?
+        for path in [dir.join("events.jsonl"), session.path.clone()] {
+            assert!(!fs::read_to_string(path).unwrap().contains("Client A"));
+        }
+        assert!(Session::from_path(&session.path)
+            .unwrap()
+            .is_encrypted_at(&mark));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn start_creates_missing_sessions_dir() {
+        let dir = testing::temp_dir("lib_start_creates_missing_sessions_dir");
+        let mut config = Config {
+            sessions_path: dir.join("notes").join("sessions"),
+            ..Default::default()
+        };
+        let out = Output {
+            quiet: true,
+            ..Default::default()
+        };
+        let err = Aggregator::build(&config).err().unwrap().to_string();
+        assert_eq!(
+            err,
+            format!(
+                "session directory `{}` doesn't exist",
+                config.sessions_path.display()
+            )
+        );
+
+        config.create_missing_dirs = true;
+        let date = DateTime::now().plus_hours(-1);
+        start(&config, &out, &date).unwrap();
+        assert_eq!(Aggregator::read_sessions_dir(&config).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn remark_first_renames_and_checks_overlap() {
+        let dir = testing::temp_dir("lib_remark_first_renames_and_checks_overlap");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let out = Output {
+            quiet: true,
+            ..Default::default()
+        };
+        let now = DateTime::now();
+        start(&config, &out, &now.plus_hours(-5)).unwrap();
+        mark(&config, &out, &now.plus_hours(-4), false).unwrap();
+        attribute(&config, &out, Attribute::Stop, false).unwrap();
+        start(&config, &out, &now.plus_hours(-2)).unwrap();
+        mark(&config, &out, &now.plus_hours(-1), false).unwrap();
+
+        let date = now.plus_hours(-3);
+        remark(&config, &out, &date, false, false, true).unwrap();
+        let session = get_last(&config, &out).unwrap().unwrap();
+        assert_eq!(session.start(), date.date);
+        assert_eq!(session.path, Session::new(&config, &date).path);
+        assert_eq!(Aggregator::read_sessions_dir(&config).unwrap().len(), 2);
+
+        let err = remark(&config, &out, &now.plus_minutes(-270), false, false, true)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("session would overlap the previous session"));
+        assert_eq!(get_last(&config, &out).unwrap().unwrap(), session);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn version_includes_build_metadata() {
+        let target = env!("TT_TARGET");
+        assert!(!target.is_empty());
+        let text = version(false);
+        assert!(text.starts_with(&format!("v{} (", env!("CARGO_PKG_VERSION"))));
+        assert!(text.ends_with(&format!("{target})")));
+
+        let json = version(true);
+        assert!(json.starts_with(&format!("{{\"version\":\"{}\",", env!("CARGO_PKG_VERSION"))));
+        assert!(json.ends_with(&format!(",\"target\":\"{target}\"}}")));
+        if env!("TT_GIT_COMMIT").is_empty() {
+            assert!(json.contains("\"commit\":null,\"dirty\":null"));
+        } else {
+            assert!(json.contains(&format!("\"commit\":\"{}\"", env!("TT_GIT_COMMIT"))));
+        }
+    }
+
+    #[test]
+    fn setup_accepts_version_option_without_config() {
+        let args = |v: &[&str]| v.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let (action, config, _) = setup(&args(&["tt", "--version"])).unwrap();
+        assert_eq!(action, Action::Version { json: false });
+        assert_eq!(config, Config::default());
+        let (action, ..) = setup(&args(&["tt", "-V", "--json"])).unwrap();
+        assert_eq!(action, Action::Version { json: true });
+        let (action, ..) = setup(&args(&["tt", "version", "--json"])).unwrap();
+        assert_eq!(action, Action::Version { json: true });
+        assert!(setup(&args(&["tt", "--version", "view"])).is_err());
+    }
+
+    #[test]
+    fn dry_run_leaves_files_untouched() {
+        let dir = testing::temp_dir("lib_dry_run_leaves_files_untouched");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let out = Output {
+            quiet: true,
+            dry_run: true,
+            ..Default::default()
+        };
+        let date = DateTime::now().plus_hours(-2);
+        start(&config, &out, &date).unwrap();
+        assert!(Aggregator::read_sessions_dir(&config).unwrap().is_empty());
+
+        let out = Output {
+            dry_run: false,
+            ..out
+        };
+        start(&config, &out, &date).unwrap();
+        let session = get_last(&config, &out).unwrap().unwrap();
+        let contents = fs::read_to_string(&session.path).unwrap();
+        let out = Output {
+            dry_run: true,
+            ..out
+        };
+        mark(&config, &out, &date.plus_hours(1), false).unwrap();
+        assert_eq!(fs::read_to_string(&session.path).unwrap(), contents);
+        assert_eq!(Aggregator::read_sessions_dir(&config).unwrap().len(), 1);
+        assert!(Backup::list(&session.path).unwrap().is_empty());
+
+        let mut marked = session.clone();
+        marked.mark(&date.plus_hours(1), false).unwrap();
+        let file = marked.to_file().unwrap();
+        let changes = get_changes(&file).unwrap();
+        let heading = file
+            .contents
+            .lines()
+            .rfind(|v| v.starts_with("### "))
+            .unwrap();
+        assert!(changes.starts_with(&format!("--- {}\n", session.path.display())));
+        assert!(changes.lines().any(|v| v == format!("+{heading}")));
+        assert!(!changes
+            .lines()
+            .any(|v| v.starts_with('-') && !v.starts_with("---")));
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,7 +1,17 @@
-use std::{env, error::Error};
+use std::{env, process::ExitCode};
+use time_tracker::{NotFound, EXIT_NOT_FOUND};
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
-    time_tracker::run(&args)?;
-    Ok(())
+    match time_tracker::run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            if err.is::<NotFound>() {
+                ExitCode::from(EXIT_NOT_FOUND)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
 }
@@ -0,0 +1,56 @@
+use crate::ui;
+use std::{error::Error, time::Instant};
+
+/// Messages of the commands beyond their results and questions they ask, set with the `--quiet`,
+/// `--verbose`, `--yes` and `--dry-run` options.
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct Output {
+    /// Leaves out messages confirming that a command succeeded, errors are still shown.
+    pub quiet: bool,
+    /// Shows details of what the command does on stderr, so that stdout stays the same.
+    pub verbose: bool,
+    /// Answers yes to every question without asking.
+    pub yes: bool,
+    /// Shows the changes to session files as a diff instead of writing them.
+    pub dry_run: bool,
+}
+
+impl Output {
+    /// Confirms that a command succeeded, see [`Output::quiet`].
+    pub fn success(&self, text: &str) {
+        if !self.quiet {
+            println!("{text}");
+        }
+    }
+
+    /// See [`Output::verbose`].
+    pub fn detail(&self, text: &str) {
+        if self.verbose {
+            eprintln!("{text}");
+        }
+    }
+
+    /// Asks `question`, see [`ui::confirm`]. Always yes with [`Output::yes`].
+    pub fn confirm(&self, question: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+        if self.yes {
+            return Ok(true);
+        }
+        ui::confirm(question, default)
+    }
+
+    /// Asks `question`, see [`ui::ask`]. Always `default` with [`Output::yes`].
+    pub fn ask(&self, question: &str, default: &str) -> Result<String, Box<dyn Error>> {
+        if self.yes {
+            return Ok(default.to_owned());
+        }
+        ui::ask(question, default)
+    }
+
+    /// Runs `f`, reports how long it took in verbose output.
+    pub fn time<T>(&self, label: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let value = f();
+        self.detail(&format!("{label} in {} ms", start.elapsed().as_millis()));
+        value
+    }
+}
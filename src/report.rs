@@ -0,0 +1,806 @@
+use crate::{
+    config::ConfigFile,
+    date_time::{DateRange, DateTime, Duration, DurationFormat},
+    session::Tag,
+    Config,
+};
+use chrono::Datelike;
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const DAYS_IN_WEEK: u64 = 7;
+/// Kept in the sessions directory, sections are named after [`Period`]s and hold `'tag' = 'goal'`
+/// entries.
+const GOALS_FILE_NAME: &str = "goals.toml";
+const GOALS_BAR_WIDTH: usize = 20;
+const GOALS_BAR_FILLED: &str = "█";
+const GOALS_BAR_EMPTY: &str = "░";
+
+/// Time to track in a week, see [`Config::weekly_goal`](crate::Config::weekly_goal).
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Goal {
+    pub target: Duration,
+}
+
+impl Goal {
+    /// Accepts the same durations as [`Duration::from_text`], the goal can't be zero.
+    pub fn from_text(text: &str) -> Result<Goal, String> {
+        let target = Duration::from_text(text)?;
+        if target == Duration::ZERO {
+            return Err(format!("goal `{}` must be longer than zero", text.trim()));
+        }
+        Ok(Goal { target })
+    }
+
+    /// Whole percents of the target that `time` covers, exceeds 100 once the goal is reached.
+    pub fn get_percentage(&self, time: Duration) -> u64 {
+        time.as_millis() * 100 / self.target.as_millis()
+    }
+
+    /// Average time needed on each day left in the week, today included, to reach the target.
+    /// `day` is 1 on the first day of the week. `None` when `time` keeps pace with an even spread
+    /// of the target over the days before today.
+    pub fn get_catch_up(&self, time: Duration, day: u64) -> Option<Duration> {
+        if !(1..=DAYS_IN_WEEK).contains(&day) {
+            return None;
+        }
+        let expected = self.target.as_millis() * (day - 1) / DAYS_IN_WEEK;
+        if time.as_millis() >= expected {
+            return None;
+        }
+        let left = self.target.as_millis() - time.as_millis();
+        Some(Duration::from_millis(left / (DAYS_IN_WEEK - day + 1)))
+    }
+
+    /// Time left to track today to keep an even spread of what's left of the target over the rest
+    /// of the week. `week` includes `today`, `day` is 1 on the first day of the week. `None`
+    /// outside of the week the goal is for.
+    pub fn get_left_today(&self, week: Duration, today: Duration, day: u64) -> Option<Duration> {
+        if !(1..=DAYS_IN_WEEK).contains(&day) {
+            return None;
+        }
+        let before = week.as_millis().saturating_sub(today.as_millis());
+        let share = self.target.as_millis().saturating_sub(before) / (DAYS_IN_WEEK - day + 1);
+        Some(Duration::from_millis(
+            share.saturating_sub(today.as_millis()),
+        ))
+    }
+
+    /// `Goal: 21h 10m 0s / 30h 0m 0s (70%)`, followed by the time needed a day when behind pace.
+    /// `day` is `None` outside of the week the goal is for, see [`Goal::get_catch_up`].
+    pub fn format_progress(
+        self,
+        time: Duration,
+        day: Option<u64>,
+        format: &DurationFormat,
+    ) -> String {
+        let mut line = format!(
+            "Goal: {} / {} ({}%)",
+            time.format(format),
+            self.target.format(format),
+            self.get_percentage(time)
+        );
+        if let Some(catch_up) = day.and_then(|v| self.get_catch_up(time, v)) {
+            line += &format!(", {} a day to catch up", catch_up.format(format));
+        }
+        line
+    }
+}
+
+/// Period a tag goal applies to, always the current one.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    fn from_text(text: &str) -> Option<Period> {
+        match text {
+            "week" => Some(Period::Week),
+            "month" => Some(Period::Month),
+            _ => None,
+        }
+    }
+
+    pub fn to_text(self) -> &'static str {
+        match self {
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+
+    /// The week or month `now` is in.
+    pub fn get_range(self, now: &DateTime, boundary: chrono::NaiveTime) -> DateRange {
+        let (first, last) = self.get_days(now.day(boundary));
+        DateRange::from_days(Some(first), Some(last), boundary)
+    }
+
+    /// First and last day of the week or month `day` is in.
+    pub fn get_days(self, day: chrono::NaiveDate) -> (chrono::NaiveDate, chrono::NaiveDate) {
+        match self {
+            Period::Week => {
+                let week = day.week(chrono::Weekday::Mon);
+                (week.first_day(), week.last_day())
+            }
+            Period::Month => {
+                let first = day.with_day(1).expect("first day of month must exist");
+                let last = first + chrono::Months::new(1) - chrono::Days::new(1);
+                (first, last)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TagGoal {
+    pub tag: Tag,
+    pub period: Period,
+    pub goal: Goal,
+}
+
+/// Goals of tags, edited in place so that comments and unrelated lines of the file are kept.
+#[derive(PartialEq, Debug)]
+pub struct Goals {
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+impl Goals {
+    /// Missing file has no goals.
+    pub fn read(config: &Config) -> Result<Goals, Box<dyn Error>> {
+        let path = config.sessions_path.join(GOALS_FILE_NAME);
+        let contents = match fs::read_to_string(&path) {
+            Ok(val) => val,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(format!("couldn't read goals: {err}"))?,
+        };
+        let goals = Goals {
+            lines: contents.lines().map(|v| v.to_owned()).collect(),
+            path,
+        };
+        goals
+            .get_entries()
+            .map_err(|err| format!("{}: {err}", goals.path.display()))?;
+        Ok(goals)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        let mut contents = self.lines.join("\n");
+        contents += "\n";
+        fs::write(&self.path, contents)
+    }
+
+    pub fn get(&self) -> Vec<TagGoal> {
+        self.get_entries()
+            .expect("goals are checked when read")
+            .into_iter()
+            .map(|(_, goal)| goal)
+            .collect()
+    }
+
+    /// Goals with the index of the line they are on.
+    fn get_entries(&self) -> Result<Vec<(usize, TagGoal)>, String> {
+        let mut entries = Vec::new();
+        let mut period = None;
+        for (i, line) in self.lines.iter().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("[").and_then(|v| v.strip_suffix("]")) {
+                period = Some(Period::from_text(name.trim()).ok_or_else(|| {
+                    format!("unknown section `{}` on line {}", name.trim(), i + 1)
+                })?);
+                continue;
+            }
+            let Some(period) = period else {
+                return Err(format!("goal outside of a section on line {}", i + 1));
+            };
+            let (tag, goal) = Goals::parse_entry(line)
+                .map_err(|err| format!("invalid goal on line {}: {err}", i + 1))?;
+            entries.push((i, TagGoal { tag, period, goal }));
+        }
+        Ok(entries)
+    }
+
+    /// `'tag' = 'goal'`, the tag can also be bare when it contains no `=`.
+    fn parse_entry(line: &str) -> Result<(Tag, Goal), String> {
+        let (key, value) = match line.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let end = line[1..]
+                    .find(quote)
+                    .ok_or("tag is missing a closing quote")?
+                    + 1;
+                let value = line[end + 1..]
+                    .trim_start()
+                    .strip_prefix("=")
+                    .ok_or("expected `=` after the tag")?;
+                (&line[1..end], value)
+            }
+            _ => line.split_once("=").ok_or("expected `'tag' = 'goal'`")?,
+        };
+        let tag = Tag::from_text(key)?;
+        let goal = Goal::from_text(&ConfigFile::parse_string(value)?)?;
+        Ok((tag, goal))
+    }
+
+    fn to_entry(tag: &Tag, goal: &Goal) -> Result<String, String> {
+        let tag = tag.to_string();
+        let quote = ['\'', '"']
+            .into_iter()
+            .find(|v| !tag.contains(*v))
+            .ok_or_else(|| format!("tag `{tag}` can't have a goal, it contains both quotes"))?;
+        Ok(format!("{quote}{tag}{quote} = '{}'", goal.target.to_text()))
+    }
+
+    /// Replaces the goal `tag` has for the same period, if any.
+    pub fn set(&mut self, goal: &TagGoal) -> Result<(), String> {
+        let line = Goals::to_entry(&goal.tag, &goal.goal)?;
+        let entries = self.get_entries()?;
+        if let Some((i, _)) = entries
+            .iter()
+            .find(|(_, v)| v.tag == goal.tag && v.period == goal.period)
+        {
+            self.lines[*i] = line;
+            return Ok(());
+        }
+        // After the last goal of the section, or right after its heading.
+        let heading = format!("[{}]", goal.period.to_text());
+        let i = entries
+            .iter()
+            .filter(|(_, v)| v.period == goal.period)
+            .map(|(i, _)| *i)
+            .next_back()
+            .or_else(|| self.lines.iter().position(|v| v.trim() == heading));
+        match i {
+            Some(i) => self.lines.insert(i + 1, line),
+            None => {
+                if self.lines.last().is_some_and(|v| !v.trim().is_empty()) {
+                    self.lines.push(String::new());
+                }
+                self.lines.push(heading);
+                self.lines.push(line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the goals of `tag` for all periods, returns whether it had any.
+    pub fn clear(&mut self, tag: &Tag) -> Result<bool, String> {
+        let lines: Vec<usize> = self
+            .get_entries()?
+            .into_iter()
+            .filter(|(_, v)| &v.tag == tag)
+            .map(|(i, _)| i)
+            .collect();
+        for i in lines.iter().rev() {
+            self.lines.remove(*i);
+        }
+        Ok(!lines.is_empty())
+    }
+
+    /// Each goal with the time of its tag in the current period and a progress bar. `totals` gives
+    /// the time of each tag within a range.
+    pub fn render(
+        &self,
+        config: &Config,
+        now: &DateTime,
+        totals: impl Fn(&DateRange) -> Result<Vec<(Tag, Duration)>, String>,
+    ) -> Result<String, String> {
+        let goals = self.get();
+        if goals.is_empty() {
+            return Ok(String::from("No goals set"));
+        }
+        let format = &config.duration_format;
+        let tag_width = goals
+            .iter()
+            .map(|v| v.tag.to_string().chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for goal in goals {
+            let range = goal.period.get_range(now, config.day_starts_at);
+            let tag = config.tag_normalizer.apply(&goal.tag);
+            let time = totals(&range)?
+                .into_iter()
+                .find(|(v, _)| v == &tag)
+                .map_or(Duration::ZERO, |(_, time)| time);
+            let percentage = goal.goal.get_percentage(time);
+            let filled = (percentage as usize * GOALS_BAR_WIDTH / 100).min(GOALS_BAR_WIDTH);
+            lines.push(format!(
+                "{:<tag_width$} {:<5} {}{} {percentage:>3}% {} / {}",
+                goal.tag.to_string(),
+                goal.period.to_text(),
+                GOALS_BAR_FILLED.repeat(filled),
+                GOALS_BAR_EMPTY.repeat(GOALS_BAR_WIDTH - filled),
+                time.format(format),
+                goal.goal.target.format(format),
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Billable time of a month, see [`Aggregator::billable_totals`](crate::session::Aggregator).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Billing {
+    /// First day of the month.
+    pub month: chrono::NaiveDate,
+    pub total: Duration,
+    pub tags: Vec<(Tag, Duration)>,
+}
+
+impl Billing {
+    /// Hours for each tag and in total, followed by the amount when
+    /// [`Config::hourly_rate`](crate::Config::hourly_rate) is set.
+    pub fn render(&self, config: &Config) -> String {
+        let month = self.month.format("%Y-%m");
+        if self.total == Duration::ZERO {
+            return format!("No billable time in {month}");
+        }
+        let rows: Vec<(String, String, String)> = self
+            .tags
+            .iter()
+            .map(|(tag, time)| (tag.to_string(), *time))
+            .chain([(String::from("Total"), self.total)])
+            .map(|(name, time)| {
+                let hours = time.as_millis() as f64 / Duration::HOUR.as_millis() as f64;
+                let amount = config.hourly_rate.map_or(String::new(), |rate| {
+                    format!("{:.2} {}", hours * rate, config.currency)
+                });
+                (name, format!("{hours:.2}h"), amount.trim_end().to_owned())
+            })
+            .collect();
+        let width = |column: fn(&(String, String, String)) -> &String| {
+            rows.iter()
+                .map(|v| column(v).chars().count())
+                .max()
+                .unwrap_or(0)
+        };
+        let (name_width, hours_width, amount_width) =
+            (width(|v| &v.0), width(|v| &v.1), width(|v| &v.2));
+        let mut lines = vec![format!("Billing {month}")];
+        for (name, hours, amount) in &rows {
+            let line = format!("{name:<name_width$} {hours:>hours_width$} {amount:>amount_width$}");
+            lines.push(line.trim_end().to_owned());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Tracked time of each context, see
+/// [`Aggregator::context_totals`](crate::session::Aggregator::context_totals).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Contexts {
+    pub totals: Vec<(String, Duration)>,
+}
+
+impl Contexts {
+    pub fn render(&self, config: &Config) -> String {
+        if self.totals.is_empty() {
+            return String::from("No tracked time by context");
+        }
+        let width = self
+            .totals
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![String::from("By context")];
+        for (name, time) in &self.totals {
+            let time = time.format(&config.duration_format);
+            lines.push(format!("{name:<width$} {time}"));
+        }
+        lines.join("\n")
+    }
+}
+
+/// A session and the idle time until the next one, see
+/// [`Aggregator::gaps`](crate::session::Aggregator::gaps).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Gap {
+    pub start: DateTime,
+    /// `None` while the session is active.
+    pub end: Option<DateTime>,
+    /// Zero when the next session starts before this one ends, `None` for the last session and
+    /// an active one.
+    pub gap: Option<Duration>,
+}
+
+/// Sessions of a day with the gaps between them.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Gaps {
+    pub day: chrono::NaiveDate,
+    pub gaps: Vec<Gap>,
+}
+
+impl Gaps {
+    /// Gaps shorter than [`Config::gap_threshold`](crate::Config::gap_threshold) are flagged, the
+    /// sessions were likely meant to be one.
+    pub fn render(&self, config: &Config) -> String {
+        let day = config.display.format_date(self.day);
+        if self.gaps.is_empty() {
+            return format!("No sessions on {day}");
+        }
+        let mut lines = vec![format!("Gaps {day}")];
+        for gap in &self.gaps {
+            let start = gap.start.to_formatted_display_time(&config.display);
+            let end = gap.end.as_ref().map_or(String::from("active"), |v| {
+                v.to_formatted_display_time(&config.display)
+            });
+            let line = match gap.gap {
+                Some(time) if time < config.gap_threshold => format!(
+                    "{start} - {end}  gap {} (short)",
+                    time.format(&config.duration_format)
+                ),
+                Some(time) => format!(
+                    "{start} - {end}  gap {}",
+                    time.format(&config.duration_format)
+                ),
+                None => format!("{start} - {end}"),
+            };
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        date_time::{Clock, DateStyle, DisplayPrefs},
+        testing,
+    };
+
+    fn hours(v: u64) -> Duration {
+        Duration::from_secs(v * 60 * 60)
+    }
+
+    #[test]
+    fn goals_set_and_clear_keep_other_lines() {
+        let dir = testing::temp_dir("goals_set_and_clear_keep_other_lines");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let rust = Tag::from_text("rust").unwrap();
+        let review = Tag::from_text("code review").unwrap();
+        let goal = |tag: &Tag, period, text| TagGoal {
+            tag: tag.clone(),
+            period,
+            goal: Goal::from_text(text).unwrap(),
+        };
+        fs::write(
+            dir.join(GOALS_FILE_NAME),
+            "# My goals.\n[week]\nrust = '8h'\n\n[month]\n",
+        )
+        .unwrap();
+        let mut goals = Goals::read(&config).unwrap();
+        assert_eq!(goals.get(), vec![goal(&rust, Period::Week, "8h")]);
+
+        goals.set(&goal(&rust, Period::Week, "10h")).unwrap();
+        goals.set(&goal(&review, Period::Week, "2h30m")).unwrap();
+        goals.set(&goal(&rust, Period::Month, "40h")).unwrap();
+        goals.write().unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(GOALS_FILE_NAME)).unwrap(),
+            "\
+            # My goals.\n\
+            [week]\n\
+            'rust' = '10h'\n\
+            'code review' = '2h30m'\n\
+            \n\
+            [month]\n\
+            'rust' = '40h'\n\
+            "
+        );
+
+        let mut goals = Goals::read(&config).unwrap();
+        assert!(goals.clear(&rust).unwrap());
+        assert!(!goals.clear(&rust).unwrap());
+        assert_eq!(goals.get(), vec![goal(&review, Period::Week, "2h30m")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn goals_set_adds_missing_section() {
+        let mut goals = Goals {
+            path: PathBuf::new(),
+            lines: Vec::new(),
+        };
+        let goal = TagGoal {
+            tag: Tag::from_text("it's").unwrap(),
+            period: Period::Month,
+            goal: Goal::from_text("1h").unwrap(),
+        };
+        goals.set(&goal).unwrap();
+        assert_eq!(goals.lines, vec!["[month]", "\"it's\" = '1h'"]);
+        assert_eq!(goals.get(), vec![goal]);
+    }
+
+    #[test]
+    fn goals_read_rejects_invalid_lines() {
+        let dir = testing::temp_dir("goals_read_rejects_invalid_lines");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        assert!(Goals::read(&config).unwrap().get().is_empty());
+        for contents in [
+            "rust = '10h'",
+            "[day]",
+            "[week]\nrust",
+            "[week]\n'rust = '10h'",
+            "[week]\nrust = 10h",
+        ] {
+            fs::write(dir.join(GOALS_FILE_NAME), contents).unwrap();
+            assert!(Goals::read(&config).is_err(), "{contents}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn goals_render_works() {
+        let rust = Tag::from_text("rust").unwrap();
+        let mut goals = Goals {
+            path: PathBuf::new(),
+            lines: Vec::new(),
+        };
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            ..Default::default()
+        };
+        let now = DateTime::new(&testing::date_default());
+        let totals = |range: &DateRange| {
+            // Goals only get the range of their period.
+            let time = if range.contains(&now.plus_days(-7).date) {
+                hours(30)
+            } else {
+                hours(6)
+            };
+            Ok(vec![(rust.clone(), time)])
+        };
+        assert_eq!(goals.render(&config, &now, totals).unwrap(), "No goals set");
+
+        for (tag, period, goal) in [
+            ("rust", Period::Week, "10h"),
+            ("rust", Period::Month, "40h"),
+            ("go", Period::Week, "1h"),
+        ] {
+            let goal = TagGoal {
+                tag: Tag::from_text(tag).unwrap(),
+                period,
+                goal: Goal::from_text(goal).unwrap(),
+            };
+            goals.set(&goal).unwrap();
+        }
+        assert_eq!(
+            goals.render(&config, &now, totals).unwrap(),
+            "\
+            rust week  ████████████░░░░░░░░  60% 6:00 / 10:00\n\
+            go   week  ░░░░░░░░░░░░░░░░░░░░   0% 0:00 / 1:00\n\
+            rust month ███████████████░░░░░  75% 30:00 / 40:00\
+            "
+        );
+    }
+
+    #[test]
+    fn period_get_range_works() {
+        let now = DateTime::new(&testing::date_default());
+        let boundary = chrono::NaiveTime::MIN;
+        let day = |v| chrono::NaiveDate::from_ymd_opt(2002, 5, v).unwrap();
+        assert_eq!(
+            Period::Week.get_range(&now, boundary),
+            DateRange::from_days(Some(day(6)), Some(day(12)), boundary)
+        );
+        assert_eq!(
+            Period::Month.get_range(&now, boundary),
+            DateRange::from_days(Some(day(1)), Some(day(31)), boundary)
+        );
+    }
+
+    #[test]
+    fn contexts_render_works() {
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            ..Default::default()
+        };
+        let contexts = Contexts {
+            totals: vec![
+                (String::from("office"), hours(12)),
+                (String::from("(unset)"), Duration::from_secs(45 * 60)),
+            ],
+        };
+        assert_eq!(
+            contexts.render(&config),
+            "\
+By context
+office  12:00
+(unset) 0:45"
+        );
+        let empty = Contexts { totals: Vec::new() };
+        assert_eq!(empty.render(&config), "No tracked time by context");
+    }
+
+    #[test]
+    fn billing_render_works() {
+        let mut config = Config::default();
+        let month = chrono::NaiveDate::from_ymd_opt(2002, 5, 1).unwrap();
+        let billing = Billing {
+            month,
+            total: hours(12) + Duration::from_secs(30 * 60),
+            tags: vec![
+                (Tag::from_text("client-a").unwrap(), hours(12)),
+                (
+                    Tag::from_text("rust").unwrap(),
+                    Duration::from_secs(45 * 60),
+                ),
+            ],
+        };
+        assert_eq!(
+            billing.render(&config),
+            "\
+Billing 2002-05
+client-a 12.00h
+rust      0.75h
+Total    12.50h"
+        );
+        config.hourly_rate = Some(85.0);
+        config.currency = String::from("EUR");
+        assert_eq!(
+            billing.render(&config),
+            "\
+Billing 2002-05
+client-a 12.00h 1020.00 EUR
+rust      0.75h   63.75 EUR
+Total    12.50h 1062.50 EUR"
+        );
+        let empty = Billing {
+            month,
+            total: Duration::ZERO,
+            tags: Vec::new(),
+        };
+        assert_eq!(empty.render(&config), "No billable time in 2002-05");
+    }
+
+    #[test]
+    fn gaps_render_works() {
+        let start = DateTime::new(&testing::date_default());
+        let config = Config {
+            duration_format: DurationFormat::Clock,
+            gap_threshold: Duration::from_secs(15 * 60),
+            ..Default::default()
+        };
+        let gaps = Gaps {
+            day: start.date.date_naive(),
+            gaps: vec![
+                Gap {
+                    start: start.clone(),
+                    end: Some(start.plus_hours(1)),
+                    gap: Some(Duration::from_secs(5 * 60)),
+                },
+                Gap {
+                    start: start.plus_minutes(65),
+                    end: Some(start.plus_hours(2)),
+                    gap: Some(Duration::HOUR),
+                },
+                Gap {
+                    start: start.plus_hours(3),
+                    end: None,
+                    gap: None,
+                },
+            ],
+        };
+        assert_eq!(
+            gaps.render(&config),
+            "\
+Gaps 2002-05-08
+12:00:00 - 13:00:00  gap 0:05 (short)
+13:05:00 - 14:00:00  gap 1:00
+15:00:00 - active"
+        );
+        let empty = Gaps {
+            day: start.date.date_naive(),
+            gaps: Vec::new(),
+        };
+        assert_eq!(empty.render(&config), "No sessions on 2002-05-08");
+
+        let config = Config {
+            display: DisplayPrefs {
+                clock: Clock::H12,
+                date_style: DateStyle::Long,
+            },
+            ..config
+        };
+        let lines: Vec<String> = gaps.render(&config).lines().map(String::from).collect();
+        assert_eq!(lines[0], "Gaps Wednesday, May 8, 2002");
+        assert_eq!(lines[1], "12:00:00 PM - 1:00:00 PM  gap 0:05 (short)");
+        assert_eq!(lines[3], "3:00:00 PM - active");
+    }
+
+    #[test]
+    fn goal_from_text_works() {
+        assert_eq!(Goal::from_text("30h"), Ok(Goal { target: hours(30) }));
+        assert_eq!(
+            Goal::from_text(" 7h30m "),
+            Ok(Goal {
+                target: Duration::from_secs(7 * 60 * 60 + 30 * 60)
+            })
+        );
+        assert!(Goal::from_text("0h").is_err());
+        assert!(Goal::from_text("30").is_err());
+        assert!(Goal::from_text("").is_err());
+    }
+
+    #[test]
+    fn goal_get_percentage_works() {
+        let goal = Goal { target: hours(30) };
+        assert_eq!(goal.get_percentage(Duration::ZERO), 0);
+        assert_eq!(
+            goal.get_percentage(Duration::from_secs(21 * 60 * 60 + 10 * 60)),
+            70
+        );
+        assert_eq!(goal.get_percentage(hours(45)), 150);
+    }
+
+    #[test]
+    fn goal_get_left_today_works() {
+        let goal = Goal::from_text("35h").unwrap();
+        let hours = |v: u64| Duration::from_secs(v * 60 * 60);
+        assert_eq!(goal.get_left_today(hours(0), hours(0), 1), Some(hours(5)));
+        // 20h before today leaves 15h for the last 3 days.
+        assert_eq!(goal.get_left_today(hours(22), hours(2), 5), Some(hours(3)));
+        assert_eq!(
+            goal.get_left_today(hours(26), hours(6), 5),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            goal.get_left_today(hours(40), hours(0), 7),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(goal.get_left_today(hours(0), hours(0), 8), None);
+    }
+
+    #[test]
+    fn goal_get_catch_up_works() {
+        let goal = Goal { target: hours(35) };
+        // Nothing is expected before the first day ends.
+        assert_eq!(goal.get_catch_up(Duration::ZERO, 1), None);
+        assert_eq!(goal.get_catch_up(hours(5), 2), None);
+        assert_eq!(
+            goal.get_catch_up(hours(4), 2),
+            Some(Duration::from_secs(31 * 60 * 60 / 6))
+        );
+        assert_eq!(goal.get_catch_up(hours(20), 7), Some(hours(15)));
+        assert_eq!(goal.get_catch_up(Duration::ZERO, 8), None);
+        assert_eq!(goal.get_catch_up(Duration::ZERO, 0), None);
+    }
+
+    #[test]
+    fn goal_format_progress_works() {
+        let goal = Goal { target: hours(30) };
+        let time = Duration::from_secs(21 * 60 * 60 + 10 * 60);
+        assert_eq!(
+            goal.format_progress(time, Some(5), &DurationFormat::Hms),
+            "Goal: 21h 10m 0s / 30h 0m 0s (70%)"
+        );
+        assert_eq!(
+            goal.format_progress(time, Some(7), &DurationFormat::Clock),
+            "Goal: 21:10 / 30:00 (70%), 8:50 a day to catch up"
+        );
+        assert_eq!(
+            goal.format_progress(time, None, &DurationFormat::Clock),
+            "Goal: 21:10 / 30:00 (70%)"
+        );
+    }
+}
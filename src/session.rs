@@ -1,912 +1,6323 @@
-use crate::{date_time::DateTime, read_sessions_dir, Config};
+use crate::{
+    crypto::Cipher,
+    date_time::{DateRange, DateTime, Duration, DurationFormat},
+    history::{Index, Summary},
+    report::Gap,
+    status::{Pomodoro, State, Status, When, BREAK_DUE_INDICATOR},
+    style::{Color, Style},
+    view::ViewData,
+    Config,
+};
 use std::{
+    borrow::Cow,
     collections::HashSet,
+    env,
     error::Error,
-    fs,
+    fmt, fs,
+    io::{self, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    thread,
 };
 
 const SESSION_HEADING_PREFIX: &str = "# ";
 const SESSION_TITLE: &str = "Session";
+const SESSION_FILE_EXTENSION: &str = "md";
+/// Contains no `:`, which isn't allowed in file names on Windows.
+const SESSION_FILE_NAME_FORMAT: &str = "%FT%H-%M-%S%z";
+/// Format of file names written by older versions, still read.
+const SESSION_FILE_NAME_FORMAT_LEGACY: &str = "%FT%T%:z";
+/// Separates the date from the workspace name in file names, `{date}__{name}.md`.
+const SESSION_NAME_SEPARATOR: &str = "__";
+const SESSION_TITLE_SEPARATOR: &str = " — ";
 const MARKS_HEADING: &str = "## Marks";
+/// Written on the line under the session heading, `<!-- tt:v2 -->`. Files without it are
+/// [`FormatVersion::V1`].
+const FORMAT_MARKER_PREFIX: &str = "<!-- tt:v";
+const FORMAT_MARKER_SUFFIX: &str = " -->";
 const MARK_HEADING_PREFIX: &str = "### ";
 const LABEL_PREFIX: &str = "- ";
 const LABEL_STOP: &str = "- stop";
 const LABEL_SKIP: &str = "- skip";
 const LABEL_TAG: &str = "- tag";
+const LABEL_BILLABLE: &str = "- billable";
+const LABEL_HOST: &str = "- host";
+const LABEL_CONTEXT: &str = "- at";
+const LABEL_ENCRYPTED: &str = "- enc";
+/// Bucket of the time without a context, see [`Aggregator::context_totals`].
+const CONTEXT_UNSET: &str = "(unset)";
 const LABEL_TAG_SURROUND: &str = "`";
+/// Longest tag in characters.
+const TAG_MAX_LENGTH: usize = 64;
+const BACKUPS_DIR: &str = ".backups";
+/// Where pruned sessions are moved, within the sessions directory.
+const TRASH_DIR: &str = ".trash";
+/// Where archived sessions are moved, within the sessions directory, in a directory per year.
+const ARCHIVE_DIR: &str = "archive";
+/// Precedes lines of mark contents that would otherwise be read as a heading or a label.
+const CONTENTS_ESCAPE: &str = "\\";
+
+/// How far ahead of the current time a mark can be placed without explicitly allowing it.
+const MARK_FUTURE_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bound on the threads reading session files, more rarely help with disk access.
+const PARSE_THREADS_MAX: usize = 8;
 
 const COMMAND_VIEW_MARK_CONTENTS_SEPARATOR: &str = "---------------------------------";
 
+/// Files that couldn't be read as sessions, each with the reason.
+pub type Failures = Vec<(PathBuf, Box<dyn Error>)>;
+
 pub struct Aggregator {
     sessions: Vec<Session>,
+    /// Files in the sessions directory that couldn't be read as sessions.
+    pub failures: Failures,
 }
 
 impl Aggregator {
+    /// Files that fail to parse are collected in [`Aggregator::failures`], fails only if there
+    /// are files but none could be read.
     pub fn build(config: &Config) -> Result<Aggregator, Box<dyn Error>> {
-        let sessions = read_sessions_dir(&config)?
-            .iter()
-            .map(|v| -> Result<Session, Box<dyn Error>> {
-                let contents = fs::read_to_string(&v)?;
-                let file = SessionFile::build(&v, &contents)?;
-                let session = Session::from_file(&file)?;
-                Ok(session)
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        if sessions.is_empty() {
-            return Err("session directory is empty")?;
-        }
-        let aggregator = Aggregator { sessions };
-        Ok(aggregator)
+        Aggregator::build_from(config, Aggregator::read_sessions_dir(config)?)
     }
 
-    /// Week - Session started in the previous week that ends in the current week is still counted to the
-    /// previous week.
-    pub fn view(&self) -> String {
-        let session = self
-            .sessions
-            .last()
-            .expect("must always have at least one session");
-        assert!(session.marks.len() > 0);
-        assert!(
-            !(session.marks.len() == 1
-                && session.marks.last().unwrap().attribute == Attribute::Stop)
-        );
-
-        // TODO: Write as many of these as functions in their relevant structs.
-        let start = DateTime::new(&session.start()).to_formatted_pretty_short();
-        let start_of_week = DateTime::get_start_of_week(&session.start());
-        let week_time = self
-            .sessions
-            .iter()
-            .filter(|v| v.start().timestamp_millis() - start_of_week.timestamp_millis() >= 0)
-            .fold(0, |acc, val| acc + val.get_time());
-        let week_time = DateTime::get_time_hr_from_milli(week_time);
-        let session_time = DateTime::get_time_hr_from_milli(session.get_time());
-        let mark_last = session
-            .marks
-            .last()
-            .expect("session must have at least one mark");
-        let mark_last_time = if session.is_active() {
-            let timestamp_now = DateTime::now().date.timestamp_millis();
-            let timestamp_mark = mark_last.date.timestamp_millis();
-            let timestamp = timestamp_now - timestamp_mark;
-            assert!(timestamp >= 0);
-            &DateTime::get_time_hr_from_milli(timestamp.try_into().unwrap())
-        } else {
-            "0"
-        };
-        let mark_last_contents = mark_last.to_line();
-
-        let mut str = String::new();
-        if !session.is_active() {
-            str += "No active session, last session:\n";
-        }
-        str += &format!(
-            "\
-            Start: {start}\n\
-            Week: {week_time}\n\
-            Time: {session_time}\n\
-            Mark: {mark_last_time}\n\
-            {COMMAND_VIEW_MARK_CONTENTS_SEPARATOR}\n\
-            {mark_last_contents}\
-            "
-        );
-        str
+    /// Like [`Aggregator::build`], but files named after a date before `cutoff` aren't read at
+    /// all. The newest file is always read, it can still be active.
+    pub fn build_since(config: &Config, cutoff: &DateTime) -> Result<Aggregator, Box<dyn Error>> {
+        let mut dir = Aggregator::read_sessions_dir_with_dates(config)?;
+        let newest = dir.pop();
+        dir.retain(|(date, _)| cutoff <= date);
+        dir.extend(newest);
+        Aggregator::build_from(config, dir.into_iter().map(|(_, path)| path).collect())
     }
-}
 
-#[derive(PartialEq, Debug)]
-pub struct SessionFile {
-    pub path: PathBuf,
-    pub contents: String,
-}
+    /// Like [`Aggregator::build`], but with the sessions in the archive as well, see
+    /// [`Aggregator::read_archive_dir`].
+    pub fn build_with_archive(config: &Config) -> Result<Aggregator, Box<dyn Error>> {
+        // Sessions are archived only once they ended, before all the others started.
+        let mut paths = Aggregator::read_archive_dir(config)?;
+        paths.extend(Aggregator::read_sessions_dir(config)?);
+        Aggregator::build_from(config, paths)
+    }
 
-impl SessionFile {
-    // TODO: add checks for path
-    fn build(path: &PathBuf, contents: &str) -> Result<SessionFile, &'static str> {
-        let contents = contents.trim();
-        if !contents.starts_with(SESSION_HEADING_PREFIX) || !contents.contains(MARKS_HEADING) {
-            return Err("couldn't parse session file");
+    /// Tags are normalized with [`Config::tag_normalizer`].
+    fn build_from(config: &Config, paths: Vec<PathBuf>) -> Result<Aggregator, Box<dyn Error>> {
+        let mut sessions = Vec::new();
+        let mut failures = Vec::new();
+        for (path, session) in paths.iter().zip(Session::from_paths(&paths)) {
+            match session {
+                Ok(mut session) => {
+                    session.normalize_tags(&config.tag_normalizer);
+                    sessions.push(session);
+                }
+                Err(err) => failures.push((path.clone(), err)),
+            }
         }
-        Ok(SessionFile {
-            path: path.clone(),
-            contents: contents.to_string(),
-        })
+        // An empty directory isn't an error, there are just no sessions yet.
+        if sessions.is_empty() {
+            if let Some((_, err)) = failures.first() {
+                return Err(format!(
+                    "no valid session found, {} files couldn't be read, first error: {err}",
+                    failures.len()
+                ))?;
+            }
+        }
+        let aggregator = Aggregator { sessions, failures };
+        Ok(aggregator)
     }
 
-    fn get_heading_with_contents(heading: &str, contents: &str) -> String {
-        let heading_level = SessionFile::get_heading_level(&heading);
-        let mut is_within = false;
-        let mut text = String::new();
-        for line in contents.lines() {
-            if line.starts_with("#") && SessionFile::get_heading_level(&line) <= heading_level {
-                is_within = false;
+    /// Checks all files in the sessions directory, see [`Session::check_marks`] for the checks
+    /// within a session. Each file is reported with the line its problem is on.
+    pub fn validate(config: &Config) -> Result<Vec<Finding>, Box<dyn Error>> {
+        let mut findings = Vec::new();
+        let mut sessions = Vec::new();
+        for path in Aggregator::read_sessions_dir(config)? {
+            let contents = match fs::read_to_string(&path) {
+                Ok(val) => val,
+                Err(err) => {
+                    let description = format!("couldn't read session file: {err}");
+                    findings.push(Finding::new(&path, None, &description, None));
+                    continue;
+                }
+            };
+            let parsed = SessionFile::build(&path, &contents)
+                .and_then(|file| Session::from_file(&file).map(|session| (file, session)));
+            let (file, session) = match parsed {
+                Ok(val) => val,
+                Err(err) => {
+                    findings.push(Finding::from(err));
+                    continue;
+                }
+            };
+            let lines = file.get_marks_with_lines();
+            for ((line, contents), mark) in lines.iter().zip(&session.marks) {
+                if !mark.has_canonical_labels(contents) {
+                    findings.push(Finding::new(
+                        &path,
+                        Some(*line),
+                        "labels are out of order or repeated",
+                        Some(Fix::Rewrite),
+                    ));
+                }
             }
-            if line.starts_with(&heading) {
-                is_within = true;
+            for (i, description) in session.check_marks() {
+                findings.push(Finding::new(&path, Some(lines[i].0), &description, None));
             }
-            if is_within {
-                text += &line.trim();
-                text += "\n";
+            if !session.is_active() && session.is_empty() {
+                findings.push(Finding::new(
+                    &path,
+                    Some(lines[0].0),
+                    "session has no tracked time",
+                    None,
+                ));
             }
+            if !session.has_matching_file_name() {
+                findings.push(Finding::new(
+                    &path,
+                    Some(lines[0].0),
+                    &format!(
+                        "file name doesn't match the first mark, expected `{}`",
+                        Session::get_file_name(&session.marks[0], session.get_name())
+                    ),
+                    Some(Fix::Rename),
+                ));
+            }
+            sessions.push((session, lines));
         }
 
-        text.trim().to_string()
+        // Workspaces are checked separately, their sessions can overlap each other.
+        sessions
+            .sort_by(|(a, _), (b, _)| (a.get_name(), a.start()).cmp(&(b.get_name(), b.start())));
+        for (i, (session, lines)) in sessions.iter().enumerate() {
+            let next = sessions.get(i + 1);
+            if next.is_some_and(|(v, _)| v.get_name() == session.get_name()) && session.is_active()
+            {
+                findings.push(Finding::new(
+                    &session.path,
+                    lines.last().map(|(line, _)| *line),
+                    "session is active but isn't the newest one",
+                    None,
+                ));
+            }
+            let preceding = i
+                .checked_sub(1)
+                .map(|i| &sessions[i])
+                .filter(|(v, _)| v.get_name() == session.get_name());
+            if let Some((preceding, _)) = preceding {
+                if preceding.end() > session.start() {
+                    findings.push(Finding::new(
+                        &session.path,
+                        Some(lines[0].0),
+                        &format!(
+                            "session overlaps with `{}`, which ends at {}",
+                            preceding.path.display(),
+                            DateTime::new(&preceding.end()).to_formatted_pretty()
+                        ),
+                        None,
+                    ));
+                }
+            }
+        }
+        Ok(findings)
     }
 
-    fn get_heading_level(heading: &str) -> u8 {
-        let mut level = 0u8;
-        for char in heading.trim().chars() {
-            if char != '#' {
-                break;
+    /// Applies the fixes of `findings`, returns how many of them were fixed.
+    pub fn fix(config: &Config, findings: &[Finding]) -> Result<usize, Box<dyn Error>> {
+        let mut paths: Vec<&PathBuf> = Vec::new();
+        for finding in findings.iter().filter(|v| v.fix.is_some()) {
+            if !paths.contains(&&finding.path) {
+                paths.push(&finding.path);
             }
-            level += 1;
         }
-        level
+        let mut count = 0;
+        for path in paths {
+            let fixes: Vec<&Fix> = findings
+                .iter()
+                .filter(|v| &v.path == path)
+                .filter_map(|v| v.fix.as_ref())
+                .collect();
+            let mut session = Session::from_path(path)?;
+            if fixes.contains(&&Fix::Rename) {
+                session.rename_to_start()?;
+            }
+            session.save(config)?;
+            count += fixes.len();
+        }
+        Ok(count)
     }
-}
-
-#[derive(PartialEq, Debug, Clone)]
-pub struct Session {
-    pub path: PathBuf,
-    pub marks: Vec<Mark>,
-}
 
-impl Session {
-    pub fn new(config: &Config, dt: &DateTime) -> Session {
-        let mark = Mark::new(&dt.date);
-        Session {
-            path: Path::join(&config.sessions_path, format!("{}.md", dt.to_formatted())),
-            marks: vec![mark],
+    /// Rewrites each of `paths` the way [`Session::save`] writes it, or with `check` only looks
+    /// for the ones that would change. Returns the files that changed along with the ones that
+    /// couldn't be parsed, which are left as they are.
+    pub fn format(
+        config: &Config,
+        paths: &[PathBuf],
+        check: bool,
+    ) -> Result<(Vec<PathBuf>, Failures), Box<dyn Error>> {
+        let mut changed = Vec::new();
+        let mut failures: Failures = Vec::new();
+        for path in paths {
+            let contents = match fs::read_to_string(path) {
+                Ok(val) => val,
+                Err(err) => {
+                    failures.push((
+                        path.clone(),
+                        format!("couldn't read session file: {err}").into(),
+                    ));
+                    continue;
+                }
+            };
+            let session =
+                match SessionFile::build(path, &contents).and_then(|v| Session::from_file(&v)) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        failures.push((path.clone(), Box::new(err)));
+                        continue;
+                    }
+                };
+            if session.to_file()?.contents == contents {
+                continue;
+            }
+            if !check {
+                session.save(config)?;
+            }
+            changed.push(path.clone());
         }
+        Ok((changed, failures))
     }
 
-    pub fn get_last(config: &Config) -> Result<Option<Session>, Box<dyn Error>> {
-        let dir = read_sessions_dir(&config)?;
-        if dir.len() == 0 {
-            return Ok(None);
+    /// Rewrites each of `paths` written in an older [`FormatVersion`] in the current one. The
+    /// original is always backed up, even with backups disabled. Returns the files that were
+    /// migrated along with the ones that couldn't be parsed, which are left as they are.
+    pub fn migrate(
+        config: &Config,
+        paths: &[PathBuf],
+    ) -> Result<(Vec<PathBuf>, Failures), Box<dyn Error>> {
+        let mut migrated = Vec::new();
+        let mut failures: Failures = Vec::new();
+        for path in paths {
+            let contents = match fs::read_to_string(path) {
+                Ok(val) => val,
+                Err(err) => {
+                    failures.push((
+                        path.clone(),
+                        format!("couldn't read session file: {err}").into(),
+                    ));
+                    continue;
+                }
+            };
+            let parsed = SessionFile::build(path, &contents)
+                .and_then(|file| Session::from_file(&file).map(|session| (file, session)));
+            let (file, session) = match parsed {
+                Ok(val) => val,
+                Err(err) => {
+                    failures.push((path.clone(), Box::new(err)));
+                    continue;
+                }
+            };
+            if file.version == FormatVersion::CURRENT {
+                continue;
+            }
+            session.save_with_backups(config, config.backups.max(1))?;
+            migrated.push(path.clone());
         }
-        let path = &dir[dir.len() - 1];
-        let contents = fs::read_to_string(&path)?;
-        let file = SessionFile::build(&path, &contents)?;
-        let session = Session::from_file(&file)?;
-        Ok(Some(session))
+        Ok((migrated, failures))
     }
 
-    fn start(&self) -> chrono::DateTime<chrono::Local> {
-        self.marks
-            .first()
-            .expect("session must have at least one mark")
-            .date
+    /// Session files in the sessions directory ordered by their start. Only regular files named
+    /// like sessions are included, see [`Session::date_from_path`]. Archived sessions aren't,
+    /// see [`Aggregator::read_archive_dir`].
+    pub fn read_sessions_dir(config: &Config) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        Ok(Aggregator::read_sessions_dir_with_dates(config)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
     }
 
-    #[allow(dead_code)]
-    fn end(&self) -> chrono::DateTime<chrono::Local> {
-        self.marks
-            .last()
-            .expect("session must have at least one mark")
-            .date
+    /// Names the sessions directory by its absolute path, a relative one is hard to place.
+    pub(crate) fn missing_dir_error(config: &Config) -> String {
+        format!(
+            "session directory `{}` doesn't exist",
+            Aggregator::get_absolute_dir(config).display()
+        )
     }
 
-    pub fn is_active(&self) -> bool {
-        self.marks
-            .last()
-            .expect("must always have at least one mark")
-            .attribute
-            != Attribute::Stop
+    pub(crate) fn get_absolute_dir(config: &Config) -> PathBuf {
+        std::path::absolute(&config.sessions_path).unwrap_or_else(|_| config.sessions_path.clone())
     }
 
-    fn get_time(&self) -> u64 {
-        let mut acc = 0;
-        let mut mark_preceding = &self.marks[0];
-        for mark in self.marks.iter().skip(1) {
-            if mark_preceding.attribute != Attribute::Skip {
-                acc += DateTime::get_time(&mark_preceding.date, &mark.date);
+    /// See [`Aggregator::read_sessions_dir`], each file comes with the date from its name.
+    fn read_sessions_dir_with_dates(
+        config: &Config,
+    ) -> Result<Vec<(DateTime, PathBuf)>, Box<dyn Error>> {
+        let entries = fs::read_dir(&config.sessions_path)
+            .map_err(|_err| Aggregator::missing_dir_error(config))?
+            .collect::<Result<Vec<_>, io::Error>>()?;
+        let mut dir = Aggregator::filter_sessions(entries)?;
+        dir.sort();
+        Ok(dir)
+    }
+
+    /// Session files moved to the archive by [`Session::archive`], ordered by their start. A
+    /// missing archive has no sessions.
+    pub fn read_archive_dir(config: &Config) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let years = match fs::read_dir(config.sessions_path.join(ARCHIVE_DIR)) {
+            Ok(val) => val.collect::<Result<Vec<_>, io::Error>>()?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(format!("couldn't read the archive: {err}"))?,
+        };
+        let mut dir = Vec::new();
+        for year in years {
+            if year.file_type()?.is_dir() {
+                let entries = fs::read_dir(year.path())?.collect::<Result<Vec<_>, io::Error>>()?;
+                dir.extend(Aggregator::filter_sessions(entries)?);
             }
-            mark_preceding = &mark;
         }
-        if self.is_active() && mark_preceding.attribute != Attribute::Skip {
-            acc += DateTime::get_time(&mark_preceding.date, &DateTime::now().date);
-        }
-        acc
+        dir.sort();
+        Ok(dir.into_iter().map(|(_, path)| path).collect())
     }
 
-    pub fn mark(&mut self, dt: &DateTime) -> Result<(), &'static str> {
-        if !self.is_active() {
-            return Err("can't mark, session has already ended");
+    /// Regular files named like sessions, each with the date from its name.
+    fn filter_sessions(entries: Vec<fs::DirEntry>) -> io::Result<Vec<(DateTime, PathBuf)>> {
+        let mut dir = Vec::new();
+        for entry in entries {
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            if let Some(date) = Session::date_from_path(&path) {
+                dir.push((DateTime::from(date.with_timezone(&chrono::Local)), path));
+            }
         }
-        let mark = Mark::new(&dt.date);
-        self.marks.push(mark);
-        Ok(())
+        Ok(dir)
     }
 
-    pub fn remark(&mut self, dt: &DateTime) {
-        let mark = self
-            .marks
-            .last_mut()
-            .expect("session must always have at least one mark");
-        mark.date = dt.date;
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
     }
 
-    pub fn unmark(&mut self) -> Option<Mark> {
-        if self.marks.len() > 1 {
-            let mark = self.marks.pop();
-            assert!(mark.is_some());
-            return mark;
-        } else {
-            return None;
-        }
+    /// Oldest first.
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
     }
 
-    pub fn set_attribute(&mut self, attribute: Attribute) {
-        self.marks
-            .last_mut()
-            .expect("session must always have at least one mark")
-            .attribute = attribute;
+    /// File of the session [`Aggregator::view`] shows.
+    pub(crate) fn get_shown_path(&self, config: &Config) -> Option<&Path> {
+        let name = config.workspace.as_deref();
+        let i = self.sessions.iter().rposition(|v| v.get_name() == name)?;
+        Some(&self.sessions[i].path)
     }
 
-    pub fn tag(&mut self, tag: &Tag) -> bool {
-        self.marks
-            .last_mut()
-            .expect("session must always have at least one mark")
-            .tags
-            .insert(tag.to_owned())
+    /// Reads the session at `path` again, it has to be one of the sessions already read.
+    pub(crate) fn reload(&mut self, config: &Config, path: &Path) -> Result<(), Box<dyn Error>> {
+        let Some(session) = self.sessions.iter_mut().find(|v| v.path == path) else {
+            return Err(format!("session `{}` isn't loaded", path.display()))?;
+        };
+        let mut reloaded = Session::from_path(path)?;
+        reloaded.normalize_tags(&config.tag_normalizer);
+        *session = reloaded;
+        Ok(())
     }
 
-    pub fn untag(&mut self, tag: &Tag) -> bool {
-        self.marks
-            .last_mut()
-            .expect("session must always have at least one mark")
-            .tags
-            .remove(&tag)
+    /// Replaces `from` with `to` in every session, see [`Session::retag`]. Returns the sessions
+    /// that changed with the number of replaced tags.
+    pub fn retag(&mut self, from: &Tag, to: &Tag) -> Vec<(&Session, usize)> {
+        self.sessions
+            .iter_mut()
+            .map(|v| (v.retag(from, to), v))
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, v)| (&*v, count))
+            .collect()
     }
 
-    /// Returns error if the content of the current mark is not empty.
-    pub fn write(&mut self, text: &str) -> Result<(), ()> {
-        let mark = self
-            .marks
-            .last_mut()
-            .expect("session must always have at least one mark");
-        if !mark.contents.is_empty() {
-            return Err(());
-        }
-        mark.write(&text);
-        Ok(())
+    /// Sessions that are [`Session::is_empty`], except the newest session of each workspace while
+    /// it's active, it can still get marks.
+    pub fn get_empty(&self) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(|(i, session)| {
+                let is_newest = !self.sessions[i + 1..]
+                    .iter()
+                    .any(|v| v.get_name() == session.get_name());
+                session.is_empty() && !(is_newest && session.is_active())
+            })
+            .map(|(_, session)| session)
+            .collect()
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn Error>> {
-        let file = self.to_file()?;
-        fs::write(&file.path, &file.contents).map_err(|e| format!("coudln't save session: {e}"))?;
-        Ok(())
+    /// Stopped sessions that ended on a day before `before`. The newest session of each workspace
+    /// is kept out of the archive so that it can still be continued.
+    pub fn get_ended_before(
+        &self,
+        before: chrono::NaiveDate,
+        boundary: chrono::NaiveTime,
+    ) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter(|(i, session)| {
+                let is_newest = !self.sessions[i + 1..]
+                    .iter()
+                    .any(|v| v.get_name() == session.get_name());
+                !is_newest
+                    && !session.is_active()
+                    && DateTime::new(&session.end()).day(boundary) < before
+            })
+            .map(|(_, session)| session)
+            .collect()
     }
 
-    // TODO: make this and all other from/to methods idiomatic using traits
-    fn from_file(file: &SessionFile) -> Result<Session, Box<dyn Error>> {
-        let mut marks: Vec<Mark> = Vec::new();
-        let marks_contents = SessionFile::get_heading_with_contents(MARKS_HEADING, &file.contents);
-        for line in marks_contents.lines() {
-            if line.starts_with(MARK_HEADING_PREFIX) {
-                let contents = SessionFile::get_heading_with_contents(&line, &marks_contents);
-                let mark = Mark::from_line(&contents)?;
-                marks.push(mark);
+    /// Time of each tag in the intervals that start within `range`, sorted by tag. An interval has
+    /// the tags of its session and of the mark that starts it, untracked tags still count under
+    /// their own tag.
+    pub fn tag_totals(&self, range: &DateRange) -> Result<Vec<(Tag, Duration)>, String> {
+        let mut totals: Vec<(Tag, Duration)> = Vec::new();
+        for session in &self.sessions {
+            for (mark, time) in session.get_intervals(&[])? {
+                if !range.contains(&mark.local_date()) {
+                    continue;
+                }
+                for tag in session.tags.union(&mark.tags) {
+                    match totals.iter_mut().find(|(v, _)| v == tag) {
+                        Some((_, total)) => *total += time,
+                        None => totals.push((tag.to_owned(), time)),
+                    }
+                }
             }
         }
-        if marks.is_empty() {
-            return Err("there must be at least one mark for a session to be valid")?;
-        }
-
-        Ok(Session {
-            path: file.path.clone(),
-            marks,
-        })
+        totals.sort_by(|a, b| a.0.text.cmp(&b.0.text));
+        Ok(totals)
     }
 
-    pub fn to_file(&self) -> Result<SessionFile, &'static str> {
-        let mut contents = format!(
-            "\
-            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
-            \n\
-            {MARKS_HEADING}\n\
-            \n\
-            "
-        );
-        for mark in &self.marks {
-            contents += &mark.to_line();
-            contents += "\n\n";
+    /// Time of the intervals that start within `range` for each context, sorted by context with
+    /// the time without one last as [`CONTEXT_UNSET`]. Intervals with any of the `untracked` tags
+    /// don't count.
+    pub fn context_totals(
+        &self,
+        range: &DateRange,
+        untracked: &[Tag],
+    ) -> Result<Vec<(String, Duration)>, String> {
+        let mut totals: Vec<(Option<String>, Duration)> = Vec::new();
+        for session in &self.sessions {
+            for (mark, time) in session.get_intervals(untracked)? {
+                if !range.contains(&mark.local_date()) {
+                    continue;
+                }
+                match totals.iter_mut().find(|(v, _)| *v == mark.context) {
+                    Some((_, total)) => *total += time,
+                    None => totals.push((mark.context.clone(), time)),
+                }
+            }
         }
+        totals.sort_by(|a, b| match (&a.0, &b.0) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        Ok(totals
+            .into_iter()
+            .map(|(context, time)| (context.unwrap_or(CONTEXT_UNSET.to_owned()), time))
+            .collect())
+    }
 
-        let file = SessionFile::build(&self.path, &contents)?;
-        Ok(file)
+    /// Billable time of intervals started within `range`, in total and for each tag, see
+    /// [`Aggregator::tag_totals`]. Intervals with any of the `untracked` tags aren't billed.
+    pub fn billable_totals(
+        &self,
+        range: &DateRange,
+        untracked: &[Tag],
+    ) -> Result<(Duration, Vec<(Tag, Duration)>), String> {
+        let mut total = Duration::ZERO;
+        let mut totals: Vec<(Tag, Duration)> = Vec::new();
+        for session in &self.sessions {
+            for (mark, time) in session.get_intervals(untracked)? {
+                if !mark.billable || !range.contains(&mark.local_date()) {
+                    continue;
+                }
+                total += time;
+                for tag in session.tags.union(&mark.tags) {
+                    match totals.iter_mut().find(|(v, _)| v == tag) {
+                        Some((_, total)) => *total += time,
+                        None => totals.push((tag.to_owned(), time)),
+                    }
+                }
+            }
+        }
+        totals.sort_by(|a, b| a.0.text.cmp(&b.0.text));
+        Ok((total, totals))
     }
-}
 
-#[derive(PartialEq, Debug, Clone)]
-pub struct Mark {
-    date: chrono::DateTime<chrono::Local>,
-    attribute: Attribute,
-    tags: HashSet<Tag>,
-    // TODO: Rename to text.
-    contents: String,
-}
+    /// Sessions that start within `range` in order of their start, each with the idle time until
+    /// the next one.
+    pub fn gaps(&self, range: &DateRange) -> Vec<Gap> {
+        let mut sessions: Vec<&Session> = self
+            .sessions
+            .iter()
+            .filter(|v| range.contains(&v.start()))
+            .collect();
+        sessions.sort_by_key(|v| v.start());
+        sessions
+            .iter()
+            .enumerate()
+            .map(|(i, session)| {
+                let is_active = session.is_active();
+                let gap = match sessions.get(i + 1) {
+                    Some(next) if !is_active => {
+                        Some(Duration::between(&session.end(), &next.start()).unwrap_or_default())
+                    }
+                    _ => None,
+                };
+                Gap {
+                    start: DateTime::new(&session.start()),
+                    end: (!is_active).then(|| DateTime::new(&session.end())),
+                    gap,
+                }
+            })
+            .collect()
+    }
 
-impl Mark {
-    fn new(date: &chrono::DateTime<chrono::Local>) -> Mark {
-        Mark {
-            date: date.clone(),
-            attribute: Attribute::None,
-            tags: HashSet::new(),
-            contents: String::new(),
-        }
+    /// Start of the week the newest session belongs to.
+    pub(crate) fn get_last_week_start(&self, boundary: chrono::NaiveTime) -> DateTime {
+        let session = self
+            .sessions
+            .last()
+            .expect("must always have at least one session");
+        DateTime::from(session.start()).start_of_week(boundary)
     }
 
-    /// Overwrites the content of this mark.
-    fn write(&mut self, text: &str) {
-        self.contents = text.to_owned();
+    /// Week - Session started in the previous week that ends in the current week is still counted to the
+    /// previous week, unless [`Config::week_split`] is set.
+    pub(crate) fn view(&self, config: &Config) -> Result<String, Box<dyn Error>> {
+        let Some((sessions, i, stale)) = self.get_shown(config) else {
+            return Err(match &config.workspace {
+                Some(name) => format!("no session to view in workspace `{name}`"),
+                None => String::from("no session to view"),
+            })?;
+        };
+        let session = &sessions[i];
+        // A session stopped on its only mark is viewed like any other ended session, with no time.
+        let Some(mark_last) = session.marks.last() else {
+            return Err(format!("session `{}` has no marks", session.path.display()))?;
+        };
+
+        // TODO: Write as many of these as functions in their relevant structs.
+        let start = DateTime::new(&session.start())
+            .to_formatted_with(&config.timestamp_format, &config.display);
+        let (start_of_week, week_time) = self.get_week_time(&sessions, session, config)?;
+        let format = &config.duration_format;
+        let goal = config.weekly_goal.map(|goal| {
+            // Day of the shown week it is today, 1 for its first day.
+            let day = (DateTime::now().day(config.day_starts_at)
+                - start_of_week.day(config.day_starts_at))
+            .num_days()
+                + 1;
+            goal.format_progress(week_time, day.try_into().ok(), format)
+        });
+        let week_time = week_time.format(format);
+        let today_time = Aggregator::get_today_time(&sessions, config)?.format(format);
+        let session_time = session
+            .get_time(&config.untracked_tags)?
+            .round(config.rounding)
+            .format(format);
+        let style = Style::new(&config.color);
+        let break_due = config
+            .pomodoro
+            .is_some_and(|v| session.get_pomodoro(&DateTime::now(), v) == Pomodoro::BreakDue);
+        let mark_last_time = if break_due {
+            let time = session.get_mark_time().format(format);
+            &style.paint(&format!("{time}{BREAK_DUE_INDICATOR}"), Color::Yellow)
+        } else if session.is_active() {
+            // The running mark shows that the session is active.
+            &style.paint(&session.get_mark_time().format(format), Color::Green)
+        } else {
+            "0"
+        };
+        let tags: Vec<String> = session
+            .get_all_tags()
+            .into_iter()
+            .map(|v| style.paint(&v.text, Color::Cyan))
+            .collect();
+        // Only listed once named workspaces are used, so that the output stays the same.
+        let mut active: Vec<Option<&str>> = sessions
+            .iter()
+            .filter(|v| v.is_active())
+            .map(|v| v.get_name())
+            .collect();
+        active.sort();
+        active.dedup();
+        let active = active.iter().any(|v| v.is_some()).then(|| {
+            let active: Vec<&str> = active.iter().map(|v| v.unwrap_or("default")).collect();
+            active.join(", ")
+        });
+        let state = if stale {
+            let line = "Stale session, run `time_tracker start` to stop it at its last mark:";
+            Some(style.paint(line, Color::Yellow))
+        } else if !session.is_active() {
+            Some(style.paint("No active session, last session:", Color::Red))
+        } else {
+            None
+        };
+
+        let breaks = session.get_break_time(&config.untracked_tags)?;
+        let data = ViewData {
+            state,
+            title: session.title.clone(),
+            start,
+            week: week_time,
+            today: today_time,
+            session: session_time,
+            breaks: (breaks != Duration::ZERO)
+                .then(|| breaks.round(config.rounding).format(format)),
+            mark_time: mark_last_time.to_owned(),
+            marks: session.marks.len().to_string(),
+            tags: Some(tags.join(", ")).filter(|v| !v.is_empty()),
+            active,
+            goal,
+            separator: style.paint(COMMAND_VIEW_MARK_CONTENTS_SEPARATOR, Color::Dim),
+            mark: mark_last.to_line(),
+            path: session.path.display().to_string(),
+        };
+        Ok(config.view_template.render(&data))
     }
 
-    pub fn erase(&mut self) {
-        self.contents = String::new();
+    /// Whether the last mark of the session [`Aggregator::view`] shows has encrypted contents.
+    pub(crate) fn is_shown_encrypted(&self, config: &Config) -> bool {
+        self.get_shown_index(config)
+            .and_then(|i| self.sessions[i].marks.last())
+            .is_some_and(|v| v.is_encrypted())
     }
 
-    fn from_line(contents: &str) -> Result<Mark, Box<dyn Error>> {
-        let contents = contents.trim();
-        let date = contents
-            .lines()
-            .next()
-            .map(|val| chrono::DateTime::from_str(&val[MARK_HEADING_PREFIX.len()..val.len()]))
-            .ok_or("couldn't parse mark heading")??;
-        let mut contents_without_heading = contents
-            .lines()
-            .skip(1)
-            .fold(String::new(), |acc, val| acc + "\n" + val)
-            .trim()
-            .to_owned();
-        let mut attribute = Attribute::None;
-        let mut tags: HashSet<Tag> = HashSet::new();
-        if contents_without_heading.starts_with(LABEL_PREFIX) {
-            for line in contents_without_heading.lines() {
-                if !line.starts_with(LABEL_PREFIX) {
-                    break;
-                }
-                let attr = Attribute::from_line(&line);
-                if attr != Attribute::None {
-                    if attribute == Attribute::None {
-                        attribute = attr;
-                    } else {
-                        return Err("multiple attributes per mark are not allowed")?;
-                    }
-                } else {
-                    tags.insert(Tag::from_line(&line)?);
-                }
-            }
-            let labels_len = tags.len() + if attribute != Attribute::None { 1 } else { 0 };
-            contents_without_heading = contents_without_heading
-                .lines()
-                .skip(labels_len)
-                .collect::<Vec<&str>>()
-                .join("\n")
-                .trim()
-                .to_owned();
+    /// Decrypts the last mark of the session [`Aggregator::view`] shows, see [`Mark::decrypt`].
+    pub(crate) fn decrypt_shown(&mut self, config: &Config, cipher: &Cipher) -> Result<(), String> {
+        let Some(i) = self.get_shown_index(config) else {
+            return Ok(());
+        };
+        match self.sessions[i].marks.last_mut() {
+            Some(mark) => mark.decrypt(cipher),
+            None => Ok(()),
         }
-        Ok(Mark {
-            date,
-            attribute,
-            tags,
-            contents: contents_without_heading,
-        })
     }
 
-    pub fn to_line(&self) -> String {
-        let mut contents = format!(
-            "{MARK_HEADING_PREFIX}{}",
-            DateTime::new(&self.date).to_formatted_pretty()
-        );
-        if self.attribute != Attribute::None || !self.tags.is_empty() {
-            contents += "\n";
-            if self.attribute != Attribute::None {
-                contents += "\n";
-                contents += &self.attribute.to_line();
-            }
-            if !self.tags.is_empty() {
-                // TODO: Put all tags on the same line?
-                let mut tags: Vec<&Tag> = self.tags.iter().collect();
-                // Sorts alphabetically.
-                tags.sort_by(|a, b| a.text.cmp(&b.text));
-                contents += &tags
-                    .iter()
-                    .fold(String::new(), |acc, val| acc + "\n" + &val.to_line());
+    /// Same session as [`Aggregator::view`], `None` without a session in [`Config::workspace`].
+    pub(crate) fn status(&self, config: &Config) -> Result<Option<Status>, Box<dyn Error>> {
+        let Some((sessions, i, stale)) = self.get_shown(config) else {
+            return Ok(None);
+        };
+        let session = &sessions[i];
+        let state = if stale {
+            State::Stale
+        } else if session.is_active() {
+            State::Active
+        } else {
+            State::Inactive
+        };
+        let mark = if session.is_active() {
+            session.get_mark_time()
+        } else {
+            Duration::ZERO
+        };
+        let break_due = config
+            .pomodoro
+            .is_some_and(|v| session.get_pomodoro(&DateTime::now(), v) == Pomodoro::BreakDue);
+        Ok(Some(Status {
+            state,
+            start: DateTime::new(&session.start()),
+            session: session
+                .get_time(&config.untracked_tags)?
+                .round(config.rounding),
+            mark,
+            break_due,
+            week: self.get_week_time(&sessions, session, config)?.1,
+            mark_text: session
+                .marks
+                .last()
+                .map(|v| v.contents().to_owned())
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Same session as [`Aggregator::view`], `None` without a session in [`Config::workspace`].
+    pub(crate) fn when(&self, config: &Config) -> Result<Option<When>, Box<dyn Error>> {
+        let Some((sessions, i, _)) = self.get_shown(config) else {
+            return Ok(None);
+        };
+        let session = &sessions[i];
+        let goal_left = match config.weekly_goal {
+            Some(goal) => {
+                let (start_of_week, week) = self.get_week_time(&sessions, session, config)?;
+                let today = Aggregator::get_today_time(&sessions, config)?;
+                let day = (DateTime::now().day(config.day_starts_at)
+                    - start_of_week.day(config.day_starts_at))
+                .num_days()
+                    + 1;
+                day.try_into()
+                    .ok()
+                    .and_then(|v| goal.get_left_today(week, today, v))
             }
+            None => None,
+        };
+        Ok(Some(When {
+            start: DateTime::new(&session.start()),
+            last_mark: DateTime::new(&session.end()),
+            active: session.is_active(),
+            goal_left,
+        }))
+    }
+
+    /// Time tracked today across `sessions`, each rounded.
+    fn get_today_time(sessions: &[Session], config: &Config) -> Result<Duration, String> {
+        let start_of_day = DateTime::now().start_of_day(config.day_starts_at);
+        let end_of_day = start_of_day.plus_days(1);
+        sessions
+            .iter()
+            .map(|v| {
+                v.get_time_within(&start_of_day, &end_of_day, &config.untracked_tags)
+                    .map(|v| v.round(config.rounding))
+            })
+            .sum()
+    }
+
+    /// Index of the newest session of [`Config::workspace`] along with all sessions and whether
+    /// it's stale. A stale session is stopped at its last mark so that the time since doesn't
+    /// count, the file is left as is.
+    fn get_shown(&self, config: &Config) -> Option<(Cow<'_, [Session]>, usize, bool)> {
+        let i = self.get_shown_index(config)?;
+        let stale = config
+            .auto_stop_after
+            .is_some_and(|v| self.sessions[i].is_stale(v));
+        if !stale {
+            return Some((Cow::Borrowed(&self.sessions), i, false));
         }
-        let trimmed = self.contents.trim();
-        if !trimmed.is_empty() {
-            contents += "\n\n";
-            contents += &trimmed;
+        let mut sessions = self.sessions.clone();
+        sessions[i].overwrite_attribute(Attribute::Stop);
+        Some((Cow::Owned(sessions), i, true))
+    }
+
+    fn get_shown_index(&self, config: &Config) -> Option<usize> {
+        let name = config.workspace.as_deref();
+        self.sessions.iter().rposition(|v| v.get_name() == name)
+    }
+
+    /// Start of the week `session` is shown in and the time tracked in it within `sessions`.
+    fn get_week_time(
+        &self,
+        sessions: &[Session],
+        session: &Session,
+        config: &Config,
+    ) -> Result<(DateTime, Duration), String> {
+        if config.week_split {
+            // The week the session reaches into, the current one for an active session.
+            let end = if session.is_active() {
+                DateTime::now()
+            } else {
+                DateTime::new(&session.end())
+            };
+            let start_of_week = end.start_of_week(config.day_starts_at);
+            let end_of_week = start_of_week.plus_days(7);
+            let week_time = sessions
+                .iter()
+                .map(|v| {
+                    v.get_time_within(&start_of_week, &end_of_week, &config.untracked_tags)
+                        .map(|v| v.round(config.rounding))
+                })
+                .sum::<Result<Duration, String>>()?;
+            Ok((start_of_week, week_time))
+        } else {
+            let start_of_week = self.get_last_week_start(config.day_starts_at);
+            let week_time = sessions
+                .iter()
+                .filter(|v| start_of_week <= v.start())
+                .map(|v| {
+                    v.get_time(&config.untracked_tags)
+                        .map(|v| v.round(config.rounding))
+                })
+                .sum::<Result<Duration, String>>()?;
+            Ok((start_of_week, week_time))
         }
-        contents
     }
 }
 
-#[derive(PartialEq, Debug, Clone)]
-pub enum Attribute {
-    Stop,
-    Skip,
-    // TODO: Remove?
-    None,
+/// Malformed session file, points to the offending line.
+#[derive(PartialEq, Debug)]
+pub struct ParseError {
+    pub path: PathBuf,
+    /// 1-based.
+    pub line: usize,
+    /// Text of the offending line, can be empty.
+    pub text: String,
+    pub reason: String,
 }
 
-impl Attribute {
-    pub fn from_text(text: &str) -> Result<Attribute, &'static str> {
-        const ATTRIBUTE_NONE: &str = "none";
-        let attribute = Attribute::from_line(&format!("{LABEL_PREFIX}{text}"));
-        if attribute != Attribute::None || text == ATTRIBUTE_NONE {
-            Ok(attribute)
-        } else {
-            Err("failed to parse attribute")
+impl ParseError {
+    /// Error at the `line` of the text being parsed, see [`ParseError::within`].
+    fn new(line: usize, text: &str, reason: &str) -> ParseError {
+        ParseError {
+            path: PathBuf::new(),
+            line,
+            text: text.to_owned(),
+            reason: reason.to_owned(),
         }
     }
 
-    fn from_line(text: &str) -> Attribute {
-        match text.trim() {
-            LABEL_STOP => Attribute::Stop,
-            LABEL_SKIP => Attribute::Skip,
-            _ => Attribute::None,
-        }
+    /// Moves the error to the file at `path`, where the parsed text starts after `lines_before`
+    /// lines.
+    fn within(mut self, path: &Path, lines_before: usize) -> ParseError {
+        self.path = path.to_path_buf();
+        self.line += lines_before;
+        self
     }
+}
 
-    fn to_line(&self) -> String {
-        match self {
-            Attribute::Stop => LABEL_STOP.to_owned(),
-            Attribute::Skip => LABEL_SKIP.to_owned(),
-            Attribute::None => String::new(),
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.reason)?;
+        if !self.text.is_empty() {
+            write!(f, " `{}`", self.text)?;
         }
+        Ok(())
     }
 }
 
-#[derive(Eq, Hash, PartialEq, Debug, Clone)]
-pub struct Tag {
-    text: String,
+impl Error for ParseError {}
+
+/// Problem found in the sessions directory by [`Aggregator::validate`].
+#[derive(PartialEq, Debug)]
+pub struct Finding {
+    pub path: PathBuf,
+    /// 1-based, `None` if the problem concerns the whole file.
+    pub line: Option<usize>,
+    pub description: String,
+    /// Set for problems that can be fixed without losing anything.
+    pub fix: Option<Fix>,
 }
 
-// TODO: Make from_line use from_text.
-impl Tag {
-    pub fn from_text(text: &str) -> Result<Tag, String> {
-        let text = text.trim();
-        if text.is_empty() {
-            Err("tag cannot be empty")?
-        } else if text.contains(LABEL_TAG_SURROUND) {
-            Err(format!("invalid character \"{LABEL_TAG_SURROUND}\""))
-        } else {
-            Ok(Tag {
-                text: text.to_owned(),
-            })
+impl Finding {
+    fn new(path: &Path, line: Option<usize>, description: &str, fix: Option<Fix>) -> Finding {
+        Finding {
+            path: path.to_path_buf(),
+            line,
+            description: description.to_owned(),
+            fix,
         }
     }
+}
 
-    fn from_line(text: &str) -> Result<Tag, String> {
-        if text.starts_with(&format!("{LABEL_TAG} {LABEL_TAG_SURROUND}"))
-            && text.ends_with(LABEL_TAG_SURROUND)
-        {
-            let start = format!("{LABEL_TAG} {LABEL_TAG_SURROUND}").len();
-            let end = text.len() - LABEL_TAG_SURROUND.len();
-            let tag_text = text[start..end].trim().to_owned();
-            if tag_text.is_empty() {
-                return Err("tag cannot be empty")?;
-            }
-            let tag = Tag { text: tag_text };
-            return Ok(tag);
-        } else {
-            return Err(format!("couldn't parse tag from string '{}'", text));
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.path.display(), self.description),
+            None => write!(f, "{}: {}", self.path.display(), self.description),
         }
     }
+}
 
-    fn to_line(&self) -> String {
-        let text = &self.text;
-        // There shouldn't be a way to store an empty string in here.
-        assert!(!text.is_empty());
-        format!("{LABEL_TAG} {LABEL_TAG_SURROUND}{text}{LABEL_TAG_SURROUND}")
+impl From<ParseError> for Finding {
+    fn from(err: ParseError) -> Finding {
+        let description = if err.text.is_empty() {
+            err.reason
+        } else {
+            format!("{} `{}`", err.reason, err.text)
+        };
+        Finding::new(&err.path, Some(err.line), &description, None)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(PartialEq, Debug)]
+pub enum Fix {
+    /// Saving the session writes the labels in order.
+    Rewrite,
+    Rename,
+}
 
-    use crate::testing;
-    use chrono::Timelike;
+/// Version of the layout of session files, older files are upgraded when read.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum FormatVersion {
+    /// Files written before versions were marked.
+    V1,
+    /// Same layout as [`FormatVersion::V1`], marked with its version.
+    V2,
+}
 
-    fn get_template(date: &str) -> String {
+impl FormatVersion {
+    pub const CURRENT: FormatVersion = FormatVersion::V2;
+
+    fn number(self) -> u32 {
+        match self {
+            FormatVersion::V1 => 1,
+            FormatVersion::V2 => 2,
+        }
+    }
+
+    fn to_marker(self) -> String {
         format!(
-            "\
-            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
-            \n\
-            {MARKS_HEADING}\n\
-            \n\
-            {MARK_HEADING_PREFIX}{date}\
-            "
+            "{FORMAT_MARKER_PREFIX}{}{FORMAT_MARKER_SUFFIX}",
+            self.number()
         )
     }
 
-    #[test]
-    fn aggregator_view_works() {
-        let date_default = testing::date_default();
-        let mark_start = Mark::new(&DateTime::new(&date_default).plus_hours(-2).date);
-        let mark_end = Mark::new(&DateTime::new(&date_default).plus_minutes(-30).date);
-        let mut session_third = Session {
-            path: PathBuf::from("sessions"),
-            marks: vec![mark_start, mark_end.clone()],
+    /// `None` if `line` isn't a version marker, an error for versions newer than
+    /// [`FormatVersion::CURRENT`].
+    fn from_marker(line: &str) -> Option<Result<FormatVersion, String>> {
+        let number = line
+            .trim()
+            .strip_prefix(FORMAT_MARKER_PREFIX)?
+            .strip_suffix(FORMAT_MARKER_SUFFIX)?;
+        let version = match number.parse::<u32>() {
+            Ok(1) => Ok(FormatVersion::V1),
+            Ok(2) => Ok(FormatVersion::V2),
+            _ => Err(format!(
+                "unknown format version `{number}`, the file may have been written by a newer version of time_tracker"
+            )),
         };
-        let start = DateTime::new(&session_third.start()).to_formatted_pretty();
+        Some(version)
+    }
 
-        // Gets ignored because it's not in the current week.
-        let mut session_first = Session {
-            path: session_third.path.clone(),
-            marks: vec![
-                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-12).date),
-                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-9).date),
-            ],
-        };
-        session_first.marks.last_mut().unwrap().attribute = Attribute::Stop;
+    /// The version after this one, `None` for [`FormatVersion::CURRENT`].
+    fn next(self) -> Option<FormatVersion> {
+        match self {
+            FormatVersion::V1 => Some(FormatVersion::V2),
+            FormatVersion::V2 => None,
+        }
+    }
 
-        let mut session_second = Session {
-            path: session_third.path.clone(),
-            marks: vec![
-                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-2).date),
-                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-1).date),
-            ],
-        };
-        session_second.marks.last_mut().unwrap().attribute = Attribute::Stop;
+    /// Brings `contents` written in this version to the current layout one version at a time,
+    /// so that a new version only needs a step from the one before it. The parser only ever
+    /// reads the current layout.
+    fn upgrade(self, contents: &str) -> String {
+        let mut version = self;
+        let mut contents = contents.to_owned();
+        while let Some(next) = version.next() {
+            contents = version.upgrade_step(contents);
+            version = next;
+        }
+        contents
+    }
 
-        let aggregator = Aggregator {
-            sessions: vec![
-                session_first.clone(),
-                session_second.clone(),
-                session_third.clone(),
-            ],
-        };
+    /// Contents of this version in the layout of the next one.
+    fn upgrade_step(self, contents: String) -> String {
+        match self {
+            // Same layout, only the marker is new.
+            FormatVersion::V1 => contents,
+            FormatVersion::V2 => contents,
+        }
+    }
+}
 
-        // Goes up to current time.
-        let output = aggregator.view();
-        let lines: Vec<&str> = output.lines().collect();
-        assert_eq!(lines.len(), 6);
-        assert_eq!(lines[0], format!("Start: {start}"));
-        // Not explicitly checking if it included the current time in the calculation, just
-        // excluding the possibility that it calculated only up to the last mark.
-        assert_ne!(lines[1], "Week: 25h 30m 0s");
-        assert_ne!(lines[2], "Time: 1h 30m 0s");
-        assert_ne!(lines[3], "Mark: 0");
-        assert_eq!(lines[4], COMMAND_VIEW_MARK_CONTENTS_SEPARATOR);
-        assert_eq!(lines[5], mark_end.to_line());
+#[derive(PartialEq, Debug)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    pub contents: String,
+    /// Number of empty lines trimmed from the start of the file.
+    lines_before: usize,
+    pub version: FormatVersion,
+}
 
-        session_third.marks.pop();
-        session_third.mark(&DateTime::now()).unwrap();
-        session_third.set_attribute(Attribute::Stop);
-        let mark = &mut session_third.marks[1];
-        mark.date = mark_end.date;
-        let mark_end = session_third.marks.last().unwrap();
-        let aggregator = Aggregator {
-            sessions: vec![
-                session_first.clone(),
-                session_second.clone(),
-                session_third.clone(),
-            ],
-        };
-        assert_eq!(
-            aggregator.view(),
-            format!(
-                "\
-                No active session, last session:\n\
-                Start: {start}\n\
-                Week: 25h 30m 0s\n\
-                Time: 1h 30m 0s\n\
-                Mark: 0\n\
-                {COMMAND_VIEW_MARK_CONTENTS_SEPARATOR}\n\
-                {}\
-                ",
-                mark_end.to_line()
+impl SessionFile {
+    /// `contents` are stored in the canonical form that is written, `\r\n` line breaks become
+    /// `\n` and the file ends with exactly one line break.
+    // TODO: add checks for path
+    fn build(path: &Path, contents: &str) -> Result<SessionFile, ParseError> {
+        let contents = contents.replace("\r\n", "\n");
+        let contents = contents.as_str();
+        let trimmed = contents.trim();
+        let lines_before = contents[..contents.len() - contents.trim_start().len()]
+            .matches('\n')
+            .count();
+        let first_line = trimmed.lines().next().unwrap_or_default();
+        if !trimmed.starts_with(SESSION_HEADING_PREFIX) {
+            return Err(ParseError::new(
+                1,
+                first_line,
+                "session file must start with a `# ` heading",
             )
-        );
+            .within(path, lines_before));
+        }
+        if !trimmed.lines().any(|v| v.starts_with(MARKS_HEADING)) {
+            return Err(ParseError::new(
+                1,
+                first_line,
+                &format!("missing `{MARKS_HEADING}` heading"),
+            )
+            .within(path, lines_before));
+        }
+        let second_line = trimmed.lines().nth(1).unwrap_or_default();
+        let version = match FormatVersion::from_marker(second_line) {
+            Some(version) => version
+                .map_err(|err| ParseError::new(2, second_line, &err).within(path, lines_before))?,
+            None => FormatVersion::V1,
+        };
+        Ok(SessionFile {
+            path: path.to_path_buf(),
+            contents: format!("{trimmed}\n"),
+            lines_before,
+            version,
+        })
     }
 
-    #[test]
-    fn session_file_build_works() {
-        let path = PathBuf::new();
-        let contents = get_template(&DateTime::now().to_formatted_pretty());
-        let file = SessionFile::build(&path, &contents).unwrap();
-        assert_eq!(&file.contents, &contents);
+    /// Writes the file atomically, the contents go to a temporary file in the same directory
+    /// which then replaces the original. Either the old or the new contents survive a crash.
+    pub fn write(&self) -> io::Result<()> {
+        let name = self.path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "session path has no file name")
+        })?;
+        let dir = self
+            .path
+            .parent()
+            .filter(|v| !v.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let path_tmp = dir.join(format!(".{}.tmp", name.to_string_lossy()));
+        let result = SessionFile::write_durable(&path_tmp, &self.contents)
+            .and_then(|_| fs::rename(&path_tmp, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&path_tmp);
+        }
+        result?;
+        // Makes the rename itself durable.
+        #[cfg(unix)]
+        fs::File::open(dir)?.sync_all()?;
+        Ok(())
+    }
+
+    fn write_durable(path: &Path, contents: &str) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    }
+
+    fn get_heading_with_contents(heading: &str, contents: &str) -> String {
+        let heading_level = SessionFile::get_heading_level(heading);
+        let mut is_within = false;
+        let mut text = String::new();
+        for line in contents.lines() {
+            if line.starts_with("#") && SessionFile::get_heading_level(line) <= heading_level {
+                is_within = false;
+            }
+            if line.starts_with(heading) {
+                is_within = true;
+            }
+            if is_within {
+                text += line.trim();
+                text += "\n";
+            }
+        }
+
+        text.trim().to_string()
+    }
+
+    /// Number of lines in the file before the marks section, which starts at its heading.
+    fn get_marks_before(&self) -> usize {
+        self.lines_before
+            + self
+                .contents
+                .lines()
+                .position(|v| v.starts_with(MARKS_HEADING))
+                .expect("session file must have the marks heading")
+    }
+
+    /// Each mark with the line of its heading, 1-based.
+    fn get_marks_with_lines(&self) -> Vec<(usize, String)> {
+        let marks_before = self.get_marks_before();
+        let marks_contents = SessionFile::get_heading_with_contents(MARKS_HEADING, &self.contents);
+        SessionFile::split_marks(&marks_contents)
+            .into_iter()
+            .map(|(start, v)| (marks_before + start + 1, v))
+            .collect()
+    }
+
+    /// Splits the contents of the marks section into individual marks, each starting with its
+    /// heading. Goes by the position of the headings so that marks with equal headings stay apart.
+    /// Returns each mark with the number of lines that precede it.
+    fn split_marks(contents: &str) -> Vec<(usize, String)> {
+        let mut marks: Vec<(usize, String)> = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if line.starts_with(MARK_HEADING_PREFIX) {
+                marks.push((i, String::new()));
+            }
+            if let Some((_, mark)) = marks.last_mut() {
+                *mark += line;
+                *mark += "\n";
+            }
+        }
+        marks
+            .into_iter()
+            .map(|(i, v)| (i, v.trim().to_owned()))
+            .collect()
+    }
+
+    /// Returns the raw contents before and after the section under `heading`, both trimmed.
+    fn get_surrounding_contents(heading: &str, contents: &str) -> (String, String) {
+        let heading_level = SessionFile::get_heading_level(heading);
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut is_within = false;
+        let mut is_after = false;
+        for line in contents.lines() {
+            if !is_within && !is_after && line.starts_with(heading) {
+                is_within = true;
+                continue;
+            }
+            if is_within
+                && line.starts_with("#")
+                && SessionFile::get_heading_level(line) <= heading_level
+            {
+                is_within = false;
+                is_after = true;
+            }
+            if is_after {
+                after.push(line);
+            } else if !is_within {
+                before.push(line);
+            }
+        }
+        (
+            before.join("\n").trim().to_string(),
+            after.join("\n").trim().to_string(),
+        )
+    }
+
+    fn get_heading_level(heading: &str) -> u8 {
+        let mut level = 0u8;
+        for char in heading.trim().chars() {
+            if char != '#' {
+                break;
+            }
+            level += 1;
+        }
+        level
+    }
+}
+
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Session {
+    pub path: PathBuf,
+    pub marks: Vec<Mark>,
+    /// Custom text of the session heading, `# Session — {title}`.
+    title: Option<String>,
+    /// Tags that apply to every mark of the session, listed right under the session heading.
+    tags: HashSet<Tag>,
+    /// Everything between the session heading and the marks heading as it was in the file.
+    header: String,
+    /// Everything after the marks section as it was in the file.
+    footer: String,
+    /// Written on the marks this session adds, see [`Session::get_host`].
+    host: Option<String>,
+}
+
+impl Session {
+    pub fn new(config: &Config, dt: &DateTime) -> Session {
+        let host = Session::get_host(config);
+        let mark = Mark {
+            host: host.clone(),
+            ..Mark::new(&dt.date)
+        };
+        Session {
+            path: Path::join(
+                &config.sessions_path,
+                Session::get_file_name(&mark, config.workspace.as_deref()),
+            ),
+            marks: vec![mark],
+            host,
+            ..Default::default()
+        }
+    }
+
+    /// Name of this machine when [`Config::record_host`] is set, from `HOSTNAME`, `COMPUTERNAME`
+    /// or `/etc/hostname`. `None` when it can't be determined.
+    fn get_host(config: &Config) -> Option<String> {
+        if !config.record_host {
+            return None;
+        }
+        ["HOSTNAME", "COMPUTERNAME"]
+            .into_iter()
+            .filter_map(|v| env::var(v).ok())
+            .chain(fs::read_to_string("/etc/hostname").ok())
+            .find_map(|v| Mark::parse_value("host", &v).ok())
+    }
+
+    /// Mark at `date` with the host of this session and the context of the last mark.
+    fn new_mark(&self, date: &chrono::DateTime<chrono::Local>) -> Mark {
+        Mark {
+            host: self.host.clone(),
+            context: self.marks.last().and_then(|v| v.context.clone()),
+            ..Mark::new(date)
+        }
+    }
+
+    /// Starts a session at `dt` next to `previous`, in the same workspace and with its title and
+    /// session tags.
+    pub fn derive_from(previous: &Session, dt: &DateTime) -> Session {
+        let mark = Mark {
+            context: None,
+            ..previous.new_mark(&dt.date)
+        };
+        let file_name = Session::get_file_name(&mark, previous.get_name());
+        Session {
+            path: previous
+                .path
+                .parent()
+                .map_or_else(|| PathBuf::from(&file_name), |v| v.join(&file_name)),
+            marks: vec![mark],
+            title: previous.title.clone(),
+            tags: previous.tags.clone(),
+            host: previous.host.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Named after the date of the first mark, in the offset it was written with, followed by the
+    /// workspace `name`.
+    fn get_file_name(mark_first: &Mark, name: Option<&str>) -> String {
+        let name = name.map_or_else(String::new, |v| format!("{SESSION_NAME_SEPARATOR}{v}"));
+        format!(
+            "{}{name}.{SESSION_FILE_EXTENSION}",
+            mark_first.date.format(SESSION_FILE_NAME_FORMAT)
+        )
+    }
+
+    /// Returns the newest session of [`Config::workspace`] that can be read, unreadable ones are
+    /// skipped with a warning.
+    pub fn get_last(config: &Config) -> Result<Option<Session>, Box<dyn Error>> {
+        Session::find_last(config, |_| true)
+    }
+
+    /// Like [`Session::get_last`], but only considers sessions that have ended or are stale.
+    pub fn get_last_stopped(config: &Config) -> Result<Option<Session>, Box<dyn Error>> {
+        Session::find_last(config, |v| {
+            !v.is_active() || config.auto_stop_after.is_some_and(|t| v.is_stale(t))
+        })
+    }
+
+    /// Newest session of [`Config::workspace`] that was in progress at `date`, see
+    /// [`Session::split`].
+    pub fn get_containing(
+        config: &Config,
+        date: &DateTime,
+    ) -> Result<Option<Session>, Box<dyn Error>> {
+        Session::find_last(config, |v| v.contains(date))
+    }
+
+    /// Newest other session of [`Config::workspace`] that started before this one.
+    pub fn get_preceding(&self, config: &Config) -> Result<Option<Session>, Box<dyn Error>> {
+        Session::find_last(config, |v| v.path != self.path && v.start() < self.start())
+    }
+
+    /// Whether `date` is after the start and before the end, the current time for active
+    /// sessions.
+    fn contains(&self, date: &DateTime) -> bool {
+        let end = if self.is_active() {
+            DateTime::now().date
+        } else {
+            self.end()
+        };
+        self.start() < date.date && date.date < end
+    }
+
+    fn find_last(
+        config: &Config,
+        predicate: impl Fn(&Session) -> bool,
+    ) -> Result<Option<Session>, Box<dyn Error>> {
+        let dir = Aggregator::read_sessions_dir(config)?;
+        let name = config.workspace.as_deref();
+        for path in dir
+            .iter()
+            .rev()
+            .filter(|v| Session::name_from_path(v) == name)
+        {
+            match Session::from_path(path) {
+                Ok(mut session) => {
+                    if !predicate(&session) {
+                        continue;
+                    }
+                    session.normalize_tags(&config.tag_normalizer);
+                    session.host = Session::get_host(config);
+                    return Ok(Some(session));
+                }
+                Err(err) => eprintln!("Warning: skipping {err}"),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Start of the session parsed from the file name, see [`Session::get_file_name`]. `None` for
+    /// hidden files and files not named like sessions.
+    fn date_from_path(path: &Path) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        if path.extension()? != SESSION_FILE_EXTENSION {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        if stem.starts_with('.') {
+            return None;
+        }
+        let stem = stem
+            .split_once(SESSION_NAME_SEPARATOR)
+            .map_or(stem, |(date, _)| date);
+        [SESSION_FILE_NAME_FORMAT, SESSION_FILE_NAME_FORMAT_LEGACY]
+            .iter()
+            .find_map(|v| chrono::DateTime::parse_from_str(stem, v).ok())
+    }
+
+    /// Workspace the file belongs to, see [`Session::get_file_name`]. `None` for the default
+    /// workspace.
+    pub fn name_from_path(path: &Path) -> Option<&str> {
+        path.file_stem()?
+            .to_str()?
+            .split_once(SESSION_NAME_SEPARATOR)
+            .map(|(_, name)| name)
+    }
+
+    /// See [`Session::name_from_path`].
+    pub fn get_name(&self) -> Option<&str> {
+        Session::name_from_path(&self.path)
+    }
+
+    /// Errors mention `path`.
+    pub fn from_path(path: &Path) -> Result<Session, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("{}: couldn't read session file: {err}", path.display()))?;
+        let file = SessionFile::build(path, &contents)?;
+        let session = Session::from_file(&file)?;
+        Ok(session)
+    }
+
+    /// Reads the files on up to [`PARSE_THREADS_MAX`] threads, see [`Session::from_path`].
+    /// Results are in the order of `paths`.
+    pub fn from_paths(paths: &[PathBuf]) -> Vec<Result<Session, Box<dyn Error>>> {
+        let threads = thread::available_parallelism()
+            .map_or(1, |v| v.get())
+            .min(PARSE_THREADS_MAX);
+        let chunk_size = paths.len().div_ceil(threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    // Errors aren't `Send`, only their messages cross the threads.
+                    scope.spawn(|| {
+                        chunk
+                            .iter()
+                            .map(|path| Session::from_path(path).map_err(|err| err.to_string()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("parsing thread must not panic"))
+                .map(|session| session.map_err(Box::from))
+                .collect()
+        })
+    }
+
+    pub fn start(&self) -> chrono::DateTime<chrono::Local> {
+        self.marks
+            .first()
+            .expect("session must have at least one mark")
+            .local_date()
+    }
+
+    pub fn end(&self) -> chrono::DateTime<chrono::Local> {
+        self.marks
+            .last()
+            .expect("session must have at least one mark")
+            .local_date()
+    }
+
+    /// Whether the session holds nothing worth keeping, either a single mark without contents or
+    /// tags, or no tracked time at all. Active sessions can be empty too.
+    pub fn is_empty(&self) -> bool {
+        let mark = &self.marks[0];
+        let is_bare = self.marks.len() == 1
+            && self.title.is_none()
+            && self.tags.is_empty()
+            && self.header.trim().is_empty()
+            && mark.tags.is_empty()
+            && mark.contents.trim().is_empty();
+        is_bare || self.get_time(&[]).is_ok_and(|v| v == Duration::ZERO)
+    }
+
+    /// Moves the file to `<sessions_path>/.trash/`, returns its new path. A file of the same name
+    /// already in the trash is replaced.
+    pub fn trash(&self) -> io::Result<PathBuf> {
+        let dir = self.path.parent().unwrap_or(Path::new("")).join(TRASH_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(self.path.file_name().unwrap_or_default());
+        fs::rename(&self.path, &path)?;
+        Ok(path)
+    }
+
+    /// Moves the file to `<sessions_path>/archive/<year of the start>/`, returns its new path.
+    pub fn archive(&self) -> io::Result<PathBuf> {
+        let dir = self
+            .path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(ARCHIVE_DIR)
+            .join(self.start().format("%Y").to_string());
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(self.path.file_name().unwrap_or_default());
+        if fs::exists(&path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            ));
+        }
+        fs::rename(&self.path, &path)?;
+        Ok(path)
+    }
+
+    /// A session is active until its last mark has [`Attribute::Stop`], a paused session is still
+    /// active. The stop mark of an ended session can't be changed, see [`Session::set_attribute`].
+    pub fn is_active(&self) -> bool {
+        self.marks
+            .last()
+            .expect("must always have at least one mark")
+            .attribute
+            != Attribute::Stop
+    }
+
+    /// Returns the index of each mark that isn't strictly after the preceding one, with a
+    /// description of the problem.
+    fn check_marks(&self) -> Vec<(usize, String)> {
+        let mut problems = Vec::new();
+        for (i, pair) in self.marks.windows(2).enumerate() {
+            let preceding = DateTime::new(&pair[0].local_date()).to_formatted_pretty();
+            if pair[1].date == pair[0].date {
+                problems.push((
+                    i + 1,
+                    format!("mark has the same time as the previous mark at {preceding}"),
+                ));
+            } else if pair[1].date < pair[0].date {
+                problems.push((
+                    i + 1,
+                    format!("mark is before the previous mark at {preceding}"),
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Whether the file is named after the first mark, in either the current or the legacy
+    /// format.
+    fn has_matching_file_name(&self) -> bool {
+        Session::date_from_path(&self.path).is_some_and(|v| v == self.marks[0].date)
+    }
+
+    /// Time since the last mark, zero when the mark is ahead of the current time.
+    fn get_mark_time(&self) -> Duration {
+        self.time_in_current_mark(&DateTime::now())
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "Warning: last mark is ahead of the current time, check the system clock"
+                );
+                Duration::ZERO
+            })
+    }
+
+    /// Time from the last mark to `now`, zero once the session has ended. `None` when the mark is
+    /// ahead of `now`.
+    pub fn time_in_current_mark(&self, now: &DateTime) -> Option<Duration> {
+        if !self.is_active() {
+            return Some(Duration::ZERO);
+        }
+        let mark_last = self
+            .marks
+            .last()
+            .expect("session must always have at least one mark");
+        Duration::between(&mark_last.local_date(), &now.date).ok()
+    }
+
+    /// Compares the time in the current mark with a work interval of `length`, see
+    /// [`Config::pomodoro`].
+    pub fn get_pomodoro(&self, now: &DateTime, length: Duration) -> Pomodoro {
+        if !self.is_active() {
+            return Pomodoro::Inactive;
+        } else if self.is_paused() {
+            return Pomodoro::Break;
+        }
+        let time = self.time_in_current_mark(now).unwrap_or(Duration::ZERO);
+        if time > length {
+            Pomodoro::BreakDue
+        } else {
+            Pomodoro::Work {
+                left: Duration::from_millis(length.as_millis() - time.as_millis()),
+            }
+        }
+    }
+
+    /// Tracked time, skipped intervals are left out. The last interval of an active session goes
+    /// up to the current time.
+    pub fn duration(&self) -> Result<Duration, String> {
+        self.get_time(&[])
+    }
+
+    /// Returns error if the marks are not in chronological order. See [`Session::is_tracked`] for
+    /// `untracked`.
+    fn get_time(&self, untracked: &[Tag]) -> Result<Duration, String> {
+        Ok(self
+            .get_intervals(untracked)?
+            .into_iter()
+            .map(|(_, time)| time)
+            .sum())
+    }
+
+    /// Tags of the session and of its marks, each once, sorted alphabetically.
+    fn get_all_tags(&self) -> Vec<&Tag> {
+        let mut tags: Vec<&Tag> = self
+            .tags
+            .iter()
+            .chain(self.marks.iter().flat_map(|v| &v.tags))
+            .collect();
+        tags.sort_by(|a, b| a.text.cmp(&b.text));
+        tags.dedup();
+        tags
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Tags that apply to the whole session, sorted alphabetically.
+    pub fn get_session_tags(&self) -> Vec<&Tag> {
+        let mut tags: Vec<&Tag> = self.tags.iter().collect();
+        tags.sort_by(|a, b| a.text.cmp(&b.text));
+        tags
+    }
+
+    /// Time of intervals within `start..end`, intervals crossing either bound are split at it.
+    fn get_time_within(
+        &self,
+        start: &DateTime,
+        end: &DateTime,
+        untracked: &[Tag],
+    ) -> Result<Duration, String> {
+        let mut time = Duration::ZERO;
+        for (mark, interval) in self.get_intervals(untracked)? {
+            let interval_start = mark.local_date();
+            let interval_end =
+                interval_start + chrono::TimeDelta::milliseconds(interval.as_millis() as i64);
+            // Intervals outside of the bounds end before they start.
+            let within =
+                Duration::between(&interval_start.max(start.date), &interval_end.min(end.date));
+            time += within.unwrap_or(Duration::ZERO);
+        }
+        Ok(time)
+    }
+
+    /// Totals of the session, the end of an active session is its last mark. Fails if the marks
+    /// are out of order. See [`Session::is_tracked`] for `untracked`.
+    pub(crate) fn summarize(&self, untracked: &[Tag]) -> Result<Summary, String> {
+        let intervals = self
+            .get_intervals(untracked)?
+            .into_iter()
+            .map(|(mark, time)| (DateTime::new(&mark.local_date()), time))
+            .collect();
+        let mut tags: Vec<(Tag, usize)> = Vec::new();
+        for tag in self
+            .tags
+            .iter()
+            .chain(self.marks.iter().flat_map(|v| &v.tags))
+        {
+            match tags.iter_mut().find(|(v, _)| v == tag) {
+                Some((_, count)) => *count += 1,
+                None => tags.push((tag.to_owned(), 1)),
+            }
+        }
+        tags.sort_by(|a, b| a.0.text.cmp(&b.0.text));
+        Ok(Summary {
+            path: self.path.clone(),
+            start: DateTime::new(&self.start()),
+            end: DateTime::new(&self.end()),
+            time: self.get_time(untracked)?,
+            break_time: self.get_break_time(untracked)?,
+            marks: self.marks.len(),
+            intervals,
+            tags,
+        })
+    }
+
+    /// Time of intervals that don't count towards the session time, counted up to now when the
+    /// session is on a break.
+    pub fn get_break_time(&self, untracked: &[Tag]) -> Result<Duration, String> {
+        Ok(self
+            .get_all_intervals()?
+            .into_iter()
+            .filter(|(mark, _)| !self.is_tracked(mark, untracked))
+            .map(|(_, time)| time)
+            .sum())
+    }
+
+    /// Intervals that count towards the session time, see [`Session::get_all_intervals`].
+    fn get_intervals(&self, untracked: &[Tag]) -> Result<Vec<(&Mark, Duration)>, String> {
+        Ok(self
+            .get_all_intervals()?
+            .into_iter()
+            .filter(|(mark, _)| self.is_tracked(mark, untracked))
+            .collect())
+    }
+
+    /// Intervals started by skipped marks don't count towards the session time, neither do ones
+    /// with any of the `untracked` tags, either on the session or on the mark.
+    fn is_tracked(&self, mark: &Mark, untracked: &[Tag]) -> bool {
+        mark.attribute != Attribute::Skip
+            && !untracked
+                .iter()
+                .any(|v| self.tags.contains(v) || mark.tags.contains(v))
+    }
+
+    /// Intervals between the marks, including skipped ones, each with the mark that starts it.
+    /// The last interval of an active session goes up to the current time.
+    fn get_all_intervals(&self) -> Result<Vec<(&Mark, Duration)>, String> {
+        let out_of_order = |err| {
+            format!(
+                "marks are out of order in session `{}`: {err}",
+                self.path.display()
+            )
+        };
+        let mut intervals = Vec::new();
+        let mut mark_preceding = &self.marks[0];
+        for mark in self.marks.iter().skip(1) {
+            let time = Duration::between(&mark_preceding.local_date(), &mark.local_date())
+                .map_err(out_of_order)?;
+            intervals.push((mark_preceding, time));
+            mark_preceding = mark;
+        }
+        if self.is_active() {
+            // A mark ahead of the current time, placed with `--future` or due to clock skew, has
+            // no time yet.
+            let time = Duration::between(&mark_preceding.local_date(), &DateTime::now().date)
+                .unwrap_or(Duration::ZERO);
+            intervals.push((mark_preceding, time));
+        }
+        Ok(intervals)
+    }
+
+    /// See [`Session::check_mark_date`] for `allow_future`.
+    pub fn mark(&mut self, dt: &DateTime, allow_future: bool) -> Result<(), String> {
+        if !self.is_active() {
+            return Err("can't mark, session has already ended")?;
+        }
+        Session::check_mark_date(self.marks.last(), dt, allow_future)?;
+        let mark = self.new_mark(&dt.date);
+        self.marks.push(mark);
+        Ok(())
+    }
+
+    /// Starts a break with a skipped mark at `dt`, see [`Session::mark`].
+    pub fn pause(&mut self, dt: &DateTime) -> Result<(), String> {
+        if self.is_paused() {
+            return Err("session is already paused")?;
+        }
+        self.mark(dt, false)?;
+        self.set_attribute(Attribute::Skip)?;
+        Ok(())
+    }
+
+    /// Ends the break started by [`Session::pause`] with a mark at `dt`, returns how long the
+    /// break was.
+    pub fn unpause(&mut self, dt: &DateTime) -> Result<Duration, String> {
+        if !self.is_active() {
+            return Err("can't unpause, session has already ended")?;
+        }
+        if !self.is_paused() {
+            return Err("session isn't paused")?;
+        }
+        self.mark(dt, false)?;
+        let start = self.marks[self.marks.len() - 2].local_date();
+        Duration::between(&start, &dt.date)
+    }
+
+    /// Splits the interval that contains `start..end` with a skipped mark at `start` and a mark
+    /// at `end` that resumes tracking. The end mark is left out when a mark is already at `end`.
+    pub fn insert_idle(&mut self, start: &DateTime, end: &DateTime) -> Result<(), String> {
+        if start.date >= end.date {
+            return Err("idle must end after it starts")?;
+        }
+        if end.date > DateTime::now().date {
+            return Err("idle can't end in the future")?;
+        }
+        let Some(i) = self.marks.iter().rposition(|v| v.date < end.date) else {
+            return Err("idle must end after the session starts")?;
+        };
+        if i == self.marks.len() - 1 && !self.is_active() {
+            return Err("can't add idle time, session has already ended")?;
+        }
+        if self.marks[i].attribute == Attribute::Skip {
+            return Err("idle falls into time that is already skipped")?;
+        }
+        let preceding = DateTime::new(&self.marks[i].local_date());
+        if start.date <= preceding.date {
+            let max = Duration::between(&preceding.date, &end.date)?;
+            return Err(format!(
+                "idle can't start before the previous mark at {}, it can be less than {} long",
+                preceding.to_formatted_pretty(),
+                max.format(&DurationFormat::Hms)
+            ));
+        }
+        let context = self.marks[i].context.clone();
+        let mut idle = self.new_mark(&start.date);
+        idle.attribute = Attribute::Skip;
+        idle.context = context.clone();
+        self.marks.insert(i + 1, idle);
+        if self.marks.get(i + 2).is_none_or(|v| v.date != end.date) {
+            let mut resume = self.new_mark(&end.date);
+            resume.context = context;
+            self.marks.insert(i + 2, resume);
+        }
+        Ok(())
+    }
+
+    /// Active session whose last mark is more than `threshold` old, most likely left running by
+    /// mistake.
+    pub fn is_stale(&self, threshold: Duration) -> bool {
+        let Some(mark_last) = self.marks.last() else {
+            return false;
+        };
+        self.is_active()
+            && Duration::between(&mark_last.local_date(), &DateTime::now().date)
+                .is_ok_and(|v| v > threshold)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.marks
+            .last()
+            .is_some_and(|v| v.attribute == Attribute::Skip)
+    }
+
+    /// When the session has only one mark, this moves the start of the session.
+    pub fn remark(&mut self, dt: &DateTime, allow_future: bool) -> Result<(), String> {
+        let preceding = self.marks.len().checked_sub(2).map(|i| &self.marks[i]);
+        Session::check_mark_date(preceding, dt, allow_future)?;
+        let mark = self
+            .marks
+            .last_mut()
+            .expect("session must always have at least one mark");
+        mark.date = dt.date.fixed_offset();
+        Ok(())
+    }
+
+    /// Moves the start of the session, it must stay before the second mark. When the session has
+    /// only one mark, this is the same as [`Session::remark`].
+    pub fn remark_first(&mut self, dt: &DateTime, allow_future: bool) -> Result<(), String> {
+        if let Some(following) = self.marks.get(1) {
+            if dt.date >= following.date {
+                return Err(format!(
+                    "start must be before the next mark at {}",
+                    DateTime::new(&following.local_date()).to_formatted_pretty()
+                ));
+            }
+        }
+        Session::check_mark_date(None, dt, allow_future)?;
+        self.marks[0].date = dt.date.fixed_offset();
+        Ok(())
+    }
+
+    /// Mark date must be strictly after the date of the `preceding` mark and, unless
+    /// `allow_future` is set, at most [`MARK_FUTURE_TOLERANCE`] ahead of the current time.
+    fn check_mark_date(
+        preceding: Option<&Mark>,
+        dt: &DateTime,
+        allow_future: bool,
+    ) -> Result<(), String> {
+        if let Some(preceding) = preceding {
+            if dt.date <= preceding.date {
+                return Err(format!(
+                    "mark must be after the previous mark at {}",
+                    DateTime::new(&preceding.local_date()).to_formatted_pretty()
+                ));
+            }
+        }
+        let now = DateTime::now().date;
+        let is_future =
+            Duration::between(&now, &dt.date).is_ok_and(|time| time > MARK_FUTURE_TOLERANCE);
+        if is_future && !allow_future {
+            return Err(format!(
+                "mark at {} is more than {} in the future, pass `--future` to allow it",
+                dt.to_formatted_pretty(),
+                MARK_FUTURE_TOLERANCE.format(&DurationFormat::Hms)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Removes the selected mark, its interval becomes part of the previous one. The first mark
+    /// can't be removed, a stop mark only with `force` as that makes the session active again.
+    pub fn unmark(&mut self, selector: &MarkSelector, force: bool) -> Result<Mark, String> {
+        let i = self.get_mark_index(selector)?;
+        if i == 0 {
+            return Err(String::from("the first mark can't be removed"));
+        }
+        if self.marks[i].attribute == Attribute::Stop && !force {
+            return Err(String::from(
+                "removing the stop mark makes the session active again, pass --force to remove it",
+            ));
+        }
+        Ok(self.marks.remove(i))
+    }
+
+    /// Appends the marks of `later`, which has to start at or after the end of this stopped
+    /// session. The stop mark becomes a mark with `gap` spanning the time in between, it's
+    /// combined with the first mark of `later` when there is none. Session tags are combined,
+    /// the title and the surrounding text of this session are kept when present.
+    pub fn merge(&mut self, later: &Session, gap: Attribute) -> Result<(), String> {
+        if self.get_name() != later.get_name() {
+            return Err(String::from("sessions are in different workspaces"));
+        }
+        if self.is_active() {
+            return Err(String::from("the earlier session is still active"));
+        }
+        let mut marks = later.marks.iter().cloned();
+        let first = marks.next().expect("session must have at least one mark");
+        let stop = self
+            .marks
+            .last_mut()
+            .expect("session must have at least one mark");
+        if first.local_date() < stop.local_date() {
+            return Err(String::from("sessions overlap"));
+        }
+        if first.local_date() == stop.local_date() {
+            stop.attribute = first.attribute;
+            stop.billable = first.billable;
+            stop.tags.extend(first.tags);
+            if stop.contents.is_empty() || first.contents.is_empty() {
+                stop.contents += &first.contents;
+            } else {
+                stop.contents = format!("{}\n\n{}", stop.contents, first.contents);
+            }
+        } else {
+            stop.attribute = gap;
+            self.marks.push(first);
+        }
+        self.marks.extend(marks);
+        self.tags.extend(later.tags.iter().cloned());
+        if self.title.is_none() {
+            self.title = later.title.clone();
+        }
+        for (text, text_later) in [
+            (&mut self.header, &later.header),
+            (&mut self.footer, &later.footer),
+        ] {
+            if text.is_empty() {
+                *text = text_later.clone();
+            } else if !text_later.is_empty() && text != text_later {
+                *text = format!("{text}\n\n{text_later}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Ends the session with a stop mark at `date` and returns the session that continues from
+    /// there, with the same title and session tags. A mark in progress at `date` is repeated at
+    /// the start of the new session along with its tags and contents.
+    pub fn split(&mut self, date: &DateTime) -> Result<Session, String> {
+        if !self.contains(date) {
+            return Err(format!(
+                "session doesn't contain {}",
+                date.to_formatted_time()
+            ));
+        }
+        let i = self
+            .marks
+            .iter()
+            .rposition(|v| v.local_date() <= date.date)
+            .expect("session starts before `date`");
+        let marks = if self.marks[i].local_date() == date.date {
+            self.marks.split_off(i)
+        } else {
+            let mut mark = self.marks[i].clone();
+            mark.date = date.date.fixed_offset();
+            let mut marks = self.marks.split_off(i + 1);
+            marks.insert(0, mark);
+            marks
+        };
+        let mut stop = self.new_mark(&date.date);
+        stop.attribute = Attribute::Stop;
+        self.marks.push(stop);
+        let mut session = Session::derive_from(self, date);
+        session.marks = marks;
+        Ok(session)
+    }
+
+    /// Sets the attribute of the last mark. Returns error when it would replace
+    /// [`Attribute::Stop`], which would reopen the session, see [`Session::overwrite_attribute`].
+    pub fn set_attribute(&mut self, attribute: Attribute) -> Result<(), String> {
+        if !self.is_active() && attribute != Attribute::Stop {
+            return Err("session has already ended, setting the attribute would reopen it")?;
+        }
+        self.overwrite_attribute(attribute);
+        Ok(())
+    }
+
+    /// Like [`Session::set_attribute`], but also replaces [`Attribute::Stop`].
+    pub fn overwrite_attribute(&mut self, attribute: Attribute) {
+        self.marks
+            .last_mut()
+            .expect("session must always have at least one mark")
+            .attribute = attribute;
+    }
+
+    /// Flips whether the last mark is billable, returns the new state.
+    pub fn toggle_billable(&mut self) -> Result<bool, String> {
+        let mark = self.get_mark_mut(&MarkSelector::Last)?;
+        mark.billable = !mark.billable;
+        Ok(mark.billable)
+    }
+
+    /// Sets the context of the last mark, the marks added after it keep it, see
+    /// [`Mark::context`]. Returns the context it had.
+    pub fn set_context(&mut self, context: Option<&str>) -> Result<Option<String>, String> {
+        let context = context
+            .map(|v| Mark::parse_value("context", v))
+            .transpose()?;
+        let mark = self.get_mark_mut(&MarkSelector::Last)?;
+        Ok(std::mem::replace(&mut mark.context, context))
+    }
+
+    pub fn tag(&mut self, tag: &Tag, selector: &MarkSelector) -> Result<bool, String> {
+        Ok(self.get_mark_mut(selector)?.tags.insert(tag.to_owned()))
+    }
+
+    /// Replaces `from` with `to` on the session and on each of its marks, returns how many
+    /// times it was replaced.
+    pub fn retag(&mut self, from: &Tag, to: &Tag) -> usize {
+        self.marks
+            .iter_mut()
+            .map(|v| &mut v.tags)
+            .chain([&mut self.tags])
+            .filter_map(|tags| tags.remove(from).then(|| tags.insert(to.to_owned())))
+            .count()
+    }
+
+    /// Replaces every tag with its canonical form, see [`TagNormalizer::apply`].
+    pub fn normalize_tags(&mut self, normalizer: &TagNormalizer) {
+        if normalizer.is_identity() {
+            return;
+        }
+        for tags in self
+            .marks
+            .iter_mut()
+            .map(|v| &mut v.tags)
+            .chain([&mut self.tags])
+        {
+            *tags = tags.iter().map(|v| normalizer.apply(v)).collect();
+        }
+    }
+
+    pub fn tag_session(&mut self, tag: &Tag) -> bool {
+        self.tags.insert(tag.to_owned())
+    }
+
+    pub fn untag_session(&mut self, tag: &Tag) -> bool {
+        self.tags.remove(tag)
+    }
+
+    pub fn untag(&mut self, tag: &Tag, selector: &MarkSelector) -> Result<bool, String> {
+        Ok(self.get_mark_mut(selector)?.tags.remove(tag))
+    }
+
+    /// Returns error if `selector` matches no mark or, for [`MarkSelector::At`], more than one, and
+    /// if it matches the stop mark of an ended session.
+    fn get_mark_mut(&mut self, selector: &MarkSelector) -> Result<&mut Mark, String> {
+        let i = self.get_mark_index(selector)?;
+        if i == self.marks.len() - 1 && !self.is_active() {
+            return Err("session has already ended, its last mark can't be changed")?;
+        }
+        Ok(&mut self.marks[i])
+    }
+
+    /// See [`Session::get_mark_mut`], without the check of the stop mark.
+    fn get_mark_index(&self, selector: &MarkSelector) -> Result<usize, String> {
+        let count = self.marks.len();
+        let i = match selector {
+            MarkSelector::Last => count - 1,
+            MarkSelector::Index(index) => {
+                let i = match *index {
+                    i if i > 0 => usize::try_from(i - 1).ok(),
+                    i if i < 0 => count.checked_sub(i.unsigned_abs() as usize),
+                    _ => None,
+                };
+                i.filter(|v| *v < count).ok_or_else(|| {
+                    format!("mark {index} is out of range, the session has {count} marks")
+                })?
+            }
+            MarkSelector::At(dt) => {
+                let end = dt.plus_minutes(1);
+                let matches: Vec<usize> = self
+                    .marks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| dt.date <= v.date && v.date < end.date)
+                    .map(|(i, _)| i)
+                    .collect();
+                match matches[..] {
+                    [i] => i,
+                    [] => return Err(format!("no mark at {}", dt.to_formatted_pretty())),
+                    _ => {
+                        return Err(format!(
+                            "{} marks at {}, select one with `--mark`",
+                            matches.len(),
+                            dt.to_formatted_pretty()
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(i)
+    }
+
+    /// Returns error if the content of the current mark is not empty or the session has ended.
+    pub fn write(&mut self, text: &str) -> Result<(), String> {
+        if !self.is_active() {
+            return Err("can't write, session has already ended")?;
+        }
+        self.write_at(&WriteTarget::Mark(MarkSelector::Last), text, WriteMode::New)
+    }
+
+    /// Writes `text` into the notes of the session or the content of a mark, see [`WriteMode`] for
+    /// what happens to the text already there.
+    pub fn write_at(
+        &mut self,
+        target: &WriteTarget,
+        text: &str,
+        mode: WriteMode,
+    ) -> Result<(), String> {
+        if let WriteMode::Title { force } = mode {
+            let WriteTarget::Mark(selector) = target else {
+                return Err(String::from("only marks have titles"));
+            };
+            let mark = self.get_mark_mut(selector)?;
+            if mark.is_encrypted() {
+                Err("mark contents are encrypted, decrypt them first")?
+            }
+            return mark.set_title(text, force);
+        }
+        let current = self.text_at(target)?;
+        let contents = match mode {
+            WriteMode::New if !current.is_empty() => match target {
+                WriteTarget::Session => Err("session already has notes")?,
+                WriteTarget::Mark(_) => Err("mark already has contents")?,
+            },
+            WriteMode::Append if !current.is_empty() => format!("{current}\n\n{}", text.trim()),
+            _ => text.trim().to_owned(),
+        };
+        match target {
+            WriteTarget::Session => self.header = contents,
+            WriteTarget::Mark(selector) => self.get_mark_mut(selector)?.write(&contents),
+        }
+        Ok(())
+    }
+
+    /// Text [`Session::write_at`] would write into.
+    pub fn text_at(&self, target: &WriteTarget) -> Result<&str, String> {
+        match target {
+            WriteTarget::Session => Ok(&self.header),
+            WriteTarget::Mark(selector) => {
+                let mark = &self.marks[self.get_mark_index(selector)?];
+                if mark.is_encrypted() {
+                    Err("mark contents are encrypted, decrypt them first")?
+                }
+                Ok(&mark.contents)
+            }
+        }
+    }
+
+    /// Whether `target` is a mark with encrypted contents, see [`Session::decrypt_at`].
+    pub fn is_encrypted_at(&self, target: &WriteTarget) -> bool {
+        match target {
+            WriteTarget::Session => false,
+            WriteTarget::Mark(selector) => self
+                .get_mark_index(selector)
+                .is_ok_and(|i| self.marks[i].is_encrypted()),
+        }
+    }
+
+    /// Decrypts the contents of the mark `target` selects, see [`Mark::decrypt`].
+    pub fn decrypt_at(&mut self, target: &WriteTarget, cipher: &Cipher) -> Result<(), String> {
+        match target {
+            WriteTarget::Session => Ok(()),
+            WriteTarget::Mark(selector) => {
+                let i = self.get_mark_index(selector)?;
+                self.marks[i].decrypt(cipher)
+            }
+        }
+    }
+
+    /// Encrypts the contents of the mark `target` selects, see [`Mark::encrypt`].
+    pub fn encrypt_at(&mut self, target: &WriteTarget, cipher: &Cipher) -> Result<(), String> {
+        match target {
+            WriteTarget::Session => Ok(()),
+            WriteTarget::Mark(selector) => {
+                let i = self.get_mark_index(selector)?;
+                self.marks[i].encrypt(cipher)
+            }
+        }
+    }
+
+    /// Whether any mark has contents written in plain text, see [`Session::encrypt_contents`].
+    pub fn has_plain_contents(&self) -> bool {
+        self.marks.iter().any(|v| !v.contents.trim().is_empty())
+    }
+
+    /// Encrypts the contents of every mark that has them in plain text, see [`Mark::encrypt`].
+    pub fn encrypt_contents(&mut self, cipher: &Cipher) -> Result<(), String> {
+        self.marks.iter_mut().try_for_each(|v| v.encrypt(cipher))
+    }
+
+    /// Titles must be a single line without `#`, surrounding whitespace is removed.
+    pub fn parse_title(text: &str) -> Result<String, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            Err("title cannot be empty")?
+        } else if text.contains('#') || text.contains('\n') {
+            Err("title cannot contain `#` or line breaks")?
+        }
+        Ok(text.to_owned())
+    }
+
+    /// See [`Session::parse_title`].
+    pub fn set_title(&mut self, text: &str) -> Result<(), String> {
+        self.title = Some(Session::parse_title(text)?);
+        Ok(())
+    }
+
+    /// Accepts both `# Session` and `# Session — {title}`, any other heading is taken as the
+    /// title as a whole.
+    fn title_from_line(line: &str) -> Option<String> {
+        let text = line.trim().strip_prefix(SESSION_HEADING_PREFIX)?.trim();
+        if text == SESSION_TITLE {
+            return None;
+        }
+        let title = text
+            .strip_prefix(&format!("{SESSION_TITLE}{SESSION_TITLE_SEPARATOR}"))
+            .unwrap_or(text)
+            .trim();
+        Some(title.to_owned()).filter(|v| !v.is_empty())
+    }
+
+    /// Moves the session file so that its name matches the start of the session, returns whether
+    /// it was moved. Fails if another file already has that name. Files named in the legacy
+    /// format are only moved when their date doesn't match.
+    pub fn rename_to_start(&mut self) -> Result<bool, Box<dyn Error>> {
+        let mark_first = self
+            .marks
+            .first()
+            .expect("session must have at least one mark");
+        if self.has_matching_file_name() {
+            return Ok(false);
+        }
+        let file_name = Session::get_file_name(mark_first, self.get_name());
+        let path = self
+            .path
+            .parent()
+            .map_or_else(|| PathBuf::from(&file_name), |v| v.join(&file_name));
+        if path == self.path {
+            return Ok(false);
+        }
+        if fs::exists(&path)? {
+            return Err(format!(
+                "can't rename session file, `{}` already exists",
+                path.display()
+            ))?;
+        }
+        fs::rename(&self.path, &path).map_err(|e| format!("couldn't rename session file: {e}"))?;
+        // Backups follow the file so that they are still found for the current session.
+        let backups_dir = Backup::get_dir(&self.path);
+        if fs::exists(&backups_dir)? {
+            fs::rename(&backups_dir, Backup::get_dir(&path))
+                .map_err(|e| format!("couldn't move session backups: {e}"))?;
+        }
+        self.path = path;
+        Ok(true)
+    }
+
+    /// Changes from `before` to this session. Marks are matched by their date, the ones left are
+    /// paired in order as moved marks, like after `remark`.
+    pub fn diff(&self, before: &Session) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if self.title != before.title {
+            changes.push(Change::Title(self.title.clone()));
+        }
+        if self.header != before.header || self.footer != before.footer {
+            changes.push(Change::Notes);
+        }
+        Change::push_tags(&mut changes, None, &before.tags, &self.tags);
+
+        let find = |marks: &'_ [Mark], date| marks.iter().position(|v: &Mark| v.date == date);
+        let removed: Vec<&Mark> = before
+            .marks
+            .iter()
+            .filter(|v| find(&self.marks, v.date).is_none())
+            .collect();
+        let added: Vec<&Mark> = self
+            .marks
+            .iter()
+            .filter(|v| find(&before.marks, v.date).is_none())
+            .collect();
+        let moved = removed.len().min(added.len());
+        for (from, to) in removed.iter().zip(&added) {
+            changes.push(Change::MarkMoved {
+                from: from.date(),
+                to: to.date(),
+            });
+            Change::push_mark(&mut changes, from, to);
+        }
+        for mark in &removed[moved..] {
+            changes.push(Change::MarkRemoved(mark.date()));
+        }
+        for mark in &added[moved..] {
+            changes.push(Change::MarkAdded(mark.date()));
+            // Compared with an empty mark, so that the text and tags it was added with are shown.
+            let empty = Mark {
+                date: mark.date,
+                ..Mark::new(&chrono::Local::now())
+            };
+            Change::push_mark(&mut changes, &empty, mark);
+        }
+        for mark in &self.marks {
+            if let Some(i) = find(&before.marks, mark.date) {
+                Change::push_mark(&mut changes, &before.marks[i], mark);
+            }
+        }
+        changes
+    }
+
+    /// Backs up the current contents of the file before overwriting it.
+    pub fn save(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        self.save_with_backups(config, config.backups)
+    }
+
+    /// See [`Session::save`], keeps `backups` backups instead of [`Config::backups`].
+    fn save_with_backups(&self, config: &Config, backups: usize) -> Result<(), Box<dyn Error>> {
+        let file = self.to_file()?;
+        Backup::create(&file, backups).map_err(|e| format!("couldn't back up session: {e}"))?;
+        file.write()
+            .map_err(|e| format!("couldn't save session: {e}"))?;
+        // The index is only a cache, it's rebuilt from the session files when it's out of date.
+        if let Err(err) = Index::update(config, self) {
+            eprintln!("Warning: couldn't update the index: {err}");
+        }
+        Ok(())
+    }
+
+    /// Replaces the file with the contents of `backup`, which are backed up like on any other
+    /// save so that restoring can be undone.
+    pub(crate) fn restore(&self, backup: &Backup, config: &Config) -> Result<(), Box<dyn Error>> {
+        let contents =
+            fs::read_to_string(&backup.path).map_err(|e| format!("couldn't read backup: {e}"))?;
+        let file = SessionFile::build(&self.path, &contents)?;
+        Session::from_file(&file)?;
+        Backup::create(&file, config.backups)
+            .map_err(|e| format!("couldn't back up session: {e}"))?;
+        file.write()
+            .map_err(|e| format!("couldn't restore session: {e}"))?;
+        Ok(())
+    }
+
+    // TODO: make this and all other from/to methods idiomatic using traits
+    fn from_file(file: &SessionFile) -> Result<Session, ParseError> {
+        let contents = file.version.upgrade(&file.contents);
+        let (header, footer) = SessionFile::get_surrounding_contents(MARKS_HEADING, &contents);
+        // The header starts at the first line of the file, which is the session heading.
+        let mut lines = header.lines().enumerate().peekable();
+        let title = lines.next().and_then(|(_, v)| Session::title_from_line(v));
+        lines.next_if(|(_, v)| FormatVersion::from_marker(v).is_some());
+        let mut lines = lines.skip_while(|(_, v)| v.trim().is_empty()).peekable();
+        let mut tags = HashSet::new();
+        while let Some((i, line)) = lines.next_if(|(_, v)| v.starts_with(LABEL_TAG)) {
+            let tag = Tag::from_line(line)
+                .map_err(|err| err.within(&file.path, file.lines_before + i))?;
+            tags.insert(tag);
+        }
+        let header = lines
+            .map(|(_, v)| v)
+            .collect::<Vec<&str>>()
+            .join("\n")
+            .trim()
+            .to_owned();
+
+        // Line numbers of the parsed parts are relative to the section.
+        let marks_before = file.get_marks_before();
+        let mut marks: Vec<Mark> = Vec::new();
+        let marks_contents = SessionFile::get_heading_with_contents(MARKS_HEADING, &contents);
+        for (start, contents) in SessionFile::split_marks(&marks_contents) {
+            let mark = Mark::from_line(&contents)
+                .map_err(|err| err.within(&file.path, marks_before + start))?;
+            marks.push(mark);
+        }
+        if marks.is_empty() {
+            return Err(ParseError::new(
+                1,
+                MARKS_HEADING,
+                "there must be at least one mark for a session to be valid",
+            )
+            .within(&file.path, marks_before));
+        }
+        Ok(Session {
+            path: file.path.clone(),
+            marks,
+            title,
+            tags,
+            header,
+            footer,
+            host: None,
+        })
+    }
+
+    pub(crate) fn to_file(&self) -> Result<SessionFile, ParseError> {
+        let mut contents = match &self.title {
+            Some(title) => {
+                format!("{SESSION_HEADING_PREFIX}{SESSION_TITLE}{SESSION_TITLE_SEPARATOR}{title}")
+            }
+            None => format!("{SESSION_HEADING_PREFIX}{SESSION_TITLE}"),
+        };
+        contents += "\n";
+        contents += &FormatVersion::CURRENT.to_marker();
+        // Same layout as the labels of a mark.
+        if !self.tags.is_empty() {
+            contents += "\n";
+            contents += &Tag::to_lines(&self.tags);
+        }
+        if !self.header.is_empty() {
+            contents += "\n\n";
+            contents += &self.header;
+        }
+        contents += &format!("\n\n{MARKS_HEADING}\n\n");
+        for mark in &self.marks {
+            contents += &mark.to_line();
+            contents += "\n\n";
+        }
+        contents += &self.footer;
+
+        let file = SessionFile::build(&self.path, &contents)?;
+        Ok(file)
+    }
+}
+
+/// Previous contents of a session file, stored in `<sessions_path>/.backups/<file name>/` under
+/// the unix timestamp of when they were replaced.
+#[derive(PartialEq, Debug)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub date: DateTime,
+}
+
+impl Backup {
+    fn get_dir(path: &Path) -> PathBuf {
+        let name = path.file_name().unwrap_or_default();
+        path.parent()
+            .unwrap_or(Path::new(""))
+            .join(BACKUPS_DIR)
+            .join(name)
+    }
+
+    /// Lists backups of the session file at `path`, newest first.
+    pub fn list(path: &Path) -> io::Result<Vec<Backup>> {
+        let entries = match fs::read_dir(Backup::get_dir(path)) {
+            Ok(val) => val,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut backups = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|v| v != SESSION_FILE_EXTENSION) {
+                continue;
+            }
+            let Some(date) = path
+                .file_stem()
+                .and_then(|v| v.to_str())
+                .and_then(|v| v.parse().ok())
+                .and_then(|v| chrono::DateTime::from_timestamp(v, 0))
+            else {
+                continue;
+            };
+            backups.push(Backup {
+                path,
+                date: DateTime::from(date.with_timezone(&chrono::Local)),
+            });
+        }
+        backups.sort_by(|a, b| b.date.cmp(&a.date));
+        Ok(backups)
+    }
+
+    /// Copies the contents currently at `file.path` unless they are the same as the new ones,
+    /// keeps only the `keep` most recent backups.
+    fn create(file: &SessionFile, keep: usize) -> io::Result<()> {
+        if keep == 0 {
+            return Ok(());
+        }
+        let contents = match fs::read_to_string(&file.path) {
+            Ok(val) => val,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if contents == file.contents {
+            return Ok(());
+        }
+
+        let dir = Backup::get_dir(&file.path);
+        fs::create_dir_all(&dir)?;
+        // Saves within the same second get the following timestamps so that none is overwritten.
+        let mut timestamp = chrono::Local::now().timestamp();
+        let mut path = dir.join(format!("{timestamp}.{SESSION_FILE_EXTENSION}"));
+        while fs::exists(&path)? {
+            timestamp += 1;
+            path = dir.join(format!("{timestamp}.{SESSION_FILE_EXTENSION}"));
+        }
+        fs::write(&path, contents)?;
+        for backup in Backup::list(&file.path)?.iter().skip(keep) {
+            fs::remove_file(&backup.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Change to a session, see [`Session::diff`]. Mark changes are identified by the date of the
+/// mark after the change.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Change {
+    MarkAdded(DateTime),
+    MarkRemoved(DateTime),
+    MarkMoved {
+        from: DateTime,
+        to: DateTime,
+    },
+    Attribute {
+        date: DateTime,
+        attribute: Attribute,
+    },
+    Billable {
+        date: DateTime,
+        billable: bool,
+    },
+    /// Context of the mark at `date`, removed when `None`.
+    Context {
+        date: DateTime,
+        context: Option<String>,
+    },
+    /// Tag of the mark at `date`, of the session when `None`.
+    TagAdded {
+        date: Option<DateTime>,
+        tag: Tag,
+    },
+    TagRemoved {
+        date: Option<DateTime>,
+        tag: Tag,
+    },
+    /// Text of the mark, `lines_before` is 0 when it had none.
+    Contents {
+        date: DateTime,
+        lines_before: usize,
+    },
+    Title(Option<String>),
+    /// Text before or after the marks section.
+    Notes,
+}
+
+impl Change {
+    fn push_mark(changes: &mut Vec<Change>, before: &Mark, mark: &Mark) {
+        let date = mark.date();
+        if before.attribute != mark.attribute {
+            changes.push(Change::Attribute {
+                date: date.clone(),
+                attribute: mark.attribute.clone(),
+            });
+        }
+        if before.billable != mark.billable {
+            changes.push(Change::Billable {
+                date: date.clone(),
+                billable: mark.billable,
+            });
+        }
+        if before.context != mark.context {
+            changes.push(Change::Context {
+                date: date.clone(),
+                context: mark.context.clone(),
+            });
+        }
+        Change::push_tags(changes, Some(&date), &before.tags, &mark.tags);
+        if before.contents.trim() != mark.contents.trim() || before.encrypted != mark.encrypted {
+            changes.push(Change::Contents {
+                date,
+                lines_before: before.contents.trim().lines().count(),
+            });
+        }
+    }
+
+    /// Tags are sorted so that the changes come in the same order every time.
+    fn push_tags(
+        changes: &mut Vec<Change>,
+        date: Option<&DateTime>,
+        before: &HashSet<Tag>,
+        tags: &HashSet<Tag>,
+    ) {
+        let sorted = |tags: Vec<&Tag>| {
+            let mut tags: Vec<Tag> = tags.into_iter().cloned().collect();
+            tags.sort_by(|a, b| a.text.cmp(&b.text));
+            tags
+        };
+        for tag in sorted(tags.difference(before).collect()) {
+            let date = date.cloned();
+            changes.push(Change::TagAdded { date, tag });
+        }
+        for tag in sorted(before.difference(tags).collect()) {
+            let date = date.cloned();
+            changes.push(Change::TagRemoved { date, tag });
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let target = |date: &Option<DateTime>| match date {
+            Some(date) => format!("mark {}", date.to_formatted_time()),
+            None => String::from("session"),
+        };
+        match self {
+            Change::MarkAdded(date) => {
+                write!(f, "added mark ### {}", date.to_formatted_pretty_short())
+            }
+            Change::MarkRemoved(date) => {
+                write!(f, "removed mark ### {}", date.to_formatted_pretty_short())
+            }
+            Change::MarkMoved { from, to } => write!(
+                f,
+                "moved mark {} to {}",
+                from.to_formatted_pretty_short(),
+                to.to_formatted_pretty_short()
+            ),
+            Change::Attribute { date, attribute } => write!(
+                f,
+                "attribute of mark {} set to {}",
+                date.to_formatted_time(),
+                attribute.name()
+            ),
+            Change::Billable { date, billable } => {
+                let state = if *billable {
+                    "billable"
+                } else {
+                    "not billable"
+                };
+                write!(f, "mark {} set {state}", date.to_formatted_time())
+            }
+            Change::Context { date, context } => match context {
+                Some(context) => write!(
+                    f,
+                    "mark {} context set to `{context}`",
+                    date.to_formatted_time()
+                ),
+                None => write!(f, "mark {} context removed", date.to_formatted_time()),
+            },
+            Change::TagAdded { date, tag } => {
+                write!(f, "tag `{tag}` added to {}", target(date))
+            }
+            Change::TagRemoved { date, tag } => {
+                write!(f, "tag `{tag}` removed from {}", target(date))
+            }
+            Change::Contents {
+                date,
+                lines_before: 0,
+            } => write!(f, "text added to mark {}", date.to_formatted_time()),
+            Change::Contents { date, lines_before } => write!(
+                f,
+                "text of mark {} overwritten (was {lines_before} {})",
+                date.to_formatted_time(),
+                if *lines_before == 1 { "line" } else { "lines" }
+            ),
+            Change::Title(Some(title)) => write!(f, "title set to `{title}`"),
+            Change::Title(None) => write!(f, "title removed"),
+            Change::Notes => write!(f, "session notes changed"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Mark {
+    /// Keeps the offset the mark was written with so that unmodified marks serialize the same,
+    /// compare and compute with [`Mark::local_date`].
+    date: chrono::DateTime<chrono::FixedOffset>,
+    attribute: Attribute,
+    /// The interval this mark starts is billed, see [`Aggregator::billable_totals`].
+    billable: bool,
+    tags: HashSet<Tag>,
+    /// Machine the mark was written on, see [`Config::record_host`].
+    host: Option<String>,
+    /// Where the work happens, like `office`, set by the user and kept by the following marks of
+    /// the session.
+    context: Option<String>,
+    /// Contents sealed by [`Cipher`], see [`Config::encrypt_contents`]. `contents` is empty while
+    /// it's set, so that the times can be read without the passphrase.
+    encrypted: Option<String>,
+    // TODO: Rename to text.
+    contents: String,
+}
+
+impl Mark {
+    fn new(date: &chrono::DateTime<chrono::Local>) -> Mark {
+        Mark {
+            date: date.fixed_offset(),
+            attribute: Attribute::None,
+            billable: false,
+            tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
+            contents: String::new(),
+        }
+    }
+
+    /// The same instant as the mark date, in the local time zone.
+    fn local_date(&self) -> chrono::DateTime<chrono::Local> {
+        self.date.with_timezone(&chrono::Local)
+    }
+
+    /// In the local time zone.
+    pub fn date(&self) -> DateTime {
+        DateTime::new(&self.local_date())
+    }
+
+    pub fn attribute(&self) -> &Attribute {
+        &self.attribute
+    }
+
+    /// Sorted alphabetically, tags of the session aren't included.
+    pub fn tags(&self) -> Vec<&Tag> {
+        let mut tags: Vec<&Tag> = self.tags.iter().collect();
+        tags.sort_by(|a, b| a.text.cmp(&b.text));
+        tags
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn is_billable(&self) -> bool {
+        self.billable
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted.is_some()
+    }
+
+    /// Seals the contents with `cipher`, marks without contents are left as they are.
+    pub fn encrypt(&mut self, cipher: &Cipher) -> Result<(), String> {
+        let contents = self.contents.trim();
+        if contents.is_empty() {
+            return Ok(());
+        }
+        self.encrypted = Some(cipher.encrypt(contents)?);
+        self.contents = String::new();
+        Ok(())
+    }
+
+    /// Opens contents sealed by [`Mark::encrypt`], so that they're written in plain text again.
+    pub fn decrypt(&mut self, cipher: &Cipher) -> Result<(), String> {
+        if let Some(payload) = &self.encrypted {
+            self.contents = cipher.decrypt(payload)?;
+            self.encrypted = None;
+        }
+        Ok(())
+    }
+
+    /// First line of the contents, usually a one-line summary of the work, `None` when empty.
+    pub fn title(&self) -> Option<&str> {
+        self.contents
+            .trim()
+            .lines()
+            .next()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Contents after the title.
+    pub fn body(&self) -> &str {
+        let contents = self.contents.trim();
+        contents
+            .split_once('\n')
+            .map_or("", |(_, body)| body.trim())
+    }
+
+    /// Replaces the title, keeping the body below it. Returns error if the mark already has a
+    /// different title, unless `force` is set.
+    pub fn set_title(&mut self, title: &str, force: bool) -> Result<(), String> {
+        let title = title.trim();
+        if title.is_empty() {
+            Err("title cannot be empty")?
+        } else if title.contains('\n') {
+            Err("title cannot contain line breaks")?
+        }
+        match self.title() {
+            Some(current) if current != title && !force => {
+                Err(format!("mark already has the title `{current}`"))?
+            }
+            _ => (),
+        }
+        let body = self.body();
+        self.contents = if body.is_empty() {
+            title.to_owned()
+        } else {
+            format!("{title}\n{body}")
+        };
+        Ok(())
+    }
+
+    /// Single line with the start and the title, `2025-01-07 14:32 Parser`.
+    pub fn to_summary_line(&self) -> String {
+        let date = DateTime::new(&self.local_date()).to_formatted_pretty_short();
+        match self.title() {
+            Some(title) => format!("{date} {title}"),
+            None => date,
+        }
+    }
+
+    /// Overwrites the content of this mark.
+    fn write(&mut self, text: &str) {
+        self.contents = text.to_owned();
+    }
+
+    pub fn erase(&mut self) {
+        self.contents = String::new();
+    }
+
+    /// Line numbers in errors are relative to `contents`.
+    fn from_line(contents: &str) -> Result<Mark, ParseError> {
+        let lines: Vec<&str> = contents.trim().lines().collect();
+        let heading = lines.first().copied().unwrap_or_default();
+        let date = heading
+            .strip_prefix(MARK_HEADING_PREFIX)
+            .and_then(|v| chrono::DateTime::<chrono::FixedOffset>::from_str(v).ok())
+            .ok_or_else(|| ParseError::new(1, heading, "couldn't parse mark heading"))?;
+        let mut i = 1;
+        while lines.get(i).is_some_and(|v| v.trim().is_empty()) {
+            i += 1;
+        }
+        let mut attribute = Attribute::None;
+        let mut billable = false;
+        let mut tags: HashSet<Tag> = HashSet::new();
+        let mut host = None;
+        let mut context = None;
+        let mut encrypted = None;
+        while let Some(line) = lines.get(i).filter(|v| v.starts_with(LABEL_PREFIX)) {
+            let attr = Attribute::from_line(line);
+            if line.trim() == LABEL_BILLABLE {
+                billable = true;
+            } else if line.starts_with(LABEL_HOST) {
+                if host.is_some() {
+                    return Err(ParseError::new(
+                        i + 1,
+                        line,
+                        "multiple hosts per mark are not allowed",
+                    ));
+                }
+                host = Some(
+                    Mark::value_from_line(LABEL_HOST, "host", line)
+                        .map_err(|err| ParseError::new(i + 1, line, &err))?,
+                );
+            } else if line.starts_with(LABEL_CONTEXT) {
+                if context.is_some() {
+                    return Err(ParseError::new(
+                        i + 1,
+                        line,
+                        "multiple contexts per mark are not allowed",
+                    ));
+                }
+                context = Some(
+                    Mark::value_from_line(LABEL_CONTEXT, "context", line)
+                        .map_err(|err| ParseError::new(i + 1, line, &err))?,
+                );
+            } else if line.starts_with(LABEL_ENCRYPTED) {
+                if encrypted.is_some() {
+                    return Err(ParseError::new(
+                        i + 1,
+                        line,
+                        "multiple encrypted contents per mark are not allowed",
+                    ));
+                }
+                encrypted = Some(
+                    Mark::value_from_line(LABEL_ENCRYPTED, "encrypted contents", line)
+                        .map_err(|err| ParseError::new(i + 1, line, &err))?,
+                );
+            } else if attr != Attribute::None {
+                if attribute != Attribute::None {
+                    return Err(ParseError::new(
+                        i + 1,
+                        line,
+                        "multiple attributes per mark are not allowed",
+                    ));
+                }
+                attribute = attr;
+            } else {
+                tags.insert(Tag::from_line(line).map_err(|err| err.within(Path::new(""), i))?);
+            }
+            i += 1;
+        }
+        let contents_without_heading = lines[i..].join("\n").trim().to_owned();
+        if encrypted.is_some() && !contents_without_heading.is_empty() {
+            let line = lines[i..].iter().find(|v| !v.trim().is_empty());
+            return Err(ParseError::new(
+                i + 1,
+                line.copied().unwrap_or_default(),
+                "mark with encrypted contents can't have plain text contents",
+            ));
+        }
+        Ok(Mark {
+            date,
+            attribute,
+            billable,
+            tags,
+            host,
+            context,
+            encrypted,
+            contents: Mark::unescape_contents(&contents_without_heading),
+        })
+    }
+
+    pub(crate) fn to_line(&self) -> String {
+        let mut contents = format!("{MARK_HEADING_PREFIX}{}", self.date.format("%F %T %:z"));
+        if self.attribute != Attribute::None
+            || self.billable
+            || self.host.is_some()
+            || self.context.is_some()
+            || self.encrypted.is_some()
+            || !self.tags.is_empty()
+        {
+            contents += "\n";
+            if self.attribute != Attribute::None {
+                contents += "\n";
+                contents += &self.attribute.to_line();
+            }
+            if self.billable {
+                contents += "\n";
+                contents += LABEL_BILLABLE;
+            }
+            if let Some(host) = &self.host {
+                contents +=
+                    &format!("\n{LABEL_HOST} {LABEL_TAG_SURROUND}{host}{LABEL_TAG_SURROUND}");
+            }
+            if let Some(context) = &self.context {
+                contents +=
+                    &format!("\n{LABEL_CONTEXT} {LABEL_TAG_SURROUND}{context}{LABEL_TAG_SURROUND}");
+            }
+            if !self.tags.is_empty() {
+                // TODO: Put all tags on the same line?
+                contents += &Tag::to_lines(&self.tags);
+            }
+            if let Some(payload) = &self.encrypted {
+                contents += &format!(
+                    "\n{LABEL_ENCRYPTED} {LABEL_TAG_SURROUND}{payload}{LABEL_TAG_SURROUND}"
+                );
+            }
+        }
+        let trimmed = self.contents.trim();
+        if !trimmed.is_empty() {
+            contents += "\n\n";
+            contents += &Mark::escape_contents(trimmed);
+        }
+        contents
+    }
+
+    /// Date, attribute, tags, context and host on one line,
+    /// `2025-01-07 11:20:00 +01:00 skip billable #rust at office @laptop`.
+    pub fn to_compact_line(&self) -> String {
+        let mut line = self.date().to_formatted_pretty();
+        if self.attribute != Attribute::None {
+            line += " ";
+            line += self.attribute.name();
+        }
+        if self.billable {
+            line += " billable";
+        }
+        for tag in self.tags() {
+            line += &format!(" #{tag}");
+        }
+        if let Some(context) = &self.context {
+            line += &format!(" at {context}");
+        }
+        if let Some(host) = &self.host {
+            line += &format!(" @{host}");
+        }
+        line
+    }
+
+    /// Text of a label like the host, trimmed, `name` is used in errors. Empty text, line breaks
+    /// and other control characters and backticks are rejected.
+    fn parse_value(name: &str, text: &str) -> Result<String, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            Err(format!("{name} cannot be empty"))?
+        } else if text.chars().any(|v| v.is_control()) {
+            Err(format!(
+                "{name} cannot contain line breaks or control characters"
+            ))?
+        } else if text.contains(LABEL_TAG_SURROUND) {
+            Err(format!("invalid character \"{LABEL_TAG_SURROUND}\""))?
+        }
+        Ok(text.to_owned())
+    }
+
+    /// Text of `label`, written like a tag as ``- host `laptop` ``.
+    fn value_from_line(label: &str, name: &str, line: &str) -> Result<String, String> {
+        let prefix = format!("{label} {LABEL_TAG_SURROUND}");
+        let text = line
+            .trim()
+            .strip_prefix(&prefix)
+            .and_then(|v| v.strip_suffix(LABEL_TAG_SURROUND))
+            .ok_or(format!("couldn't parse {name} label"))?;
+        Mark::parse_value(name, text)
+    }
+
+    /// Whether the labels in `contents`, the raw text of this mark, are written the same way
+    /// [`Mark::to_line`] writes them.
+    fn has_canonical_labels(&self, contents: &str) -> bool {
+        Mark::get_labels(contents) == Mark::get_labels(&self.to_line())
+    }
+
+    fn get_labels(contents: &str) -> Vec<&str> {
+        contents
+            .lines()
+            .skip(1)
+            .skip_while(|v| v.trim().is_empty())
+            .take_while(|v| v.starts_with(LABEL_PREFIX))
+            .map(|v| v.trim())
+            .collect()
+    }
+
+    /// Escapes lines that start with a heading, the escape itself, or, on the first line, a
+    /// label, the same way as in Markdown.
+    fn escape_contents(text: &str) -> String {
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let start = line.trim_start();
+                if start.starts_with('#')
+                    || start.starts_with(CONTENTS_ESCAPE)
+                    || (i == 0 && start.starts_with(LABEL_PREFIX))
+                {
+                    format!("{CONTENTS_ESCAPE}{line}")
+                } else {
+                    line.to_owned()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn unescape_contents(text: &str) -> String {
+        text.lines()
+            .map(|line| line.strip_prefix(CONTENTS_ESCAPE).unwrap_or(line))
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
+
+/// Mark a command applies to.
+#[derive(PartialEq, Debug, Clone)]
+pub enum MarkSelector {
+    Last,
+    /// 1-based from the first mark, negative counts from the end so that `-1` is the last mark.
+    Index(i64),
+    /// The mark within the minute starting at the date.
+    At(DateTime),
+}
+
+/// Text a command writes into.
+#[derive(PartialEq, Debug, Clone)]
+pub enum WriteTarget {
+    /// Notes between the session heading and the marks.
+    Session,
+    Mark(MarkSelector),
+}
+
+/// How [`Session::write_at`] treats text that is already there.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum WriteMode {
+    /// Fails unless there is no text yet.
+    New,
+    /// Adds a new paragraph after the text.
+    Append,
+    Overwrite,
+    /// Replaces only the title of a mark, see [`Mark::set_title`].
+    Title {
+        force: bool,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Attribute {
+    Stop,
+    Skip,
+    // TODO: Remove?
+    None,
+}
+
+impl Attribute {
+    /// Accepts `stop` or `end`, `skip` and `none` or `clear`, in any case.
+    pub fn from_text(text: &str) -> Result<Attribute, String> {
+        match text.trim().to_lowercase().as_str() {
+            "stop" | "end" => Ok(Attribute::Stop),
+            "skip" => Ok(Attribute::Skip),
+            "none" | "clear" => Ok(Attribute::None),
+            _ => Err(format!(
+                "invalid attribute `{}`, use `stop`, `skip` or `clear`",
+                text.trim()
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Stop => "stop",
+            Attribute::Skip => "skip",
+            Attribute::None => "none",
+        }
+    }
+
+    fn from_line(text: &str) -> Attribute {
+        match text.trim() {
+            LABEL_STOP => Attribute::Stop,
+            LABEL_SKIP => Attribute::Skip,
+            _ => Attribute::None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            Attribute::Stop => LABEL_STOP.to_owned(),
+            Attribute::Skip => LABEL_SKIP.to_owned(),
+            Attribute::None => String::new(),
+        }
+    }
+}
+
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct Tag {
+    text: String,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Tag {
+    /// Runs of whitespace become a single space. Line breaks and other control characters, tags
+    /// longer than [`TAG_MAX_LENGTH`] and names of labels like `skip` are rejected.
+    pub fn from_text(text: &str) -> Result<Tag, String> {
+        if text.chars().any(char::is_control) {
+            Err("tag cannot contain line breaks or control characters")?
+        }
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let label = [LABEL_STOP, LABEL_SKIP, LABEL_BILLABLE]
+            .iter()
+            .find(|v| v.strip_prefix(LABEL_PREFIX) == Some(&text));
+        if text.is_empty() {
+            Err("tag cannot be empty")?
+        } else if text.contains(LABEL_TAG_SURROUND) {
+            Err(format!("invalid character \"{LABEL_TAG_SURROUND}\""))
+        } else if text.chars().count() > TAG_MAX_LENGTH {
+            Err(format!(
+                "tag cannot be longer than {TAG_MAX_LENGTH} characters"
+            ))
+        } else if let Some(label) = label {
+            Err(format!(
+                "tag `{text}` would be confused with the `{label}` label"
+            ))
+        } else {
+            Ok(Tag { text })
+        }
+    }
+
+    /// The error points to the first line.
+    fn from_line(text: &str) -> Result<Tag, ParseError> {
+        let prefix = format!("{LABEL_TAG} {LABEL_TAG_SURROUND}");
+        let tag_text = text
+            .strip_prefix(&prefix)
+            .and_then(|v| v.strip_suffix(LABEL_TAG_SURROUND))
+            .ok_or_else(|| ParseError::new(1, text, "couldn't parse tag"))?;
+        Tag::from_text(tag_text).map_err(|err| ParseError::new(1, text, &err))
+    }
+
+    /// Sorted alphabetically, each line is preceded by a line break.
+    fn to_lines(tags: &HashSet<Tag>) -> String {
+        let mut tags: Vec<&Tag> = tags.iter().collect();
+        tags.sort_by(|a, b| a.text.cmp(&b.text));
+        tags.iter()
+            .fold(String::new(), |acc, val| acc + "\n" + &val.to_line())
+    }
+
+    fn to_line(&self) -> String {
+        let text = &self.text;
+        // There shouldn't be a way to store an empty string in here.
+        assert!(!text.is_empty());
+        format!("{LABEL_TAG} {LABEL_TAG_SURROUND}{text}{LABEL_TAG_SURROUND}")
+    }
+}
+
+/// Maps tags to their canonical form, see [`Config::tag_normalizer`].
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct TagNormalizer {
+    /// `(alias, canonical tag)`, the first matching alias is used.
+    pub aliases: Vec<(String, Tag)>,
+    /// Tags are lowercased, aliases then match regardless of case.
+    pub case_insensitive: bool,
+}
+
+impl TagNormalizer {
+    /// Whether every tag is kept as is.
+    pub fn is_identity(&self) -> bool {
+        self.aliases.is_empty() && !self.case_insensitive
+    }
+
+    pub fn apply(&self, tag: &Tag) -> Tag {
+        let fold = |text: &str| {
+            if self.case_insensitive {
+                text.to_lowercase()
+            } else {
+                text.to_owned()
+            }
+        };
+        let text = fold(&tag.text);
+        let text = self
+            .aliases
+            .iter()
+            .find(|(alias, _)| fold(alias) == text)
+            .map_or(text, |(_, canonical)| fold(&canonical.text));
+        Tag { text }
+    }
+
+    /// Short description of the normalization, empty for [`TagNormalizer::is_identity`].
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.case_insensitive {
+            parts.push(String::from("case insensitive"));
+        }
+        if !self.aliases.is_empty() {
+            let aliases: Vec<String> = self
+                .aliases
+                .iter()
+                .map(|(alias, canonical)| format!("{alias}={canonical}"))
+                .collect();
+            parts.push(format!("aliases {}", aliases.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        date_time::{DisplayPrefs, TimestampFormat},
+        report::Goal,
+        style::ColorMode,
+        testing,
+    };
+    use chrono::Timelike;
+
+    fn get_template(date: &str) -> String {
+        format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}{date}\
+            "
+        )
+    }
+
+    #[test]
+    fn aggregator_build_skips_unparsable_files() {
+        let dir = testing::temp_dir("aggregator_build_skips_unparsable_files");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let session = Session::new(&config, &DateTime::now().plus_hours(-2));
+        session.save(&config).unwrap();
+        let path_broken = Session::new(&config, &DateTime::now().plus_hours(-1)).path;
+        fs::write(&path_broken, "Scratch notes.").unwrap();
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.sessions, vec![session.clone()]);
+        assert_eq!(aggregator.failures.len(), 1);
+        assert_eq!(aggregator.failures[0].0, path_broken);
+        assert!(aggregator.failures[0]
+            .1
+            .to_string()
+            .contains(&format!("{}:1:", path_broken.display())));
+
+        fs::remove_file(&session.path).unwrap();
+        let err = Aggregator::build(&config).err().unwrap().to_string();
+        assert!(err.contains("no valid session found"));
+        assert!(err.contains(&path_broken.display().to_string()));
+        fs::remove_file(&path_broken).unwrap();
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert!(aggregator.is_empty());
+        assert!(aggregator.failures.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_build_since_skips_older_files() {
+        let dir = testing::temp_dir("aggregator_build_since_skips_older_files");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let cutoff = DateTime::now().plus_hours(-10);
+        // Never read, they would be reported as failures otherwise.
+        for hours in [-30, -20] {
+            let path = Session::new(&config, &cutoff.plus_hours(hours)).path;
+            fs::write(path, "Scratch notes.").unwrap();
+        }
+        let mut session_old = Session::new(&config, &cutoff.plus_hours(-5));
+        session_old.set_attribute(Attribute::Stop).unwrap();
+        session_old.save(&config).unwrap();
+
+        // The newest file is read even before the cutoff.
+        let aggregator = Aggregator::build_since(&config, &cutoff).unwrap();
+        assert_eq!(aggregator.sessions, vec![session_old.clone()]);
+        assert!(aggregator.failures.is_empty());
+
+        let session_new = Session::new(&config, &cutoff.plus_hours(2));
+        session_new.save(&config).unwrap();
+        let aggregator = Aggregator::build_since(&config, &cutoff).unwrap();
+        assert_eq!(aggregator.sessions, vec![session_new]);
+        assert!(aggregator.failures.is_empty());
+
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.sessions.len(), 2);
+        assert_eq!(aggregator.failures.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_reload_works() {
+        let dir = testing::temp_dir("aggregator_reload_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let date_default = DateTime::new(&testing::date_default());
+        let mut session_old = Session::new(&config, &date_default.plus_hours(-5));
+        session_old.set_attribute(Attribute::Stop).unwrap();
+        session_old.save(&config).unwrap();
+        let mut session = Session::new(&config, &date_default.plus_hours(-1));
+        session.save(&config).unwrap();
+
+        let mut aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(
+            aggregator.get_shown_path(&config),
+            Some(session.path.as_path())
+        );
+        session.mark(&date_default, false).unwrap();
+        session.save(&config).unwrap();
+        aggregator.reload(&config, &session.path).unwrap();
+        assert_eq!(aggregator.sessions, vec![session_old, session]);
+
+        assert!(aggregator.reload(&config, &dir.join("missing.md")).is_err());
+        let config_other = Config {
+            workspace: Some(String::from("other")),
+            ..config
+        };
+        assert_eq!(aggregator.get_shown_path(&config_other), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_get_empty_works() {
+        let dir = testing::temp_dir("aggregator_get_empty_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let config_other = Config {
+            sessions_path: dir.clone(),
+            workspace: Some(String::from("other")),
+            ..Default::default()
+        };
+        let date_default = DateTime::new(&testing::date_default());
+        let abandoned = Session::new(&config, &date_default.plus_hours(-5));
+        abandoned.save(&config).unwrap();
+        let mut kept = Session::new(&config, &date_default.plus_hours(-4));
+        kept.mark(&date_default.plus_hours(-3), false).unwrap();
+        kept.set_attribute(Attribute::Stop).unwrap();
+        kept.save(&config).unwrap();
+        let mut zero = Session::new(&config, &date_default.plus_hours(-2));
+        zero.write("Started by accident.").unwrap();
+        zero.set_attribute(Attribute::Stop).unwrap();
+        zero.save(&config).unwrap();
+        let active_other = Session::new(&config_other, &date_default.plus_hours(-1));
+        active_other.save(&config_other).unwrap();
+        let active = Session::new(&config, &date_default);
+        active.save(&config).unwrap();
+
+        // The newest sessions of both workspaces are still active.
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.get_empty(), vec![&abandoned, &zero]);
+
+        let path = zero.trash().unwrap();
+        assert_eq!(
+            path,
+            dir.join(TRASH_DIR).join(zero.path.file_name().unwrap())
+        );
+        assert!(!zero.path.exists());
+        assert_eq!(Session::from_path(&path).unwrap().marks, zero.marks);
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.get_empty(), vec![&abandoned]);
+        assert!(aggregator.failures.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_archive_works() {
+        let dir = testing::temp_dir("aggregator_archive_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let boundary = config.day_starts_at;
+        let date_default = DateTime::new(&testing::date_default());
+        let mut sessions = Vec::new();
+        for days in [-400, -3, -2] {
+            let mut session = Session::new(&config, &date_default.plus_days(days));
+            session
+                .mark(&date_default.plus_days(days).plus_hours(1), false)
+                .unwrap();
+            session.set_attribute(Attribute::Stop).unwrap();
+            session.save(&config).unwrap();
+            sessions.push(session);
+        }
+        let active = Session::new(&config, &date_default.plus_days(-1));
+        active.save(&config).unwrap();
+
+        let aggregator = Aggregator::build(&config).unwrap();
+        let before = date_default.plus_days(-2).day(boundary);
+        assert_eq!(
+            aggregator.get_ended_before(before, boundary),
+            vec![&sessions[0], &sessions[1]]
+        );
+        // The newest session is kept even when it has ended.
+        let before = date_default.day(boundary);
+        assert_eq!(
+            aggregator.get_ended_before(before, boundary),
+            vec![&sessions[0], &sessions[1], &sessions[2]]
+        );
+        fs::remove_file(&active.path).unwrap();
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.get_ended_before(before, boundary).len(), 2);
+        fs::write(&active.path, active.to_file().unwrap().contents).unwrap();
+
+        let mut archived = Vec::new();
+        for session in &sessions[..2] {
+            archived.push(session.archive().unwrap());
+        }
+        let year = |session: &Session| session.start().format("%Y").to_string();
+        assert_eq!(
+            archived,
+            vec![
+                dir.join(ARCHIVE_DIR)
+                    .join(year(&sessions[0]))
+                    .join(sessions[0].path.file_name().unwrap()),
+                dir.join(ARCHIVE_DIR)
+                    .join(year(&sessions[1]))
+                    .join(sessions[1].path.file_name().unwrap()),
+            ]
+        );
+        assert!(sessions[0].archive().is_err());
+        assert_eq!(Aggregator::read_archive_dir(&config).unwrap(), archived);
+        assert_eq!(
+            Aggregator::read_sessions_dir(&config).unwrap(),
+            vec![sessions[2].path.clone(), active.path.clone()]
+        );
+        assert_eq!(Aggregator::build(&config).unwrap().sessions.len(), 2);
+        let aggregator = Aggregator::build_with_archive(&config).unwrap();
+        assert_eq!(aggregator.sessions.len(), 4);
+        assert_eq!(aggregator.sessions[0].path, archived[0]);
+
+        // Archived sessions are never the last one.
+        fs::remove_file(&active.path).unwrap();
+        fs::remove_file(&sessions[2].path).unwrap();
+        assert_eq!(Session::get_last(&config).unwrap(), None);
+        assert_eq!(Session::get_last_stopped(&config).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_build_since_works_in_empty_dir() {
+        let dir = testing::temp_dir("aggregator_build_since_works_in_empty_dir");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let aggregator = Aggregator::build_since(&config, &DateTime::now()).unwrap();
+        assert!(aggregator.is_empty());
+        assert!(aggregator.failures.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_tag_totals_merges_normalized_tags() {
+        let dir = testing::temp_dir("aggregator_tag_totals_merges_normalized_tags");
+        let config = Config {
+            sessions_path: dir.clone(),
+            tag_normalizer: TagNormalizer {
+                aliases: vec![(String::from("tt"), Tag::from_text("time tracker").unwrap())],
+                case_insensitive: true,
+            },
+            ..Default::default()
+        };
+        let date_default = DateTime::new(&testing::date_default());
+        for (hours, tag) in [(-5, "Time Tracker"), (-3, "TT"), (-1, "rust")] {
+            let mut session = Session::new(&config, &date_default.plus_hours(hours));
+            session.tag_session(&Tag::from_text(tag).unwrap());
+            session
+                .mark(&date_default.plus_hours(hours + 1), false)
+                .unwrap();
+            session.set_attribute(Attribute::Stop).unwrap();
+            session.save(&config).unwrap();
+        }
+
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()),
+            Ok(vec![
+                (Tag::from_text("rust").unwrap(), Duration::HOUR),
+                (
+                    Tag::from_text("time tracker").unwrap(),
+                    Duration::HOUR + Duration::HOUR
+                ),
+            ])
+        );
+        // Files keep the spelling they were written with.
+        let config_plain = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let aggregator = Aggregator::build(&config_plain).unwrap();
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()).unwrap().len(),
+            3
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_context_totals_works() {
+        let date_default = DateTime::new(&testing::date_default());
+        let office = Some(String::from("office"));
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: [-4, -3, -2, -1, 0]
+                .iter()
+                .map(|v| Mark::new(&date_default.plus_hours(*v).date))
+                .collect(),
+            ..Default::default()
+        };
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.marks[1].context = office.clone();
+        session.marks[2].context = Some(String::from("home"));
+        session.marks[3].context = office;
+        let aggregator = Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
+        };
+
+        assert_eq!(
+            aggregator.context_totals(&DateRange::default(), &[]),
+            Ok(vec![
+                (String::from("home"), Duration::HOUR),
+                (String::from("office"), Duration::HOUR + Duration::HOUR),
+                (String::from(CONTEXT_UNSET), Duration::HOUR),
+            ])
+        );
+        let range = DateRange {
+            start: Some(date_default.plus_hours(-2)),
+            end: None,
+        };
+        assert_eq!(
+            aggregator.context_totals(&range, &[]),
+            Ok(vec![
+                (String::from("home"), Duration::HOUR),
+                (String::from("office"), Duration::HOUR),
+            ])
+        );
+    }
+
+    #[test]
+    fn aggregator_tag_totals_works() {
+        let date_default = DateTime::new(&testing::date_default());
+        let rust = Tag::from_text("rust").unwrap();
+        let client = Tag::from_text("client").unwrap();
+        // Hours from the default date, the last mark stops the session.
+        let session = |hours: &[i64]| {
+            let mut session = Session {
+                path: PathBuf::from("sessions"),
+                marks: hours
+                    .iter()
+                    .map(|v| Mark::new(&date_default.plus_hours(*v).date))
+                    .collect(),
+                ..Default::default()
+            };
+            session.set_attribute(Attribute::Stop).unwrap();
+            session
+        };
+        let mut first = session(&[-30, -29]);
+        first.tag_session(&rust);
+        let mut second = session(&[-3, -2, -1, 0]);
+        second.marks[0].tags.insert(rust.clone());
+        second.marks[1].attribute = Attribute::Skip;
+        second.marks[1].tags.insert(rust.clone());
+        second.marks[2].tags.insert(rust.clone());
+        second.tag_session(&client);
+        let aggregator = Aggregator {
+            sessions: vec![first, second],
+            failures: Vec::new(),
+        };
+
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()),
+            Ok(vec![
+                (client.clone(), Duration::HOUR + Duration::HOUR),
+                (
+                    rust.clone(),
+                    Duration::HOUR + Duration::HOUR + Duration::HOUR
+                ),
+            ])
+        );
+        let range = DateRange {
+            start: Some(date_default.plus_hours(-24)),
+            end: None,
+        };
+        assert_eq!(
+            aggregator.tag_totals(&range),
+            Ok(vec![
+                (client, Duration::HOUR + Duration::HOUR),
+                (rust, Duration::HOUR + Duration::HOUR),
+            ])
+        );
+    }
+
+    #[test]
+    fn aggregator_gaps_works() {
+        let date_default = DateTime::new(&testing::date_default());
+        let session = |start: i64, end: Option<i64>| {
+            let mut marks = vec![Mark::new(&date_default.plus_minutes(start).date)];
+            if let Some(end) = end {
+                let mut mark = Mark::new(&date_default.plus_minutes(end).date);
+                mark.attribute = Attribute::Stop;
+                marks.push(mark);
+            }
+            Session {
+                path: PathBuf::from("sessions"),
+                marks,
+                ..Default::default()
+            }
+        };
+        let aggregator = Aggregator {
+            sessions: vec![
+                session(0, Some(60)),
+                // Overlaps the next session.
+                session(120, Some(200)),
+                session(70, Some(100)),
+                session(180, Some(240)),
+                // Active, so its gap is left open.
+                session(300, None),
+            ],
+            failures: Vec::new(),
+        };
+        let minutes = |v: u64| Duration::from_secs(v * 60);
+
+        let gaps = aggregator.gaps(&DateRange::default());
+        let times: Vec<Option<Duration>> = gaps.iter().map(|v| v.gap).collect();
+        assert_eq!(
+            times,
+            vec![
+                Some(minutes(10)),
+                Some(minutes(20)),
+                Some(Duration::ZERO),
+                Some(minutes(60)),
+                None
+            ]
+        );
+        assert_eq!(gaps[1].start, date_default.plus_minutes(70));
+        assert_eq!(gaps[4].end, None);
+        assert_eq!(gaps[3].end, Some(date_default.plus_minutes(240)));
+
+        let range = DateRange {
+            start: Some(date_default.plus_minutes(100)),
+            end: Some(date_default.plus_minutes(200)),
+        };
+        let gaps = aggregator.gaps(&range);
+        assert_eq!(gaps.len(), 2);
+        assert_eq!(gaps[1].gap, None);
+    }
+
+    #[test]
+    fn aggregator_billable_totals_works() {
+        let date_default = DateTime::new(&testing::date_default());
+        let rust = Tag::from_text("rust").unwrap();
+        let client = Tag::from_text("client").unwrap();
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: [-4, -3, -2, -1, 0]
+                .iter()
+                .map(|v| Mark::new(&date_default.plus_hours(*v).date))
+                .collect(),
+            ..Default::default()
+        };
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.tag_session(&client);
+        session.marks[0].billable = true;
+        session.marks[0].tags.insert(rust.clone());
+        // Skipped and untracked intervals aren't billed even when billable.
+        session.marks[1].billable = true;
+        session.marks[1].attribute = Attribute::Skip;
+        session.marks[2].billable = true;
+        session.marks[2]
+            .tags
+            .insert(Tag::from_text("lunch").unwrap());
+        session.marks[3].tags.insert(rust.clone());
+        let aggregator = Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
+        };
+        let untracked = [Tag::from_text("lunch").unwrap()];
+
+        assert_eq!(
+            aggregator.billable_totals(&DateRange::default(), &untracked),
+            Ok((
+                Duration::HOUR,
+                vec![(client.clone(), Duration::HOUR), (rust, Duration::HOUR)]
+            ))
+        );
+        assert_eq!(
+            aggregator.billable_totals(&DateRange::default(), &[]),
+            Ok((
+                Duration::HOUR + Duration::HOUR,
+                vec![
+                    (client, Duration::HOUR + Duration::HOUR),
+                    (Tag::from_text("lunch").unwrap(), Duration::HOUR),
+                    (Tag::from_text("rust").unwrap(), Duration::HOUR),
+                ]
+            ))
+        );
+        let range = DateRange {
+            start: Some(date_default.plus_hours(-3)),
+            end: None,
+        };
+        assert_eq!(
+            aggregator.billable_totals(&range, &untracked),
+            Ok((Duration::ZERO, Vec::new()))
+        );
+    }
+
+    #[test]
+    fn aggregator_build_matches_serial_reading() {
+        let dir = testing::temp_dir("aggregator_build_matches_serial_reading");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let start = DateTime::now().plus_hours(-1000);
+        for i in 0..200 {
+            let mut session = Session::new(&config, &start.plus_hours(i * 2));
+            if i % 7 == 3 {
+                fs::write(&session.path, format!("Scratch notes {i}.")).unwrap();
+                continue;
+            }
+            session.mark(&start.plus_hours(i * 2 + 1), true).unwrap();
+            session.set_attribute(Attribute::Stop).unwrap();
+            session.to_file().unwrap().write().unwrap();
+        }
+
+        let paths = Aggregator::read_sessions_dir(&config).unwrap();
+        let mut sessions = Vec::new();
+        let mut failures = Vec::new();
+        for path in &paths {
+            match Session::from_path(path) {
+                Ok(session) => sessions.push(session),
+                Err(err) => failures.push((path.clone(), err.to_string())),
+            }
+        }
+        let aggregator = Aggregator::build(&config).unwrap();
+        assert_eq!(aggregator.sessions.len(), 171);
+        assert_eq!(aggregator.sessions, sessions);
+        let aggregator_failures: Vec<(PathBuf, String)> = aggregator
+            .failures
+            .iter()
+            .map(|(path, err)| (path.clone(), err.to_string()))
+            .collect();
+        assert_eq!(aggregator_failures, failures);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_read_sessions_dir_ignores_decoys() {
+        let dir = testing::temp_dir("aggregator_read_sessions_dir_ignores_decoys");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let session_names = [
+            "2025-01-07T06:00:00+00:00.md",
+            // Earlier than the above even though it sorts after it.
+            "2025-01-07T10:00:00+05:00.md",
+            "2025-01-08T09:00:00+01:00.md",
+            // Current format, between the legacy ones.
+            "2025-01-07T08-30-00+0100.md",
+        ];
+        for name in session_names {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        for name in [
+            ".DS_Store",
+            ".2025-01-09T09:00:00+01:00.md",
+            "2025-01-09T09:00:00+01:00.md.swp",
+            "2025-01-09T09:00:00+01:00.txt",
+            "2025-01-09T09:00:00+01:00",
+            "notes.md",
+            "2025-01-09.md",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        fs::create_dir(dir.join("archive")).unwrap();
+        fs::create_dir(dir.join("2025-01-10T09:00:00+01:00.md")).unwrap();
+
+        assert_eq!(
+            Aggregator::read_sessions_dir(&config).unwrap(),
+            vec![
+                dir.join(session_names[1]),
+                dir.join(session_names[0]),
+                dir.join(session_names[3]),
+                dir.join(session_names[2]),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(Aggregator::read_sessions_dir(&config).is_err());
+    }
+
+    #[test]
+    fn aggregator_format_works() {
+        let dir = testing::temp_dir("aggregator_format_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let canonical = "# Session\n<!-- tt:v2 -->\n\n- tag `work`\n\nNotes.\n\n## Marks\n\n\
+            ### 2025-01-06 09:00:00 +01:00\n\n- tag `rust`\n\nParser.\n\n\
+            ### 2025-01-06 10:00:00 +01:00\n\n- stop\n";
+        let files = [
+            ("2025-01-06T09-00-00+0100.md", canonical),
+            // Extra blank lines, labels out of order and trailing newlines.
+            (
+                "2025-01-07T09-00-00+0100.md",
+                "\n\n# Session\n\n\n## Marks\n\n\n\n\
+                ### 2025-01-07 09:00:00 +01:00\n\n- tag `rust`\n- skip\n\n\n\
+                ### 2025-01-07 10:00:00 +01:00\n- stop\n\n\n",
+            ),
+            ("2025-01-08T09-00-00+0100.md", "Scratch notes.\n"),
+        ];
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        let paths = Aggregator::read_sessions_dir(&config).unwrap();
+        let messy = dir.join(files[1].0);
+
+        let (changed, failures) = Aggregator::format(&config, &paths, true).unwrap();
+        assert_eq!(changed, vec![messy.clone()]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, dir.join(files[2].0));
+        assert_eq!(fs::read_to_string(&messy).unwrap(), files[1].1);
+
+        let (changed, _) = Aggregator::format(&config, &paths, false).unwrap();
+        assert_eq!(changed, vec![messy.clone()]);
+        assert_eq!(
+            fs::read_to_string(&messy).unwrap(),
+            "# Session\n<!-- tt:v2 -->\n\n## Marks\n\n\
+            ### 2025-01-07 09:00:00 +01:00\n\n- skip\n- tag `rust`\n\n\
+            ### 2025-01-07 10:00:00 +01:00\n\n- stop\n"
+        );
+        assert_eq!(fs::read_to_string(dir.join(files[0].0)).unwrap(), canonical);
+        assert_eq!(
+            fs::read_to_string(dir.join(files[2].0)).unwrap(),
+            files[2].1
+        );
+        let (changed, _) = Aggregator::format(&config, &paths, true).unwrap();
+        assert!(changed.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn format_version_works() {
+        let path = PathBuf::from("sessions");
+        let contents = format!("# Session\n\n{MARKS_HEADING}\n\n### 2025-01-07 09:00:00 +01:00");
+        let file = SessionFile::build(&path, &contents).unwrap();
+        assert_eq!(file.version, FormatVersion::V1);
+        let session = Session::from_file(&file).unwrap();
+        assert_eq!(session.marks.len(), 1);
+        assert_eq!(session.header, "");
+
+        let file = session.to_file().unwrap();
+        assert_eq!(file.version, FormatVersion::V2);
+        assert!(file.contents.starts_with("# Session\n<!-- tt:v2 -->\n\n"));
+        assert_eq!(Session::from_file(&file).unwrap(), session);
+
+        let contents = contents.replacen("\n", "\n<!-- tt:v3 -->\n", 1);
+        let err = SessionFile::build(&path, &contents).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.reason.starts_with("unknown format version `3`"));
+        // Only recognized under the heading.
+        let contents = format!("# Session\n\nText.\n<!-- tt:v3 -->\n\n{MARKS_HEADING}\n\n### 2025-01-07 09:00:00 +01:00");
+        let file = SessionFile::build(&path, &contents).unwrap();
+        assert_eq!(file.version, FormatVersion::V1);
+        assert_eq!(
+            Session::from_file(&file).unwrap().header,
+            "Text.\n<!-- tt:v3 -->"
+        );
+    }
+
+    #[test]
+    fn aggregator_migrate_works() {
+        let dir = testing::temp_dir("aggregator_migrate_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 0,
+            ..Default::default()
+        };
+        let v1 = "# Session\n\n## Marks\n\n### 2025-01-06 09:00:00 +01:00\n\n- stop\n";
+        let v2 =
+            "# Session\n<!-- tt:v2 -->\n\n## Marks\n\n### 2025-01-07 09:00:00 +01:00\n\n- stop\n";
+        let files = [
+            ("2025-01-06T09-00-00+0100.md", v1),
+            ("2025-01-07T09-00-00+0100.md", v2),
+            ("2025-01-08T09-00-00+0100.md", "Scratch notes."),
+        ];
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        let paths = Aggregator::read_sessions_dir(&config).unwrap();
+        let old = dir.join(files[0].0);
+
+        let (migrated, failures) = Aggregator::migrate(&config, &paths).unwrap();
+        assert_eq!(migrated, vec![old.clone()]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, dir.join(files[2].0));
+        assert_eq!(
+            fs::read_to_string(&old).unwrap(),
+            v1.replacen("\n", "\n<!-- tt:v2 -->\n", 1)
+        );
+        // The original is backed up even with backups disabled.
+        let backups = Backup::list(&old).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), v1);
+        assert!(Backup::list(&dir.join(files[1].0)).unwrap().is_empty());
+        assert_eq!(fs::read_to_string(dir.join(files[1].0)).unwrap(), v2);
+
+        let (migrated, _) = Aggregator::migrate(&config, &paths).unwrap();
+        assert!(migrated.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_validate_works() {
+        let dir = testing::temp_dir("aggregator_validate_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let files = [
+            (
+                "2025-01-06T09-00-00+0100.md",
+                "# Session\n\n## Marks\n\n### 2025-01-06 09:00:00 +01:00\n\n- tag `work`\n- stop\n",
+            ),
+            (
+                "2025-01-07T09-00-00+0100.md",
+                "# Session\n\n## Marks\n\n\
+                ### 2025-01-07 09:00:00 +01:00\n\n\
+                ### 2025-01-07 08:00:00 +01:00\n\n\
+                ### 2025-01-07 10:00:00 +01:00\n\n\
+                ### 2025-01-07 10:00:00 +01:00\n\n- stop\n",
+            ),
+            // Named after a different date and still active.
+            (
+                "2025-01-08T09-00-00+0100.md",
+                "# Session\n\n## Marks\n\n\
+                ### 2025-01-08 08:00:00 +01:00\n\n\
+                ### 2025-01-08 09:30:00 +01:00\n",
+            ),
+            // Overlaps with the above.
+            (
+                "2025-01-08T09:00:00+01:00.md",
+                "# Session\n\n## Marks\n\n\
+                ### 2025-01-08 09:00:00 +01:00\n\n\
+                ### 2025-01-08 10:00:00 +01:00\n\n- stop\n",
+            ),
+            ("2025-01-09T09-00-00+0100.md", "Scratch notes."),
+        ];
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        let check = |findings: &[Finding], expected: &[(usize, Option<usize>, &str)]| {
+            assert_eq!(findings.len(), expected.len(), "{findings:?}");
+            for (finding, (file, line, description)) in findings.iter().zip(expected) {
+                assert_eq!(finding.path, dir.join(files[*file].0));
+                assert_eq!(finding.line, *line);
+                assert!(
+                    finding.description.starts_with(description),
+                    "{}",
+                    finding.description
+                );
+            }
+        };
+
+        let findings = Aggregator::validate(&config).unwrap();
+        check(
+            &findings,
+            &[
+                (0, Some(5), "labels are out of order"),
+                (0, Some(5), "session has no tracked time"),
+                (1, Some(7), "mark is before the previous mark"),
+                (1, Some(11), "mark has the same time as the previous mark"),
+                (2, Some(5), "file name doesn't match the first mark"),
+                (4, Some(1), "session file must start with a `# ` heading"),
+                (2, Some(7), "session is active but isn't the newest one"),
+                (3, Some(5), "session overlaps with"),
+            ],
+        );
+        assert_eq!(findings[0].fix, Some(Fix::Rewrite));
+        assert_eq!(findings[4].fix, Some(Fix::Rename));
+        assert_eq!(findings.iter().filter(|v| v.fix.is_some()).count(), 2);
+        assert_eq!(
+            findings[5].to_string(),
+            format!(
+                "{}:1: session file must start with a `# ` heading `Scratch notes.`",
+                dir.join(files[4].0).display()
+            )
+        );
+
+        assert_eq!(Aggregator::fix(&config, &findings).unwrap(), 2);
+        let path_renamed = dir.join("2025-01-08T08-00-00+0100.md");
+        assert!(fs::exists(&path_renamed).unwrap());
+        let findings = Aggregator::validate(&config).unwrap();
+        assert_eq!(findings.len(), 6);
+        assert!(findings.iter().all(|v| v.fix.is_none()));
+        assert!(findings.iter().any(|v| v.path == path_renamed));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aggregator_view_works() {
+        let date_default = testing::date_default();
+        let mark_start = Mark::new(&DateTime::new(&date_default).plus_hours(-2).date);
+        let mark_end = Mark::new(&DateTime::new(&date_default).plus_minutes(-30).date);
+        let mut session_third = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_start, mark_end.clone()],
+            ..Default::default()
+        };
+        let start = DateTime::new(&session_third.start()).to_formatted_pretty_short();
+
+        // Gets ignored because it's not in the current week.
+        let mut session_first = Session {
+            path: session_third.path.clone(),
+            marks: vec![
+                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-12).date),
+                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-9).date),
+            ],
+            ..Default::default()
+        };
+        session_first.marks.last_mut().unwrap().attribute = Attribute::Stop;
+
+        let mut session_second = Session {
+            path: session_third.path.clone(),
+            marks: vec![
+                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-2).date),
+                Mark::new(&DateTime::new(&testing::date_default()).plus_days(-1).date),
+            ],
+            ..Default::default()
+        };
+        session_second.marks.last_mut().unwrap().attribute = Attribute::Stop;
+
+        let aggregator = Aggregator {
+            sessions: vec![
+                session_first.clone(),
+                session_second.clone(),
+                session_third.clone(),
+            ],
+            failures: Vec::new(),
+        };
+
+        let config = Config {
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        // Goes up to current time.
+        let output = aggregator.view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], format!("Start: {start}"));
+        // Not explicitly checking if it included the current time in the calculation, just
+        // excluding the possibility that it calculated only up to the last mark.
+        assert_ne!(lines[1], "Week: 25h 30m 0s");
+        // The active interval reaches into today.
+        assert!(lines[2].starts_with("Today: "));
+        assert_ne!(lines[3], "Time: 1h 30m 0s");
+        assert_ne!(lines[4], "Mark: 0");
+        assert_eq!(lines[5], "Marks: 2");
+        assert_eq!(lines[6], COMMAND_VIEW_MARK_CONTENTS_SEPARATOR);
+        assert_eq!(lines[7], mark_end.to_line());
+
+        session_third
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        session_third.tag_session(&Tag::from_text("review").unwrap());
+        session_third.tag_session(&Tag::from_text("rust").unwrap());
+
+        session_third.marks.pop();
+        session_third.mark(&DateTime::now(), false).unwrap();
+        session_third.set_attribute(Attribute::Stop).unwrap();
+        let mark = &mut session_third.marks[1];
+        mark.date = mark_end.date;
+        let mark_end = session_third.marks.last().unwrap();
+        let aggregator = Aggregator {
+            sessions: vec![
+                session_first.clone(),
+                session_second.clone(),
+                session_third.clone(),
+            ],
+            failures: Vec::new(),
+        };
+        assert_eq!(
+            aggregator.view(&config).unwrap(),
+            format!(
+                "\
+                No active session, last session:\n\
+                Start: {start}\n\
+                Week: 25h 30m 0s\n\
+                Today: 0h 0m 0s\n\
+                Time: 1h 30m 0s\n\
+                Mark: 0\n\
+                Marks: 2  Tags: review, rust\n\
+                {COMMAND_VIEW_MARK_CONTENTS_SEPARATOR}\n\
+                {}\
+                ",
+                mark_end.to_line()
+            )
+        );
+    }
+
+    #[test]
+    fn aggregator_view_handles_degenerate_sessions() {
+        let aggregator = |session| Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
+        };
+        let config = Config {
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        assert!(Aggregator {
+            sessions: Vec::new(),
+            failures: Vec::new(),
+        }
+        .view(&config)
+        .is_err());
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let err = aggregator(session).view(&config).err().unwrap();
+        assert_eq!(err.to_string(), "session `sessions` has no marks");
+
+        // Stopped on its only mark.
+        let mut session = Session::new(&config, &DateTime::new(&testing::date_default()));
+        session.set_attribute(Attribute::Stop).unwrap();
+        let output = aggregator(session).view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "No active session, last session:");
+        assert_eq!(lines[4], "Time: 0h 0m 0s");
+        assert_eq!(lines[5], "Mark: 0");
+        assert_eq!(lines[6], "Marks: 1");
+
+        // Running mark ahead of the current time.
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.mark(&DateTime::now().plus_hours(1), true).unwrap();
+        let output = aggregator(session).view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "Time: 2h 0m 0s");
+        assert_eq!(lines[4], "Mark: 0h 0m 0s");
+
+        // Stale, the time since the last mark isn't counted.
+        let config_stale = Config {
+            auto_stop_after: Some(Duration::from_secs(10 * 60 * 60)),
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-14));
+        session
+            .mark(&DateTime::now().plus_hours(-12), false)
+            .unwrap();
+        let aggregator = aggregator(session);
+        let output = aggregator.view(&config_stale).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Stale session, run `time_tracker start` to stop it at its last mark:"
+        );
+        assert_eq!(lines[4], "Time: 2h 0m 0s");
+        assert_eq!(lines[5], "Mark: 0");
+        let output = aggregator.view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "Time: 14h 0m 0s");
+    }
+
+    #[test]
+    fn aggregator_view_colors_output() {
+        let config = Config {
+            color: ColorMode::Never,
+            timestamp_format: TimestampFormat::Absolute,
+            ..Default::default()
+        };
+        let date = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&config, &date);
+        session
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        session.mark(&date.plus_hours(1), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        let aggregator = Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
+        };
+        let start = date.to_formatted_with(&TimestampFormat::Absolute, &DisplayPrefs::default());
+        let heading = date.plus_hours(1).to_formatted_pretty();
+
+        assert_eq!(
+            aggregator.view(&config).unwrap(),
+            format!(
+                "\
+                No active session, last session:\n\
+                Start: {start}\n\
+                Week: 1h 0m 0s\n\
+                Today: 0h 0m 0s\n\
+                Time: 1h 0m 0s\n\
+                Mark: 0\n\
+                Marks: 2  Tags: rust\n\
+                {COMMAND_VIEW_MARK_CONTENTS_SEPARATOR}\n\
+                ### {heading}\n\
+                \n\
+                - stop\
+                "
+            )
+        );
+        let config = Config {
+            color: ColorMode::Always,
+            ..config
+        };
+        assert_eq!(
+            aggregator.view(&config).unwrap(),
+            format!(
+                "\
+                \x1b[31mNo active session, last session:\x1b[0m\n\
+                Start: {start}\n\
+                Week: 1h 0m 0s\n\
+                Today: 0h 0m 0s\n\
+                Time: 1h 0m 0s\n\
+                Mark: 0\n\
+                Marks: 2  Tags: \x1b[36mrust\x1b[0m\n\
+                \x1b[2m{COMMAND_VIEW_MARK_CONTENTS_SEPARATOR}\x1b[0m\n\
+                ### {heading}\n\
+                \n\
+                - stop\
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn aggregator_view_splits_weeks() {
+        let week_start =
+            DateTime::new(&testing::date_default()).start_of_week(chrono::NaiveTime::MIN);
+        // Marks as hours from the start of the week, the last one stops the session.
+        let session = |hours: &[i64]| {
+            let mut session = Session {
+                path: PathBuf::from("sessions"),
+                marks: hours
+                    .iter()
+                    .map(|v| Mark::new(&week_start.plus_hours(*v).date))
+                    .collect(),
+                ..Default::default()
+            };
+            session.set_attribute(Attribute::Stop).unwrap();
+            session
+        };
+        let config = Config::default();
+        let config_split = Config {
+            week_split: true,
+            ..Default::default()
+        };
+        let week_time = |aggregator: &Aggregator, config: &Config| {
+            let output = aggregator.view(config).unwrap();
+            let line = output.lines().find(|v| v.starts_with("Week: ")).unwrap();
+            line.to_owned()
+        };
+
+        // Ends on the boundary, straddles it and starts on it.
+        let mut aggregator = Aggregator {
+            sessions: vec![session(&[-3, 0]), session(&[-2, -1, 1])],
+            failures: Vec::new(),
+        };
+        assert_eq!(week_time(&aggregator, &config), "Week: 6h 0m 0s");
+        assert_eq!(week_time(&aggregator, &config_split), "Week: 1h 0m 0s");
+        aggregator.sessions.push(session(&[0, 2]));
+        assert_eq!(week_time(&aggregator, &config), "Week: 2h 0m 0s");
+        assert_eq!(week_time(&aggregator, &config_split), "Week: 3h 0m 0s");
+
+        // Intervals are split at the configured start of the day.
+        let config_day_start = Config {
+            day_starts_at: chrono::NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+            week_split: true,
+            ..Default::default()
+        };
+        assert_eq!(week_time(&aggregator, &config_day_start), "Week: 1h 0m 0s");
+    }
+
+    #[test]
+    fn aggregator_view_rounds_session_totals() {
+        let date_default = testing::date_default();
+        let session = |start: i64, end: i64| {
+            let mut session = Session {
+                path: PathBuf::from("sessions"),
+                marks: vec![
+                    Mark::new(&DateTime::new(&date_default).plus_seconds(start).date),
+                    Mark::new(&DateTime::new(&date_default).plus_seconds(end).date),
+                ],
+                ..Default::default()
+            };
+            session.set_attribute(Attribute::Stop).unwrap();
+            session
+        };
+        let mut aggregator = Aggregator {
+            sessions: vec![
+                // 7m 30s, rounds up.
+                session(-5 * 60 * 60, -5 * 60 * 60 + 7 * 60 + 30),
+                // 1h 7m 29s, rounds down.
+                session(-2 * 60 * 60, -60 * 60 + 7 * 60 + 29),
+            ],
+            failures: Vec::new(),
+        };
+        let config = Config {
+            rounding: Duration::from_secs(15 * 60),
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        let output = aggregator.view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[2], "Week: 1h 15m 0s");
+        assert_eq!(lines[4], "Time: 1h 0m 0s");
+
+        let config_plain = Config {
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        let output = aggregator.view(&config_plain).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[2], "Week: 1h 14m 59s");
+        assert_eq!(lines[4], "Time: 1h 7m 29s");
+
+        let config = Config {
+            weekly_goal: Some(Goal::from_text("30h").unwrap()),
+            color: ColorMode::Never,
+            ..Default::default()
+        };
+        let output = aggregator.view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // No pace outside of the current week.
+        assert_eq!(lines[7], "Goal: 1h 14m 59s / 30h 0m 0s (4%)");
+        assert_eq!(lines[8], COMMAND_VIEW_MARK_CONTENTS_SEPARATOR);
+
+        aggregator.sessions[1].set_title("Client X").unwrap();
+        let output = aggregator.view(&config_plain).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "Title: Client X");
+        assert!(lines[2].starts_with("Start: "));
+    }
+
+    #[test]
+    fn session_file_build_works() {
+        let path = PathBuf::new();
+        let contents = get_template(&DateTime::now().to_formatted_pretty());
+        let file = SessionFile::build(&path, &contents).unwrap();
+        assert_eq!(file.contents, format!("{contents}\n"));
+    }
+
+    #[test]
+    fn session_file_get_heading_with_contents_works() {
+        let dt = &DateTime::now();
+        let contents = get_template(&dt.to_formatted_pretty());
+        let heading_contents = SessionFile::get_heading_with_contents(MARKS_HEADING, &contents);
+        assert_eq!(
+            heading_contents,
+            format!(
+                "\
+                {MARKS_HEADING}\n\
+                \n\
+                {MARK_HEADING_PREFIX}{}\
+                ",
+                dt.to_formatted_pretty()
+            )
+        );
+    }
+
+    #[test]
+    fn session_file_get_surrounding_contents_works() {
+        let contents = "\
+            # Session\n\
+            \n\
+            Some text.\n\
+            \n\
+            ## Marks\n\
+            \n\
+            ### 2025-01-07 14:00:00 +01:00\n\
+            \n\
+            #### Detail\n\
+            \n\
+            ## Notes\n\
+            \n\
+            # Other\n\
+            ";
+        assert_eq!(
+            SessionFile::get_surrounding_contents(MARKS_HEADING, contents),
+            (
+                String::from("# Session\n\nSome text."),
+                String::from("## Notes\n\n# Other")
+            )
+        );
+        assert_eq!(
+            SessionFile::get_surrounding_contents(MARKS_HEADING, "# Session\n\n## Marks\n"),
+            (String::from("# Session"), String::new())
+        );
+    }
+
+    #[test]
+    fn session_file_split_marks_works() {
+        let contents = format!(
+            "\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:1\n\
+            \n\
+            First.\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:10\n\
+            \n\
+            {LABEL_STOP}\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:10\n\
+            #### Detail\n\
+            Third.\
+            "
+        );
+        assert_eq!(
+            SessionFile::split_marks(&contents),
+            vec![
+                (2, format!("{MARK_HEADING_PREFIX}2025-01-07 09:1\n\nFirst.")),
+                (
+                    6,
+                    format!("{MARK_HEADING_PREFIX}2025-01-07 09:10\n\n{LABEL_STOP}")
+                ),
+                (
+                    10,
+                    format!("{MARK_HEADING_PREFIX}2025-01-07 09:10\n#### Detail\nThird.")
+                ),
+            ]
+        );
+        assert!(SessionFile::split_marks(MARKS_HEADING).is_empty());
+    }
+
+    #[test]
+    fn session_from_file_keeps_marks_with_equal_timestamps_apart() {
+        let dt = DateTime::now().plus_hours(-1);
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![
+                Mark::new(&dt.date),
+                Mark::new(&dt.date),
+                Mark::new(&dt.date),
+            ],
+            ..Default::default()
+        };
+        session.marks[0].write("First.");
+        session.marks[1]
+            .tags
+            .insert(Tag::from_text("rust").unwrap());
+        session.marks[1].write("Second.");
+        session.marks[2].attribute = Attribute::Stop;
+        let file = session.to_file().unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), session);
+    }
+
+    #[test]
+    fn session_file_get_heading_level_works() {
+        assert_eq!(SessionFile::get_heading_level("# Heading"), 1);
+        assert_eq!(SessionFile::get_heading_level("## Heading"), 2);
+        assert_eq!(SessionFile::get_heading_level("##### Heading"), 5);
+    }
+
+    #[test]
+    fn session_new_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("."),
+            ..Default::default()
+        };
+        let dt = DateTime::now();
+        let mark = Mark::new(&dt.date);
+        let session = Session {
+            path: config
+                .sessions_path
+                .join(format!("{}.md", dt.date.format(SESSION_FILE_NAME_FORMAT))),
+            marks: vec![mark],
+            ..Default::default()
+        };
+        assert_eq!(Session::new(&config, &DateTime::now()), session);
+    }
+
+    #[test]
+    fn session_start_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now());
+        let date = testing::now_plus_secs(30);
+        session.marks.push(Mark::new(&date));
+        assert_eq!(session.start(), session.marks.first().unwrap().date);
+    }
+
+    #[test]
+    fn session_end_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        assert_eq!(session.end(), session.marks.last().unwrap().date);
+        session.mark(&DateTime::now(), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        session.marks.last_mut().unwrap().date = testing::now_plus_secs(30).fixed_offset();
+        assert_eq!(session.end(), session.marks.last().unwrap().date);
+    }
+
+    #[test]
+    fn session_is_active_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        assert!(session.is_active());
+        assert!(session.marks.last().unwrap().attribute != Attribute::Stop);
+        session.mark(&DateTime::now(), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert!(!session.is_active());
+        assert!(session.marks.last().unwrap().attribute == Attribute::Stop);
+    }
+
+    #[test]
+    fn mark_accessors_work() {
+        let config = Config::default();
+        let date = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&config, &date);
+        session.write("Parser.").unwrap();
+        for tag in ["rust", "cli"] {
+            session
+                .tag(&Tag::from_text(tag).unwrap(), &MarkSelector::Last)
+                .unwrap();
+        }
+        session.mark(&date.plus_minutes(30), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        let mark = &session.marks[0];
+        assert_eq!(mark.date(), date);
+        assert_eq!(mark.attribute(), &Attribute::None);
+        assert_eq!(mark.contents(), "Parser.");
+        let tags: Vec<String> = mark.tags().iter().map(|v| v.to_string()).collect();
+        assert_eq!(tags, ["cli", "rust"]);
+        assert_eq!(session.marks[1].attribute(), &Attribute::Stop);
+        assert_eq!(session.duration(), Ok(Duration::from_secs(30 * 60)));
+    }
+
+    #[test]
+    fn session_is_empty_works() {
+        let config = Config::default();
+        let date = DateTime::now().plus_hours(-1);
+        let session = Session::new(&config, &date);
+        assert!(session.is_empty());
+        let mut written = session.clone();
+        written.write("Notes.").unwrap();
+        assert!(!written.is_empty());
+        let mut tagged = session.clone();
+        tagged.tag_session(&Tag::from_text("rust").unwrap());
+        assert!(!tagged.is_empty());
+        let mut titled = session.clone();
+        titled.set_title("Parser").unwrap();
+        assert!(!titled.is_empty());
+
+        // Contents don't matter without any time.
+        let mut stopped = written.clone();
+        stopped.set_attribute(Attribute::Stop).unwrap();
+        assert!(stopped.is_empty());
+        let mut stopped = written.clone();
+        stopped.mark(&date.plus_minutes(5), false).unwrap();
+        stopped.set_attribute(Attribute::Stop).unwrap();
+        assert!(!stopped.is_empty());
+        let mut marked = session.clone();
+        marked.mark(&date.plus_minutes(5), false).unwrap();
+        assert!(!marked.is_empty());
+    }
+
+    // It ignores `mark_first` and counts to current time, so `mark_second` is the final time.
+    #[test]
+    fn session_get_time_ignores_marks_if_they_are_skipped() {
+        let mut mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
+        mark_first.attribute = Attribute::Skip;
+        let mark_second = Mark::new(&testing::now_plus_secs(-54 * 60 - 10)); // 54m 10s
+        let mark_third = Mark::new(&testing::now_plus_secs(-10 * 60));
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second, mark_third],
+            ..Default::default()
+        };
+        assert_eq!(session.get_time(&[]), Ok(Duration::from_secs(54 * 60 + 10)));
+    }
+
+    #[test]
+    fn session_get_time_ignores_current_time_if_last_mark_is_skipped() {
+        let mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
+        let mut mark_second = Mark::new(&testing::now_plus_secs(-60 * 60 - 33 * 60 - 20)); // 1h 33m 20s
+        mark_second.attribute = Attribute::Skip;
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second],
+            ..Default::default()
+        };
+        assert_eq!(
+            session.get_time(&[]),
+            Ok(Duration::from_secs(60 * 60 + 26 * 60 + 40))
+        );
+    }
+
+    #[test]
+    fn session_get_break_time_counts_marks_if_they_are_skipped() {
+        let mut mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
+        mark_first.attribute = Attribute::Skip;
+        let mark_second = Mark::new(&testing::now_plus_secs(-54 * 60 - 10)); // 54m 10s
+        let mark_third = Mark::new(&testing::now_plus_secs(-10 * 60));
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second, mark_third],
+            ..Default::default()
+        };
+        assert_eq!(
+            session.get_break_time(&[]),
+            Ok(Duration::from_secs(2 * 60 * 60 + 5 * 60 + 50))
+        );
+    }
+
+    #[test]
+    fn session_get_break_time_counts_current_time_if_last_mark_is_skipped() {
+        let mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
+        let mut mark_second = Mark::new(&testing::now_plus_secs(-60 * 60 - 33 * 60 - 20)); // 1h 33m 20s
+        mark_second.attribute = Attribute::Skip;
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second],
+            ..Default::default()
+        };
+        assert_eq!(
+            session.get_break_time(&[]),
+            Ok(Duration::from_secs(60 * 60 + 33 * 60 + 20))
+        );
+    }
+
+    #[test]
+    fn session_get_time_works_with_equal_timestamps() {
+        let mark_first = Mark::new(&testing::date_default());
+        let mut mark_second = Mark::new(&testing::date_default());
+        mark_second.attribute = Attribute::Stop;
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second],
+            ..Default::default()
+        };
+        assert_eq!(session.get_time(&[]), Ok(Duration::ZERO));
+    }
+
+    #[test]
+    fn session_get_time_fails_when_marks_are_out_of_order() {
+        let mark_first = Mark::new(&testing::date_default());
+        let mut mark_second =
+            Mark::new(&DateTime::new(&testing::date_default()).plus_hours(-2).date);
+        mark_second.attribute = Attribute::Stop;
+        let session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first.clone(), mark_second.clone()],
+            ..Default::default()
+        };
+        let err = session.get_time(&[]).unwrap_err();
+        assert!(err.contains(&DateTime::new(&mark_first.local_date()).to_formatted_pretty()));
+        assert!(err.contains(&DateTime::new(&mark_second.local_date()).to_formatted_pretty()));
+
+        let aggregator = Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
+        };
+        assert!(aggregator.view(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn session_mark_works() {
+        let dt = DateTime::now();
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        let mark = Mark::new(&dt.date);
+        session.mark(&DateTime::now(), false).unwrap();
+        assert_eq!(session.marks.len(), 2);
+        assert_eq!(session.marks[1], mark);
+    }
+
+    #[test]
+    fn session_mark_preserves_integrity_of_previous_content() {
+        let dt = DateTime::now();
+        let mark_first = Mark::new(&dt.plus_hours(-2).date);
+        let mark_second = Mark::new(&dt.plus_minutes(-13).date);
+        let mut session = Session {
+            path: PathBuf::from(format!(
+                "./sessions/{}.md",
+                dt.date.format(SESSION_FILE_NAME_FORMAT)
+            )),
+            marks: vec![mark_first, mark_second],
+            ..Default::default()
+        };
+        let mut clone = session.clone();
+        session.mark(&DateTime::now(), false).unwrap();
+        assert_eq!(session.marks.len(), 3);
+        clone.marks.push(session.marks[2].clone());
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_mark_fails_when_not_after_previous_mark() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![Mark::new(&testing::now_plus_secs(-60 * 60))],
+            ..Default::default()
+        };
+        let clone = session.clone();
+        assert!(session
+            .mark(&DateTime::now().plus_hours(-1), false)
+            .is_err());
+        assert!(session
+            .mark(&DateTime::now().plus_hours(-2), false)
+            .is_err());
+        assert_eq!(session, clone);
+        assert!(session.mark(&DateTime::now(), false).is_ok());
+    }
+
+    #[test]
+    fn session_mark_fails_when_too_far_in_future() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![Mark::new(&testing::now_plus_secs(-60 * 60))],
+            ..Default::default()
+        };
+        assert!(session
+            .mark(&DateTime::now().plus_minutes(1), false)
+            .is_ok());
+        assert!(session
+            .mark(&DateTime::now().plus_minutes(10), false)
+            .is_err());
+        assert_eq!(session.marks.len(), 2);
+        assert!(session
+            .mark(&DateTime::now().plus_minutes(10), true)
+            .is_ok());
+        assert_eq!(session.marks.len(), 3);
+    }
+
+    #[test]
+    fn cannot_mark_when_session_ended() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.mark(&DateTime::now(), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        let clone = session.clone();
+        assert!(session.mark(&DateTime::now(), false).is_err());
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_remark_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.mark(&DateTime::now(), false).unwrap();
+        let clone = session.clone();
+        session.marks.last_mut().unwrap().date = testing::now_plus_secs(30).fixed_offset();
+        assert_ne!(session, clone);
+        session.remark(&DateTime::now(), false).unwrap();
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_remark_fails_when_not_after_previous_mark() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![
+                Mark::new(&testing::now_plus_secs(-2 * 60 * 60)),
+                Mark::new(&testing::now_plus_secs(-60 * 60)),
+            ],
+            ..Default::default()
+        };
+        let clone = session.clone();
+        assert!(session
+            .remark(&DateTime::now().plus_hours(-2), false)
+            .is_err());
+        assert!(session
+            .remark(&DateTime::now().plus_hours(-3), false)
+            .is_err());
+        assert!(session
+            .remark(&DateTime::now().plus_minutes(10), false)
+            .is_err());
+        assert_eq!(session, clone);
+        session
+            .remark(&DateTime::now().plus_hours(-1).plus_minutes(-30), false)
+            .unwrap();
+        assert_eq!(
+            session.marks.last().unwrap().date,
+            DateTime::now().plus_minutes(-90).date
+        );
+    }
+
+    #[test]
+    fn session_remark_moves_start_of_single_mark_session() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![Mark::new(&testing::now_plus_secs(-60 * 60))],
+            ..Default::default()
+        };
+        session
+            .remark(&DateTime::now().plus_hours(-3), false)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_hours(-3).date);
+        assert!(session
+            .remark(&DateTime::now().plus_minutes(10), false)
+            .is_err());
+        session
+            .remark(&DateTime::now().plus_minutes(10), true)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_minutes(10).date);
+    }
+
+    #[test]
+    fn session_remark_first_works() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![
+                Mark::new(&testing::now_plus_secs(-2 * 60 * 60)),
+                Mark::new(&testing::now_plus_secs(-60 * 60)),
+            ],
+            ..Default::default()
+        };
+        let clone = session.clone();
+        assert!(session
+            .remark_first(&DateTime::now().plus_hours(-1), false)
+            .is_err());
+        assert!(session
+            .remark_first(&DateTime::now().plus_minutes(-30), false)
+            .is_err());
+        assert_eq!(session, clone);
+        session
+            .remark_first(&DateTime::now().plus_minutes(-145), false)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_minutes(-145).date);
+        assert_eq!(session.marks[1], clone.marks[1]);
+        session
+            .remark_first(&DateTime::now().plus_minutes(-61), false)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_minutes(-61).date);
+    }
+
+    #[test]
+    fn session_remark_first_moves_single_mark() {
+        let mut session = Session {
+            path: PathBuf::from("sessions"),
+            marks: vec![Mark::new(&testing::now_plus_secs(-60 * 60))],
+            ..Default::default()
+        };
+        session
+            .remark_first(&DateTime::now().plus_hours(-3), false)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_hours(-3).date);
+        assert_eq!(session.marks.len(), 1);
+        assert!(session
+            .remark_first(&DateTime::now().plus_minutes(10), false)
+            .is_err());
+        session
+            .remark_first(&DateTime::now().plus_minutes(10), true)
+            .unwrap();
+        assert_eq!(session.start(), DateTime::now().plus_minutes(10).date);
+    }
+
+    #[test]
+    fn session_save_is_atomic() {
+        let dir = testing::temp_dir("session_save_is_atomic");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 0,
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        session.write("Saved.").unwrap();
+        session.save(&config).unwrap();
+        let contents = fs::read_to_string(&session.path).unwrap();
+        assert_eq!(contents, session.to_file().unwrap().contents);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Leftover from a crash doesn't prevent saving.
+        let path_tmp = dir.join(format!(
+            ".{}.tmp",
+            session.path.file_name().unwrap().to_string_lossy()
+        ));
+        fs::write(&path_tmp, "partial").unwrap();
+        session.save(&config).unwrap();
+        assert!(!fs::exists(&path_tmp).unwrap());
+
+        // The temporary file can't be created, the original stays as it was.
+        fs::create_dir(&path_tmp).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert!(session.save(&config).is_err());
+        assert_eq!(fs::read_to_string(&session.path).unwrap(), contents);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_save_backs_up_previous_contents() {
+        let dir = testing::temp_dir("session_save_backs_up_previous_contents");
+        let config = Config {
+            sessions_path: dir.clone(),
+            backups: 2,
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        session.save(&config).unwrap();
+        assert!(Backup::list(&session.path).unwrap().is_empty());
+
+        let mut saved = Vec::new();
+        for text in ["First.", "Second.", "Third."] {
+            saved.push(fs::read_to_string(&session.path).unwrap());
+            session.marks.last_mut().unwrap().erase();
+            session.write(text).unwrap();
+            session.save(&config).unwrap();
+        }
+        let backups = Backup::list(&session.path).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), saved[2]);
+        assert_eq!(fs::read_to_string(&backups[1].path).unwrap(), saved[1]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_restore_works() {
+        let dir = testing::temp_dir("session_restore_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        let contents = fs::read_to_string(&session.path).unwrap();
+        session.write("Mistake.").unwrap();
+        session.save(&config).unwrap();
+        let contents_mistake = fs::read_to_string(&session.path).unwrap();
+
+        let backups = Backup::list(&session.path).unwrap();
+        session.restore(&backups[0], &config).unwrap();
+        assert_eq!(fs::read_to_string(&session.path).unwrap(), contents);
+        // Restoring is backed up as well.
+        let backups = Backup::list(&session.path).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(
+            fs::read_to_string(&backups[0].path).unwrap(),
+            contents_mistake
+        );
+
+        fs::write(&backups[0].path, "Scratch notes.").unwrap();
+        assert!(session.restore(&backups[0], &config).is_err());
+        assert_eq!(fs::read_to_string(&session.path).unwrap(), contents);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_get_last_works_per_workspace() {
+        let dir = testing::temp_dir("session_get_last_works_per_workspace");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let config_named = Config {
+            sessions_path: dir.clone(),
+            workspace: Some(String::from("internal")),
+            ..Default::default()
+        };
+        let session = Session::new(&config, &DateTime::now().plus_hours(-3));
+        session.save(&config).unwrap();
+        let session_named = Session::new(&config_named, &DateTime::now().plus_hours(-1));
+        session_named.save(&config_named).unwrap();
+        assert!(session_named
+            .path
+            .to_string_lossy()
+            .ends_with("__internal.md"));
+        assert_eq!(session.get_name(), None);
+        assert_eq!(session_named.get_name(), Some("internal"));
+        assert_eq!(
+            Session::date_from_path(&session_named.path),
+            Some(session_named.marks[0].date)
+        );
+
+        assert_eq!(Session::get_last(&config).unwrap(), Some(session.clone()));
+        assert_eq!(
+            Session::get_last(&config_named).unwrap(),
+            Some(session_named.clone())
+        );
+        let config_other = Config {
+            sessions_path: dir.clone(),
+            workspace: Some(String::from("other")),
+            ..Default::default()
+        };
+        assert_eq!(Session::get_last(&config_other).unwrap(), None);
+
+        // Both active, each listed and summed into the week.
+        let aggregator = Aggregator::build(&config).unwrap();
+        let output = aggregator.view(&config).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "Week: 4h 0m 0s");
+        assert_eq!(lines[3], "Time: 3h 0m 0s");
+        assert_eq!(lines[6], "Active: default, internal");
+        let output = aggregator.view(&config_named).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[3], "Time: 1h 0m 0s");
+        assert!(aggregator.view(&config_other).is_err());
+        assert_eq!(Aggregator::validate(&config).unwrap(), Vec::new());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_get_last_skips_non_session_files() {
+        let dir = testing::temp_dir("session_get_last_skips_non_session_files");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        assert_eq!(Session::get_last(&config).unwrap(), None);
+
+        let session_first = Session::new(&config, &DateTime::now().plus_hours(-3));
+        session_first.save(&config).unwrap();
+        let session_second = Session::new(&config, &DateTime::now().plus_hours(-2));
+        session_second.save(&config).unwrap();
+        // Newer than both sessions.
+        let path_broken = Session::new(&config, &DateTime::now().plus_hours(-1)).path;
+        fs::write(&path_broken, "Scratch notes.").unwrap();
+        fs::write(dir.join("zz-notes.md"), "Scratch notes.").unwrap();
+        assert_eq!(Session::get_last(&config).unwrap(), Some(session_second));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_get_last_stopped_works() {
+        let dir = testing::temp_dir("session_get_last_stopped_works");
+        let mut config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let mut session_first = Session::new(&config, &DateTime::now().plus_hours(-5));
+        session_first.set_attribute(Attribute::Stop).unwrap();
+        session_first.save(&config).unwrap();
+        assert_eq!(
+            Session::get_last_stopped(&config).unwrap(),
+            Some(session_first.clone())
+        );
+        let session_second = Session::new(&config, &DateTime::now().plus_hours(-3));
+        session_second.save(&config).unwrap();
+        assert_eq!(
+            Session::get_last_stopped(&config).unwrap(),
+            Some(session_first)
+        );
+        config.auto_stop_after = Some(Duration::from_secs(60 * 60));
+        assert_eq!(
+            Session::get_last_stopped(&config).unwrap(),
+            Some(session_second)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_rename_to_start_works() {
+        let dir = testing::temp_dir("session_rename_to_start_works");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        let path_old = session.path.clone();
+        assert!(!session.rename_to_start().unwrap());
+        assert_eq!(session.path, path_old);
+
+        let dt = DateTime::now().plus_hours(-2);
+        session.remark(&dt, false).unwrap();
+        assert!(session.rename_to_start().unwrap());
+        session.save(&config).unwrap();
+        assert_eq!(
+            session.path,
+            dir.join(format!("{}.md", dt.date.format(SESSION_FILE_NAME_FORMAT)))
+        );
+        assert!(!fs::exists(&path_old).unwrap());
+        assert_eq!(Backup::list(&session.path).unwrap().len(), 1);
+        let contents = fs::read_to_string(&session.path).unwrap();
+        let file = SessionFile::build(&session.path, &contents).unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), session);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_rename_to_start_keeps_legacy_file_names() {
+        let dir = testing::temp_dir("session_rename_to_start_keeps_legacy_file_names");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let dt = DateTime::now().plus_hours(-1);
+        let mut session = Session::new(&config, &dt);
+        session.path = dir.join(format!(
+            "{}.md",
+            dt.date.format(SESSION_FILE_NAME_FORMAT_LEGACY)
+        ));
+        session.save(&config).unwrap();
+        let path_legacy = session.path.clone();
+        assert!(!session.rename_to_start().unwrap());
+        assert_eq!(Session::get_last(&config).unwrap(), Some(session.clone()));
+
+        let dt = dt.plus_minutes(-30);
+        session.remark(&dt, false).unwrap();
+        assert!(session.rename_to_start().unwrap());
+        assert_eq!(
+            session.path,
+            dir.join(format!("{}.md", dt.date.format(SESSION_FILE_NAME_FORMAT)))
+        );
+        assert!(!fs::exists(&path_legacy).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_rename_to_start_fails_when_file_exists() {
+        let dir = testing::temp_dir("session_rename_to_start_fails_when_file_exists");
+        let config = Config {
+            sessions_path: dir.clone(),
+            ..Default::default()
+        };
+        let dt = DateTime::now().plus_hours(-2);
+        let other = Session::new(&config, &dt);
+        other.save(&config).unwrap();
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.save(&config).unwrap();
+        let path_old = session.path.clone();
+        session.remark(&dt, false).unwrap();
+        assert!(session.rename_to_start().is_err());
+        assert_eq!(session.path, path_old);
+        assert!(fs::exists(&path_old).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn session_unmark_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now());
+
+        assert_eq!(session.marks.len(), 1);
+        assert!(session.unmark(&MarkSelector::Last, false).is_err());
+        assert_eq!(session.marks.len(), 1);
+
+        session.mark(&DateTime::now().plus_hours(1), true).unwrap();
+        assert_eq!(session.marks.len(), 2);
+
+        let mark_first = session.marks.first().unwrap().clone();
+        let mark_last = session.marks.last().unwrap().clone();
+        assert_ne!(mark_first, mark_last);
+        assert_eq!(session.unmark(&MarkSelector::Last, false), Ok(mark_last));
+        assert_eq!(session.marks.len(), 1);
+        assert_eq!(session.marks.last().unwrap(), &mark_first);
+    }
+
+    #[test]
+    fn session_unmark_removes_selected_mark() {
+        let start = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&Config::default(), &start);
+        for minutes in [30, 60, 90] {
+            session.mark(&start.plus_minutes(minutes), false).unwrap();
+        }
+        session.set_attribute(Attribute::Stop).unwrap();
+        let marks = session.marks.clone();
+
+        assert!(session.unmark(&MarkSelector::Index(1), true).is_err());
+        assert!(session.unmark(&MarkSelector::Index(-4), true).is_err());
+        assert!(session.unmark(&MarkSelector::Last, false).is_err());
+        assert!(session.unmark(&MarkSelector::Index(5), false).is_err());
+        assert_eq!(session.marks, marks);
+
+        assert_eq!(
+            session.unmark(&MarkSelector::At(start.plus_minutes(60)), false),
+            Ok(marks[2].clone())
+        );
+        assert_eq!(
+            session.unmark(&MarkSelector::Index(-2), false),
+            Ok(marks[1].clone())
+        );
+        assert_eq!(session.marks, [marks[0].clone(), marks[3].clone()]);
+        assert_eq!(
+            session.summarize(&[]).unwrap().time,
+            Duration::from_secs(90 * 60)
+        );
+        assert_eq!(
+            session.unmark(&MarkSelector::Last, true),
+            Ok(marks[3].clone())
+        );
+        assert!(session.is_active());
+    }
+
+    #[test]
+    fn mark_to_compact_line_works() {
+        let date = DateTime::new(&testing::date_default());
+        let mut mark = Mark::new(&date.date);
+        assert_eq!(mark.to_compact_line(), date.to_formatted_pretty());
+        mark.attribute = Attribute::Skip;
+        mark.billable = true;
+        mark.tags = HashSet::from([
+            Tag::from_text("web").unwrap(),
+            Tag::from_text("rust").unwrap(),
+        ]);
+        mark.contents = String::from("Parser");
+        assert_eq!(
+            mark.to_compact_line(),
+            format!("{} skip billable #rust #web", date.to_formatted_pretty())
+        );
+        mark.host = Some(String::from("laptop"));
+        assert!(mark.to_compact_line().ends_with(" #web @laptop"));
+    }
+
+    #[test]
+    fn session_set_attribute_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now());
+        assert_eq!(session.marks.last().unwrap().attribute, Attribute::None);
+        session.set_attribute(Attribute::Skip).unwrap();
+        assert_eq!(session.marks.last().unwrap().attribute, Attribute::Skip);
+    }
+
+    #[test]
+    fn session_tag_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        // To have at least 2 marks.
+        session.mark(&DateTime::now(), false).unwrap();
+        let mut clone = session.clone();
+        session
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        clone
+            .marks
+            .last_mut()
+            .unwrap()
+            .tags
+            .insert(Tag::from_text("rust").unwrap().clone());
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_untag_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        // To have at least 2 marks.
+        session.mark(&DateTime::now(), false).unwrap();
+        let clone = session.clone();
+        session
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        session
+            .untag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_tag_selects_mark() {
+        let config = Config::default();
+        let now = DateTime::now();
+        let mut session = Session::new(&config, &now.plus_hours(-3));
+        session.mark(&now.plus_hours(-2), false).unwrap();
+        session.mark(&now.plus_hours(-1), false).unwrap();
+        let tag = Tag::from_text("rust").unwrap();
+        let has_tag = |session: &Session| -> Vec<bool> {
+            session
+                .marks
+                .iter()
+                .map(|v| v.tags.contains(&tag))
+                .collect()
+        };
+
+        assert_eq!(session.tag(&tag, &MarkSelector::Index(-2)), Ok(true));
+        assert_eq!(has_tag(&session), vec![false, true, false]);
+        assert_eq!(session.tag(&tag, &MarkSelector::Index(1)), Ok(true));
+        assert_eq!(session.tag(&tag, &MarkSelector::Index(-3)), Ok(false));
+        assert_eq!(has_tag(&session), vec![true, true, false]);
+        assert_eq!(
+            session.tag(&tag, &MarkSelector::At(now.plus_hours(-1))),
+            Ok(true)
+        );
+        assert_eq!(has_tag(&session), vec![true, true, true]);
+        assert_eq!(session.untag(&tag, &MarkSelector::Last), Ok(true));
+        assert_eq!(
+            session.untag(&tag, &MarkSelector::At(now.plus_hours(-2))),
+            Ok(true)
+        );
+        assert_eq!(has_tag(&session), vec![true, false, false]);
+
+        for index in [0, 4, -4] {
+            assert_eq!(
+                session.tag(&tag, &MarkSelector::Index(index)),
+                Err(format!(
+                    "mark {index} is out of range, the session has 3 marks"
+                ))
+            );
+        }
+        assert!(session
+            .tag(&tag, &MarkSelector::At(now.plus_minutes(-30)))
+            .is_err());
+        session.mark(&now.plus_seconds(-1), false).unwrap();
+        session.mark(&now, false).unwrap();
+        let at = DateTime::new(&now.date.with_second(0).unwrap().with_nanosecond(0).unwrap());
+        if now.plus_seconds(-1).date >= at.date {
+            assert_eq!(
+                session.tag(&tag, &MarkSelector::At(at.clone())),
+                Err(format!(
+                    "2 marks at {}, select one with `--mark`",
+                    at.to_formatted_pretty()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn aggregator_retag_works() {
+        let config = Config::default();
+        let now = DateTime::now();
+        let from = Tag::from_text("clienta").unwrap();
+        let to = Tag::from_text("client-alpha").unwrap();
+        let mut session_first = Session::new(&config, &now.plus_hours(-5));
+        session_first.tag_session(&from);
+        session_first.tag(&from, &MarkSelector::Last).unwrap();
+        session_first.mark(&now.plus_hours(-4), false).unwrap();
+        // Already has the new tag too.
+        session_first.tag(&from, &MarkSelector::Last).unwrap();
+        session_first.tag(&to, &MarkSelector::Last).unwrap();
+        session_first.set_attribute(Attribute::Stop).unwrap();
+        let mut session_second = Session::new(&config, &now.plus_hours(-3));
+        session_second
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        let mut aggregator = Aggregator {
+            sessions: vec![session_first.clone(), session_second.clone()],
+            failures: Vec::new(),
+        };
+
+        let changed = aggregator.retag(&from, &to);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0.path, session_first.path);
+        assert_eq!(changed[0].1, 3);
+        assert_eq!(session_first.retag(&from, &to), 3);
+        assert_eq!(aggregator.sessions, vec![session_first, session_second]);
+        assert!(aggregator.retag(&from, &to).is_empty());
+    }
+
+    #[test]
+    fn session_write_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        // To have at least 2 marks.
+        session.mark(&DateTime::now(), false).unwrap();
+        let mut clone = session.clone();
+        session.write("hello").unwrap();
+        clone.marks.iter_mut().last().unwrap().write("hello");
+        assert_eq!(session, clone);
+    }
+
+    #[test]
+    fn session_write_errors_when_there_is_content() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let mut session = Session::new(&config, &DateTime::now());
+        session.write("Some content.").unwrap();
+        assert!(session.write("Some other content.").is_err());
+    }
+
+    #[test]
+    fn session_write_at_works() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let start = DateTime::now().plus_hours(-1);
+        let mut session = Session::new(&config, &start);
+        session.mark(&start.plus_minutes(30), false).unwrap();
+        let notes = WriteTarget::Session;
+        let first = WriteTarget::Mark(MarkSelector::Index(-2));
+
+        session.write_at(&notes, "Plan.", WriteMode::New).unwrap();
+        assert!(session.write_at(&notes, "Other.", WriteMode::New).is_err());
+        session
+            .write_at(&notes, "Done.", WriteMode::Append)
+            .unwrap();
+        assert_eq!(session.text_at(&notes), Ok("Plan.\n\nDone."));
+        session
+            .write_at(&notes, "Redo.", WriteMode::Overwrite)
+            .unwrap();
+        assert_eq!(session.header, "Redo.");
+
+        session
+            .write_at(&first, "Parser.", WriteMode::Append)
+            .unwrap();
+        assert!(session.write_at(&first, "Lexer.", WriteMode::New).is_err());
+        session
+            .write_at(&first, "Lexer.", WriteMode::Append)
+            .unwrap();
+        assert_eq!(session.marks[0].contents, "Parser.\n\nLexer.");
+        session
+            .write_at(&first, "Tests.", WriteMode::Overwrite)
+            .unwrap();
+        assert_eq!(session.text_at(&first), Ok("Tests."));
+        let title = WriteMode::Title { force: false };
+        assert!(session.write_at(&notes, "Plan", title).is_err());
+        assert!(session.write_at(&first, "Lexer", title).is_err());
+        session.write_at(&first, "Tests.", title).unwrap();
+        assert_eq!(session.marks[0].contents, "Tests.");
+        assert_eq!(session.marks[1].contents, "");
+
+        let missing = WriteTarget::Mark(MarkSelector::Index(3));
+        assert!(session
+            .write_at(&missing, "x", WriteMode::Overwrite)
+            .is_err());
+        assert!(session.text_at(&missing).is_err());
+
+        // Notes can still be written once the stop mark is locked.
+        session.set_attribute(Attribute::Stop).unwrap();
+        let last = WriteTarget::Mark(MarkSelector::Last);
+        assert!(session.write_at(&last, "x", WriteMode::Overwrite).is_err());
+        assert_eq!(session.text_at(&last), Ok(""));
+        session
+            .write_at(&notes, "Wrap-up.", WriteMode::Append)
+            .unwrap();
+        let file = session.to_file().unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), session);
+    }
+
+    #[test]
+    fn session_ended_last_mark_is_locked() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let tag = Tag::from_text("rust").unwrap();
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-1));
+        session.tag(&tag, &MarkSelector::Last).unwrap();
+        session.mark(&DateTime::now(), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        let ended = session.clone();
+
+        assert!(session.set_attribute(Attribute::Skip).is_err());
+        assert!(session.set_attribute(Attribute::None).is_err());
+        assert!(session.write("Too late.").is_err());
+        assert!(session.tag(&tag, &MarkSelector::Last).is_err());
+        assert!(session.untag(&tag, &MarkSelector::Index(-1)).is_err());
+        assert_eq!(session, ended);
+        // Stopping again changes nothing and earlier marks stay editable.
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert_eq!(session.untag(&tag, &MarkSelector::Index(1)), Ok(true));
+
+        session.overwrite_attribute(Attribute::None);
+        assert!(session.is_active());
+        session.write("Reopened.").unwrap();
+    }
+
+    #[test]
+    fn session_from_file_works() {
+        let DateTime { date, .. } = DateTime::now();
+        let mark_first_dt = DateTime {
+            date: date.with_hour(5).unwrap().with_minute(23).unwrap(),
+        };
+        let mark_first = Mark::new(&mark_first_dt.date);
+        // Same time as the first mark.
+        let mark_second_dt = DateTime {
+            date: mark_first.local_date().with_minute(23).unwrap(),
+        };
+        let mut mark_second = Mark::new(&mark_second_dt.date);
+        mark_second.attribute = Attribute::Stop;
+
+        let contents = format!(
+            "\
+                {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+                \n\
+                {MARKS_HEADING}\n\
+                \n\
+                {MARK_HEADING_PREFIX}{}\n\
+                \n\
+                {MARK_HEADING_PREFIX}{}\n\
+                \n\
+                {LABEL_STOP}\n\
+                ",
+            mark_first_dt.to_formatted_pretty(),
+            mark_second_dt.to_formatted_pretty(),
+        );
+        let file = SessionFile::build(&PathBuf::new(), &contents).unwrap();
+        let session = Session {
+            path: file.path.clone(),
+            marks: vec![mark_first, mark_second],
+            ..Default::default()
+        };
+
+        assert_eq!(Session::from_file(&file).unwrap(), session);
+    }
+
+    #[test]
+    fn session_from_file_keeps_mark_offsets() {
+        let marker = FormatVersion::CURRENT.to_marker();
+        // Travelled from UTC+2 to UTC-5 in the middle of the session.
+        let contents = format!(
+            "\
+                {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+                {marker}\n\
+                \n\
+                {MARKS_HEADING}\n\
+                \n\
+                {MARK_HEADING_PREFIX}2025-01-07 14:00:00 +02:00\n\
+                \n\
+                {MARK_HEADING_PREFIX}2025-01-07 09:30:00 -05:00\n\
+                \n\
+                {LABEL_STOP}\n\
+                \n\
+                "
+        );
+        let file =
+            SessionFile::build(&PathBuf::from("2025-01-07T14:00:00+02:00.md"), &contents).unwrap();
+        let session = Session::from_file(&file).unwrap();
+        assert_eq!(
+            session.get_time(&[]),
+            Ok(Duration::from_secs(2 * 60 * 60 + 30 * 60))
+        );
+        assert_eq!(
+            session.start(),
+            chrono::DateTime::parse_from_rfc3339("2025-01-07T12:00:00Z").unwrap()
+        );
+        assert!(session.start() < session.end());
+        assert_eq!(session.to_file().unwrap(), file);
+
+        let mut session = session;
+        assert!(!session.rename_to_start().unwrap());
+    }
+
+    #[test]
+    fn session_from_file_parses_crlf() {
+        let contents = "# Session — Parser\r\n<!-- tt:v2 -->\r\n\r\n- tag `work`\r\n\r\n\
+            Notes.\r\n\r\n## Marks\r\n\r\n\
+            ### 2025-01-07 09:00:00 +01:00\r\n\r\n- billable\r\n- tag `rust`\r\n\r\n\
+            First line.\r\n\r\nSecond paragraph.\r\n\r\n\
+            ### 2025-01-07 10:00:00 +01:00\r\n\r\n- stop\r\n";
+        let file = SessionFile::build(&PathBuf::from("sessions"), contents).unwrap();
+        assert!(!file.contents.contains('\r'));
+        let session = Session::from_file(&file).unwrap();
+        assert_eq!(session.title, Some(String::from("Parser")));
+        assert_eq!(
+            session.tags,
+            HashSet::from([Tag::from_text("work").unwrap()])
+        );
+        assert_eq!(session.marks.len(), 2);
+        let first = &session.marks[0];
+        assert_eq!(
+            first.date,
+            chrono::DateTime::parse_from_rfc3339("2025-01-07T09:00:00+01:00").unwrap()
+        );
+        assert!(first.billable);
+        assert_eq!(first.tags, HashSet::from([Tag::from_text("rust").unwrap()]));
+        assert_eq!(first.contents, "First line.\n\nSecond paragraph.");
+        assert_eq!(session.marks[1].attribute, Attribute::Stop);
+
+        let written = session.to_file().unwrap().contents;
+        assert!(!written.contains('\r'));
+        assert_eq!(written, contents.replace("\r\n", "\n"));
     }
 
     #[test]
-    fn session_file_get_heading_with_contents_works() {
-        let dt = &DateTime::now();
-        let contents = get_template(&dt.to_formatted_pretty());
-        let heading_contents = SessionFile::get_heading_with_contents(MARKS_HEADING, &contents);
-        assert_eq!(
-            heading_contents,
-            format!(
-                "\
+    fn session_remark_uses_local_offset() {
+        let contents = format!(
+            "\
+                {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+                \n\
                 {MARKS_HEADING}\n\
                 \n\
-                {MARK_HEADING_PREFIX}{}\
-                ",
-                dt.to_formatted_pretty()
-            )
+                {MARK_HEADING_PREFIX}2025-01-07 14:00:00 +14:00\n\
+                \n\
+                {MARK_HEADING_PREFIX}2025-01-07 09:30:00 -12:00\n\
+                \n\
+                "
         );
+        let file = SessionFile::build(&PathBuf::from("sessions"), &contents).unwrap();
+        let mut session = Session::from_file(&file).unwrap();
+        let dt = DateTime::now();
+        session.remark(&dt, false).unwrap();
+        let contents = session.to_file().unwrap().contents;
+        assert!(contents.contains("2025-01-07 14:00:00 +14:00"));
+        assert!(!contents.contains("-12:00"));
+        assert!(contents.contains(&dt.to_formatted_pretty()));
     }
 
     #[test]
-    fn session_file_get_heading_level_works() {
-        assert_eq!(SessionFile::get_heading_level("# Heading"), 1);
-        assert_eq!(SessionFile::get_heading_level("## Heading"), 2);
-        assert_eq!(SessionFile::get_heading_level("##### Heading"), 5);
-    }
+    fn session_to_file_matches_fixture() {
+        let fixture = include_str!("../tests/fixtures/session.md");
+        let path = PathBuf::from("2025-01-06T09-00-00+0100.md");
+        let file = SessionFile::build(&path, fixture).unwrap();
+        let session = Session::from_file(&file).unwrap();
+        assert_eq!(session.to_file().unwrap().contents, fixture);
 
-    #[test]
-    fn session_new_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("."),
-        };
-        let dt = DateTime::now();
-        let mark = Mark::new(&dt.date);
-        let session = Session {
-            path: config
-                .sessions_path
-                .join(format!("{}.md", dt.to_formatted())),
-            marks: vec![mark],
-        };
-        assert_eq!(Session::new(&config, &DateTime::now()), session);
+        // Extra blank lines, tags before the attribute, CRLF and no trailing newline.
+        let messy = "\r\n# Session\r\n<!-- tt:v2 -->\r\n\r\n- tag `work`\r\n- tag `client`\r\n\r\n\
+            Working on the parser.\r\n\r\n\r\n## Marks\r\n\r\n\
+            ### 2025-01-06 09:00:00 +01:00\r\n\r\n- tag `rust`\r\n- tag `parser`\r\n- billable\r\n\r\n\
+            Tokenizer.\r\n\r\n\r\n\
+            ### 2025-01-06 10:00:00 +01:00\r\n- tag `lunch`\r\n- skip\r\n\r\n\
+            ### 2025-01-06 10:30:00 +01:00\r\n\r\nError messages.\r\n\r\n\
+            ### 2025-01-06 12:00:00 +01:00\r\n\r\n- stop\r\n\r\n\
+            ## Links\r\n\r\n[issue](https://example.com)";
+        let file = SessionFile::build(&path, messy).unwrap();
+        let session = Session::from_file(&file).unwrap();
+        assert_eq!(session.to_file().unwrap().contents, fixture);
     }
 
     #[test]
-    fn session_start_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        let date = testing::now_plus_secs(30);
-        session.marks.push(Mark::new(&date));
-        assert_eq!(session.start(), session.marks.first().unwrap().date);
+    fn session_file_build_tolerates_crlf() {
+        let fixture = include_str!("../tests/fixtures/session.md");
+        let path = PathBuf::from("2025-01-06T09-00-00+0100.md");
+        let lf = SessionFile::build(&path, fixture).unwrap();
+        let crlf = SessionFile::build(&path, &fixture.replace('\n', "\r\n")).unwrap();
+        assert_eq!(crlf.contents, lf.contents);
+        assert_eq!(
+            Session::from_file(&crlf).unwrap(),
+            Session::from_file(&lf).unwrap()
+        );
     }
 
     #[test]
-    fn session_end_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        assert_eq!(session.end(), session.marks.last().unwrap().date);
-        session.mark(&DateTime::now()).unwrap();
-        session.set_attribute(Attribute::Stop);
-        session.marks.last_mut().unwrap().date = testing::now_plus_secs(30);
-        assert_eq!(session.end(), session.marks.last().unwrap().date);
+    fn session_to_file_keeps_unknown_contents() {
+        let marker = FormatVersion::CURRENT.to_marker();
+        let dt = DateTime::now().plus_hours(-1);
+        let header = format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            {marker}\n\
+            \n\
+            Working on the  parser,\n\
+            see the issue.\n\
+            \n\
+            ## Notes\n\
+            \n\
+            - keep   this\n\
+            \n\
+            ```\n\
+            # not a heading\n\
+            ```\
+            "
+        );
+        let footer = "## Links\n\n[issue](https://example.com)";
+        let contents = format!(
+            "\
+            {header}\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}{}\n\
+            \n\
+            {footer}\n\
+            ",
+            dt.to_formatted_pretty()
+        );
+        let file = SessionFile::build(&PathBuf::from("sessions"), &contents).unwrap();
+        let mut session = Session::from_file(&file).unwrap();
+        assert_eq!(session.to_file().unwrap().contents, contents);
+
+        let dt_mark = DateTime::now();
+        session.mark(&dt_mark, false).unwrap();
+        session
+            .tag(&Tag::from_text("rust").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        session.write("Done.").unwrap();
+        let contents = session.to_file().unwrap().contents;
+        assert!(contents.starts_with(&format!("{header}\n\n{MARKS_HEADING}\n\n")));
+        assert!(contents.ends_with(&format!("\n\nDone.\n\n{footer}\n")));
+        let file = SessionFile::build(&PathBuf::from("sessions"), &contents).unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), session);
     }
 
     #[test]
-    fn session_is_active_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        assert!(session.is_active());
-        assert!(session.marks.last().unwrap().attribute != Attribute::Stop);
-        session.mark(&DateTime::now()).unwrap();
-        session.set_attribute(Attribute::Stop);
-        assert!(!session.is_active());
-        assert!(session.marks.last().unwrap().attribute == Attribute::Stop);
+    fn session_title_works() {
+        let marker = FormatVersion::CURRENT.to_marker();
+        let dt = DateTime::now().plus_hours(-1);
+        let contents = format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}{SESSION_TITLE_SEPARATOR}Client X onboarding\n\
+            {marker}\n\
+            \n\
+            Text.\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}{}\
+            ",
+            dt.to_formatted_pretty()
+        );
+        let file = SessionFile::build(&PathBuf::from("sessions"), &contents).unwrap();
+        let mut session = Session::from_file(&file).unwrap();
+        assert_eq!(session.title.as_deref(), Some("Client X onboarding"));
+        assert_eq!(session.header, "Text.");
+        assert_eq!(session.to_file().unwrap(), file);
+
+        session.set_title("  Client Y ").unwrap();
+        let contents = session.to_file().unwrap().contents;
+        assert!(contents.starts_with(&format!(
+            "{SESSION_HEADING_PREFIX}{SESSION_TITLE}{SESSION_TITLE_SEPARATOR}Client Y\n{marker}\n\nText.\n\n"
+        )));
+        assert!(session.set_title("issue #12").is_err());
+        assert!(session.set_title("two\nlines").is_err());
+        assert!(session.set_title(" ").is_err());
+        assert_eq!(session.title.as_deref(), Some("Client Y"));
+
+        let session = Session::new(&Config::default(), &dt);
+        assert_eq!(session.title, None);
+        assert!(session.to_file().unwrap().contents.starts_with(&format!(
+            "{SESSION_HEADING_PREFIX}{SESSION_TITLE}\n{marker}\n\n{MARKS_HEADING}"
+        )));
     }
 
-    // It ignores `mark_first` and counts to current time, so `mark_second` is the final time.
     #[test]
-    fn session_get_time_ignores_marks_if_they_are_skipped() {
-        let mut mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
-        mark_first.attribute = Attribute::Skip;
-        let mark_second = Mark::new(&testing::now_plus_secs(-54 * 60 - 10)); // 54m 10s
-        let mark_third = Mark::new(&testing::now_plus_secs(-10 * 60));
-        let session = Session {
-            path: PathBuf::from("sessions"),
-            marks: vec![mark_first, mark_second, mark_third],
-        };
-        assert_eq!(session.get_time(), (54 * 60 + 10) * 1000);
+    fn session_tags_work() {
+        let marker = FormatVersion::CURRENT.to_marker();
+        let dt = DateTime::now().plus_hours(-1);
+        let contents = format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}client-b{LABEL_TAG_SURROUND}\n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}client-a{LABEL_TAG_SURROUND}\n\
+            \n\
+            Text.\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}{}\
+            ",
+            dt.to_formatted_pretty()
+        );
+        let file = SessionFile::build(&PathBuf::from("sessions"), &contents).unwrap();
+        let mut session = Session::from_file(&file).unwrap();
+        let client_a = Tag::from_text("client-a").unwrap();
+        let client_b = Tag::from_text("client-b").unwrap();
+        assert_eq!(
+            session.tags,
+            HashSet::from([client_a.clone(), client_b.clone()])
+        );
+        assert_eq!(session.header, "Text.");
+        assert!(session.marks[0].tags.is_empty());
+        // Sorted alphabetically.
+        let contents = session.to_file().unwrap().contents;
+        assert!(contents.starts_with(&format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            {marker}\n\
+            \n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}client-a{LABEL_TAG_SURROUND}\n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}client-b{LABEL_TAG_SURROUND}\n\
+            \n\
+            Text.\n\
+            "
+        )));
+
+        assert!(!session.tag_session(&client_a));
+        assert!(session.untag_session(&client_b));
+        assert!(!session.untag_session(&client_b));
+        let file = session.to_file().unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), session);
     }
 
     #[test]
-    fn session_get_time_ignores_current_time_if_last_mark_is_skipped() {
-        let mark_first = Mark::new(&testing::now_plus_secs(-3 * 60 * 60));
-        let mut mark_second = Mark::new(&testing::now_plus_secs(-1 * 60 * 60 - 33 * 60 - 20)); // 1h 33m 20s
+    fn aggregator_tag_totals_includes_session_tags() {
+        let rust = Tag::from_text("rust").unwrap();
+        let client = Tag::from_text("client").unwrap();
+        let mut mark_first = Mark::new(&testing::now_plus_secs(-60 * 60));
+        mark_first.tags.insert(rust.clone());
+        let mut mark_second = Mark::new(&testing::now_plus_secs(-30 * 60));
         mark_second.attribute = Attribute::Skip;
-        let session = Session {
+        let mark_third = Mark::new(&testing::now_plus_secs(-20 * 60));
+        let mut mark_fourth = Mark::new(&testing::now_plus_secs(-10 * 60));
+        mark_fourth.attribute = Attribute::Stop;
+        let mut session = Session {
             path: PathBuf::from("sessions"),
-            marks: vec![mark_first, mark_second],
+            marks: vec![mark_first, mark_second, mark_third, mark_fourth],
+            ..Default::default()
         };
-        assert_eq!(session.get_time(), (1 * 60 * 60 + 26 * 60 + 40) * 1000);
-    }
+        let aggregator = Aggregator {
+            sessions: vec![session.clone()],
+            failures: Vec::new(),
+        };
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()),
+            Ok(vec![(rust.clone(), Duration::from_secs(30 * 60))])
+        );
 
-    #[test]
-    fn session_mark_works() {
-        let dt = DateTime::now();
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
+        session.tag_session(&client);
+        session.tag_session(&rust);
+        assert_eq!(session.get_time(&[]), Ok(Duration::from_secs(40 * 60)));
+        let aggregator = Aggregator {
+            sessions: vec![session],
+            failures: Vec::new(),
         };
-        let mut session = Session::new(&config, &DateTime::now());
-        let mark = Mark::new(&dt.date);
-        session.mark(&DateTime::now()).unwrap();
-        assert_eq!(session.marks.len(), 2);
-        assert_eq!(session.marks[1], mark);
+        // Skipped intervals are excluded and mark tags don't count twice.
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()),
+            Ok(vec![
+                (client, Duration::from_secs(40 * 60)),
+                (rust, Duration::from_secs(40 * 60)),
+            ])
+        );
     }
 
     #[test]
-    fn session_mark_preserves_integrity_of_previous_content() {
-        let dt = DateTime::now();
-        let mark_first = Mark::new(&dt.date.with_hour(14).unwrap());
-        let mark_second = Mark::new(&mark_first.date.with_minute(47).unwrap());
+    fn session_get_time_excludes_untracked_tags() {
+        let lunch = Tag::from_text("lunch").unwrap();
+        let untracked = [Tag::from_text("break").unwrap(), lunch.clone()];
+        let mut mark_first = Mark::new(&testing::now_plus_secs(-60 * 60));
+        mark_first.tags.insert(Tag::from_text("rust").unwrap());
+        let mut mark_second = Mark::new(&testing::now_plus_secs(-50 * 60));
+        mark_second.tags.insert(lunch.clone());
+        let mut mark_third = Mark::new(&testing::now_plus_secs(-30 * 60));
+        mark_third.attribute = Attribute::Skip;
+        mark_third.tags.insert(lunch.clone());
+        let mark_fourth = Mark::new(&testing::now_plus_secs(-20 * 60));
+        let mut mark_fifth = Mark::new(&testing::now_plus_secs(-10 * 60));
+        mark_fifth.attribute = Attribute::Stop;
         let mut session = Session {
-            path: PathBuf::from(format!("./sessions/{}.md", dt.to_formatted())),
-            marks: vec![mark_first, mark_second],
+            path: PathBuf::from("sessions"),
+            marks: vec![mark_first, mark_second, mark_third, mark_fourth, mark_fifth],
+            ..Default::default()
         };
-        let mut clone = session.clone();
-        session.mark(&DateTime::now()).unwrap();
-        assert_eq!(session.marks.len(), 3);
-        clone.marks.push(session.marks[2].clone());
-        assert_eq!(session, clone);
+        assert_eq!(session.get_time(&[]), Ok(Duration::from_secs(40 * 60)));
+        assert_eq!(
+            session.get_time(&untracked),
+            Ok(Duration::from_secs(20 * 60))
+        );
+        assert_eq!(
+            session.get_break_time(&untracked),
+            Ok(Duration::from_secs(30 * 60))
+        );
+        // Still counted under its own tag.
+        let aggregator = Aggregator {
+            sessions: vec![session.clone()],
+            failures: Vec::new(),
+        };
+        assert_eq!(
+            aggregator.tag_totals(&DateRange::default()),
+            Ok(vec![
+                (lunch.clone(), Duration::from_secs(20 * 60)),
+                (
+                    Tag::from_text("rust").unwrap(),
+                    Duration::from_secs(10 * 60)
+                ),
+            ])
+        );
+        let summary = session.summarize(&untracked).unwrap();
+        assert_eq!(summary.time, Duration::from_secs(20 * 60));
+        assert_eq!(summary.intervals.len(), 2);
+
+        session.tag_session(&lunch);
+        assert_eq!(session.get_time(&untracked), Ok(Duration::ZERO));
     }
 
     #[test]
-    fn cannot_mark_when_session_ended() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        session.mark(&DateTime::now()).unwrap();
-        session.set_attribute(Attribute::Stop);
-        let clone = session.clone();
-        assert!(session.mark(&DateTime::now()).is_err());
-        assert_eq!(session, clone);
+    fn session_pause_and_unpause_work() {
+        let config = Config::default();
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-2));
+        assert_eq!(
+            session.unpause(&DateTime::now()),
+            Err(String::from("session isn't paused"))
+        );
+        session.pause(&DateTime::now().plus_hours(-1)).unwrap();
+        assert_eq!(session.marks[1].attribute, Attribute::Skip);
+        assert!(session.pause(&DateTime::now()).is_err());
+        // Must be after the break started.
+        assert!(session.unpause(&DateTime::now().plus_hours(-1)).is_err());
+        assert_eq!(
+            session.unpause(&DateTime::now().plus_minutes(-20)),
+            Ok(Duration::from_secs(40 * 60))
+        );
+        assert_eq!(session.marks.len(), 3);
+        assert_eq!(session.marks[2].attribute, Attribute::None);
+
+        session.pause(&DateTime::now().plus_minutes(-10)).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert!(session.pause(&DateTime::now()).is_err());
+        assert_eq!(
+            session.unpause(&DateTime::now()),
+            Err(String::from("can't unpause, session has already ended"))
+        );
     }
 
     #[test]
-    fn session_remark_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        session.mark(&DateTime::now()).unwrap();
-        let clone = session.clone();
-        session.marks.last_mut().unwrap().date = testing::now_plus_secs(30);
-        assert_ne!(session, clone);
-        session.remark(&DateTime::now());
-        assert_eq!(session, clone);
+    fn session_insert_idle_works() {
+        let config = Config::default();
+        let now = DateTime::now();
+        let mut session = Session::new(&config, &now.plus_hours(-3));
+        session.mark(&now.plus_hours(-1), false).unwrap();
+        assert_eq!(
+            session.insert_idle(&now.plus_hours(-4), &now.plus_hours(-2)),
+            Err(format!(
+                "idle can't start before the previous mark at {}, it can be less than 1h 0m 0s long",
+                now.plus_hours(-3).to_formatted_pretty()
+            ))
+        );
+        // Spans the second mark.
+        assert!(session
+            .insert_idle(&now.plus_minutes(-150), &now.plus_minutes(-30))
+            .is_err());
+        assert!(session.insert_idle(&now.plus_hours(-1), &now).is_err());
+        assert!(session
+            .insert_idle(&now.plus_minutes(-30), &now.plus_minutes(10))
+            .is_err());
+        assert_eq!(session.marks.len(), 2);
+
+        session
+            .insert_idle(&now.plus_minutes(-150), &now.plus_minutes(-120))
+            .unwrap();
+        session.insert_idle(&now.plus_minutes(-40), &now).unwrap();
+        let marks: Vec<(chrono::DateTime<chrono::Local>, Attribute)> = session
+            .marks
+            .iter()
+            .map(|v| (v.local_date(), v.attribute.clone()))
+            .collect();
+        assert_eq!(
+            marks,
+            vec![
+                (now.plus_hours(-3).date, Attribute::None),
+                (now.plus_minutes(-150).date, Attribute::Skip),
+                (now.plus_minutes(-120).date, Attribute::None),
+                (now.plus_hours(-1).date, Attribute::None),
+                (now.plus_minutes(-40).date, Attribute::Skip),
+                (now.date, Attribute::None),
+            ]
+        );
+        assert_eq!(
+            session.get_time(&[]).unwrap().round(Duration::MINUTE),
+            Duration::from_secs(110 * 60)
+        );
+
+        // Ends right on the next mark.
+        session
+            .insert_idle(&now.plus_minutes(-90), &now.plus_hours(-1))
+            .unwrap();
+        assert_eq!(session.marks.len(), 7);
+        assert_eq!(session.marks[3].attribute, Attribute::Skip);
+
+        assert_eq!(
+            session.insert_idle(&now.plus_minutes(-30), &now.plus_minutes(-20)),
+            Err(String::from("idle falls into time that is already skipped"))
+        );
+
+        let mut session = Session::new(&config, &now.plus_hours(-3));
+        session.mark(&now.plus_hours(-2), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert_eq!(
+            session.insert_idle(&now.plus_minutes(-90), &now.plus_hours(-1)),
+            Err(String::from(
+                "can't add idle time, session has already ended"
+            ))
+        );
     }
 
     #[test]
-    fn session_unmark_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
+    fn session_is_stale_works() {
+        let config = Config::default();
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-12));
+        assert!(session.is_stale(Duration::from_secs(10 * 60 * 60)));
+        assert!(!session.is_stale(Duration::from_secs(13 * 60 * 60)));
+        session
+            .mark(&DateTime::now().plus_hours(-1), false)
+            .unwrap();
+        assert!(!session.is_stale(Duration::from_secs(10 * 60 * 60)));
+        session.mark(&DateTime::now().plus_hours(1), true).unwrap();
+        assert!(!session.is_stale(Duration::ZERO));
 
-        assert_eq!(session.marks.len(), 1);
-        assert_eq!(session.unmark(), None);
-        assert_eq!(session.marks.len(), 1);
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-12));
+        session.set_attribute(Attribute::Stop).unwrap();
+        assert!(!session.is_stale(Duration::from_secs(10 * 60 * 60)));
+    }
 
-        session.mark(&DateTime::now().plus_hours(1)).unwrap();
-        assert_eq!(session.marks.len(), 2);
+    #[test]
+    fn session_get_pomodoro_works() {
+        let config = Config::default();
+        let dt = DateTime::new(&testing::date_default());
+        let minutes = |v: u64| Duration::from_secs(v * 60);
+        let mut session = Session::new(&config, &dt.plus_hours(-2));
+        session.marks.push(Mark::new(&dt.plus_minutes(-30).date));
 
-        let mark_first = session.marks.first().unwrap().clone();
-        let mark_last = session.marks.last().unwrap().clone();
-        assert_ne!(mark_first, mark_last);
-        assert_eq!(session.unmark(), Some(mark_last));
-        assert_eq!(session.marks.len(), 1);
-        assert_eq!(session.marks.last().unwrap(), &mark_first);
+        assert_eq!(session.time_in_current_mark(&dt), Some(minutes(30)));
+        assert_eq!(session.time_in_current_mark(&dt.plus_minutes(-31)), None);
+        assert_eq!(
+            session.get_pomodoro(&dt, minutes(50)),
+            Pomodoro::Work { left: minutes(20) }
+        );
+        assert_eq!(
+            session.get_pomodoro(&dt, minutes(30)),
+            Pomodoro::Work { left: minutes(0) }
+        );
+        assert_eq!(session.get_pomodoro(&dt, minutes(25)), Pomodoro::BreakDue);
+        // A mark ahead of the current time has the whole interval left.
+        assert_eq!(
+            session.get_pomodoro(&dt.plus_minutes(-31), minutes(25)),
+            Pomodoro::Work { left: minutes(25) }
+        );
+
+        session.marks[1].attribute = Attribute::Skip;
+        assert_eq!(session.get_pomodoro(&dt, minutes(25)), Pomodoro::Break);
+        session.marks[1].attribute = Attribute::Stop;
+        assert_eq!(session.time_in_current_mark(&dt), Some(Duration::ZERO));
+        assert_eq!(session.get_pomodoro(&dt, minutes(25)), Pomodoro::Inactive);
     }
 
     #[test]
-    fn session_set_attribute_works() {
+    fn session_split_works() {
         let config = Config {
             sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
         };
-        let mut session = Session::new(&config, &DateTime::now());
-        assert_eq!(session.marks.last().unwrap().attribute, Attribute::None);
-        session.set_attribute(Attribute::Skip);
-        assert_eq!(session.marks.last().unwrap().attribute, Attribute::Skip);
+        let dt = DateTime::new(&testing::date_default());
+        let rust = Tag::from_text("rust").unwrap();
+        let mut session = Session::new(&config, &dt.plus_hours(-3));
+        session.set_title("Parser").unwrap();
+        session.tag_session(&rust);
+        session.mark(&dt.plus_hours(-2), false).unwrap();
+        session.write("Lexer.").unwrap();
+        session.tag(&rust, &MarkSelector::Last).unwrap();
+        session.mark(&dt.plus_hours(-1), false).unwrap();
+        session.set_attribute(Attribute::Stop).unwrap();
+
+        // Mid interval, the mark in progress is repeated.
+        let mut first = session.clone();
+        let second = first.split(&dt.plus_minutes(-90)).unwrap();
+        assert_eq!(first.marks.len(), 3);
+        assert!(!first.is_active());
+        assert_eq!(first.get_time(&[]), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(second.marks.len(), 2);
+        assert_eq!(second.start(), dt.plus_minutes(-90).date);
+        assert_eq!(second.marks[0].contents, "Lexer.");
+        assert!(second.marks[0].tags.contains(&rust));
+        assert_eq!(second.get_time(&[]), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(second.get_title(), Some("Parser"));
+        assert_eq!(second.get_session_tags(), vec![&rust]);
+        assert_eq!(
+            second.path,
+            Session::new(&config, &dt.plus_minutes(-90)).path
+        );
+        for session in [&first, &second] {
+            assert_eq!(session.check_marks(), Vec::new());
+            let file = session.to_file().unwrap();
+            assert_eq!(Session::from_file(&file).unwrap(), *session);
+        }
+
+        // On a mark, it moves to the new session.
+        let mut first = session.clone();
+        let second = first.split(&dt.plus_hours(-2)).unwrap();
+        assert_eq!(first.marks.len(), 2);
+        assert_eq!(first.get_time(&[]), Ok(Duration::HOUR));
+        assert_eq!(second.marks, session.marks[1..]);
+
+        assert!(session.clone().split(&dt.plus_hours(-3)).is_err());
+        assert!(session.clone().split(&dt.plus_hours(-1)).is_err());
+        assert!(session.clone().split(&dt.plus_hours(-4)).is_err());
     }
 
-    #[test]
-    fn session_tag_works() {
-        let config = Config {
-            sessions_path: PathBuf::from("sessions"),
-        };
-        let mut session = Session::new(&config, &DateTime::now());
-        // To have at least 2 marks.
-        session.mark(&DateTime::now()).unwrap();
-        let mut clone = session.clone();
-        session.tag(&Tag::from_text("rust").unwrap());
-        clone
-            .marks
-            .last_mut()
-            .unwrap()
-            .tags
-            .insert(Tag::from_text("rust").unwrap().clone());
-        assert_eq!(session, clone);
+    #[test]
+    fn session_split_works_on_active_session() {
+        let config = Config::default();
+        let mut session = Session::new(&config, &DateTime::now().plus_hours(-2));
+        session.write("Notes.").unwrap();
+        let mut first = session.clone();
+        let second = first.split(&DateTime::now().plus_hours(-1)).unwrap();
+        assert!(!first.is_active());
+        assert_eq!(first.get_time(&[]), Ok(Duration::HOUR));
+        assert!(second.is_active());
+        assert_eq!(second.marks.len(), 1);
+        assert_eq!(second.marks[0].contents, "Notes.");
+
+        // The current time is the end of an active session.
+        assert!(session
+            .clone()
+            .split(&DateTime::now().plus_minutes(5))
+            .is_err());
     }
 
     #[test]
-    fn session_untag_works() {
+    fn session_merge_works() {
         let config = Config {
             sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
         };
-        let mut session = Session::new(&config, &DateTime::now());
-        // To have at least 2 marks.
-        session.mark(&DateTime::now()).unwrap();
-        let clone = session.clone();
-        session.tag(&Tag::from_text("rust").unwrap());
-        session.untag(&Tag::from_text("rust").unwrap());
-        assert_eq!(session, clone);
-    }
+        let dt = DateTime::new(&testing::date_default());
+        let rust = Tag::from_text("rust").unwrap();
+        let mut earlier = Session::new(&config, &dt.plus_hours(-3));
+        earlier.tag_session(&rust);
+        earlier.write("Parser.").unwrap();
+        earlier.mark(&dt.plus_hours(-2), false).unwrap();
+        earlier.set_attribute(Attribute::Stop).unwrap();
+        let mut later = Session::new(&config, &dt.plus_minutes(-110));
+        later.set_title("After the crash").unwrap();
+        later.tag_session(&Tag::from_text("cli").unwrap());
+        later.write("Lexer.").unwrap();
+        later.mark(&dt.plus_hours(-1), false).unwrap();
+        later.set_attribute(Attribute::Stop).unwrap();
 
-    #[test]
-    fn session_write_works() {
-        let config = Config {
+        let mut merged = earlier.clone();
+        merged.merge(&later, Attribute::None).unwrap();
+        assert_eq!(merged.path, earlier.path);
+        assert_eq!(merged.marks.len(), 4);
+        assert!(!merged.is_active());
+        assert_eq!(merged.check_marks(), Vec::new());
+        assert_eq!(merged.get_title(), Some("After the crash"));
+        assert_eq!(merged.get_session_tags().len(), 2);
+        assert_eq!(
+            merged.get_time(&[]).unwrap(),
+            Duration::HOUR + later.get_time(&[]).unwrap() + Duration::from_secs(10 * 60)
+        );
+        let file = merged.to_file().unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), merged);
+
+        let mut skipped = earlier.clone();
+        skipped.merge(&later, Attribute::Skip).unwrap();
+        assert_eq!(
+            skipped.get_time(&[]).unwrap(),
+            Duration::HOUR + later.get_time(&[]).unwrap()
+        );
+
+        // Back to back, the stop mark and the first mark of `later` become one.
+        let mut adjacent = Session::new(&config, &dt.plus_hours(-2));
+        adjacent.write("Tests.").unwrap();
+        adjacent.mark(&dt.plus_hours(-1), false).unwrap();
+        let mut merged = earlier.clone();
+        merged.merge(&adjacent, Attribute::None).unwrap();
+        assert_eq!(merged.marks.len(), 3);
+        assert_eq!(merged.marks[1].contents, "Tests.");
+        let file = merged.to_file().unwrap();
+        assert_eq!(Session::from_file(&file).unwrap(), merged);
+
+        assert!(later.clone().merge(&earlier, Attribute::None).is_err());
+        let overlapping = Session::new(&config, &dt.plus_minutes(-150));
+        assert!(earlier
+            .clone()
+            .merge(&overlapping, Attribute::None)
+            .is_err());
+        let config_other = Config {
             sessions_path: PathBuf::from("sessions"),
+            workspace: Some(String::from("other")),
+            ..Default::default()
         };
-        let mut session = Session::new(&config, &DateTime::now());
-        // To have at least 2 marks.
-        session.mark(&DateTime::now()).unwrap();
-        let mut clone = session.clone();
-        session.write("hello").unwrap();
-        clone.marks.iter_mut().last().unwrap().write("hello");
-        assert_eq!(session, clone);
+        let other = Session::new(&config_other, &dt.plus_minutes(-110));
+        assert!(earlier.clone().merge(&other, Attribute::None).is_err());
     }
 
     #[test]
-    fn session_write_errors_when_there_is_content() {
+    fn session_derive_from_works() {
         let config = Config {
             sessions_path: PathBuf::from("sessions"),
+            workspace: Some(String::from("internal")),
+            ..Default::default()
         };
-        let mut session = Session::new(&config, &DateTime::now());
-        session.write("Some content.").unwrap();
-        assert!(session.write("Some other content.").is_err());
+        let dt = DateTime::new(&testing::date_default());
+        let mut previous = Session::new(&config, &dt.plus_days(-1));
+        previous.write("Yesterday.").unwrap();
+        previous
+            .tag(&Tag::from_text("meeting").unwrap(), &MarkSelector::Last)
+            .unwrap();
+        previous.set_attribute(Attribute::Stop).unwrap();
+
+        // No session tags or title to inherit.
+        let session = Session::derive_from(&previous, &dt);
+        assert_eq!(session, Session::new(&config, &dt));
+        assert_eq!(session.get_title(), None);
+        assert!(session.get_session_tags().is_empty());
+
+        previous.set_title("Client").unwrap();
+        previous.tag_session(&Tag::from_text("rust").unwrap());
+        previous.tag_session(&Tag::from_text("client").unwrap());
+        let session = Session::derive_from(&previous, &dt);
+        assert_eq!(session.get_name(), Some("internal"));
+        assert_eq!(session.marks.len(), 1);
+        assert_eq!(session.marks[0].tags, HashSet::new());
+        assert_eq!(session.get_title(), Some("Client"));
+        let tags: Vec<String> = session
+            .get_session_tags()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(tags, vec!["client", "rust"]);
     }
 
     #[test]
-    fn session_from_file_works() {
-        let DateTime { date, .. } = DateTime::now();
-        let mark_first_dt = DateTime {
-            date: date.with_hour(5).unwrap(),
-            // FIX: same time for both marks breaks reading of them - doesn't read labels and keeps
-            // the whole content as is in the file
-            // date: date.with_hour(5).unwrap().with_minute(23).unwrap(),
-            // formatted: DateTime::format(&date.with_hour(5).unwrap().with_minute(23).unwrap()),
-        };
-        let mark_first = Mark::new(&mark_first_dt.date);
-        let mark_second_dt = DateTime {
-            date: mark_first.date.with_minute(23).unwrap(),
+    fn session_title_from_line_works() {
+        assert_eq!(Session::title_from_line("# Session"), None);
+        assert_eq!(
+            Session::title_from_line("# Session — Client X"),
+            Some(String::from("Client X"))
+        );
+        assert_eq!(
+            Session::title_from_line("# Sprint review"),
+            Some(String::from("Sprint review"))
+        );
+        assert_eq!(Session::title_from_line("Session"), None);
+    }
+
+    #[test]
+    fn session_from_file_reports_line_of_error() {
+        let path = PathBuf::from("sessions/session.md");
+        let parse = |contents: &str| {
+            SessionFile::build(&path, contents).and_then(|file| Session::from_file(&file))
         };
-        let mut mark_second = Mark::new(&mark_second_dt.date);
-        mark_second.attribute = Attribute::Stop;
+        let contents = format!(
+            "\
+            \n\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:00:00 +01:00\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 9:10\n\
+            "
+        );
+        let err = parse(&contents).unwrap_err();
+        assert_eq!(
+            err,
+            ParseError {
+                path: path.clone(),
+                line: 8,
+                text: format!("{MARK_HEADING_PREFIX}2025-01-07 9:10"),
+                reason: String::from("couldn't parse mark heading"),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "sessions/session.md:8: couldn't parse mark heading `### 2025-01-07 9:10`"
+        );
+
+        // Shorter than the heading prefix.
+        let err = Mark::from_line("##").unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (1, "##"));
+        let err = Mark::from_line("").unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (1, ""));
 
         let contents = format!(
             "\
-                {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
-                \n\
-                {MARKS_HEADING}\n\
-                \n\
-                {MARK_HEADING_PREFIX}{}\n\
-                \n\
-                {MARK_HEADING_PREFIX}{}\n\
-                \n\
-                {LABEL_STOP}\n\
-                ",
-            mark_first_dt.to_formatted_pretty(),
-            mark_second_dt.to_formatted_pretty(),
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:00:00 +01:00\n\
+            \n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}rust{LABEL_TAG_SURROUND}\n\
+            {LABEL_STOP}\n\
+            {LABEL_SKIP}\n\
+            "
         );
-        let file = SessionFile::build(&PathBuf::new(), &contents).unwrap();
-        let session = Session {
-            path: file.path.clone(),
-            marks: vec![mark_first, mark_second],
-        };
+        let err = parse(&contents).unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (9, LABEL_SKIP));
 
-        assert_eq!(Session::from_file(&file).unwrap(), session);
+        let contents = format!(
+            "\
+            {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+            \n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}rust{LABEL_TAG_SURROUND}\n\
+            {LABEL_TAG} {LABEL_TAG_SURROUND}{LABEL_TAG_SURROUND}\n\
+            \n\
+            {MARKS_HEADING}\n\
+            \n\
+            {MARK_HEADING_PREFIX}2025-01-07 09:00:00 +01:00\n\
+            \n\
+            {LABEL_TAG} rust\n\
+            "
+        );
+        let err = parse(&contents).unwrap_err();
+        assert_eq!(err.line, 4);
+        assert_eq!(err.reason, "tag cannot be empty");
+        let err = parse(&contents.replace("- tag ``", "")).unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (10, "- tag rust"));
+
+        let err = parse(&format!(
+            "{SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\n{MARKS_HEADING}"
+        ))
+        .unwrap_err();
+        assert_eq!(err.line, 3);
+        let err = parse(&format!("\n\nSession\n\n{MARKS_HEADING}")).unwrap_err();
+        assert_eq!((err.line, err.text.as_str()), (3, "Session"));
+        let err = parse(&format!("{SESSION_HEADING_PREFIX}{SESSION_TITLE}")).unwrap_err();
+        assert_eq!(err.line, 1);
+        assert!(err.to_string().contains(MARKS_HEADING));
     }
 
     #[test]
@@ -924,6 +6335,7 @@ mod tests {
 
     #[test]
     fn session_to_file_works() -> Result<(), Box<dyn Error>> {
+        let marker = FormatVersion::CURRENT.to_marker();
         let dt = DateTime::now();
         let mark_first_dt = DateTime {
             date: dt.date.with_hour(5).unwrap(),
@@ -933,25 +6345,33 @@ mod tests {
             date: mark_first_dt.date.with_minute(44).unwrap(),
         };
         let mark_second = Mark {
-            date: mark_second_dt.date,
+            date: mark_second_dt.date.fixed_offset(),
             attribute: Attribute::None,
+            billable: false,
             tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("I am the second mark!\nHi!\n"),
         };
         let config = Config {
             sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
         };
         let session = Session {
-            path: config
-                .sessions_path
-                .join(format!("{}.md", mark_first_dt.to_formatted())),
+            path: config.sessions_path.join(format!(
+                "{}.md",
+                mark_first_dt.date.format(SESSION_FILE_NAME_FORMAT)
+            )),
             marks: vec![mark_first, mark_second],
+            ..Default::default()
         };
         let file = SessionFile::build(
             &session.path,
             &format!(
                 "\
                     {SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\
+                    {marker}\n\
                     \n\
                     {MARKS_HEADING}\n\
                     \n\
@@ -975,25 +6395,48 @@ mod tests {
     fn session_to_file_and_from_file() {
         let dt = DateTime::now();
         let mark_first = Mark {
-            date: dt.date.with_hour(5).unwrap().with_minute(54).unwrap(),
+            date: dt
+                .date
+                .with_hour(5)
+                .unwrap()
+                .with_minute(54)
+                .unwrap()
+                .fixed_offset(),
             attribute: Attribute::None,
+            billable: false,
             tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("feat/some-branch\n\nDid a few things"),
         };
         let mark_second = Mark {
-            date: dt.date.with_hour(6).unwrap().with_minute(13).unwrap(),
+            date: dt
+                .date
+                .with_hour(6)
+                .unwrap()
+                .with_minute(13)
+                .unwrap()
+                .fixed_offset(),
             attribute: Attribute::None,
+            billable: false,
             tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("feat/new-feature"),
         };
         let config = Config {
             sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
         };
         let session = Session {
-            path: config
-                .sessions_path
-                .join(&format!("{}.md", DateTime::format(&mark_first.date))),
+            path: config.sessions_path.join(format!(
+                "{}.md",
+                mark_first.local_date().format(SESSION_FILE_NAME_FORMAT)
+            )),
             marks: vec![mark_first, mark_second],
+            ..Default::default()
         };
         let file = session.to_file().unwrap();
         assert_eq!(Session::from_file(&file).unwrap(), session);
@@ -1029,6 +6472,32 @@ mod tests {
         assert_eq!(mark, clone);
     }
 
+    #[test]
+    fn mark_title_works() {
+        let dt = DateTime::new(&testing::date_default());
+        let mut mark = Mark::new(&dt.date);
+        assert_eq!(mark.title(), None);
+        assert_eq!(mark.body(), "");
+        assert_eq!(mark.to_summary_line(), "2002-05-08 12:00");
+        mark.set_title("feat/parser", false).unwrap();
+        assert_eq!(mark.contents, "feat/parser");
+
+        mark.write("  Parser  ");
+        assert_eq!(mark.title(), Some("Parser"));
+        assert_eq!(mark.body(), "");
+        assert_eq!(mark.to_summary_line(), "2002-05-08 12:00 Parser");
+
+        mark.write("Parser\nHandles lists.\n\nAnd nested ones.");
+        assert_eq!(mark.title(), Some("Parser"));
+        assert_eq!(mark.body(), "Handles lists.\n\nAnd nested ones.");
+        mark.set_title("Parser", false).unwrap();
+        assert!(mark.set_title("Lexer", false).is_err());
+        assert!(mark.set_title("two\nlines", true).is_err());
+        assert!(mark.set_title(" ", true).is_err());
+        mark.set_title("Lexer", true).unwrap();
+        assert_eq!(mark.contents, "Lexer\nHandles lists.\n\nAnd nested ones.");
+    }
+
     #[test]
     fn mark_erase_works() {
         let dt = DateTime::now();
@@ -1055,9 +6524,13 @@ mod tests {
             dt.to_formatted_pretty()
         );
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::Stop,
+            billable: false,
             tags: HashSet::from_iter([Tag::from_text("rust")?, Tag::from_text("time tracker")?]),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is some content."),
         };
         assert_eq!(Mark::from_line(&contents).unwrap(), mark);
@@ -1081,13 +6554,63 @@ mod tests {
         assert!(Mark::from_line(&contents).is_err());
     }
 
+    #[test]
+    fn mark_contents_colliding_with_syntax_round_trip() {
+        let dt = DateTime::now().plus_hours(-1);
+        let colliding = [
+            LABEL_STOP.to_owned(),
+            LABEL_SKIP.to_owned(),
+            format!("{LABEL_TAG} {LABEL_TAG_SURROUND}x{LABEL_TAG_SURROUND}"),
+            String::from("- end"),
+            format!("{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty()),
+            format!("Text.\n{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty()),
+            format!("Text.\n  {MARK_HEADING_PREFIX}heading"),
+            format!("Text.\n\n{MARKS_HEADING}"),
+            format!("Text.\n{SESSION_HEADING_PREFIX}{SESSION_TITLE}"),
+            String::from("\\- stop\n\\\\ two"),
+            String::from("Text.\n- stop\n- item"),
+        ];
+        for text in colliding {
+            let mut session = Session {
+                path: PathBuf::from("sessions"),
+                marks: vec![Mark::new(&dt.date)],
+                ..Default::default()
+            };
+            session.write(&text).unwrap();
+            session.mark(&DateTime::now(), false).unwrap();
+            session.set_attribute(Attribute::Stop).unwrap();
+            let file = session.to_file().unwrap();
+            let parsed = Session::from_file(&file).unwrap();
+            assert_eq!(parsed, session, "{text}");
+            assert_eq!(parsed.marks[0].contents, text);
+        }
+    }
+
+    #[test]
+    fn mark_escape_contents_works() {
+        assert_eq!(Mark::escape_contents("- stop\n- skip"), "\\- stop\n- skip");
+        assert_eq!(
+            Mark::escape_contents("a\n## b\n  # c"),
+            "a\n\\## b\n\\  # c"
+        );
+        assert_eq!(Mark::escape_contents("\\a\n-b"), "\\\\a\n-b");
+        assert_eq!(
+            Mark::unescape_contents("\\- stop\n\\\\a\nb"),
+            "- stop\n\\a\nb"
+        );
+    }
+
     #[test]
     fn mark_to_line_works() -> Result<(), Box<dyn Error>> {
         let dt = DateTime::now();
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::Stop,
+            billable: false,
             tags: HashSet::from_iter([Tag::from_text("time tracker")?, Tag::from_text("rust")?]),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is a content of a mark.\nHow are you?\n"),
         };
         let output = format!(
@@ -1112,33 +6635,49 @@ mod tests {
         let dt = DateTime::now();
 
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::None,
+            billable: false,
             tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is a content of a mark.\nHow are you?"),
         };
         assert_eq!(mark, Mark::from_line(&mark.to_line())?);
 
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::Stop,
+            billable: false,
             tags: HashSet::new(),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is a content of a mark.\nHow are you?"),
         };
         assert_eq!(mark, Mark::from_line(&mark.to_line())?);
 
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::None,
+            billable: false,
             tags: HashSet::from_iter([Tag::from_text("rust")?, Tag::from_text("time tracker")?]),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is a content of a mark.\nHow are you?"),
         };
         assert_eq!(mark, Mark::from_line(&mark.to_line())?);
 
         let mark = Mark {
-            date: dt.date,
+            date: dt.date.fixed_offset(),
             attribute: Attribute::Stop,
+            billable: false,
             tags: HashSet::from_iter([Tag::from_text("rust")?, Tag::from_text("time tracker")?]),
+            host: None,
+            context: None,
+            encrypted: None,
             contents: String::from("This is a content of a mark.\nHow are you?"),
         };
         assert_eq!(mark, Mark::from_line(&mark.to_line())?);
@@ -1146,13 +6685,293 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn mark_billable_round_trip_works() -> Result<(), Box<dyn Error>> {
+        let dt = DateTime::new(&testing::date_default());
+        let heading = format!("{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty());
+        let mut mark = Mark::new(&dt.date);
+        mark.billable = true;
+        assert_eq!(mark.to_line(), format!("{heading}\n\n{LABEL_BILLABLE}"));
+        assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+
+        mark.attribute = Attribute::Skip;
+        mark.tags.insert(Tag::from_text("client-a")?);
+        mark.contents = String::from("Invoice prep.");
+        assert_eq!(
+            mark.to_line(),
+            format!(
+                "{heading}\n\n{LABEL_SKIP}\n{LABEL_BILLABLE}\n{LABEL_TAG} `client-a`\n\n\
+                 Invoice prep."
+            )
+        );
+        assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+
+        // The label can be anywhere among the others.
+        let parsed = Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_TAG} `client-a`\n{LABEL_BILLABLE}\n{LABEL_SKIP}\n\nInvoice prep."
+        ))?;
+        assert_eq!(parsed, mark);
+        assert!(Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_STOP}\n{LABEL_BILLABLE}\n{LABEL_SKIP}"
+        ))
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mark_host_round_trip_works() -> Result<(), Box<dyn Error>> {
+        let dt = DateTime::new(&testing::date_default());
+        let heading = format!("{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty());
+        let mut mark = Mark::new(&dt.date);
+        mark.host = Some(String::from("laptop"));
+        assert_eq!(
+            mark.to_line(),
+            format!("{heading}\n\n{LABEL_HOST} `laptop`")
+        );
+        assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+        assert_eq!(mark.host(), Some("laptop"));
+
+        // A host next to an attribute isn't taken for a second attribute.
+        mark.attribute = Attribute::Stop;
+        mark.tags.insert(Tag::from_text("rust")?);
+        assert_eq!(
+            mark.to_line(),
+            format!("{heading}\n\n{LABEL_STOP}\n{LABEL_HOST} `laptop`\n{LABEL_TAG} `rust`")
+        );
+        assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+        let parsed = Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_HOST} `laptop`\n{LABEL_TAG} `rust`\n{LABEL_STOP}"
+        ))?;
+        assert_eq!(parsed, mark);
+
+        // Marks without the label have no host.
+        assert_eq!(
+            Mark::from_line(&format!("{heading}\n\n{LABEL_STOP}"))?.host(),
+            None
+        );
+
+        assert!(Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_HOST} `laptop`\n{LABEL_HOST} `desktop`"
+        ))
+        .is_err());
+        assert!(Mark::from_line(&format!("{heading}\n\n{LABEL_HOST} ``")).is_err());
+        assert!(Mark::from_line(&format!("{heading}\n\n{LABEL_HOST} laptop")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mark_context_round_trip_works() -> Result<(), Box<dyn Error>> {
+        let dt = DateTime::new(&testing::date_default());
+        let heading = format!("{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty());
+        let mut mark = Mark::new(&dt.date);
+        mark.context = Some(String::from("office"));
+        assert_eq!(
+            mark.to_line(),
+            format!("{heading}\n\n{LABEL_CONTEXT} `office`")
+        );
+        assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+
+        mark.attribute = Attribute::Skip;
+        mark.host = Some(String::from("laptop"));
+        let line =
+            format!("{heading}\n\n{LABEL_SKIP}\n{LABEL_HOST} `laptop`\n{LABEL_CONTEXT} `office`");
+        assert_eq!(mark.to_line(), line);
+        assert_eq!(Mark::from_line(&line)?, mark);
+        assert_eq!(
+            Mark::from_line(&format!(
+                "{heading}\n\n{LABEL_CONTEXT} `office`\n{LABEL_SKIP}\n{LABEL_HOST} `laptop`"
+            ))?,
+            mark
+        );
+
+        assert!(Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_CONTEXT} `office`\n{LABEL_CONTEXT} `home`"
+        ))
+        .is_err());
+        assert!(Mark::from_line(&format!("{heading}\n\n{LABEL_CONTEXT} ` `")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn mark_encrypted_label_works() -> Result<(), Box<dyn Error>> {
+        let dt = DateTime::new(&testing::date_default());
+        let heading = format!("{MARK_HEADING_PREFIX}{}", dt.to_formatted_pretty());
+        let line =
+            format!("{heading}\n\n{LABEL_SKIP}\n{LABEL_TAG} `rust`\n{LABEL_ENCRYPTED} `c2VhbGVk`");
+        let mark = Mark::from_line(&line)?;
+        assert!(mark.is_encrypted());
+        assert_eq!(mark.attribute, Attribute::Skip);
+        assert_eq!(mark.contents(), "");
+        assert_eq!(mark.title(), None);
+        assert_eq!(mark.to_line(), line);
+
+        assert!(Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_ENCRYPTED} `c2VhbGVk`\n\nPlain text."
+        ))
+        .is_err());
+        assert!(Mark::from_line(&format!(
+            "{heading}\n\n{LABEL_ENCRYPTED} `c2VhbGVk`\n{LABEL_ENCRYPTED} `c2VhbGVk`"
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn session_with_encrypted_marks_keeps_times() {
+        let dt = DateTime::new(&testing::date_default());
+        let path = PathBuf::from("sessions/2025-01-07.md");
+        let mark = |minutes: i64, labels: &str| {
+            format!(
+                "{MARK_HEADING_PREFIX}{}\n\n{labels}",
+                dt.plus_minutes(minutes).to_formatted_pretty()
+            )
+        };
+        let contents = format!(
+            "{SESSION_HEADING_PREFIX}{SESSION_TITLE}\n\n{MARKS_HEADING}\n\n{}\n\n{}\n\n{}\n",
+            mark(0, &format!("{LABEL_ENCRYPTED} `c2VhbGVk`")),
+            mark(30, "Plain text."),
+            mark(90, LABEL_STOP),
+        );
+        let mut session =
+            Session::from_file(&SessionFile::build(&path, &contents).unwrap()).unwrap();
+        assert_eq!(session.get_time(&[]), Ok(Duration::from_secs(90 * 60)));
+        let first = WriteTarget::Mark(MarkSelector::Index(1));
+        assert!(session.is_encrypted_at(&first));
+        assert!(!session.is_encrypted_at(&WriteTarget::Mark(MarkSelector::Index(2))));
+        assert!(session.text_at(&first).is_err());
+        assert!(session
+            .write_at(&first, "Other.", WriteMode::Overwrite)
+            .is_err());
+        assert!(session.has_plain_contents());
+        let marks = &contents[contents.find(MARKS_HEADING).unwrap()..];
+        assert!(session.to_file().unwrap().contents.ends_with(marks));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn session_encrypt_contents_round_trip() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let date = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&config, &date.plus_hours(-1));
+        let first = WriteTarget::Mark(MarkSelector::Index(1));
+        session
+            .write_at(&first, "Call with Client A.", WriteMode::New)
+            .unwrap();
+        session.mark(&date, false).unwrap();
+        let cipher = Cipher::new("correct horse").unwrap();
+
+        session.encrypt_contents(&cipher).unwrap();
+        assert!(!session.has_plain_contents());
+        assert!(session.is_encrypted_at(&first));
+        // The last mark had no contents, so there is nothing to encrypt.
+        assert!(!session.is_encrypted_at(&WriteTarget::Mark(MarkSelector::Last)));
+        let text = session.to_file().unwrap().contents;
+        assert!(!text.contains("Client A"));
+
+        let mut parsed =
+            Session::from_file(&SessionFile::build(&session.path, &text).unwrap()).unwrap();
+        assert!(parsed
+            .decrypt_at(&first, &Cipher::new("wrong").unwrap())
+            .is_err());
+        parsed.decrypt_at(&first, &cipher).unwrap();
+        assert_eq!(parsed.text_at(&first), Ok("Call with Client A."));
+    }
+
+    #[test]
+    fn session_set_context_carries_to_new_marks() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let date = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&config, &date.plus_hours(-4));
+        assert_eq!(session.set_context(Some(" office ")), Ok(None));
+        session.mark(&date.plus_hours(-3), false).unwrap();
+        assert_eq!(session.marks[1].context(), Some("office"));
+        session
+            .insert_idle(
+                &date.plus_hours(-4).plus_minutes(10),
+                &date.plus_hours(-4).plus_minutes(20),
+            )
+            .unwrap();
+        assert_eq!(session.marks[1].context(), Some("office"));
+        assert_eq!(session.marks[2].context(), Some("office"));
+
+        assert_eq!(
+            session.set_context(Some("home")),
+            Ok(Some(String::from("office")))
+        );
+        session.mark(&date.plus_hours(-2), false).unwrap();
+        assert_eq!(session.marks[3].context(), Some("home"));
+        assert_eq!(session.marks[4].context(), Some("home"));
+        assert_eq!(session.set_context(None), Ok(Some(String::from("home"))));
+        session.mark(&date.plus_hours(-1), false).unwrap();
+        assert_eq!(session.marks[5].context(), None);
+        assert!(session.set_context(Some("a`b")).is_err());
+
+        session.set_context(Some("home")).unwrap();
+        let derived = Session::derive_from(&session, &date);
+        assert_eq!(derived.marks[0].context(), None);
+    }
+
+    #[test]
+    fn session_writes_host_on_new_marks() {
+        let config = Config {
+            sessions_path: PathBuf::from("sessions"),
+            ..Default::default()
+        };
+        let date = DateTime::new(&testing::date_default());
+        let mut session = Session::new(&config, &date);
+        assert_eq!(session.marks[0].host(), None);
+
+        session.host = Some(String::from("laptop"));
+        session.mark(&date.plus_hours(1), false).unwrap();
+        session.pause(&date.plus_hours(2)).unwrap();
+        assert_eq!(session.marks[0].host(), None);
+        assert_eq!(session.marks[1].host(), Some("laptop"));
+        assert_eq!(session.marks[2].host(), Some("laptop"));
+        let derived = Session::derive_from(&session, &date.plus_hours(3));
+        assert_eq!(derived.marks[0].host(), Some("laptop"));
+    }
+
     #[test]
     fn attribute_from_text_works() -> Result<(), Box<dyn Error>> {
         assert_eq!(Attribute::from_text("stop")?, Attribute::Stop);
         assert_eq!(Attribute::from_text("skip")?, Attribute::Skip);
         assert_eq!(Attribute::from_text("none")?, Attribute::None);
+        assert_eq!(Attribute::from_text(" End ")?, Attribute::Stop);
+        assert_eq!(Attribute::from_text("CLEAR")?, Attribute::None);
         assert!(Attribute::from_text("hello").is_err());
+        assert!(Attribute::from_text("banana").is_err());
+        assert!(Attribute::from_text("").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn attribute_round_trip_works() -> Result<(), Box<dyn Error>> {
+        for (text, attribute) in [
+            ("stop", Attribute::Stop),
+            ("end", Attribute::Stop),
+            ("skip", Attribute::Skip),
+            ("clear", Attribute::None),
+        ] {
+            let parsed = Attribute::from_text(text)?;
+            assert_eq!(parsed, attribute);
+            assert_eq!(Attribute::from_line(&parsed.to_line()), attribute);
+            assert_eq!(Attribute::from_text(parsed.name())?, attribute);
+        }
 
+        // The label survives saving a mark.
+        let mut mark = Mark::new(&testing::date_default());
+        for attribute in [Attribute::Stop, Attribute::Skip, Attribute::None] {
+            mark.attribute = attribute;
+            assert_eq!(Mark::from_line(&mark.to_line())?, mark);
+        }
         Ok(())
     }
 
@@ -1186,6 +7005,58 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tag_from_text_collapses_whitespace() -> Result<(), Box<dyn Error>> {
+        assert_eq!(Tag::from_text("  time   tracker ")?.text, "time tracker");
+        assert_eq!(Tag::from_text("code review")?.text, "code review");
+        assert!(Tag::from_text("   ").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tag_from_text_rejects_control_characters() {
+        assert_eq!(
+            Tag::from_text("rust\n- stop"),
+            Err(String::from(
+                "tag cannot contain line breaks or control characters"
+            ))
+        );
+        assert!(Tag::from_text("rust\r").is_err());
+        assert!(Tag::from_text("time\ttracker").is_err());
+        assert!(Tag::from_text("bell\u{7}").is_err());
+        assert!(Tag::from_text("# heading").is_ok());
+        assert!(Tag::from_text("- rust").is_ok());
+    }
+
+    #[test]
+    fn tag_from_text_limits_length() {
+        let longest = "a".repeat(TAG_MAX_LENGTH);
+        assert!(Tag::from_text(&longest).is_ok());
+        assert!(Tag::from_text(&"ř".repeat(TAG_MAX_LENGTH)).is_ok());
+        assert_eq!(
+            Tag::from_text(&format!("{longest}a")),
+            Err(format!(
+                "tag cannot be longer than {TAG_MAX_LENGTH} characters"
+            ))
+        );
+    }
+
+    #[test]
+    fn tag_from_text_rejects_label_names() {
+        assert_eq!(
+            Tag::from_text(" skip "),
+            Err(format!(
+                "tag `skip` would be confused with the `{LABEL_SKIP}` label"
+            ))
+        );
+        assert!(Tag::from_text("stop").is_err());
+        assert!(Tag::from_text("billable").is_err());
+        assert!(Tag::from_text("skipped").is_ok());
+        assert!(Tag::from_text("Stop").is_ok());
+        assert!(Tag::from_text("tag").is_ok());
+    }
+
     #[test]
     fn tag_from_line_works() -> Result<(), Box<dyn Error>> {
         assert_eq!(
@@ -1196,12 +7067,28 @@ mod tests {
                 text: String::from("rust")
             }
         );
-        assert!(Tag::from_line(&format!("rust")).is_err());
+        assert!(Tag::from_line("rust").is_err());
         assert!(Tag::from_line(&format!("{LABEL_TAG} rust")).is_err());
         assert!(Tag::from_line(&format!("{LABEL_TAG} {LABEL_TAG_SURROUND}rust")).is_err());
         assert!(Tag::from_line(&format!("{LABEL_TAG} rust{LABEL_TAG_SURROUND}")).is_err());
         assert!(Tag::from_line(&format!("{LABEL_TAG_SURROUND}rust")).is_err());
         assert!(Tag::from_line(&format!("rust{LABEL_TAG_SURROUND}")).is_err());
+        assert_eq!(
+            Tag::from_line(&format!(
+                "{LABEL_TAG} {LABEL_TAG_SURROUND} time  tracker {LABEL_TAG_SURROUND}"
+            ))?
+            .text,
+            "time tracker"
+        );
+        let err = Tag::from_line(&format!(
+            "{LABEL_TAG} {LABEL_TAG_SURROUND}skip{LABEL_TAG_SURROUND}"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("would be confused"), "{err}");
+        assert!(Tag::from_line(&format!(
+            "{LABEL_TAG} {LABEL_TAG_SURROUND} {LABEL_TAG_SURROUND}"
+        ))
+        .is_err());
 
         Ok(())
     }
@@ -1215,4 +7102,117 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn session_diff_works() {
+        let config = Config::default();
+        let start = DateTime::new(&testing::date_default());
+        let mut before = Session::new(&config, &start);
+        before.mark(&start.plus_minutes(30), false).unwrap();
+        let tag = Tag::from_text("rust").unwrap();
+        let mut session = before.clone();
+        assert_eq!(session.diff(&before), vec![]);
+
+        session
+            .write_at(
+                &WriteTarget::Mark(MarkSelector::Index(1)),
+                "Parser",
+                WriteMode::New,
+            )
+            .unwrap();
+        session.remark(&start.plus_minutes(45), false).unwrap();
+        session.mark(&start.plus_minutes(60), false).unwrap();
+        session.tag(&tag, &MarkSelector::Last).unwrap();
+        session.tag_session(&Tag::from_text("work").unwrap());
+        session.set_attribute(Attribute::Stop).unwrap();
+        let changes = session.diff(&before);
+        assert_eq!(
+            changes,
+            vec![
+                Change::TagAdded {
+                    date: None,
+                    tag: Tag::from_text("work").unwrap()
+                },
+                Change::MarkMoved {
+                    from: start.plus_minutes(30),
+                    to: start.plus_minutes(45)
+                },
+                Change::MarkAdded(start.plus_minutes(60)),
+                Change::Attribute {
+                    date: start.plus_minutes(60),
+                    attribute: Attribute::Stop
+                },
+                Change::TagAdded {
+                    date: Some(start.plus_minutes(60)),
+                    tag: tag.clone()
+                },
+                Change::Contents {
+                    date: start.clone(),
+                    lines_before: 0
+                },
+            ]
+        );
+        assert_eq!(
+            changes[2].to_string(),
+            format!(
+                "added mark ### {}",
+                start.plus_minutes(60).to_formatted_pretty_short()
+            )
+        );
+        assert_eq!(
+            changes[4].to_string(),
+            format!(
+                "tag `rust` added to mark {}",
+                start.plus_minutes(60).to_formatted_time()
+            )
+        );
+
+        let before = session.clone();
+        session
+            .write_at(
+                &WriteTarget::Mark(MarkSelector::Index(1)),
+                "Lexer",
+                WriteMode::Overwrite,
+            )
+            .unwrap();
+        session.overwrite_attribute(Attribute::None);
+        session.untag(&tag, &MarkSelector::Last).unwrap();
+        session.unmark(&MarkSelector::Last, false).unwrap();
+        let changes = session.diff(&before);
+        assert_eq!(
+            changes,
+            vec![
+                Change::MarkRemoved(start.plus_minutes(60)),
+                Change::Contents {
+                    date: start.clone(),
+                    lines_before: 1
+                },
+            ]
+        );
+        assert_eq!(
+            changes[1].to_string(),
+            format!(
+                "text of mark {} overwritten (was 1 line)",
+                start.to_formatted_time()
+            )
+        );
+
+        let before = session.clone();
+        session.set_context(Some("office")).unwrap();
+        let changes = session.diff(&before);
+        assert_eq!(
+            changes,
+            vec![Change::Context {
+                date: start.plus_minutes(45),
+                context: Some(String::from("office"))
+            }]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            format!(
+                "mark {} context set to `office`",
+                start.plus_minutes(45).to_formatted_time()
+            )
+        );
+    }
 }
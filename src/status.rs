@@ -0,0 +1,408 @@
+use crate::{
+    date_time::{DateTime, DisplayPrefs, Duration, DurationFormat, TimestampFormat},
+    json,
+};
+
+pub const STATUS_EMPTY: &str = "No sessions yet";
+/// Follows the time of a mark longer than [`Config::pomodoro`](crate::Config::pomodoro).
+pub const BREAK_DUE_INDICATOR: &str = " (!)";
+/// Separates the parts of the Waybar tooltip.
+const WAYBAR_SEPARATOR: &str = " · ";
+/// Separates the key and the value on a line of [`Status::render_porcelain`].
+const PORCELAIN_SEPARATOR: char = '\t';
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum State {
+    Active,
+    /// The last session has ended.
+    Inactive,
+    /// Active, but without a mark for longer than [`Config::auto_stop_after`](crate::Config).
+    Stale,
+}
+
+impl State {
+    fn name(self) -> &'static str {
+        match self {
+            State::Active => "active",
+            State::Inactive => "inactive",
+            State::Stale => "stale",
+        }
+    }
+}
+
+/// Current work interval, see [`Session::get_pomodoro`](crate::session::Session::get_pomodoro).
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Pomodoro {
+    Work {
+        left: Duration,
+    },
+    /// The current mark is longer than the work interval.
+    BreakDue,
+    /// The session is paused.
+    Break,
+    /// There is no active session.
+    Inactive,
+}
+
+impl Pomodoro {
+    /// Single line meant to be polled by a status bar, `12m left` or `break due`.
+    pub fn render(&self) -> String {
+        match self {
+            Pomodoro::Work { left } => format!("{} left", format_short(*left)),
+            Pomodoro::BreakDue => String::from("break due"),
+            Pomodoro::Break => String::from("on a break"),
+            Pomodoro::Inactive => State::Inactive.name().to_owned(),
+        }
+    }
+}
+
+/// Summary of the session shown by `view`, see [`Aggregator::status`](crate::session::Aggregator).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Status {
+    pub state: State,
+    pub start: DateTime,
+    pub session: Duration,
+    /// Time since the last mark, zero unless the session is active.
+    pub mark: Duration,
+    /// The mark is longer than [`Config::pomodoro`](crate::Config::pomodoro).
+    pub break_due: bool,
+    pub week: Duration,
+    /// Contents of the last mark.
+    pub mark_text: String,
+}
+
+impl Status {
+    /// Single line, `active: 1h 12m 0s, mark 0h 8m 0s, week 14h 0m 0s`.
+    pub fn render(&self, format: &DurationFormat) -> String {
+        format!(
+            "{}: {}, mark {}{}, week {}",
+            self.state.name(),
+            self.session.format(format),
+            self.mark.format(format),
+            self.get_break_due_indicator(),
+            self.week.format(format)
+        )
+    }
+
+    fn get_break_due_indicator(&self) -> &'static str {
+        if self.break_due {
+            BREAK_DUE_INDICATOR
+        } else {
+            ""
+        }
+    }
+
+    /// JSON object read by Waybar's custom modules, always a single line.
+    pub fn render_waybar(&self, now: &DateTime, prefs: &DisplayPrefs) -> String {
+        // Today's start is known from the context, only the time is shown.
+        let start = if self.start.date.date_naive() == now.date.date_naive() {
+            prefs.format_time(&self.start.date, false)
+        } else {
+            self.start.to_formatted_relative(now, prefs)
+        };
+        let tooltip = [
+            format!("Started {start}"),
+            format!(
+                "mark {}{}",
+                format_short(self.mark),
+                self.get_break_due_indicator()
+            ),
+            format!("week {}", format_short(self.week)),
+        ]
+        .join(WAYBAR_SEPARATOR);
+        waybar_json(&format_short(self.session), self.state.name(), &tooltip)
+    }
+
+    /// See [`Status::render_waybar`], for when there are no sessions.
+    pub fn render_waybar_empty() -> String {
+        waybar_json("", State::Inactive.name(), STATUS_EMPTY)
+    }
+
+    /// `key<TAB>value` lines for scripts, keys keep their meaning across versions and new ones are
+    /// only added at the end. In order: `active` is `1` until the session ends, stale sessions
+    /// included, and `0` after, `start` is in seconds since the Unix epoch, `week_ms`,
+    /// `session_ms` and `mark_ms` are in milliseconds and `mark_text` is the contents of the last
+    /// mark. Values are escaped with [`escape_porcelain`].
+    pub fn render_porcelain(&self) -> String {
+        let active = if self.state == State::Inactive { 0 } else { 1 };
+        [
+            ("active", active.to_string()),
+            ("start", self.start.date.timestamp().to_string()),
+            ("week_ms", self.week.as_millis().to_string()),
+            ("session_ms", self.session.as_millis().to_string()),
+            ("mark_ms", self.mark.as_millis().to_string()),
+            ("mark_text", escape_porcelain(&self.mark_text)),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{key}{PORCELAIN_SEPARATOR}{value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+
+    /// See [`Status::render_porcelain`], for when there are no sessions only `active` is given.
+    pub fn render_porcelain_empty() -> String {
+        format!("active{PORCELAIN_SEPARATOR}0")
+    }
+}
+
+/// Answer of `when`, see [`Aggregator::when`](crate::session::Aggregator::when).
+#[derive(PartialEq, Debug, Clone)]
+pub struct When {
+    pub start: DateTime,
+    /// Date of the last mark, when the session ended unless it's active.
+    pub last_mark: DateTime,
+    pub active: bool,
+    /// Time left to track today to stay on pace with
+    /// [`Config::weekly_goal`](crate::Config::weekly_goal), `None` without a goal.
+    pub goal_left: Option<Duration>,
+}
+
+impl When {
+    /// `Last mark: 25m ago`, the start and when the daily share of the goal is reached by working
+    /// on without breaks.
+    pub fn render(
+        &self,
+        now: &DateTime,
+        timestamp_format: &TimestampFormat,
+        prefs: &DisplayPrefs,
+    ) -> String {
+        let ago =
+            format_short(Duration::between(&self.last_mark.date, &now.date).unwrap_or_default());
+        let mut lines = vec![if self.active {
+            format!("Last mark: {ago} ago")
+        } else {
+            format!("Last session ended {ago} ago")
+        }];
+        // Relative to `now` rather than the clock, like the rest of the output.
+        let start = match timestamp_format {
+            TimestampFormat::Relative => self.start.to_formatted_relative(now, prefs),
+            TimestampFormat::Absolute => self.start.to_formatted_display(prefs),
+        };
+        lines.push(format!("Started: {start}"));
+        match self.goal_left {
+            Some(Duration::ZERO) => lines.push(String::from("Daily goal: reached")),
+            Some(left) if self.active => {
+                let minutes = left.as_secs().div_ceil(60);
+                let at = prefs.format_time(&now.plus_minutes(minutes as i64).date, false);
+                lines.push(format!("Daily goal: at {at}, {} left", format_short(left)));
+            }
+            Some(left) => lines.push(format!("Daily goal: {} left", format_short(left))),
+            None => (),
+        }
+        lines.join("\n")
+    }
+}
+
+fn waybar_json(text: &str, class: &str, tooltip: &str) -> String {
+    format!(
+        "{{\"text\": {}, \"class\": {}, \"tooltip\": {}}}",
+        json::to_string(text),
+        json::to_string(class),
+        json::to_string(tooltip)
+    )
+}
+
+/// Backslashes, tabs, line feeds and carriage returns as `\\`, `\t`, `\n` and `\r`, so that a
+/// value stays on its line.
+fn escape_porcelain(text: &str) -> String {
+    let mut str = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => str += "\\\\",
+            '\t' => str += "\\t",
+            '\n' => str += "\\n",
+            '\r' => str += "\\r",
+            c => str.push(c),
+        }
+    }
+    str
+}
+
+/// Whole hours and minutes, units that are zero are left out, `1h 12m`, `14h` or `8m`.
+fn format_short(time: Duration) -> String {
+    let minutes = time.as_secs() / 60;
+    let parts: Vec<String> = [(minutes / 60, 'h'), (minutes % 60, 'm')]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect();
+    if parts.is_empty() {
+        return String::from("0m");
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn status() -> Status {
+        Status {
+            state: State::Active,
+            start: DateTime::new(&testing::date_default()).plus_minutes(-72),
+            session: Duration::from_secs(72 * 60 + 30),
+            mark: Duration::from_secs(8 * 60),
+            break_due: false,
+            week: Duration::from_secs(14 * 60 * 60),
+            mark_text: String::from("Parser."),
+        }
+    }
+
+    #[test]
+    fn format_short_works() {
+        assert_eq!(format_short(Duration::ZERO), "0m");
+        assert_eq!(format_short(Duration::from_secs(59)), "0m");
+        assert_eq!(format_short(Duration::from_secs(8 * 60)), "8m");
+        assert_eq!(format_short(Duration::from_secs(14 * 60 * 60)), "14h");
+        assert_eq!(format_short(Duration::from_secs(72 * 60)), "1h 12m");
+    }
+
+    #[test]
+    fn status_render_works() {
+        assert_eq!(
+            status().render(&DurationFormat::Hms),
+            "active: 1h 12m 30s, mark 0h 8m 0s, week 14h 0m 0s"
+        );
+    }
+
+    #[test]
+    fn status_render_shows_break_due() {
+        let status = Status {
+            break_due: true,
+            ..status()
+        };
+        assert_eq!(
+            status.render(&DurationFormat::Hms),
+            "active: 1h 12m 30s, mark 0h 8m 0s (!), week 14h 0m 0s"
+        );
+        let now = DateTime::new(&testing::date_default());
+        assert!(status
+            .render_waybar(&now, &DisplayPrefs::default())
+            .contains("mark 8m (!)"));
+    }
+
+    #[test]
+    fn pomodoro_render_works() {
+        let left = Duration::from_secs(12 * 60 + 30);
+        assert_eq!(Pomodoro::Work { left }.render(), "12m left");
+        assert_eq!(Pomodoro::BreakDue.render(), "break due");
+        assert_eq!(Pomodoro::Break.render(), "on a break");
+        assert_eq!(Pomodoro::Inactive.render(), "inactive");
+    }
+
+    #[test]
+    fn status_render_waybar_works() {
+        let now = DateTime::new(&testing::date_default());
+        assert_eq!(
+            status().render_waybar(&now, &DisplayPrefs::default()),
+            "{\"text\": \"1h 12m\", \"class\": \"active\", \
+             \"tooltip\": \"Started 10:48 · mark 8m · week 14h\"}"
+        );
+        let status = Status {
+            state: State::Stale,
+            start: now.plus_days(-1),
+            ..status()
+        };
+        assert_eq!(
+            status.render_waybar(&now, &DisplayPrefs::default()),
+            "{\"text\": \"1h 12m\", \"class\": \"stale\", \
+             \"tooltip\": \"Started yesterday 12:00 · mark 8m · week 14h\"}"
+        );
+        assert_eq!(
+            Status::render_waybar_empty(),
+            "{\"text\": \"\", \"class\": \"inactive\", \"tooltip\": \"No sessions yet\"}"
+        );
+        assert!(!status
+            .render_waybar(&now, &DisplayPrefs::default())
+            .contains('\n'));
+        assert_eq!(
+            waybar_json("a\"\nb", "active", ""),
+            "{\"text\": \"a\\\"\\nb\", \"class\": \"active\", \"tooltip\": \"\"}"
+        );
+    }
+
+    #[test]
+    fn status_render_porcelain_works() {
+        let start = status().start.date.timestamp();
+        assert_eq!(
+            status().render_porcelain(),
+            format!(
+                "active\t1\n\
+                 start\t{start}\n\
+                 week_ms\t50400000\n\
+                 session_ms\t4350000\n\
+                 mark_ms\t480000\n\
+                 mark_text\tParser."
+            )
+        );
+        let inactive = Status {
+            state: State::Inactive,
+            mark: Duration::ZERO,
+            mark_text: String::new(),
+            ..status()
+        };
+        assert_eq!(
+            inactive.render_porcelain(),
+            format!(
+                "active\t0\n\
+                 start\t{start}\n\
+                 week_ms\t50400000\n\
+                 session_ms\t4350000\n\
+                 mark_ms\t0\n\
+                 mark_text\t"
+            )
+        );
+        let stale = Status {
+            state: State::Stale,
+            ..status()
+        };
+        assert!(stale.render_porcelain().starts_with("active\t1\n"));
+        assert_eq!(Status::render_porcelain_empty(), "active\t0");
+    }
+
+    #[test]
+    fn escape_porcelain_works() {
+        assert_eq!(escape_porcelain("plain text"), "plain text");
+        assert_eq!(escape_porcelain("a\tb\r\nc\\n"), "a\\tb\\r\\nc\\\\n");
+        let status = Status {
+            mark_text: String::from("Parser.\n\n- [ ] tests\tdone"),
+            ..status()
+        };
+        let porcelain = status.render_porcelain();
+        assert_eq!(porcelain.lines().count(), 6);
+        assert!(porcelain.ends_with("\nmark_text\tParser.\\n\\n- [ ] tests\\tdone"));
+    }
+
+    #[test]
+    fn when_render_works() {
+        let now = DateTime::new(&testing::date_default());
+        let mut when = When {
+            start: now.plus_hours(-3),
+            last_mark: now.plus_minutes(-25),
+            active: true,
+            goal_left: Some(Duration::from_secs(2 * 60 * 60 + 10 * 60)),
+        };
+        assert_eq!(
+            when.render(&now, &TimestampFormat::Relative, &DisplayPrefs::default()),
+            "Last mark: 25m ago\nStarted: today 09:00\nDaily goal: at 14:10, 2h 10m left"
+        );
+        when.active = false;
+        when.last_mark = now.plus_minutes(-135);
+        assert_eq!(
+            when.render(&now, &TimestampFormat::Relative, &DisplayPrefs::default()),
+            "Last session ended 2h 15m ago\nStarted: today 09:00\nDaily goal: 2h 10m left"
+        );
+        when.goal_left = Some(Duration::ZERO);
+        assert!(when
+            .render(&now, &TimestampFormat::Relative, &DisplayPrefs::default())
+            .ends_with("\nDaily goal: reached"));
+        when.goal_left = None;
+        assert_eq!(
+            when.render(&now, &TimestampFormat::Relative, &DisplayPrefs::default())
+                .lines()
+                .count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/// Disables colors when set to anything but an empty string, see <https://no-color.org>.
+const ENV_NO_COLOR: &str = "NO_COLOR";
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(PartialEq, Debug, Default, Clone)]
+pub enum ColorMode {
+    /// Colors only when writing to a terminal and [`ENV_NO_COLOR`] isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn from_text(text: &str) -> Result<ColorMode, String> {
+        match text.trim() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            text => Err(format!(
+                "invalid color `{text}`, use `auto`, `always` or `never`"
+            )),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Cyan,
+    Dim,
+}
+
+impl Color {
+    fn get_code(self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Cyan => "\x1b[36m",
+            Color::Dim => "\x1b[2m",
+        }
+    }
+}
+
+/// Colors text written to stdout, or leaves it plain when colors are disabled.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Style {
+    is_enabled: bool,
+}
+
+impl Style {
+    pub fn new(mode: &ColorMode) -> Style {
+        let is_enabled = match mode {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                env::var_os(ENV_NO_COLOR).is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+            }
+        };
+        Style { is_enabled }
+    }
+
+    pub fn paint(&self, text: &str, color: Color) -> String {
+        if self.is_enabled {
+            format!("{}{text}{ANSI_RESET}", color.get_code())
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_mode_from_text_works() {
+        assert_eq!(ColorMode::from_text(" auto "), Ok(ColorMode::Auto));
+        assert_eq!(ColorMode::from_text("always"), Ok(ColorMode::Always));
+        assert_eq!(ColorMode::from_text("never"), Ok(ColorMode::Never));
+        assert!(ColorMode::from_text("yes").is_err());
+    }
+
+    #[test]
+    fn style_paint_works() {
+        let style = Style::new(&ColorMode::Always);
+        assert_eq!(style.paint("rust", Color::Cyan), "\x1b[36mrust\x1b[0m");
+        let style = Style::new(&ColorMode::Never);
+        assert_eq!(style.paint("rust", Color::Cyan), "rust");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::date_time::DateTime;
-use chrono::{Datelike, Timelike};
+use chrono::{Datelike, TimeZone, Timelike};
+use std::{env, fs, path::PathBuf, process};
 
 /// Returns `2002:05:05T12:00:00` with your local timezone. The day is a Wednesday.
 pub fn date_default() -> chrono::DateTime<chrono::Local> {
@@ -25,3 +26,97 @@ pub fn now_plus_secs(secs: i64) -> chrono::DateTime<chrono::Local> {
         .unwrap()
         .into()
 }
+
+/// Creates an empty directory inside the system temp directory, `name` must be unique per test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("time_tracker-{}-{name}", process::id()));
+    if path.exists() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+/// Time zone switching between UTC+1 and UTC+2 like Central European Time in 2025, DST starts on
+/// 2025-03-30 at 02:00 and ends on 2025-10-26 at 03:00. Makes DST transitions testable regardless
+/// of the time zone of the machine.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DstZone;
+
+impl DstZone {
+    fn standard() -> chrono::FixedOffset {
+        chrono::FixedOffset::east_opt(60 * 60).unwrap()
+    }
+
+    fn summer() -> chrono::FixedOffset {
+        chrono::FixedOffset::east_opt(2 * 60 * 60).unwrap()
+    }
+}
+
+impl TimeZone for DstZone {
+    type Offset = chrono::FixedOffset;
+
+    fn from_offset(_offset: &chrono::FixedOffset) -> DstZone {
+        DstZone
+    }
+
+    fn offset_from_local_date(
+        &self,
+        local: &chrono::NaiveDate,
+    ) -> chrono::LocalResult<chrono::FixedOffset> {
+        self.offset_from_local_datetime(&local.and_time(chrono::NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(
+        &self,
+        local: &chrono::NaiveDateTime,
+    ) -> chrono::LocalResult<chrono::FixedOffset> {
+        // Summer offset first, it's the earlier instant for ambiguous times.
+        let valid: Vec<chrono::FixedOffset> = [DstZone::summer(), DstZone::standard()]
+            .into_iter()
+            .filter(|offset| {
+                let utc = *local - chrono::TimeDelta::seconds(offset.local_minus_utc().into());
+                self.offset_from_utc_datetime(&utc) == *offset
+            })
+            .collect();
+        match valid[..] {
+            [offset] => chrono::LocalResult::Single(offset),
+            [earliest, latest] => chrono::LocalResult::Ambiguous(earliest, latest),
+            _ => chrono::LocalResult::None,
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> chrono::FixedOffset {
+        self.offset_from_utc_datetime(&utc.and_time(chrono::NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> chrono::FixedOffset {
+        let start = chrono::NaiveDate::from_ymd_opt(2025, 3, 30)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap();
+        let end = chrono::NaiveDate::from_ymd_opt(2025, 10, 26)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap();
+        if *utc >= start && *utc < end {
+            DstZone::summer()
+        } else {
+            DstZone::standard()
+        }
+    }
+}
+
+/// Ambiguous times resolve to the earlier instant, panics on nonexistent ones.
+pub fn dst_zone_date(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+) -> chrono::DateTime<DstZone> {
+    DstZone
+        .with_ymd_and_hms(year, month, day, hour, minute, 0)
+        .earliest()
+        .unwrap()
+}
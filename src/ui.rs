@@ -0,0 +1,168 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+/// How many times a question is asked again after an answer that is neither yes nor no.
+const CONFIRM_RETRIES: usize = 3;
+
+/// Asks `question` on the terminal, see [`confirm_with`]. Fails right away when stdin isn't a
+/// terminal, so that scripts don't hang on a question, they can pass `--yes` instead.
+pub fn confirm(question: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        Err("can't ask for confirmation when stdin isn't a terminal, pass --yes to confirm")?;
+    }
+    confirm_with(
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        question,
+        default,
+    )
+}
+
+/// Writes `question` to `writer` and reads the answer from `reader`. Accepts `y`, `yes`, `n` and
+/// `no` in any case, an empty answer is `default`. Anything else asks again, up to
+/// [`CONFIRM_RETRIES`] times. The end of input is no.
+pub fn confirm_with(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    question: &str,
+    default: bool,
+) -> Result<bool, Box<dyn Error>> {
+    let choices = if default { "(Y/n)" } else { "(y/N)" };
+    for _ in 0..=CONFIRM_RETRIES {
+        writeln!(writer, "{question} {choices}")?;
+        writer.flush()?;
+        let mut buf = String::new();
+        if reader.read_line(&mut buf)? == 0 {
+            return Ok(false);
+        }
+        match buf.trim().to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            answer => writeln!(writer, "Unrecognized answer `{answer}`, use y or n")?,
+        }
+    }
+    Err("no valid answer given")?
+}
+
+/// Asks `question` on the terminal, see [`ask_with`] and [`confirm`].
+pub fn ask(question: &str, default: &str) -> Result<String, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        Err("can't ask for input when stdin isn't a terminal, pass --yes to use the defaults")?;
+    }
+    ask_with(
+        &mut io::stdin().lock(),
+        &mut io::stdout(),
+        question,
+        default,
+    )
+}
+
+/// Asks for a secret on the terminal, what is typed isn't shown where `stty` is available.
+pub fn ask_secret(question: &str) -> Result<String, Box<dyn Error>> {
+    if !io::stdin().is_terminal() {
+        Err("can't ask for input when stdin isn't a terminal")?;
+    }
+    eprint!("{question} ");
+    io::stderr().flush()?;
+    let stty = |arg: &str| {
+        Command::new("stty")
+            .arg(arg)
+            .stdin(Stdio::inherit())
+            .status()
+            .is_ok_and(|v| v.success())
+    };
+    let hidden = stty("-echo");
+    let mut buf = String::new();
+    let read = io::stdin().lock().read_line(&mut buf);
+    if hidden {
+        stty("echo");
+        eprintln!();
+    }
+    if read? == 0 {
+        Err("no answer given")?;
+    }
+    Ok(buf.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// Writes `question` with `default` to `writer` and reads a line from `reader`, an empty answer
+/// is `default`.
+pub fn ask_with(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> Result<String, Box<dyn Error>> {
+    writeln!(writer, "{question} [{default}]")?;
+    writer.flush()?;
+    let mut buf = String::new();
+    if reader.read_line(&mut buf)? == 0 {
+        Err("no answer given")?;
+    }
+    match buf.trim() {
+        "" => Ok(default.to_owned()),
+        answer => Ok(answer.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn confirm_input(input: &str, default: bool) -> (Result<bool, String>, String) {
+        let mut writer = Vec::new();
+        let result = confirm_with(&mut input.as_bytes(), &mut writer, "Continue?", default)
+            .map_err(|err| err.to_string());
+        (result, String::from_utf8(writer).unwrap())
+    }
+
+    #[test]
+    fn confirm_with_works() {
+        assert_eq!(confirm_input("y\n", false).0, Ok(true));
+        assert_eq!(confirm_input("YES\r\n", false).0, Ok(true));
+        assert_eq!(confirm_input(" No \n", true).0, Ok(false));
+        assert_eq!(confirm_input("n", true).0, Ok(false));
+        assert_eq!(
+            confirm_input("\n", true),
+            (Ok(true), String::from("Continue? (Y/n)\n"))
+        );
+        assert_eq!(confirm_input("\r\n", false).0, Ok(false));
+        assert_eq!(confirm_input("", true).0, Ok(false));
+    }
+
+    #[test]
+    fn confirm_with_asks_again() {
+        let (result, output) = confirm_input("maybe\ny\n", false);
+        assert_eq!(result, Ok(true));
+        assert_eq!(
+            output,
+            "Continue? (y/N)\nUnrecognized answer `maybe`, use y or n\nContinue? (y/N)\n"
+        );
+        assert_eq!(confirm_input("a\nb\nc\nyes\n", false).0, Ok(true));
+        let (result, output) = confirm_input("a\nb\nc\nd\ny\n", false);
+        assert_eq!(result, Err(String::from("no valid answer given")));
+        assert_eq!(output.matches("Continue?").count(), 4);
+    }
+
+    #[test]
+    fn ask_with_works() {
+        let ask = |input: &str| {
+            let mut writer = Vec::new();
+            let answer = ask_with(&mut input.as_bytes(), &mut writer, "Path?", "~/notes")
+                .map_err(|err| err.to_string());
+            (answer, String::from_utf8(writer).unwrap())
+        };
+        assert_eq!(
+            ask("\n"),
+            (
+                Ok(String::from("~/notes")),
+                String::from("Path? [~/notes]\n")
+            )
+        );
+        assert_eq!(ask(" ~/work \r\n").0, Ok(String::from("~/work")));
+        assert_eq!(ask("").0, Err(String::from("no answer given")));
+    }
+}
@@ -0,0 +1,261 @@
+/// Reproduces the built-in output of `view`.
+const VIEW_TEMPLATE_DEFAULT: &str = "\
+[{state}\n]\
+[Title: {title}\n]\
+Start: {start}\n\
+Week: {week}\n\
+Today: {today}\n\
+Time: {session}\n\
+[Breaks: {breaks}\n]\
+Mark: {mark_time}\n\
+Marks: {marks}[  Tags: {tags}][\nActive: {active}]\n\
+[{goal}\n]\
+{separator}\n\
+{mark}";
+
+/// Values shown by `view`, `None` where there is nothing to show.
+#[derive(PartialEq, Debug, Default)]
+pub struct ViewData {
+    /// Whether the session is stale or has ended, `None` for an active session.
+    pub state: Option<String>,
+    pub title: Option<String>,
+    pub start: String,
+    pub week: String,
+    pub today: String,
+    pub session: String,
+    /// Time of the session spent on breaks, `None` without any.
+    pub breaks: Option<String>,
+    pub mark_time: String,
+    pub marks: String,
+    pub tags: Option<String>,
+    /// Active workspaces, only once named workspaces are used.
+    pub active: Option<String>,
+    pub goal: Option<String>,
+    pub separator: String,
+    /// Contents of the last mark.
+    pub mark: String,
+    pub path: String,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Placeholder {
+    State,
+    Title,
+    Start,
+    Week,
+    Today,
+    Session,
+    Breaks,
+    MarkTime,
+    Marks,
+    Tags,
+    Active,
+    Goal,
+    Separator,
+    Mark,
+    Path,
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Placeholder> {
+        let placeholder = match name {
+            "state" => Placeholder::State,
+            "title" => Placeholder::Title,
+            "start" => Placeholder::Start,
+            "week" => Placeholder::Week,
+            "today" => Placeholder::Today,
+            "session" => Placeholder::Session,
+            "breaks" => Placeholder::Breaks,
+            "mark_time" => Placeholder::MarkTime,
+            "marks" => Placeholder::Marks,
+            "tags" => Placeholder::Tags,
+            "active" => Placeholder::Active,
+            "goal" => Placeholder::Goal,
+            "separator" => Placeholder::Separator,
+            "mark" => Placeholder::Mark,
+            "path" => Placeholder::Path,
+            _ => return None,
+        };
+        Some(placeholder)
+    }
+
+    fn get<'a>(&self, data: &'a ViewData) -> Option<&'a str> {
+        let value = match self {
+            Placeholder::State => return data.state.as_deref(),
+            Placeholder::Title => return data.title.as_deref(),
+            Placeholder::Breaks => return data.breaks.as_deref(),
+            Placeholder::Tags => return data.tags.as_deref(),
+            Placeholder::Active => return data.active.as_deref(),
+            Placeholder::Goal => return data.goal.as_deref(),
+            Placeholder::Start => &data.start,
+            Placeholder::Week => &data.week,
+            Placeholder::Today => &data.today,
+            Placeholder::Session => &data.session,
+            Placeholder::MarkTime => &data.mark_time,
+            Placeholder::Marks => &data.marks,
+            Placeholder::Separator => &data.separator,
+            Placeholder::Mark => &data.mark,
+            Placeholder::Path => &data.path,
+        };
+        Some(value)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+    /// Left out when any of its placeholders has nothing to show.
+    Optional(Vec<Segment>),
+}
+
+/// Text with `{placeholder}`s of [`ViewData`] fields. Text within `[` and `]` is left out when any
+/// of its placeholders has nothing to show. `{{`, `}}`, `[[` and `]]` stand for the characters
+/// themselves and `\n` for a line break.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ViewTemplate {
+    segments: Vec<Segment>,
+}
+
+impl Default for ViewTemplate {
+    fn default() -> ViewTemplate {
+        ViewTemplate::parse(VIEW_TEMPLATE_DEFAULT).expect("default view template must be valid")
+    }
+}
+
+impl ViewTemplate {
+    pub fn parse(text: &str) -> Result<ViewTemplate, String> {
+        let mut segments = Vec::new();
+        // Segments of the optional group being parsed.
+        let mut optional: Option<Vec<Segment>> = None;
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied();
+            let is_doubled = matches!(c, '{' | '}' | '[' | ']') && next == Some(c);
+            if is_doubled {
+                chars.next();
+                literal.push(c);
+                continue;
+            }
+            match c {
+                '\\' if next == Some('n') => {
+                    chars.next();
+                    literal.push('\n');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("placeholder `{{{name}` isn't closed")),
+                        }
+                    }
+                    let placeholder = Placeholder::from_name(name.trim())
+                        .ok_or_else(|| format!("unknown placeholder `{{{name}}}`"))?;
+                    let target = optional.as_mut().unwrap_or(&mut segments);
+                    ViewTemplate::push_literal(target, &mut literal);
+                    target.push(Segment::Placeholder(placeholder));
+                }
+                '[' => {
+                    if optional.is_some() {
+                        return Err(String::from("optional groups can't be nested"));
+                    }
+                    ViewTemplate::push_literal(&mut segments, &mut literal);
+                    optional = Some(Vec::new());
+                }
+                ']' if optional.is_some() => {
+                    let mut group = optional.take().expect("optional group must be open");
+                    ViewTemplate::push_literal(&mut group, &mut literal);
+                    segments.push(Segment::Optional(group));
+                }
+                c => literal.push(c),
+            }
+        }
+        if optional.is_some() {
+            return Err(String::from("optional group isn't closed with `]`"));
+        }
+        ViewTemplate::push_literal(&mut segments, &mut literal);
+        Ok(ViewTemplate { segments })
+    }
+
+    fn push_literal(segments: &mut Vec<Segment>, literal: &mut String) {
+        if !literal.is_empty() {
+            segments.push(Segment::Text(std::mem::take(literal)));
+        }
+    }
+
+    pub fn render(&self, data: &ViewData) -> String {
+        ViewTemplate::render_segments(&self.segments, data).0
+    }
+
+    /// Also returns whether every placeholder had something to show, those that didn't are left
+    /// empty.
+    fn render_segments(segments: &[Segment], data: &ViewData) -> (String, bool) {
+        let mut str = String::new();
+        let mut is_complete = true;
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => str += text,
+                Segment::Placeholder(placeholder) => match placeholder.get(data) {
+                    Some(value) => str += value,
+                    None => is_complete = false,
+                },
+                Segment::Optional(group) => {
+                    let (text, is_group_complete) = ViewTemplate::render_segments(group, data);
+                    if is_group_complete {
+                        str += &text;
+                    }
+                }
+            }
+        }
+        (str, is_complete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_template_parse_works() {
+        assert_eq!(
+            ViewTemplate::parse("{start}\\n[Tags: {tags}]{{x}} [[1]]"),
+            Ok(ViewTemplate {
+                segments: vec![
+                    Segment::Placeholder(Placeholder::Start),
+                    Segment::Text(String::from("\n")),
+                    Segment::Optional(vec![
+                        Segment::Text(String::from("Tags: ")),
+                        Segment::Placeholder(Placeholder::Tags),
+                    ]),
+                    Segment::Text(String::from("{x} [1]")),
+                ]
+            })
+        );
+        assert_eq!(
+            ViewTemplate::parse("{start} {branch}"),
+            Err(String::from("unknown placeholder `{branch}`"))
+        );
+        assert!(ViewTemplate::parse("{start").is_err());
+        assert!(ViewTemplate::parse("[{title}").is_err());
+        assert!(ViewTemplate::parse("[[{title}]").is_ok());
+        assert!(ViewTemplate::parse("[[{title}] [{tags}]]").is_err());
+    }
+
+    #[test]
+    fn view_template_render_works() {
+        let data = ViewData {
+            start: String::from("today 09:00"),
+            week: String::from("5h"),
+            tags: Some(String::from("rust")),
+            ..Default::default()
+        };
+        let template =
+            ViewTemplate::parse("{start}[ ({tags})][\nTitle: {title}]\nWeek: {week}").unwrap();
+        assert_eq!(template.render(&data), "today 09:00 (rust)\nWeek: 5h");
+        let template = ViewTemplate::parse("Title: {title}, week: {week}").unwrap();
+        assert_eq!(template.render(&data), "Title: , week: 5h");
+    }
+}